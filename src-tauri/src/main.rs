@@ -7,12 +7,13 @@
 //! CompareIt core library. All commands run locally with zero network access.
 
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, State};
 
 use compare_it::{
-    ComparisonEngine, ProgressReporter,
+    ComparisonEngine, ProgressClock, ProgressReporter,
     export::calculate_summary,
     types::{
         CompareConfig, CompareMode, ComparisonResult, ComparisonSummary,
@@ -40,6 +41,12 @@ pub struct UiCompareConfig {
     pub ignore_columns: Option<Vec<String>>,
     pub ignore_regex: Option<String>,
     pub results_base: Option<String>,
+    /// CLI name of the similarity algorithm (e.g. `jaccard`, `tf-idf`); `diff` if unset
+    pub similarity_algorithm: Option<String>,
+    /// Largest detailed diff kept per text pair, in bytes (1 MB if unset, at most 64 MB)
+    pub max_diff_bytes: Option<usize>,
+    /// Cap on the number of candidate pairs compared (no cap if unset)
+    pub max_pairs: Option<usize>,
 }
 
 /// Progress event sent to the frontend
@@ -51,6 +58,9 @@ pub struct ProgressEvent {
     pub current: u64,
     pub total: u64,
     pub percentage: f64,
+    /// Estimated seconds left in this stage; `None` until the first item is done
+    pub eta_seconds: Option<f64>,
+    pub items_per_sec: f64,
 }
 
 /// Comparison result summary for the UI
@@ -60,10 +70,15 @@ pub struct UiComparisonSummary {
     pub total_files_set1: usize,
     pub total_files_set2: usize,
     pub pairs_compared: usize,
+    pub only_in_set1: usize,
+    pub only_in_set2: usize,
     pub identical_pairs: usize,
     pub different_pairs: usize,
+    pub content_diff_pairs: usize,
+    pub structural_diff_pairs: usize,
     pub error_pairs: usize,
     pub average_similarity: f64,
+    pub weighted_average_similarity: f64,
     pub min_similarity: f64,
     pub max_similarity: f64,
 }
@@ -74,10 +89,15 @@ impl From<ComparisonSummary> for UiComparisonSummary {
             total_files_set1: s.total_files_set1,
             total_files_set2: s.total_files_set2,
             pairs_compared: s.pairs_compared,
+            only_in_set1: s.only_in_set1,
+            only_in_set2: s.only_in_set2,
             identical_pairs: s.identical_pairs,
             different_pairs: s.different_pairs,
+            content_diff_pairs: s.content_diff_pairs,
+            structural_diff_pairs: s.structural_diff_pairs,
             error_pairs: s.error_pairs,
             average_similarity: s.average_similarity,
+            weighted_average_similarity: s.weighted_average_similarity,
             min_similarity: s.min_similarity,
             max_similarity: s.max_similarity,
         }
@@ -93,14 +113,21 @@ pub struct CompareResponse {
     pub results: Vec<ComparisonResult>,
     pub error: Option<String>,
     pub results_dir: Option<String>,
+    /// True if the user cancelled the run and `results` are partial
+    pub cancelled: bool,
 }
 
+/// Cancellation flag of the comparison currently running, if any
+#[derive(Default)]
+struct CancelState(Mutex<Option<Arc<AtomicBool>>>);
+
 /// Progress reporter that emits events to the Tauri frontend
 struct TauriProgressReporter {
     app_handle: AppHandle,
     stage: std::sync::Mutex<String>,
     total: AtomicU64,
     current: AtomicU64,
+    clock: ProgressClock,
 }
 
 impl TauriProgressReporter {
@@ -110,6 +137,7 @@ impl TauriProgressReporter {
             stage: std::sync::Mutex::new(String::new()),
             total: AtomicU64::new(0),
             current: AtomicU64::new(0),
+            clock: ProgressClock::new(),
         }
     }
     
@@ -124,12 +152,16 @@ impl TauriProgressReporter {
             0.0
         };
         
+        let rate = self.clock.rate(current, total);
+
         let event = ProgressEvent {
             stage: stage.clone(),
             message: format!("{}: {}/{}", stage, current, total),
             current,
             total,
             percentage,
+            eta_seconds: rate.eta_seconds,
+            items_per_sec: rate.items_per_sec,
         };
         
         let _ = self.app_handle.emit("compare-progress", event);
@@ -141,12 +173,19 @@ impl ProgressReporter for TauriProgressReporter {
         *self.stage.lock().unwrap() = message.to_string();
         self.total.store(total, Ordering::SeqCst);
         self.current.store(0, Ordering::SeqCst);
+        self.clock.restart();
         self.emit_progress();
     }
 
     fn inc(&self, delta: u64) {
-        self.current.fetch_add(delta, Ordering::SeqCst);
-        self.emit_progress();
+        let before = self.current.fetch_add(delta, Ordering::SeqCst);
+        let after = before + delta;
+        // Fingerprinting ticks once per file, so only emit when the whole
+        // percentage moves (or the stage completes) to avoid flooding the UI
+        let total = self.total.load(Ordering::SeqCst).max(1);
+        if before * 100 / total != after * 100 / total || after >= total {
+            self.emit_progress();
+        }
     }
 
     fn finish(&self, message: &str) {
@@ -158,7 +197,9 @@ impl ProgressReporter for TauriProgressReporter {
 }
 
 /// Convert UI config to library config
-fn ui_config_to_compare_config(ui_config: &UiCompareConfig) -> CompareConfig {
+///
+/// Fails with a message for the UI if the similarity algorithm is unknown.
+fn ui_config_to_compare_config(ui_config: &UiCompareConfig) -> Result<CompareConfig, String> {
     let mode = match ui_config.mode.as_deref() {
         Some("text") => CompareMode::Text,
         Some("structured") => CompareMode::Structured,
@@ -168,14 +209,20 @@ fn ui_config_to_compare_config(ui_config: &UiCompareConfig) -> CompareConfig {
     let pairing = match ui_config.pairing.as_deref() {
         Some("same-path") => PairingStrategy::SamePath,
         Some("same-name") => PairingStrategy::SameName,
+        Some("by-content-hash") => PairingStrategy::ByContentHash,
         _ => PairingStrategy::AllVsAll,
     };
+
+    let similarity_algorithm = match ui_config.similarity_algorithm.as_deref() {
+        Some(name) => name.parse::<SimilarityAlgorithm>()?,
+        None => SimilarityAlgorithm::Diff,
+    };
     
-    CompareConfig {
+    Ok(CompareConfig {
         mode,
         pairing,
         top_k: ui_config.top_k.unwrap_or(3).min(100), // Clamp top_k to reasonable max
-        max_pairs: None,
+        max_pairs: ui_config.max_pairs,
         key_columns: ui_config.key_columns.clone().unwrap_or_default(),
         numeric_tolerance: validate_numeric_tolerance(ui_config.numeric_tolerance),
         normalization: NormalizationOptions {
@@ -184,9 +231,10 @@ fn ui_config_to_compare_config(ui_config: &UiCompareConfig) -> CompareConfig {
             ignore_all_ws: ui_config.ignore_all_ws.unwrap_or(false),
             ignore_case: ui_config.ignore_case.unwrap_or(false),
             skip_empty_lines: ui_config.skip_empty_lines.unwrap_or(false),
+            ..NormalizationOptions::default()
         },
-        similarity_algorithm: SimilarityAlgorithm::Diff,
-        max_diff_bytes: 1024 * 1024,
+        similarity_algorithm,
+        max_diff_bytes: validate_max_diff_bytes(ui_config.max_diff_bytes),
         output_jsonl: None,
         output_csv: None,
         output_dir: None,
@@ -194,8 +242,10 @@ fn ui_config_to_compare_config(ui_config: &UiCompareConfig) -> CompareConfig {
         verbose: false,
         exclude_patterns: ui_config.exclude_patterns.clone().unwrap_or_default(),
         ignore_columns: ui_config.ignore_columns.clone().unwrap_or_default(),
-        ignore_regex: validate_regex_pattern(ui_config.ignore_regex.clone()),
-    }
+        ignore_regexes: validate_regex_pattern(ui_config.ignore_regex.clone()).into_iter().collect(),
+        // Options the UI does not expose yet keep their library defaults
+        ..CompareConfig::default()
+    })
 }
 
 /// Maximum allowed path length to prevent buffer-related issues
@@ -204,6 +254,12 @@ const MAX_PATH_LENGTH: usize = 4096;
 /// Maximum allowed regex pattern length to prevent ReDoS via long patterns
 const MAX_REGEX_LENGTH: usize = 1000;
 
+/// Default size of a text pair's detailed diff shown in the UI
+const DEFAULT_MAX_DIFF_BYTES: usize = 1024 * 1024;
+
+/// Ceiling on the detailed diff size, so one pair cannot exhaust the webview
+const MAX_DIFF_BYTES_CEILING: usize = 64 * 1024 * 1024;
+
 /// Validate and canonicalize a path to prevent path traversal attacks
 ///
 /// Security measures:
@@ -280,6 +336,11 @@ fn validate_numeric_tolerance(tolerance: Option<f64>) -> f64 {
     }
 }
 
+/// Validate maximum diff size
+fn validate_max_diff_bytes(max_diff_bytes: Option<usize>) -> usize {
+    max_diff_bytes.map_or(DEFAULT_MAX_DIFF_BYTES, |bytes| bytes.min(MAX_DIFF_BYTES_CEILING))
+}
+
 /// Validate and sanitize regex pattern
 fn validate_regex_pattern(pattern: Option<String>) -> Option<String> {
     pattern.and_then(|p| {
@@ -297,11 +358,24 @@ fn validate_regex_pattern(pattern: Option<String>) -> Option<String> {
 #[tauri::command]
 async fn run_comparison(
     app_handle: AppHandle,
+    cancel_state: State<'_, CancelState>,
     config: UiCompareConfig,
 ) -> Result<CompareResponse, String> {
     let progress = TauriProgressReporter::new(app_handle.clone());
     
-    let compare_config = ui_config_to_compare_config(&config);
+    let compare_config = match ui_config_to_compare_config(&config) {
+        Ok(c) => c,
+        Err(e) => {
+            return Ok(CompareResponse {
+                success: false,
+                summary: None,
+                results: vec![],
+                error: Some(e),
+                results_dir: None,
+                cancelled: false,
+            });
+        }
+    };
     
     // Validate and canonicalize paths to prevent path traversal
     let path1 = match validate_path(&config.path1) {
@@ -313,6 +387,7 @@ async fn run_comparison(
                 results: vec![],
                 error: Some(e),
                 results_dir: None,
+                cancelled: false,
             });
         }
     };
@@ -326,20 +401,29 @@ async fn run_comparison(
                 results: vec![],
                 error: Some(e),
                 results_dir: None,
+                cancelled: false,
             });
         }
     };
     
+    // Register a fresh cancellation flag for this run
+    let cancel = Arc::new(AtomicBool::new(false));
+    *cancel_state.0.lock().unwrap() = Some(cancel.clone());
+
     // Run comparison in a blocking task
     let results_base = compare_config.results_base.clone();
     let result = tokio::task::spawn_blocking(move || {
-        let engine = ComparisonEngine::new(&compare_config).with_progress(&progress);
-        engine.run(&path1, &path2)
+        let engine = ComparisonEngine::new(&compare_config)
+            .with_progress(&progress)
+            .with_cancel(cancel);
+        engine.run(&path1, &path2).map(|results| (results, engine.is_cancelled()))
     }).await;
+
+    *cancel_state.0.lock().unwrap() = None;
     
     match result {
-        Ok(Ok(results)) => {
-            let summary = calculate_summary(&results, 0, 0);
+        Ok(Ok((results, cancelled))) => {
+            let summary = calculate_summary(&results, 0, 0, None);
             let results_dir = results_base.canonicalize()
                 .map(|p| p.display().to_string())
                 .unwrap_or_else(|_| results_base.display().to_string());
@@ -350,6 +434,7 @@ async fn run_comparison(
                 results,
                 error: None,
                 results_dir: Some(results_dir),
+                cancelled,
             })
         }
         Ok(Err(e)) => Ok(CompareResponse {
@@ -358,6 +443,7 @@ async fn run_comparison(
             results: vec![],
             error: Some(e.to_string()),
             results_dir: None,
+            cancelled: false,
         }),
         Err(e) => Ok(CompareResponse {
             success: false,
@@ -365,10 +451,25 @@ async fn run_comparison(
             results: vec![],
             error: Some(format!("Task panicked: {}", e)),
             results_dir: None,
+            cancelled: false,
         }),
     }
 }
 
+/// Request cancellation of the running comparison
+///
+/// Returns false if no comparison is running.
+#[tauri::command]
+fn cancel_comparison(cancel_state: State<'_, CancelState>) -> bool {
+    match cancel_state.0.lock().unwrap().as_ref() {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
 /// Get app version
 #[tauri::command]
 fn get_version() -> String {
@@ -379,10 +480,55 @@ fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .manage(CancelState::default())
         .invoke_handler(tauri::generate_handler![
             run_comparison,
+            cancel_comparison,
             get_version,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ui_config(similarity_algorithm: Option<&str>) -> UiCompareConfig {
+        serde_json::from_value(serde_json::json!({
+            "path1": "a",
+            "path2": "b",
+            "similarityAlgorithm": similarity_algorithm,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ui_config_maps_similarity_algorithm() {
+        let config = ui_config_to_compare_config(&ui_config(Some("jaccard"))).unwrap();
+        assert_eq!(config.similarity_algorithm, SimilarityAlgorithm::Jaccard);
+        let config = ui_config_to_compare_config(&ui_config(None)).unwrap();
+        assert_eq!(config.similarity_algorithm, SimilarityAlgorithm::Diff);
+
+        let error = ui_config_to_compare_config(&ui_config(Some("soundex"))).unwrap_err();
+        assert!(error.contains("Unknown similarity algorithm 'soundex'"));
+    }
+
+    #[test]
+    fn test_ui_config_maps_diff_and_pair_limits() {
+        let with_limits = |value: serde_json::Value| {
+            let mut json = serde_json::json!({ "path1": "a", "path2": "b" });
+            json.as_object_mut().unwrap().extend(value.as_object().unwrap().clone());
+            ui_config_to_compare_config(&serde_json::from_value(json).unwrap()).unwrap()
+        };
+
+        let config = with_limits(serde_json::json!({ "maxDiffBytes": 4096, "maxPairs": 50 }));
+        assert_eq!((config.max_diff_bytes, config.max_pairs), (4096, Some(50)));
+
+        let config = with_limits(serde_json::json!({}));
+        assert_eq!((config.max_diff_bytes, config.max_pairs), (DEFAULT_MAX_DIFF_BYTES, None));
+
+        let config = with_limits(serde_json::json!({ "maxDiffBytes": usize::MAX }));
+        assert_eq!(config.max_diff_bytes, MAX_DIFF_BYTES_CEILING);
+    }
+}
@@ -0,0 +1,251 @@
+//! Comparing archives entry by entry
+//!
+//! With `follow_archives`, a `.zip`, `.tar`, `.tar.gz` or `.tgz` path given
+//! as a side of a comparison is compared like a folder. Its entries are
+//! indexed under archive-relative paths such as `dist.zip/bin/run.sh`, so
+//! they pair with the same entry of the other archive and keep the same path
+//! in results from one run to the next. The entries' contents are unpacked
+//! to a temporary folder, and [`resolve_entry_path`] maps an entry path to
+//! its unpacked copy wherever a file is opened. Archives found inside a
+//! compared folder are compared as single files.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use log::warn;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tempfile::TempDir;
+
+/// Most bytes unpacked from one archive before it is rejected
+pub const MAX_UNPACKED_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Most entries unpacked from one archive before it is rejected
+pub const MAX_UNPACKED_ENTRIES: usize = 100_000;
+
+/// Archives currently unpacked, as (archive path, folder holding its entries)
+static UNPACKED: Mutex<Vec<(PathBuf, PathBuf)>> = Mutex::new(Vec::new());
+
+/// An archive unpacked for comparison; the files are deleted when it is dropped
+pub struct ExtractedArchive {
+    /// Folder holding the archive's entries
+    pub root: PathBuf,
+    _dir: TempDir,
+}
+
+impl Drop for ExtractedArchive {
+    fn drop(&mut self) {
+        UNPACKED.lock().unwrap_or_else(|e| e.into_inner()).retain(|(_, root)| root != &self.root);
+    }
+}
+
+/// True if `path` names an archive format that can be followed
+pub fn is_archive_path(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_lowercase();
+    [".zip", ".tar", ".tar.gz", ".tgz"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// Folder holding the entries of `archive`, if it is currently unpacked
+pub fn unpacked_root(archive: &Path) -> Option<PathBuf> {
+    let unpacked = UNPACKED.lock().unwrap_or_else(|e| e.into_inner());
+    unpacked.iter().find(|(path, _)| path == archive).map(|(_, root)| root.clone())
+}
+
+/// Location of the file behind `path`
+///
+/// An archive-relative entry path like `dist.zip/bin/run.sh` resolves to the
+/// entry's unpacked copy; any other path is returned unchanged.
+pub fn resolve_entry_path(path: &Path) -> Cow<'_, Path> {
+    let unpacked = UNPACKED.lock().unwrap_or_else(|e| e.into_inner());
+    for (archive, root) in unpacked.iter() {
+        if let Ok(entry) = path.strip_prefix(archive) {
+            if !entry.as_os_str().is_empty() {
+                return Cow::Owned(root.join(entry));
+            }
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Unpack the archive at `path` into a new temporary folder
+///
+/// Until the returned archive is dropped, [`resolve_entry_path`] maps paths
+/// below `path` into the folder. Entries whose paths would land outside the
+/// folder (absolute paths or `..` components) and entries that are not
+/// regular files, such as links, are skipped. An archive holding more than
+/// [`MAX_UNPACKED_ENTRIES`] entries or [`MAX_UNPACKED_BYTES`] of content is
+/// rejected. Unpacked files take the archive's modification time, so cached
+/// fingerprints stay valid until the archive itself changes.
+pub fn extract_archive(path: &Path) -> Result<ExtractedArchive> {
+    extract_archive_within(path, MAX_UNPACKED_BYTES, MAX_UNPACKED_ENTRIES)
+}
+
+fn extract_archive_within(path: &Path, max_bytes: u64, max_entries: usize) -> Result<ExtractedArchive> {
+    let dir = tempfile::Builder::new()
+        .prefix("archive-")
+        .tempdir()
+        .context("Failed to create a folder to unpack the archive into")?;
+    let root = dir.path().join(path.file_name().unwrap_or_default());
+    fs::create_dir(&root)?;
+
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut unpacker = Unpacker {
+        archive: path,
+        root: &root,
+        modified: file.metadata().and_then(|m| m.modified()).ok(),
+        bytes: 0,
+        entries: 0,
+        max_bytes,
+        max_entries,
+    };
+    let file = BufReader::new(file);
+    let name = path.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        let mut archive =
+            zip::ZipArchive::new(file).with_context(|| format!("Failed to read zip archive {}", path.display()))?;
+        for i in 0..archive.len() {
+            let mut entry = archive
+                .by_index(i)
+                .with_context(|| format!("Failed to read zip archive {}", path.display()))?;
+            if entry.is_file() {
+                let entry_path = PathBuf::from(entry.name());
+                unpacker.unpack(&entry_path, &mut entry)?;
+            }
+        }
+    } else {
+        let reader: Box<dyn Read> = if name.ends_with(".tar") { Box::new(file) } else { Box::new(GzDecoder::new(file)) };
+        let mut archive = tar::Archive::new(reader);
+        let entries = archive
+            .entries()
+            .with_context(|| format!("Failed to read tar archive {}", path.display()))?;
+        for entry in entries {
+            let mut entry = entry.with_context(|| format!("Failed to read tar archive {}", path.display()))?;
+            if entry.header().entry_type().is_file() {
+                let entry_path = entry.path()?.into_owned();
+                unpacker.unpack(&entry_path, &mut entry)?;
+            }
+        }
+    }
+
+    UNPACKED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((path.to_path_buf(), root.clone()));
+    Ok(ExtractedArchive { root, _dir: dir })
+}
+
+/// Writes an archive's entries below `root` within the unpacking limits
+struct Unpacker<'a> {
+    archive: &'a Path,
+    root: &'a Path,
+    modified: Option<SystemTime>,
+    bytes: u64,
+    entries: usize,
+    max_bytes: u64,
+    max_entries: usize,
+}
+
+impl Unpacker<'_> {
+    fn unpack(&mut self, entry_path: &Path, content: &mut dyn Read) -> Result<()> {
+        if !entry_path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            warn!(
+                "Skipping {} in {}: it would unpack outside the archive",
+                entry_path.display(),
+                self.archive.display()
+            );
+            return Ok(());
+        }
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            anyhow::bail!("{} holds more than {} entries", self.archive.display(), self.max_entries);
+        }
+
+        let target = self.root.join(entry_path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&target).with_context(|| format!("Failed to create {}", target.display()))?;
+        let remaining = self.max_bytes - self.bytes;
+        let written = io::copy(&mut content.take(remaining + 1), &mut file)
+            .with_context(|| format!("Failed to unpack {} from {}", entry_path.display(), self.archive.display()))?;
+        if written > remaining {
+            anyhow::bail!("{} unpacks to more than {} bytes", self.archive.display(), self.max_bytes);
+        }
+        self.bytes += written;
+        if let Some(modified) = self.modified {
+            file.set_modified(modified)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CompareConfig, ComparisonResult};
+    use crate::ComparisonEngine;
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_zips_are_compared_entry_by_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("release-1.0.zip"), dir.path().join("release-1.1.zip"));
+        let binary: &[u8] = &[0x7f, b'E', b'L', b'F', 0, 1, 2, 3];
+        write_zip(&old, &[("bin/tool", binary), ("docs/CHANGES.txt", b"1.0 first release\n"), ("README.txt", b"Hello\n")]);
+        write_zip(&new, &[("bin/tool", binary), ("docs/CHANGES.txt", b"1.1 bug fixes\n"), ("README.txt", b"Hello\n")]);
+
+        let config = CompareConfig { follow_archives: true, ..CompareConfig::default() };
+        let (results, summary) = ComparisonEngine::new(&config).run_in_memory(&old, &new).unwrap();
+
+        assert_eq!(summary.pairs_compared, 3);
+        let different: Vec<&ComparisonResult> = results.iter().filter(|r| !r.is_identical()).collect();
+        assert_eq!(different.len(), 1);
+        let (file1, file2) = different[0].file_paths();
+        assert_eq!(file1, old.join("docs/CHANGES.txt").display().to_string());
+        assert_eq!(file2, new.join("docs/CHANGES.txt").display().to_string());
+        // The unpacked copies are gone once the run is over
+        assert_eq!(resolve_entry_path(Path::new(&file1)), Path::new(&file1));
+    }
+
+    #[test]
+    fn test_entries_escaping_the_archive_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.zip");
+        write_zip(&path, &[("../escape.txt", b"outside\n"), ("/etc/absolute.txt", b"outside\n"), ("kept.txt", b"inside\n")]);
+
+        let archive = extract_archive(&path).unwrap();
+        assert!(archive.root.join("kept.txt").is_file());
+        assert_eq!(fs::read_dir(&archive.root).unwrap().count(), 1);
+        assert_eq!(unpacked_root(&path), Some(archive.root.clone()));
+        assert_eq!(resolve_entry_path(&path.join("kept.txt")), archive.root.join("kept.txt"));
+
+        drop(archive);
+        assert_eq!(unpacked_root(&path), None);
+    }
+
+    #[test]
+    fn test_archives_past_the_unpacking_limits_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bomb.zip");
+        write_zip(&path, &[("a.txt", &[b'a'; 600]), ("b.txt", &[b'b'; 600])]);
+
+        let error = extract_archive_within(&path, 1000, 10).err().unwrap();
+        assert!(error.to_string().contains("more than 1000 bytes"), "{}", error);
+        let error = extract_archive_within(&path, 10_000, 1).err().unwrap();
+        assert!(error.to_string().contains("more than 1 entries"), "{}", error);
+        assert!(extract_archive_within(&path, 10_000, 10).is_ok());
+    }
+}
@@ -0,0 +1,159 @@
+//! Column type inference for structured comparison
+//!
+//! Structured values are compared as text unless both parse as numbers. This
+//! module classifies each column from a sample of its values so values can be
+//! compared by meaning instead:
+//!
+//! - **Boolean**: `TRUE` matches `true`
+//! - **Integer / Float**: `$1,000` matches `1000`; currency symbols and
+//!   thousands separators are ignored
+//! - **Date**: `2024-01-01` matches `2024-1-1` and `2024/01/01`
+//!
+//! Each kind of coercion is off unless switched on through [`TypeCoercion`].
+
+use crate::types::{ColumnType, TypeCoercion};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+/// Number of rows per file sampled to infer column types
+pub const TYPE_SAMPLE_ROWS: usize = 100;
+
+/// Date-only formats recognized by [`parse_date`]
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%d.%m.%Y"];
+
+/// Date-time formats recognized by [`parse_date`]
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"];
+
+/// Classify a column from sample values
+///
+/// Empty values are ignored. A column is only given a type if every other
+/// value parses as it; a column with no values at all is a string column.
+pub fn infer_column_type<'a>(values: impl IntoIterator<Item = &'a str>) -> ColumnType {
+    let values: Vec<&str> = values.into_iter().map(str::trim).filter(|v| !v.is_empty()).collect();
+    if values.is_empty() {
+        ColumnType::String
+    } else if values.iter().all(|v| parse_bool(v).is_some()) {
+        ColumnType::Boolean
+    } else if values.iter().all(|v| parse_number(v).is_some_and(|n| n.fract() == 0.0 && !v.contains('.'))) {
+        ColumnType::Integer
+    } else if values.iter().all(|v| parse_number(v).is_some()) {
+        ColumnType::Float
+    } else if values.iter().all(|v| parse_date(v).is_some()) {
+        ColumnType::Date
+    } else {
+        ColumnType::String
+    }
+}
+
+/// True if `coercion` allows comparing values of `column_type` by meaning
+pub fn coerces(column_type: ColumnType, coercion: &TypeCoercion) -> bool {
+    match column_type {
+        ColumnType::Boolean => coercion.booleans,
+        ColumnType::Integer | ColumnType::Float => coercion.numbers,
+        ColumnType::Date => coercion.dates,
+        ColumnType::String => false,
+    }
+}
+
+/// Parse a boolean written as `true` or `false`, in any case
+///
+/// Other spellings such as `yes` or `1` are left as text, so they never
+/// match `true`.
+pub fn parse_bool(value: &str) -> Option<bool> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Parse a number, ignoring currency symbols and thousands separators
+///
+/// Commas are only accepted as thousands separators in groups of three
+/// (`1,000,000`), so a decimal comma such as `1,5` is not read as `15`.
+pub fn parse_number(value: &str) -> Option<f64> {
+    let mut s = value.trim();
+    let negative = s.starts_with('-');
+    if negative || s.starts_with('+') {
+        s = s[1..].trim_start();
+    }
+    s = s.trim_start_matches(['$', '€', '£', '¥']).trim_start();
+
+    let (integer, fraction) = match s.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (s, None),
+    };
+    if integer.contains(',') {
+        let mut groups = integer.split(',');
+        let first = groups.next()?;
+        if first.is_empty() || first.len() > 3 || !groups.all(|g| g.len() == 3) {
+            return None;
+        }
+    }
+    let mut plain = integer.replace(',', "");
+    if let Some(fraction) = fraction {
+        plain.push('.');
+        plain.push_str(fraction);
+    }
+    if plain.is_empty() || !plain.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    let number: f64 = plain.parse().ok()?;
+    Some(if negative { -number } else { number })
+}
+
+/// Parse a date or date-time in a common unambiguous format
+pub fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.naive_utc());
+    }
+    DATETIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_column_type() {
+        assert_eq!(infer_column_type(["TRUE", "false", "", "True"]), ColumnType::Boolean);
+        assert_eq!(infer_column_type(["true", "yes"]), ColumnType::String);
+        assert_eq!(infer_column_type(["1", "$1,000", "-42"]), ColumnType::Integer);
+        assert_eq!(infer_column_type(["1", "2.5", "€3"]), ColumnType::Float);
+        assert_eq!(infer_column_type(["2024-01-01", "2024-1-31", "2024-02-03T10:00:00"]), ColumnType::Date);
+        assert_eq!(infer_column_type(["1", "two"]), ColumnType::String);
+        assert_eq!(infer_column_type(["", " "]), ColumnType::String);
+    }
+
+    #[test]
+    fn test_parse_bool_only_folds_case() {
+        assert_eq!(parse_bool(" TRUE "), Some(true));
+        assert_eq!(parse_bool("False"), Some(false));
+        assert_eq!(parse_bool("yes"), None);
+        assert_eq!(parse_bool("1"), None);
+    }
+
+    #[test]
+    fn test_parse_number_strips_grouping_and_currency() {
+        assert_eq!(parse_number("$1,000"), Some(1000.0));
+        assert_eq!(parse_number("1,234,567.89"), Some(1234567.89));
+        assert_eq!(parse_number("-$5.50"), Some(-5.5));
+        assert_eq!(parse_number("1e3"), Some(1000.0));
+        assert_eq!(parse_number("1,5"), None);
+        assert_eq!(parse_number("12,34"), None);
+        assert_eq!(parse_number("$"), None);
+        assert_eq!(parse_number("abc"), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! Byte-level binary file comparison
+//!
+//! Binary files have no lines or records, so this module compares them
+//! offset by offset. Both files are streamed in fixed-size blocks, so memory
+//! use is constant regardless of file size.
+
+use crate::input::open_input;
+use crate::types::{ComparisonResult, FileEntry};
+use anyhow::{Context, Result};
+use std::io::Read;
+
+/// Block size used when streaming both files
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Maximum number of differing ranges recorded per pair
+///
+/// Differing bytes beyond this many ranges are still counted in
+/// `differing_bytes` and `byte_similarity`.
+pub const MAX_BINARY_DIFF_RANGES: usize = 100;
+
+/// Compare two binary files byte by byte
+///
+/// Records the first [`MAX_BINARY_DIFF_RANGES`] ranges of differing bytes as
+/// half-open `(start, end)` offsets. If one file is longer, its extra tail is
+/// reported as a differing range.
+pub fn compare_binary_files(file1: &FileEntry, file2: &FileEntry) -> Result<ComparisonResult> {
+    let mut reader1 = open_reader(file1)?;
+    let mut reader2 = open_reader(file2)?;
+
+    let mut buf1 = vec![0u8; BLOCK_SIZE];
+    let mut buf2 = vec![0u8; BLOCK_SIZE];
+    let mut ranges = DiffRanges::default();
+    let mut offset: u64 = 0;
+
+    loop {
+        let n1 = read_block(&mut reader1, &mut buf1)
+            .with_context(|| format!("Failed to read {}", file1.path.display()))?;
+        let n2 = read_block(&mut reader2, &mut buf2)
+            .with_context(|| format!("Failed to read {}", file2.path.display()))?;
+
+        let common = n1.min(n2);
+        for (i, (a, b)) in buf1[..common].iter().zip(&buf2[..common]).enumerate() {
+            if a != b {
+                ranges.mark(offset + i as u64, 1);
+            }
+        }
+
+        // One file ended: everything the other still has is a difference
+        if n1 != n2 {
+            let tail_start = offset + common as u64;
+            let tail_len = file1.size.max(file2.size).saturating_sub(tail_start);
+            ranges.mark(tail_start, tail_len);
+            break;
+        }
+        if n1 == 0 {
+            break;
+        }
+        offset += n1 as u64;
+    }
+
+    let total = file1.size.max(file2.size);
+    let byte_similarity = if total == 0 {
+        1.0
+    } else {
+        (total - ranges.differing_bytes) as f64 / total as f64
+    };
+
+    let linked_id = format!(
+        "{}:{}",
+        &file1.content_hash[..16.min(file1.content_hash.len())],
+        &file2.content_hash[..16.min(file2.content_hash.len())]
+    );
+
+    Ok(ComparisonResult::Binary {
+        linked_id,
+        file1_path: file1.path.display().to_string(),
+        file2_path: file2.path.display().to_string(),
+        file1_size: file1.size,
+        file2_size: file2.size,
+        first_diff_offset: ranges.ranges.first().map(|r| r.0),
+        identical: ranges.differing_bytes == 0,
+        differing_ranges: ranges.ranges,
+        differing_bytes: ranges.differing_bytes,
+        byte_similarity,
+    })
+}
+
+/// Accumulates differing byte ranges, merging adjacent ones
+#[derive(Default)]
+struct DiffRanges {
+    ranges: Vec<(u64, u64)>,
+    differing_bytes: u64,
+    /// True while the last recorded range can still be extended
+    open: bool,
+}
+
+impl DiffRanges {
+    fn mark(&mut self, start: u64, len: u64) {
+        if len == 0 {
+            return;
+        }
+        self.differing_bytes += len;
+        if let Some(last) = self.ranges.last_mut() {
+            if self.open && last.1 == start {
+                last.1 = start + len;
+                return;
+            }
+        }
+        self.open = self.ranges.len() < MAX_BINARY_DIFF_RANGES;
+        if self.open {
+            self.ranges.push((start, start + len));
+        }
+    }
+}
+
+fn open_reader(entry: &FileEntry) -> Result<Box<dyn Read + Send>> {
+    open_input(&entry.path).with_context(|| format!("Failed to open {}", entry.path.display()))
+}
+
+/// Fill `buf` as far as possible, returning fewer bytes only at end of file
+fn read_block(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::index_single_file;
+
+    #[test]
+    fn test_binary_diff_at_offset_1024() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bytes: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        bytes[0] = 0; // make sure the file is detected as binary
+        let path1 = dir.path().join("fw_a.bin");
+        std::fs::write(&path1, &bytes).unwrap();
+        bytes[1024] ^= 0xFF;
+        let path2 = dir.path().join("fw_b.bin");
+        std::fs::write(&path2, &bytes).unwrap();
+
+        let file1 = index_single_file(&path1).unwrap();
+        let file2 = index_single_file(&path2).unwrap();
+        let result = compare_binary_files(&file1, &file2).unwrap();
+
+        match result {
+            ComparisonResult::Binary {
+                differing_ranges,
+                first_diff_offset,
+                differing_bytes,
+                byte_similarity,
+                identical,
+                ..
+            } => {
+                assert_eq!(differing_ranges, vec![(1024, 1025)]);
+                assert_eq!(first_diff_offset, Some(1024));
+                assert_eq!(differing_bytes, 1);
+                assert!(byte_similarity > 0.999 && byte_similarity < 1.0);
+                assert!(!identical);
+            }
+            other => panic!("expected a binary result, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_binary_diff_length_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("a.bin");
+        let path2 = dir.path().join("b.bin");
+        std::fs::write(&path1, [0u8; 10]).unwrap();
+        std::fs::write(&path2, [0u8; 16]).unwrap();
+
+        let file1 = index_single_file(&path1).unwrap();
+        let file2 = index_single_file(&path2).unwrap();
+        let result = compare_binary_files(&file1, &file2).unwrap();
+
+        let ComparisonResult::Binary { differing_ranges, byte_similarity, .. } = result else {
+            panic!("expected a binary result");
+        };
+        assert_eq!(differing_ranges, vec![(10, 16)]);
+        assert_eq!(byte_similarity, 10.0 / 16.0);
+    }
+
+    #[test]
+    fn test_diff_ranges_merge_and_cap() {
+        let mut ranges = DiffRanges::default();
+        ranges.mark(5, 1);
+        ranges.mark(6, 1);
+        for i in 0..MAX_BINARY_DIFF_RANGES as u64 + 10 {
+            ranges.mark(100 + i * 2, 1);
+        }
+        assert_eq!(ranges.ranges[0], (5, 7));
+        assert_eq!(ranges.ranges.len(), MAX_BINARY_DIFF_RANGES);
+        assert_eq!(ranges.differing_bytes, 2 + MAX_BINARY_DIFF_RANGES as u64 + 10);
+    }
+}
@@ -11,18 +11,32 @@
 //! **Phase 3 Enhancement:**
 //! Adds Excel/OpenDocument support via `calamine`. Excel rows are converted into
 //! the same `ByteRecord` format used for CSVs, enabling unified comparison logic.
+//!
+//! JSON arrays of objects and NDJSON files are handled the same way: each
+//! top-level object is flattened into a pseudo-record whose columns are JSON
+//! paths (`user.address.city`, `items.0.sku`).
 
+use crate::archive::resolve_entry_path;
+use crate::column_types::{coerces, infer_column_type, parse_bool, parse_date, parse_number, TYPE_SAMPLE_ROWS};
+use crate::diagnostics::{Diagnostics, UnreadableFile};
+use crate::external_sort::{RunSpiller, SortedRuns};
+use crate::fingerprint::read_text_decoded;
+use crate::input::{open_input, read_input_to_string};
 use crate::types::{
-    ColumnMismatch, CompareConfig, FieldMismatch, FileEntry, FileType, StructuredComparisonResult,
+    AnomalyKind, ColumnMismatch, ColumnProfile, ColumnProfileDiff, ColumnType, CompareConfig, CsvDialect, FieldMismatch,
+    FieldUpdate, FileEntry, FileType, KeyNormalization, SampleSpec, StructuredComparisonResult, StructuredPatch,
+    ThreeWayChange, ThreeWayResult, ToleranceMode, REDACTED,
 };
 use anyhow::{Context, Result};
 use calamine::{open_workbook_auto, Data, Reader};
 use csv::{ByteRecord, ReaderBuilder};
+use log::{debug, info, warn};
 use rayon::prelude::*;
+use serde_json::Value;
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::path::Path;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
 
 /// A record with its composite key for sorted comparison
 ///
@@ -40,19 +54,253 @@ struct KeyedRecord {
 /// This implementation reads records into sorted vectors and performs a linear
 /// merge-join, which is far more memory-efficient than HashMap-based comparison.
 ///
-/// Supports comparing any combination of CSV, TSV, and Excel files.
+/// Supports comparing any combination of CSV, TSV, and Excel files; the two
+/// files are parsed in parallel. With `config.external_sort`, large CSV/TSV files are sorted on disk
+/// and streamed through the merge-join instead.
+/// Ragged rows and duplicate keys are recorded in `diagnostics`.
+///
+/// With `config.positional`, rows are instead paired by position (row N
+/// against row N) and mismatch samples are keyed `row N`.
 pub fn compare_structured_files(
     file1: &FileEntry,
     file2: &FileEntry,
     config: &CompareConfig,
+    diagnostics: &Diagnostics,
+) -> Result<StructuredComparisonResult> {
+    // Read both files into key-sorted record streams based on file type.
+    // Key columns are given in file 1 names, so translate them for file 2
+    // and then rename file 2's headers onto file 1's names.
+    let inferred;
+    let config = if config.auto_key && config.key_columns.is_empty() && !config.positional {
+        let (headers, rows) = sample_rows(file1, config).with_context(|| UnreadableFile(file1.path.clone()))?;
+        inferred = CompareConfig {
+            key_columns: infer_key_columns(&headers, &rows, &config.ignore_columns),
+            ..config.clone()
+        };
+        debug!("Inferred key column(s) {:?} for {}", inferred.key_columns, file1.path.display());
+        &inferred
+    } else {
+        config
+    };
+    let key_columns2 = unalias_columns(&config.key_columns, &config.column_aliases);
+    // Both files are parsed at once. `rayon::join` runs on the current pool
+    // (the `--threads` pool during a run), so when many pairs are already
+    // compared in parallel the second parse waits for a free worker rather
+    // than adding threads.
+    let (opened1, opened2) = rayon::join(
+        || open_record_stream(file1, &config.key_columns, config, diagnostics),
+        || open_record_stream(file2, &key_columns2, config, diagnostics),
+    );
+    let opened1 = opened1.with_context(|| UnreadableFile(file1.path.clone()))?;
+    let opened2 = opened2.with_context(|| UnreadableFile(file2.path.clone()))?;
+    let mut result = join_records(opened1, opened2, (&file1.path, &file2.path), config, diagnostics)?;
+
+    result.linked_id = format!(
+        "{}:{}",
+        &file1.content_hash[..16.min(file1.content_hash.len())],
+        &file2.content_hash[..16.min(file2.content_hash.len())]
+    );
+    result.file1_path = file1.path.display().to_string();
+    result.file2_path = file2.path.display().to_string();
+    result.file1_size = file1.size;
+    result.file2_size = file2.size;
+    if config.auto_key && !config.positional {
+        result.inferred_key_columns = config.key_columns.clone();
+    }
+    Ok(result)
+}
+
+/// Compare two record sets that are already in memory
+///
+/// Runs the same merge-join and mismatch reporting as
+/// [`compare_structured_files`], for rows loaded from elsewhere (a database
+/// query, a data frame) without writing them to disk first. `headers2` and
+/// `records2` use file 2 names, so `config.column_aliases` applies as usual.
+///
+/// The result's linked ID and file paths are empty and its sizes are 0.
+pub fn compare_records(
+    headers1: Vec<String>,
+    records1: impl IntoIterator<Item = Vec<String>>,
+    headers2: Vec<String>,
+    records2: impl IntoIterator<Item = Vec<String>>,
+    config: &CompareConfig,
+) -> Result<StructuredComparisonResult> {
+    let key_columns2 = unalias_columns(&config.key_columns, &config.column_aliases);
+    let opened1 = records_in_memory(headers1, records1, &config.key_columns, config);
+    let opened2 = records_in_memory(headers2, records2, &key_columns2, config);
+    join_records(opened1, opened2, (Path::new("records1"), Path::new("records2")), config, &Diagnostics::new())
+}
+
+/// Compare two edited copies of a structured file against their common ancestor
+///
+/// Records of all three files are matched by `config.key_columns` with the
+/// same sorted merge-join as [`compare_structured_files`]. Each field that
+/// differs from `base` is attributed to the copy that changed it; a field
+/// changed differently in both copies is a conflict, as is a record deleted
+/// in one copy and edited in the other. Only columns present in all three
+/// files are compared, and column aliases do not apply.
+pub fn compare_three_way(
+    base: &FileEntry,
+    left: &FileEntry,
+    right: &FileEntry,
+    config: &CompareConfig,
+) -> Result<ThreeWayResult> {
+    if config.positional {
+        anyhow::bail!("Three-way comparison matches records by key and cannot be positional");
+    }
+
+    let diagnostics = Diagnostics::new();
+    let files = [base, left, right];
+    let mut headers: Vec<Vec<String>> = Vec::with_capacity(files.len());
+    let mut streams: Vec<RecordStream> = Vec::with_capacity(files.len());
+    for file in files {
+        let (file_headers, stream, _) = open_record_stream(file, &config.key_columns, config, &diagnostics)?;
+        headers.push(file_headers);
+        streams.push(stream);
+    }
+
+    // Compare the non-key columns all three files share, in base order
+    let ignored = |c: &str| config.ignore_columns.iter().any(|i| i == c) || config.key_columns.iter().any(|k| k == c);
+    let mut columns: Vec<&str> = Vec::new();
+    for column in headers[0].iter().map(String::as_str) {
+        let shared = headers[1..].iter().all(|other| other.iter().any(|h| h == column));
+        if shared && !ignored(column) && !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+    let indices: Vec<HashMap<&str, usize>> = headers
+        .iter()
+        .map(|file_headers| file_headers.iter().enumerate().map(|(i, h)| (h.as_str(), i)).collect())
+        .collect();
+
+    let same = |column: &str, a: &str, b: &str| {
+        if config.treat_empty_as_missing && is_missing(a, config) && is_missing(b, config) {
+            return true;
+        }
+        let (tolerance, ulps) = config.numeric_tolerance_for(column);
+        values_equal(a, b, tolerance, ulps, config.tolerance_mode)
+    };
+    let shown = |column: &str, value: &str| {
+        if config.redacts_column(column) { redact_value(value) } else { value.to_string() }
+    };
+    // A whole record, for changes that add or delete it
+    let describe = |side: usize, record: &Option<KeyedRecord>| {
+        record.as_ref().map(|r| {
+            columns
+                .iter()
+                .map(|c| format!("{}={}", c, shown(c, field_str(&r.record, &indices[side], c))))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+    };
+    let records_match = |(side1, rec1): (usize, &KeyedRecord), (side2, rec2): (usize, &KeyedRecord)| {
+        columns.iter().all(|c| {
+            same(c, field_str(&rec1.record, &indices[side1], c), field_str(&rec2.record, &indices[side2], c))
+        })
+    };
+
+    let mut result = ThreeWayResult {
+        base_path: base.path.display().to_string(),
+        left_path: left.path.display().to_string(),
+        right_path: right.path.display().to_string(),
+        ..ThreeWayResult::default()
+    };
+
+    // Three-way merge-join: take the smallest current key from every stream
+    // that holds it. Repeated keys are paired by position, as in two-way joins.
+    let mut current: Vec<Option<KeyedRecord>> = Vec::with_capacity(streams.len());
+    for stream in &mut streams {
+        current.push(stream.next().transpose()?);
+    }
+    let mut duplicates: [DuplicateKeys; 3] = Default::default();
+
+    while let Some(key) = current.iter().flatten().map(|r| &r.key).min().cloned() {
+        let mut records: [Option<KeyedRecord>; 3] = Default::default();
+        for side in 0..3 {
+            if current[side].as_ref().is_some_and(|r| r.key == key) {
+                let next = streams[side].next().transpose()?;
+                if next.as_ref().is_some_and(|n| n.key == key) {
+                    duplicates[side].note(&key);
+                }
+                records[side] = std::mem::replace(&mut current[side], next);
+            }
+        }
+
+        let whole = |records: &[Option<KeyedRecord>; 3]| ThreeWayChange {
+            key: key.clone(),
+            column: None,
+            base: describe(0, &records[0]),
+            left: describe(1, &records[1]),
+            right: describe(2, &records[2]),
+        };
+        match &records {
+            [Some(b), Some(l), Some(r)] => {
+                let mut changed = false;
+                for column in &columns {
+                    let (bv, lv, rv) = (
+                        field_str(&b.record, &indices[0], column),
+                        field_str(&l.record, &indices[1], column),
+                        field_str(&r.record, &indices[2], column),
+                    );
+                    let bucket = match (same(column, bv, lv), same(column, bv, rv)) {
+                        (true, true) => continue,
+                        (false, true) => &mut result.changed_in_left,
+                        (true, false) => &mut result.changed_in_right,
+                        (false, false) if same(column, lv, rv) => &mut result.changed_in_both,
+                        (false, false) => &mut result.conflicts,
+                    };
+                    changed = true;
+                    bucket.push(ThreeWayChange {
+                        key: key.clone(),
+                        column: Some(column.to_string()),
+                        base: Some(shown(column, bv)),
+                        left: Some(shown(column, lv)),
+                        right: Some(shown(column, rv)),
+                    });
+                }
+                if !changed {
+                    result.unchanged_records += 1;
+                }
+            }
+            // Deleted in one copy: a conflict if the other copy edited it
+            [Some(b), Some(l), None] if records_match((0, b), (1, l)) => result.changed_in_right.push(whole(&records)),
+            [Some(b), None, Some(r)] if records_match((0, b), (2, r)) => result.changed_in_left.push(whole(&records)),
+            [Some(_), None, None] => result.changed_in_both.push(whole(&records)),
+            [None, Some(_), None] => result.changed_in_left.push(whole(&records)),
+            [None, None, Some(_)] => result.changed_in_right.push(whole(&records)),
+            // Added in both copies: agreed if the records match
+            [None, Some(l), Some(r)] if records_match((1, l), (2, r)) => result.changed_in_both.push(whole(&records)),
+            _ => result.conflicts.push(whole(&records)),
+        }
+    }
+
+    for (side, file) in files.iter().enumerate() {
+        duplicates[side].report(&file.path, &diagnostics);
+    }
+    for anomaly in diagnostics.report().anomalies {
+        warn!("{}: {}", anomaly.path, anomaly.detail);
+    }
+    Ok(result)
+}
+
+/// Headers, record stream and type-inference sample of one side
+type OpenedRecords = (Vec<String>, RecordStream, Vec<ByteRecord>);
+
+/// Merge-join two opened record sets and summarize their differences
+///
+/// `paths` name the two sides in diagnostics. The result's linked ID, paths
+/// and sizes are left empty for the caller to fill in.
+fn join_records(
+    (headers1, mut stream1, sample1): OpenedRecords,
+    (mut headers2, mut stream2, sample2): OpenedRecords,
+    paths: (&Path, &Path),
+    config: &CompareConfig,
+    diagnostics: &Diagnostics,
 ) -> Result<StructuredComparisonResult> {
-    // Parse both files into sorted vectors based on file type
-    let (headers1, mut records1) = read_structured_records(&file1.path, &file1.file_type, &config.key_columns)?;
-    let (headers2, mut records2) = read_structured_records(&file2.path, &file2.file_type, &config.key_columns)?;
+    apply_column_aliases(&mut headers2, &config.column_aliases);
 
-    // Parallel sort by key (using rayon)
-    records1.par_sort_by(|a, b| a.key.cmp(&b.key));
-    records2.par_sort_by(|a, b| a.key.cmp(&b.key));
+    // Key columns are only excluded from field comparison when they match rows
+    let key_columns: &[String] = if config.positional { &[] } else { &config.key_columns };
 
     // Filter out ignored columns
     let ignored_cols: HashSet<&str> = config.ignore_columns.iter().map(|s| s.as_str()).collect();
@@ -69,18 +317,19 @@ pub fn compare_structured_files(
         .filter(|s| !ignored_cols.contains(s))
         .collect();
 
-    let common_columns: Vec<String> = columns1
-        .intersection(&columns2)
-        .map(|s| s.to_string())
-        .collect();
-    let columns_only_in_file1: Vec<String> = columns1
-        .difference(&columns2)
-        .map(|s| s.to_string())
-        .collect();
-    let columns_only_in_file2: Vec<String> = columns2
-        .difference(&columns1)
-        .map(|s| s.to_string())
-        .collect();
+    // Column lists follow header order so results are deterministic
+    let in_header_order = |headers: &[String], keep: &dyn Fn(&str) -> bool| -> Vec<String> {
+        let mut seen = HashSet::new();
+        headers
+            .iter()
+            .map(|s| s.as_str())
+            .filter(|c| keep(c) && seen.insert(*c))
+            .map(|s| s.to_string())
+            .collect()
+    };
+    let common_columns = in_header_order(&headers1, &|c| columns1.contains(c) && columns2.contains(c));
+    let mut columns_only_in_file1 = in_header_order(&headers1, &|c| columns1.contains(c) && !columns2.contains(c));
+    let mut columns_only_in_file2 = in_header_order(&headers2, &|c| columns2.contains(c) && !columns1.contains(c));
 
     // Build column index maps for fast field access
     let col_indices1: HashMap<&str, usize> = headers1
@@ -94,73 +343,224 @@ pub fn compare_structured_files(
         .map(|(i, h)| (h.as_str(), i))
         .collect();
 
-    // Merge-join: linear scan through both sorted vectors
-    let mut idx1 = 0;
-    let mut idx2 = 0;
+    // Infer each common column's type from the leading rows of both files
+    let column_types: HashMap<&str, ColumnType> = common_columns
+        .iter()
+        .map(|col| {
+            let values1 = sample1.iter().map(|rec| field_str(rec, &col_indices1, col));
+            let values2 = sample2.iter().map(|rec| field_str(rec, &col_indices2, col));
+            (col.as_str(), infer_column_type(values1.chain(values2)))
+        })
+        .collect();
+
     let mut common_count = 0;
     let mut only_in_file1_count = 0;
     let mut only_in_file2_count = 0;
     let mut field_mismatches: HashMap<String, Vec<FieldMismatch>> = HashMap::new();
+    let mut duplicate_keys: Vec<String> = Vec::new();
+    // With `treat_empty_as_missing`, one-sided columns holding a value in
+    // some matched record; the others are as good as absent
+    let mut filled_columns: HashSet<String> = HashSet::new();
+    // With `structured_patch`, every difference rather than the counts alone
+    let mut patch = config.structured_patch.then(StructuredPatch::default);
+    // With `profile_columns`, running aggregates of the common columns per file
+    let profiler = || ColumnProfiler::new(&common_columns, &column_types, config);
+    let mut profilers = config.profile_columns.then(|| (profiler(), profiler()));
 
-    while idx1 < records1.len() && idx2 < records2.len() {
-        let rec1 = &records1[idx1];
-        let rec2 = &records2[idx2];
+    // Compare field values of two matched records
+    let mut compare_fields = |key: &str, rec1: &ByteRecord, rec2: &ByteRecord| {
+        if config.treat_empty_as_missing {
+            let filled1 = columns_only_in_file1.iter().filter(|c| !is_missing(field_str(rec1, &col_indices1, c), config));
+            let filled2 = columns_only_in_file2.iter().filter(|c| !is_missing(field_str(rec2, &col_indices2, c), config));
+            for column in filled1.chain(filled2) {
+                if !filled_columns.contains(column) {
+                    filled_columns.insert(column.clone());
+                }
+            }
+        }
+
+        for col in &common_columns {
+            // Skip key columns in mismatch analysis
+            if key_columns.contains(col) {
+                continue;
+            }
 
-        match rec1.key.cmp(&rec2.key) {
-            Ordering::Equal => {
-                // Keys match - compare field values
-                common_count += 1;
+            let val1 = get_field_value(rec1, &col_indices1, col);
+            let val2 = get_field_value(rec2, &col_indices2, col);
+            if config.treat_empty_as_missing && is_missing(&val1, config) && is_missing(&val2, config) {
+                continue;
+            }
 
-                for col in &common_columns {
-                    // Skip key columns in mismatch analysis
-                    if config.key_columns.contains(col) {
-                        continue;
-                    }
+            let (tolerance, ulps) = config.numeric_tolerance_for(col);
+            let column_type = column_types.get(col.as_str()).copied().unwrap_or_default();
+            let column_type = if coerces(column_type, &config.type_coercion) { column_type } else { ColumnType::String };
+            if !typed_values_equal(&val1, &val2, column_type, (tolerance, ulps), config.tolerance_mode) {
+                let (value1, value2) = if config.redacts_column(col) {
+                    (redact_value(&val1), redact_value(&val2))
+                } else {
+                    (val1, val2)
+                };
+                if let Some(patch) = &mut patch {
+                    patch.updated.push(FieldUpdate {
+                        key: key.to_string(),
+                        column: col.clone(),
+                        from: value1.clone(),
+                        to: value2.clone(),
+                    });
+                }
+                field_mismatches.entry(col.clone()).or_default().push(FieldMismatch {
+                    key: key.to_string(),
+                    value1,
+                    value2,
+                });
+            }
+        }
+    };
 
-                    let val1 = get_field_value(&rec1.record, &col_indices1, col);
-                    let val2 = get_field_value(&rec2.record, &col_indices2, col);
+    // Removed and added keys, every one for the patch and otherwise up to
+    // `max_listed_keys`; the closure above holds `patch` until the join is done
+    let mut removed_keys = Vec::new();
+    let mut added_keys = Vec::new();
+    let record_key = |keys: &Vec<String>| config.structured_patch || keys.len() < config.max_listed_keys;
 
-                    if !values_equal(&val1, &val2, config.numeric_tolerance) {
-                        field_mismatches.entry(col.clone()).or_default().push(FieldMismatch {
-                            key: rec1.key.clone(),
-                            value1: val1,
-                            value2: val2,
-                        });
+    if config.positional {
+        // Row N of file 1 against row N of file 2; extra trailing rows are
+        // removals or additions
+        loop {
+            match (next_record(&mut stream1, paths.0)?, next_record(&mut stream2, paths.1)?) {
+                (Some(rec1), Some(rec2)) => {
+                    common_count += 1;
+                    compare_fields(&format!("row {}", common_count), &rec1.record, &rec2.record);
+                    if let Some((p1, p2)) = &mut profilers {
+                        p1.add(&rec1.record, &col_indices1);
+                        p2.add(&rec2.record, &col_indices2);
                     }
                 }
-
-                idx1 += 1;
-                idx2 += 1;
-            }
-            Ordering::Less => {
-                // Key only in file1
-                only_in_file1_count += 1;
-                idx1 += 1;
+                (Some(rec1), None) => {
+                    only_in_file1_count += 1;
+                    if let Some((p1, _)) = &mut profilers {
+                        p1.add(&rec1.record, &col_indices1);
+                    }
+                    if record_key(&removed_keys) {
+                        removed_keys.push(format!("row {}", common_count + only_in_file1_count));
+                    }
+                }
+                (None, Some(rec2)) => {
+                    only_in_file2_count += 1;
+                    if let Some((_, p2)) = &mut profilers {
+                        p2.add(&rec2.record, &col_indices2);
+                    }
+                    if record_key(&added_keys) {
+                        added_keys.push(format!("row {}", common_count + only_in_file2_count));
+                    }
+                }
+                (None, None) => break,
             }
-            Ordering::Greater => {
-                // Key only in file2
-                only_in_file2_count += 1;
-                idx2 += 1;
+        }
+    } else {
+        // Merge-join: linear scan through both sorted streams. The sort is
+        // stable, so rows sharing a key are paired by position within the
+        // key group, and surplus rows count as only in their file.
+        let mut duplicates1 = DuplicateKeys::default();
+        let mut duplicates2 = DuplicateKeys::default();
+        let mut rec1 = next_record(&mut stream1, paths.0)?;
+        let mut rec2 = next_record(&mut stream2, paths.1)?;
+
+        loop {
+            let ordering = match (&rec1, &rec2) {
+                (Some(r1), Some(r2)) => r1.key.cmp(&r2.key),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+
+            match ordering {
+                Ordering::Equal => {
+                    // Keys match - compare field values
+                    if let (Some(r1), Some(r2)) = (&rec1, &rec2) {
+                        common_count += 1;
+                        compare_fields(&r1.key, &r1.record, &r2.record);
+                        if let Some((p1, p2)) = &mut profilers {
+                            p1.add(&r1.record, &col_indices1);
+                            p2.add(&r2.record, &col_indices2);
+                        }
+                    }
+                    rec1 = advance(&mut stream1, paths.0, rec1, &mut duplicates1)?;
+                    rec2 = advance(&mut stream2, paths.1, rec2, &mut duplicates2)?;
+                }
+                Ordering::Less => {
+                    // Key only in file1
+                    only_in_file1_count += 1;
+                    if let Some(r1) = &rec1 {
+                        if record_key(&removed_keys) {
+                            removed_keys.push(r1.key.clone());
+                        }
+                        if let Some((p1, _)) = &mut profilers {
+                            p1.add(&r1.record, &col_indices1);
+                        }
+                    }
+                    rec1 = advance(&mut stream1, paths.0, rec1, &mut duplicates1)?;
+                }
+                Ordering::Greater => {
+                    // Key only in file2
+                    only_in_file2_count += 1;
+                    if let Some(r2) = &rec2 {
+                        if record_key(&added_keys) {
+                            added_keys.push(r2.key.clone());
+                        }
+                        if let Some((_, p2)) = &mut profilers {
+                            p2.add(&r2.record, &col_indices2);
+                        }
+                    }
+                    rec2 = advance(&mut stream2, paths.1, rec2, &mut duplicates2)?;
+                }
             }
         }
+
+        // Duplicate keys make merge-join matching ambiguous - surface them
+        duplicates1.report(paths.0, diagnostics);
+        duplicates2.report(paths.1, diagnostics);
+        duplicate_keys = duplicates1.keys;
+        duplicate_keys.extend(duplicates2.keys);
+        duplicate_keys.sort();
+        duplicate_keys.dedup();
+        duplicate_keys.truncate(MAX_DUPLICATE_KEYS);
+    }
+
+    let cap = config.max_listed_keys;
+    let only_in_keys_truncated = only_in_file1_count > cap || only_in_file2_count > cap;
+    let only_in_file1_keys: Vec<String> = removed_keys.iter().take(cap).cloned().collect();
+    let only_in_file2_keys: Vec<String> = added_keys.iter().take(cap).cloned().collect();
+    if let Some(patch) = &mut patch {
+        patch.removed_keys = removed_keys;
+        patch.added_keys = added_keys;
+    }
+
+    // A column only one file has, empty in every matched record, is no difference
+    if config.treat_empty_as_missing {
+        let filled = |c: &String| filled_columns.contains(c);
+        columns_only_in_file1.retain(filled);
+        columns_only_in_file2.retain(filled);
     }
 
-    // Count remaining records
-    only_in_file1_count += records1.len() - idx1;
-    only_in_file2_count += records2.len() - idx2;
+    // Every record is either matched or only on its own side
+    let file1_row_count = common_count + only_in_file1_count;
+    let file2_row_count = common_count + only_in_file2_count;
 
     // Build column mismatch summary
     let column_mismatches: Vec<ColumnMismatch> = common_columns
         .iter()
-        .filter(|col| !config.key_columns.contains(*col))
+        .filter(|col| !key_columns.contains(*col))
         .filter_map(|col| {
-            let mismatches = field_mismatches.get(col);
+            let mismatches = field_mismatches.remove(col);
             if let Some(m) = mismatches {
                 if !m.is_empty() {
                     return Some(ColumnMismatch {
                         column_name: col.clone(),
+                        inferred_type: column_types.get(col.as_str()).copied().unwrap_or_default(),
                         mismatch_count: m.len(),
                         sample_mismatches: m.iter().take(5).cloned().collect(),
+                        all_mismatches: if config.full_mismatch_detail { m } else { Vec::new() },
                     });
                 }
             }
@@ -170,31 +570,38 @@ pub fn compare_structured_files(
 
     let total_field_mismatches: usize = column_mismatches.iter().map(|c| c.mismatch_count).sum();
 
+    let column_profiles = match profilers {
+        Some((p1, p2)) => common_columns
+            .iter()
+            .zip(p1.finish().into_iter().zip(p2.finish()))
+            .map(|(col, (profile1, profile2))| ColumnProfileDiff::new(col.clone(), profile1, profile2))
+            .collect(),
+        None => Vec::new(),
+    };
+
     // Calculate similarity score using Jaccard-style formula
-    let total_unique = records1.len() + records2.len() - common_count;
+    let total_unique = file1_row_count + file2_row_count - common_count;
     let similarity_score = if total_unique > 0 {
         common_count as f64 / total_unique as f64
     } else {
         1.0
     };
 
-    // Create linked ID
-    let linked_id = format!(
-        "{}:{}",
-        &file1.content_hash[..16.min(file1.content_hash.len())],
-        &file2.content_hash[..16.min(file2.content_hash.len())]
-    );
-
+    // An added or removed column is a difference even when every shared value matches
     let identical = only_in_file1_count == 0
         && only_in_file2_count == 0
-        && total_field_mismatches == 0;
+        && total_field_mismatches == 0
+        && columns_only_in_file1.is_empty()
+        && columns_only_in_file2.is_empty();
 
     Ok(StructuredComparisonResult {
-        linked_id,
-        file1_path: file1.path.display().to_string(),
-        file2_path: file2.path.display().to_string(),
-        file1_row_count: records1.len(),
-        file2_row_count: records2.len(),
+        linked_id: String::new(),
+        file1_path: String::new(),
+        file2_path: String::new(),
+        file1_size: 0,
+        file2_size: 0,
+        file1_row_count,
+        file2_row_count,
         common_records: common_count,
         only_in_file1: only_in_file1_count,
         only_in_file2: only_in_file2_count,
@@ -204,167 +611,1018 @@ pub fn compare_structured_files(
         columns_only_in_file1,
         columns_only_in_file2,
         common_columns,
+        duplicate_keys,
         identical,
+        patch,
+        column_profiles,
+        only_in_file1_keys,
+        only_in_file2_keys,
+        only_in_keys_truncated,
+        inferred_key_columns: Vec::new(),
+        sampled: config.sample.is_some(),
+        sample_size: if config.sample.is_some() { file1_row_count + file2_row_count } else { 0 },
     })
 }
 
-/// Read structured records from a file based on its type
-///
-/// Dispatches to the appropriate reader (CSV/TSV or Excel) and returns
-/// a unified format of headers + keyed records.
-fn read_structured_records(
-    path: &Path,
-    file_type: &FileType,
-    key_columns: &[String],
-) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
-    match file_type {
-        FileType::Excel => parse_excel_into_sorted_vec(path, key_columns),
-        FileType::Csv | FileType::Tsv => {
-            let delimiter = get_delimiter(file_type);
-            parse_csv_into_sorted_vec(path, delimiter, key_columns)
+/// Records of one file, in the order they are compared
+enum RecordStream {
+    /// Parsed (and, when keyed, sorted) in memory
+    Memory(std::vec::IntoIter<KeyedRecord>),
+    /// Merged back from sorted runs spilled to disk (`--external-sort`)
+    External(SortedRuns),
+}
+
+impl Iterator for RecordStream {
+    type Item = Result<KeyedRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            RecordStream::Memory(records) => records.next().map(Ok),
+            RecordStream::External(runs) => runs
+                .next()
+                .map(|r| r.map(|(key, record)| KeyedRecord { key, record })),
         }
-        _ => anyhow::bail!("Unsupported file type for structured comparison: {:?}", file_type),
     }
 }
 
-/// Get the appropriate delimiter for a file type
-pub fn get_delimiter(file_type: &FileType) -> u8 {
-    match file_type {
-        FileType::Tsv => b'\t',
-        _ => b',',
+/// Open a file's records as a stream, sorted by key unless comparing by position
+///
+/// With `config.external_sort`, CSV/TSV records beyond the chunk size are
+/// sorted on disk so memory stays bounded; other formats are sorted in memory.
+/// Also returns the first [`TYPE_SAMPLE_ROWS`] records in file order, for
+/// column type inference.
+fn open_record_stream(
+    file: &FileEntry,
+    key_columns: &[String],
+    config: &CompareConfig,
+    diagnostics: &Diagnostics,
+) -> Result<OpenedRecords> {
+    match (config.external_sort, &file.file_type) {
+        (Some(chunk_bytes), FileType::Csv | FileType::Tsv) if !config.positional => {
+            let dialect = csv_dialect(file, config)?;
+            let mut spiller = RunSpiller::new(chunk_bytes);
+            let mut sample = Vec::new();
+            let mut spill = |rec: KeyedRecord| {
+                if sample.len() < TYPE_SAMPLE_ROWS {
+                    sample.push(rec.record.clone());
+                }
+                spiller.push(rec.key, rec.record)
+            };
+            let mut sampler = config.sample.map(|spec| RecordSampler::new(spec, false));
+            let headers = read_csv_records(&file.path, dialect, key_columns, config, diagnostics, |rec| {
+                match &mut sampler {
+                    Some(sampler) => sampler.offer(rec).map_or(Ok(()), &mut spill),
+                    None => spill(rec),
+                }
+            })?;
+            for rec in sampler.map(RecordSampler::finish).unwrap_or_default() {
+                spill(rec)?;
+            }
+            let runs = spiller.finish()?;
+            if runs.spilled_runs() > 0 {
+                debug!("Sorted {} on disk in {} run(s)", file.path.display(), runs.spilled_runs());
+            }
+            Ok((headers, RecordStream::External(runs), sample))
+        }
+        _ => {
+            let (headers, records) =
+                read_structured_records(file, key_columns, config, diagnostics)?;
+            let (stream, sample) = memory_stream(records, config);
+            Ok((headers, stream, sample))
+        }
     }
 }
 
-/// Parse a CSV/TSV file into a vector of keyed records (memory-efficient)
-///
-/// Returns headers and a vector of (key, ByteRecord) pairs ready for sorting.
-fn parse_csv_into_sorted_vec(
-    path: &Path,
-    delimiter: u8,
+/// Key in-memory rows the way parsed files are keyed, and open them as a stream
+fn records_in_memory(
+    headers: Vec<String>,
+    rows: impl IntoIterator<Item = Vec<String>>,
     key_columns: &[String],
-) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
-    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    config: &CompareConfig,
+) -> OpenedRecords {
+    let key_indices = resolve_key_indices(&headers, key_columns);
+    let records = rows
+        .into_iter()
+        .map(|row| {
+            let record = ByteRecord::from(row);
+            KeyedRecord {
+                key: build_composite_key(&record, &key_indices, config),
+                record,
+            }
+        })
+        .collect();
+    let (stream, sample) = memory_stream(records, config);
+    (headers, stream, sample)
+}
 
-    let mut reader = ReaderBuilder::new()
-        .delimiter(delimiter)
-        .has_headers(true)
-        .flexible(true)
-        .from_reader(file);
+/// Sort records by key unless comparing by position, keeping a sample in file order
+///
+/// With `config.sample`, only the sampled records are kept, before sorting.
+fn memory_stream(mut records: Vec<KeyedRecord>, config: &CompareConfig) -> (RecordStream, Vec<ByteRecord>) {
+    if let Some(spec) = config.sample {
+        let mut sampler = RecordSampler::new(spec, config.positional);
+        records = records.into_iter().filter_map(|rec| sampler.offer(rec)).collect();
+        records.extend(sampler.finish());
+    }
+    let sample = records.iter().take(TYPE_SAMPLE_ROWS).map(|r| r.record.clone()).collect();
+    if !config.positional {
+        // Parallel sort by key (using rayon)
+        records.par_sort_by(|a, b| a.key.cmp(&b.key));
+    }
+    (RecordStream::Memory(records.into_iter()), sample)
+}
 
-    // Get headers
-    let headers: Vec<String> = reader
-        .headers()
-        .with_context(|| format!("Failed to read headers from {}", path.display()))?
-        .iter()
-        .map(|s| s.to_string())
-        .collect();
+/// Take the next record from the stream of the file at `path`
+fn next_record(stream: &mut RecordStream, path: &Path) -> Result<Option<KeyedRecord>> {
+    stream.next().transpose().with_context(|| UnreadableFile(path.to_path_buf()))
+}
 
-    // Determine key column indices
-    let key_indices: Vec<usize> = if key_columns.is_empty() {
-        // Use first column as key by default
-        vec![0]
-    } else {
-        key_columns
-            .iter()
-            .filter_map(|k| headers.iter().position(|h| h == k))
-            .collect()
-    };
+/// Take the next record from a sorted stream, noting a repeat of the current key
+fn advance(
+    stream: &mut RecordStream,
+    path: &Path,
+    current: Option<KeyedRecord>,
+    duplicates: &mut DuplicateKeys,
+) -> Result<Option<KeyedRecord>> {
+    let next = next_record(stream, path)?;
+    if let (Some(current), Some(next)) = (&current, &next) {
+        if current.key == next.key {
+            duplicates.note(&next.key);
+        }
+    }
+    Ok(next)
+}
 
-    // Parse records into vector (no HashMap overhead!)
-    let mut records: Vec<KeyedRecord> = Vec::new();
+/// Most duplicated keys listed on a result; the anomaly still counts them all
+const MAX_DUPLICATE_KEYS: usize = 100;
 
-    for result in reader.byte_records() {
-        let record = result?;
+/// Duplicate keys seen while scanning a sorted record stream
+#[derive(Default)]
+struct DuplicateKeys {
+    count: usize,
+    /// Distinct duplicated keys in sorted order, up to `MAX_DUPLICATE_KEYS`
+    keys: Vec<String>,
+}
 
-        // Build composite key from key columns
-        let key: String = key_indices
-            .iter()
-            .filter_map(|&i| {
-                record.get(i).and_then(|bytes| std::str::from_utf8(bytes).ok())
-            })
-            .collect::<Vec<_>>()
-            .join("|");
+impl DuplicateKeys {
+    fn note(&mut self, key: &str) {
+        self.count += 1;
+        // The stream is sorted, so repeats of a key arrive together
+        if self.keys.len() < MAX_DUPLICATE_KEYS && self.keys.last().map(String::as_str) != Some(key) {
+            self.keys.push(key.to_string());
+        }
+    }
 
-        records.push(KeyedRecord { key, record });
+    /// Record a duplicate-key anomaly if any key appeared more than once
+    fn report(&self, path: &Path, diagnostics: &Diagnostics) {
+        if let Some(first) = self.keys.first() {
+            diagnostics.record(
+                AnomalyKind::DuplicateKeys,
+                path,
+                format!("{} duplicate key occurrence(s), e.g. '{}'", self.count, first),
+            );
+        }
     }
+}
 
-    Ok((headers, records))
+/// Picks the records `config.sample` keeps as they stream past in file order
+struct RecordSampler {
+    spec: SampleSpec,
+    /// Hash the row number instead of the key, for positional comparison
+    positional: bool,
+    seen: u64,
+    /// With [`SampleSpec::Random`], the records kept so far, largest hash on top
+    smallest: BinaryHeap<HashedRecord>,
 }
 
-/// Parse an Excel/OpenDocument file into a vector of keyed records
-///
-/// Uses calamine to read the first worksheet and converts rows into ByteRecords
-/// for compatibility with the CSV comparison engine.
-fn parse_excel_into_sorted_vec(
-    path: &Path,
-    key_columns: &[String],
-) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
-    // Open workbook using auto-detection
-    let mut workbook = open_workbook_auto(path)
-        .with_context(|| format!("Failed to open Excel file: {}", path.display()))?;
+/// A record held by [`RecordSampler`], ordered by key hash then file position
+struct HashedRecord {
+    hash: u64,
+    position: u64,
+    record: KeyedRecord,
+}
 
-    // Get sheet names
-    let sheet_names = workbook.sheet_names().to_vec();
-    if sheet_names.is_empty() {
-        return Ok((Vec::new(), Vec::new()));
+impl PartialEq for HashedRecord {
+    fn eq(&self, other: &Self) -> bool {
+        (self.hash, self.position) == (other.hash, other.position)
     }
+}
 
-    // Read first sheet
-    let first_sheet = &sheet_names[0];
-    let range = workbook
-        .worksheet_range(first_sheet)
-        .with_context(|| format!("Failed to read worksheet '{}' from {}", first_sheet, path.display()))?;
-
-    let mut rows = range.rows();
+impl Eq for HashedRecord {}
 
-    // Extract headers from first row
-    let headers: Vec<String> = match rows.next() {
-        Some(row) => row.iter().map(|cell| cell.to_string().trim().to_string()).collect(),
-        None => return Ok((Vec::new(), Vec::new())),
-    };
+impl PartialOrd for HashedRecord {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-    // Determine key column indices
-    let key_indices: Vec<usize> = if key_columns.is_empty() {
-        vec![0]
-    } else {
-        key_columns
-            .iter()
-            .filter_map(|k| headers.iter().position(|h| h == k))
-            .collect()
-    };
+impl Ord for HashedRecord {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.hash, self.position).cmp(&(other.hash, other.position))
+    }
+}
 
-    // Parse data rows into KeyedRecords
-    let mut records: Vec<KeyedRecord> = Vec::new();
+impl RecordSampler {
+    fn new(spec: SampleSpec, positional: bool) -> Self {
+        Self { spec, positional, seen: 0, smallest: BinaryHeap::new() }
+    }
 
-    for row in rows {
-        // Convert Excel row to ByteRecord
-        let mut byte_record = ByteRecord::new();
-        for cell in row.iter() {
-            let cell_str = excel_cell_to_string(cell);
-            byte_record.push_field(cell_str.as_bytes());
+    /// Offer the next record; returns it if it is kept right away
+    ///
+    /// [`SampleSpec::Random`] only knows which records it keeps at the end,
+    /// so it holds them until [`RecordSampler::finish`].
+    fn offer(&mut self, record: KeyedRecord) -> Option<KeyedRecord> {
+        self.seen += 1;
+        match self.spec {
+            SampleSpec::FirstN(n) => (self.seen <= n as u64).then_some(record),
+            SampleSpec::Fraction(fraction) => {
+                ((self.hash(&record, 0) as f64) < fraction * u64::MAX as f64).then_some(record)
+            }
+            SampleSpec::Random { n, seed } => {
+                let candidate = HashedRecord { hash: self.hash(&record, seed), position: self.seen, record };
+                if self.smallest.len() < n {
+                    self.smallest.push(candidate);
+                } else if self.smallest.peek().is_some_and(|largest| candidate < *largest) {
+                    self.smallest.pop();
+                    self.smallest.push(candidate);
+                }
+                None
+            }
         }
+    }
 
-        // Pad with empty fields if row is shorter than headers
-        while byte_record.len() < headers.len() {
-            byte_record.push_field(b"");
+    /// The records held back by [`SampleSpec::Random`], in file order
+    fn finish(self) -> Vec<KeyedRecord> {
+        let mut held = self.smallest.into_vec();
+        held.sort_by_key(|r| r.position);
+        held.into_iter().map(|r| r.record).collect()
+    }
+
+    fn hash(&self, record: &KeyedRecord, seed: u64) -> u64 {
+        if self.positional {
+            xxh3_64_with_seed(&self.seen.to_le_bytes(), seed)
+        } else {
+            xxh3_64_with_seed(record.key.as_bytes(), seed)
         }
+    }
+}
 
-        // Build composite key
-        let key: String = key_indices
+/// Running aggregates of the common columns of one file, for `config.profile_columns`
+struct ColumnProfiler<'a> {
+    columns: Vec<ColumnAggregate<'a>>,
+    config: &'a CompareConfig,
+}
+
+/// Running aggregates of one column
+struct ColumnAggregate<'a> {
+    name: &'a str,
+    /// Numeric statistics are kept for numeric, unredacted columns
+    numeric: bool,
+    count: usize,
+    null_count: usize,
+    distinct: DistinctCounter,
+    min: f64,
+    max: f64,
+    sum: f64,
+    numbers: usize,
+}
+
+impl<'a> ColumnProfiler<'a> {
+    fn new(columns: &'a [String], column_types: &HashMap<&str, ColumnType>, config: &'a CompareConfig) -> Self {
+        let columns = columns
             .iter()
-            .filter_map(|&i| {
-                byte_record.get(i).and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .map(|name| ColumnAggregate {
+                name,
+                numeric: matches!(column_types.get(name.as_str()), Some(ColumnType::Integer | ColumnType::Float))
+                    && !config.redacts_column(name),
+                count: 0,
+                null_count: 0,
+                distinct: DistinctCounter::default(),
+                min: f64::INFINITY,
+                max: f64::NEG_INFINITY,
+                sum: 0.0,
+                numbers: 0,
             })
-            .collect::<Vec<_>>()
-            .join("|");
-
-        records.push(KeyedRecord { key, record: byte_record });
+            .collect();
+        Self { columns, config }
     }
 
-    Ok((headers, records))
-}
+    fn add(&mut self, record: &ByteRecord, col_indices: &HashMap<&str, usize>) {
+        for column in &mut self.columns {
+            let value = field_str(record, col_indices, column.name);
+            column.count += 1;
+            if is_missing(value, self.config) {
+                column.null_count += 1;
+                continue;
+            }
+            column.distinct.insert(xxh3_64_with_seed(value.as_bytes(), 0));
+            if let Some(number) = column.numeric.then(|| parse_number(value)).flatten() {
+                column.min = column.min.min(number);
+                column.max = column.max.max(number);
+                column.sum += number;
+                column.numbers += 1;
+            }
+        }
+    }
 
-/// Convert an Excel cell to a string representation
+    fn finish(self) -> Vec<ColumnProfile> {
+        self.columns
+            .into_iter()
+            .map(|c| {
+                let has_numbers = c.numbers > 0;
+                ColumnProfile {
+                    count: c.count,
+                    null_count: c.null_count,
+                    distinct_count: c.distinct.count(),
+                    min: has_numbers.then_some(c.min),
+                    max: has_numbers.then_some(c.max),
+                    mean: has_numbers.then(|| c.sum / c.numbers as f64),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Distinct values counted exactly up to [`DistinctCounter::EXACT_LIMIT`]
+///
+/// Past the limit the value hashes are folded into a HyperLogLog sketch of
+/// 2^[`DistinctCounter::PRECISION`] registers, so memory stays fixed per
+/// column and the count becomes an estimate (typically within 2%).
+enum DistinctCounter {
+    Exact(HashSet<u64>),
+    Sketch(Box<[u8]>),
+}
+
+impl Default for DistinctCounter {
+    fn default() -> Self {
+        Self::Exact(HashSet::new())
+    }
+}
+
+impl DistinctCounter {
+    const PRECISION: u32 = 12;
+    const EXACT_LIMIT: usize = 1 << Self::PRECISION;
+
+    fn insert(&mut self, hash: u64) {
+        match self {
+            Self::Exact(hashes) => {
+                hashes.insert(hash);
+                if hashes.len() > Self::EXACT_LIMIT {
+                    let mut registers = vec![0u8; 1 << Self::PRECISION].into_boxed_slice();
+                    for &hash in hashes.iter() {
+                        Self::record(&mut registers, hash);
+                    }
+                    *self = Self::Sketch(registers);
+                }
+            }
+            Self::Sketch(registers) => Self::record(registers, hash),
+        }
+    }
+
+    /// Keep, per register chosen by the top bits, the longest run of leading zeros seen
+    fn record(registers: &mut [u8], hash: u64) {
+        let index = (hash >> (64 - Self::PRECISION)) as usize;
+        let rest = hash << Self::PRECISION;
+        let rank = (rest.leading_zeros() + 1).min(64 - Self::PRECISION + 1) as u8;
+        registers[index] = registers[index].max(rank);
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            Self::Exact(hashes) => hashes.len(),
+            Self::Sketch(registers) => {
+                let m = registers.len() as f64;
+                let sum: f64 = registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+                let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+                let empty = registers.iter().filter(|&&r| r == 0).count();
+                if estimate <= 2.5 * m && empty > 0 {
+                    // Linear counting is more accurate for small cardinalities
+                    (m * (m / empty as f64).ln()).round() as usize
+                } else {
+                    estimate.round() as usize
+                }
+            }
+        }
+    }
+}
+
+/// Read structured records from a file based on its type
+///
+/// Dispatches to the appropriate reader (CSV/TSV, Excel, JSON or fixed-width)
+/// and returns a unified format of headers + keyed records. Key fields are
+/// normalized with `config.key_normalization`. With `config.excel_formulas`,
+/// Excel formula cells yield their formula instead of their cached value.
+fn read_structured_records(
+    file: &FileEntry,
+    key_columns: &[String],
+    config: &CompareConfig,
+    diagnostics: &Diagnostics,
+) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
+    let path = file.path.as_path();
+    match file.file_type {
+        FileType::Excel => parse_excel_into_sorted_vec(path, key_columns, config),
+        FileType::Json => parse_json_into_sorted_vec(path, key_columns, config),
+        FileType::FixedWidth => {
+            let headers = config.fixed_width_headers();
+            parse_fixed_width_into_sorted_vec(path, &config.fixed_width, headers, key_columns, config)
+        }
+        FileType::Csv | FileType::Tsv => {
+            parse_csv_into_sorted_vec(path, csv_dialect(file, config)?, key_columns, config, diagnostics)
+        }
+        _ => anyhow::bail!("Unsupported file type for structured comparison: {:?}", file.file_type),
+    }
+}
+
+/// Mask a field value for redacted output
+///
+/// Empty cells stay empty so "missing vs. present" changes remain visible.
+fn redact_value(value: &str) -> String {
+    if value.is_empty() {
+        String::new()
+    } else {
+        REDACTED.to_string()
+    }
+}
+
+/// Rename file 2 headers onto their file 1 equivalents
+fn apply_column_aliases(headers: &mut [String], aliases: &HashMap<String, String>) {
+    for header in headers.iter_mut() {
+        if let Some(alias) = aliases.get(header) {
+            *header = alias.clone();
+        }
+    }
+}
+
+/// Translate file 1 column names back to the file 2 names they alias
+fn unalias_columns(columns: &[String], aliases: &HashMap<String, String>) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| {
+            aliases
+                .iter()
+                .find(|(_, file1_name)| *file1_name == column)
+                .map(|(file2_name, _)| file2_name.clone())
+                .unwrap_or_else(|| column.clone())
+        })
+        .collect()
+}
+
+/// Get the appropriate delimiter for a file type
+pub fn get_delimiter(file_type: &FileType) -> u8 {
+    match file_type {
+        FileType::Tsv => b'\t',
+        _ => b',',
+    }
+}
+
+/// The dialect to read a CSV/TSV file with
+///
+/// Uses the dialect detected while indexing, with `config.csv_delimiter`
+/// taking precedence over the detected delimiter. Fails if either character
+/// is not ASCII, as the csv reader splits on single bytes.
+fn csv_dialect(file: &FileEntry, config: &CompareConfig) -> Result<CsvDialect> {
+    let dialect = file.dialect.unwrap_or_else(|| CsvDialect::for_file_type(&file.file_type));
+    CsvDialect::new(config.csv_delimiter.unwrap_or(dialect.delimiter), dialect.quote)
+        .with_context(|| format!("Cannot read {}", file.path.display()))
+}
+
+/// Parse a CSV/TSV file into a vector of keyed records (memory-efficient)
+///
+/// Returns headers and a vector of (key, ByteRecord) pairs ready for sorting.
+fn parse_csv_into_sorted_vec(
+    path: &Path,
+    dialect: CsvDialect,
+    key_columns: &[String],
+    config: &CompareConfig,
+    diagnostics: &Diagnostics,
+) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
+    // Parse records into vector (no HashMap overhead!)
+    let mut records: Vec<KeyedRecord> = Vec::new();
+    let headers = read_csv_records(path, dialect, key_columns, config, diagnostics, |rec| {
+        records.push(rec);
+        Ok(())
+    })?;
+    Ok((headers, records))
+}
+
+/// Read a CSV/TSV file, passing each keyed record to `sink` in file order
+///
+/// Returns the headers. Rows whose field count differs from the header are
+/// still passed on, but reported to `diagnostics` as ragged. Lines starting
+/// with `config.comment_char` are skipped, and how many were skipped is logged.
+///
+/// Without `config.has_header`, the first line is a record like any other
+/// and the columns are named by position: `col_0`, `col_1`, ...
+fn read_csv_records(
+    path: &Path,
+    dialect: CsvDialect,
+    key_columns: &[String],
+    config: &CompareConfig,
+    diagnostics: &Diagnostics,
+    mut sink: impl FnMut(KeyedRecord) -> Result<()>,
+) -> Result<Vec<String>> {
+    let (mut reader, headers) = open_csv_reader(path, dialect, config)?;
+
+    // Determine key column indices
+    let key_indices = resolve_key_indices(&headers, key_columns);
+
+    let mut ragged_rows = 0;
+
+    for result in reader.byte_records() {
+        let record = result.with_context(|| format!("Failed to parse a record in {}", path.display()))?;
+
+        if record.len() != headers.len() {
+            ragged_rows += 1;
+        }
+
+        // Build composite key from key columns
+        let key = build_composite_key(&record, &key_indices, config);
+
+        sink(KeyedRecord { key, record })?;
+    }
+
+    if ragged_rows > 0 {
+        diagnostics.record(
+            AnomalyKind::RaggedRows,
+            path,
+            format!("{} row(s) do not have {} fields like the header", ragged_rows, headers.len()),
+        );
+    }
+
+    let comment_lines = reader.into_inner().lines;
+    if comment_lines > 0 {
+        info!("Skipped {} comment line(s) in {}", comment_lines, path.display());
+    }
+
+    Ok(headers)
+}
+
+/// CSV reader over a (possibly gzipped) input, counting comment lines
+type CsvReader = csv::Reader<CommentCounter<Box<dyn std::io::Read + Send>>>;
+
+/// Open a CSV/TSV file and read its header row
+///
+/// Without a header row, csv returns the first record as the header (and
+/// still yields it as a record), so only its width is used and the columns
+/// are named `col_0`, `col_1`, ...
+fn open_csv_reader(
+    path: &Path,
+    dialect: CsvDialect,
+    config: &CompareConfig,
+) -> Result<(CsvReader, Vec<String>)> {
+    let file = open_input(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let comment = config.comment_char;
+
+    // CsvDialect::new only accepts ASCII, so the casts keep the whole character
+    let mut reader = ReaderBuilder::new()
+        .delimiter(dialect.delimiter as u8)
+        .quote(dialect.quote as u8)
+        .has_headers(config.has_header)
+        .flexible(true)
+        .comment(comment)
+        .from_reader(CommentCounter::new(file, comment));
+
+    let first_row = reader
+        .headers()
+        .with_context(|| format!("Failed to read headers from {}", path.display()))?;
+    let headers: Vec<String> = if config.has_header {
+        first_row.iter().map(|s| s.to_string()).collect()
+    } else {
+        positional_columns(first_row.len())
+    };
+    Ok((reader, headers))
+}
+
+/// Column names for a file without a header row: `col_0`, `col_1`, ...
+pub(crate) fn positional_columns(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("col_{}", i)).collect()
+}
+
+/// Reader adapter counting the lines that start with a comment byte
+///
+/// The csv crate skips comment lines silently, so this counts them on the way
+/// through. A quoted field spanning lines whose continuation starts with the
+/// comment byte is counted too, although csv keeps it as data.
+struct CommentCounter<R> {
+    inner: R,
+    comment: Option<u8>,
+    at_line_start: bool,
+    lines: usize,
+}
+
+impl<R> CommentCounter<R> {
+    fn new(inner: R, comment: Option<u8>) -> Self {
+        Self { inner, comment, at_line_start: true, lines: 0 }
+    }
+}
+
+impl<R: std::io::Read> std::io::Read for CommentCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(comment) = self.comment {
+            for &byte in &buf[..n] {
+                if self.at_line_start && byte == comment {
+                    self.lines += 1;
+                }
+                self.at_line_start = byte == b'\n';
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Parse an Excel/OpenDocument file into a vector of keyed records
+///
+/// Uses calamine to read the first worksheet and converts rows into ByteRecords
+/// for compatibility with the CSV comparison engine. With `config.excel_formulas`,
+/// cells holding a formula are read as `=FORMULA` rather than the cached result.
+fn parse_excel_into_sorted_vec(
+    path: &Path,
+    key_columns: &[String],
+    config: &CompareConfig,
+) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
+    // Open workbook using auto-detection
+    let mut workbook = open_workbook_auto(resolve_entry_path(path))
+        .with_context(|| format!("Failed to open Excel file: {}", path.display()))?;
+
+    // Get sheet names
+    let sheet_names = workbook.sheet_names().to_vec();
+    if sheet_names.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    // Read first sheet
+    let first_sheet = &sheet_names[0];
+    let range = workbook
+        .worksheet_range(first_sheet)
+        .with_context(|| format!("Failed to read worksheet '{}' from {}", first_sheet, path.display()))?;
+
+    // Formula strings, positioned independently of the value range
+    let formula_range = if config.excel_formulas {
+        match workbook.worksheet_formula(first_sheet) {
+            Ok(range) => Some(range),
+            Err(e) => {
+                warn!("Cannot read formulas from {}, comparing values: {}", path.display(), e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let (start_row, start_col) = range.start().unwrap_or((0, 0));
+
+    let mut rows = range.rows();
+
+    // Extract headers from first row
+    let headers: Vec<String> = match rows.next() {
+        Some(row) => row.iter().map(|cell| cell.to_string().trim().to_string()).collect(),
+        None => return Ok((Vec::new(), Vec::new())),
+    };
+
+    // Determine key column indices
+    let key_indices = resolve_key_indices(&headers, key_columns);
+
+    // Parse data rows into KeyedRecords
+    let mut records: Vec<KeyedRecord> = Vec::new();
+
+    for (row_idx, row) in rows.enumerate() {
+        // Convert Excel row to ByteRecord
+        let mut byte_record = ByteRecord::new();
+        for (col_idx, cell) in row.iter().enumerate() {
+            // A formula cell is compared by its formula, any other by its value
+            let position = (start_row + row_idx as u32 + 1, start_col + col_idx as u32);
+            let cell_str = match formula_range.as_ref().and_then(|f| f.get_value(position)) {
+                Some(formula) if !formula.is_empty() => format!("={}", formula),
+                _ => excel_cell_to_string(cell),
+            };
+            byte_record.push_field(cell_str.as_bytes());
+        }
+
+        // Pad with empty fields if row is shorter than headers
+        while byte_record.len() < headers.len() {
+            byte_record.push_field(b"");
+        }
+
+        // Build composite key
+        let key = build_composite_key(&byte_record, &key_indices, config);
+
+        records.push(KeyedRecord { key, record: byte_record });
+    }
+
+    Ok((headers, records))
+}
+
+/// Parse a JSON array of objects or an NDJSON file into a vector of keyed records
+///
+/// Each top-level object is flattened into a pseudo-record whose columns are
+/// dotted JSON paths. The header is the union of all paths in first-seen order;
+/// records missing a path get an empty field, just like a short Excel row.
+fn parse_json_into_sorted_vec(
+    path: &Path,
+    key_columns: &[String],
+    config: &CompareConfig,
+) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
+    let rows = read_json_rows(path)?;
+
+    // Flatten every row and collect the union of column paths
+    let mut headers: Vec<String> = Vec::new();
+    let mut header_index: HashMap<String, usize> = HashMap::new();
+    let flattened: Vec<Vec<(String, String)>> = rows
+        .iter()
+        .map(|row| {
+            let fields = flatten_json_record(row);
+            for (column, _) in &fields {
+                if !header_index.contains_key(column) {
+                    header_index.insert(column.clone(), headers.len());
+                    headers.push(column.clone());
+                }
+            }
+            fields
+        })
+        .collect();
+
+    let key_indices = resolve_key_indices(&headers, key_columns);
+
+    let mut records: Vec<KeyedRecord> = Vec::with_capacity(flattened.len());
+    for fields in flattened {
+        let mut values = vec![String::new(); headers.len()];
+        for (column, value) in fields {
+            values[header_index[&column]] = value;
+        }
+
+        let mut byte_record = ByteRecord::new();
+        for value in &values {
+            byte_record.push_field(value.as_bytes());
+        }
+
+        let key = build_composite_key(&byte_record, &key_indices, config);
+        records.push(KeyedRecord { key, record: byte_record });
+    }
+
+    Ok((headers, records))
+}
+
+/// Parse a fixed-width text file into a vector of keyed records
+///
+/// Each non-blank line is sliced into fields by the `[start, end)` character
+/// ranges in `layout`; fields are trimmed of their space padding, and ranges
+/// past the end of a short line yield empty fields.
+fn parse_fixed_width_into_sorted_vec(
+    path: &Path,
+    layout: &[(usize, usize)],
+    headers: Vec<String>,
+    key_columns: &[String],
+    config: &CompareConfig,
+) -> Result<(Vec<String>, Vec<KeyedRecord>)> {
+    let (text, _) = read_text_decoded(path)?;
+    let key_indices = resolve_key_indices(&headers, key_columns);
+
+    let mut records: Vec<KeyedRecord> = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut byte_record = ByteRecord::new();
+        for &(start, end) in layout {
+            let field: String = chars[start.min(chars.len())..end.min(chars.len())].iter().collect();
+            byte_record.push_field(field.trim().as_bytes());
+        }
+
+        let key = build_composite_key(&byte_record, &key_indices, config);
+        records.push(KeyedRecord { key, record: byte_record });
+    }
+
+    Ok((headers, records))
+}
+
+/// Read the top-level rows of a JSON or NDJSON file
+///
+/// Accepts a JSON array (each element is a row), a single JSON object (one row),
+/// or newline-delimited JSON where every non-empty line is a row.
+pub(crate) fn read_json_rows(path: &Path) -> Result<Vec<Value>> {
+    let content = read_input_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Array(items)) => Ok(items),
+        Ok(value) => Ok(vec![value]),
+        Err(_) => content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str::<Value>(line).with_context(|| {
+                    format!("Invalid JSON on line {} of {}", idx + 1, path.display())
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Flatten a JSON value into `(path, value)` pairs
+///
+/// Nested objects use dotted paths (`user.address.city`) and arrays use indexed
+/// paths (`items.0.sku`). A scalar at the top level is stored under `value`.
+pub(crate) fn flatten_json_record(value: &Value) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    match value {
+        Value::Object(_) | Value::Array(_) => flatten_json_into(value, "", &mut fields),
+        scalar => fields.push(("value".to_string(), json_scalar_to_string(scalar))),
+    }
+    fields
+}
+
+fn flatten_json_into(value: &Value, prefix: &str, fields: &mut Vec<(String, String)>) {
+    let join = |segment: &str| {
+        if prefix.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{}.{}", prefix, segment)
+        }
+    };
+
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (name, child) in map {
+                flatten_json_into(child, &join(name), fields);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (idx, child) in items.iter().enumerate() {
+                flatten_json_into(child, &join(&idx.to_string()), fields);
+            }
+        }
+        other => fields.push((prefix.to_string(), json_scalar_to_string(other))),
+    }
+}
+
+/// Convert a JSON leaf to a string representation
+///
+/// `null` is kept distinct from a missing path (which becomes an empty field),
+/// and empty containers are preserved as `{}`/`[]` so they are not silently dropped.
+fn json_scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// Resolve key column names to header indices (first column if none given)
+fn resolve_key_indices(headers: &[String], key_columns: &[String]) -> Vec<usize> {
+    if key_columns.is_empty() {
+        // Use first column as key by default
+        vec![0]
+    } else {
+        key_columns
+            .iter()
+            .filter_map(|k| headers.iter().position(|h| h == k))
+            .collect()
+    }
+}
+
+/// Rows of file 1 profiled to infer key columns
+const AUTO_KEY_SAMPLE_ROWS: usize = 10_000;
+
+/// Leading columns considered for keys of two or three columns
+const AUTO_KEY_MAX_COMBINED: usize = 12;
+
+/// Headers and leading rows of a structured file, for key inference
+///
+/// CSV/TSV files are only read up to [`AUTO_KEY_SAMPLE_ROWS`]; other formats
+/// are parsed whole and then cut down.
+fn sample_rows(file: &FileEntry, config: &CompareConfig) -> Result<(Vec<String>, Vec<ByteRecord>)> {
+    match file.file_type {
+        FileType::Csv | FileType::Tsv => {
+            let (mut reader, headers) = open_csv_reader(&file.path, csv_dialect(file, config)?, config)?;
+            let rows = reader
+                .byte_records()
+                .take(AUTO_KEY_SAMPLE_ROWS)
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("Failed to parse a record in {}", file.path.display()))?;
+            Ok((headers, rows))
+        }
+        _ => {
+            let (headers, records) = read_structured_records(file, &[], config, &Diagnostics::new())?;
+            Ok((headers, records.into_iter().take(AUTO_KEY_SAMPLE_ROWS).map(|r| r.record).collect()))
+        }
+    }
+}
+
+/// Pick key columns that identify every row of `rows`
+///
+/// Returns the fewest columns whose combined values are unique and never
+/// empty: one column if any qualifies, else two, else three of the first
+/// [`AUTO_KEY_MAX_COMBINED`] columns. Columns named like an identifier
+/// (`id`, `key`, `uuid`, `customer_id`) are tried first, then the rest in
+/// file order. Falls back to the first column when nothing is unique.
+pub fn infer_key_columns(headers: &[String], rows: &[ByteRecord], ignore_columns: &[String]) -> Vec<String> {
+    let mut candidates: Vec<usize> = (0..headers.len())
+        .filter(|&i| !ignore_columns.contains(&headers[i]))
+        .filter(|&i| rows.iter().all(|row| row.get(i).is_some_and(|v| !v.is_empty())))
+        .collect();
+    candidates.sort_by_key(|&i| !looks_like_identifier(&headers[i]));
+
+    let unique = |columns: &[usize]| {
+        let mut seen = HashSet::with_capacity(rows.len());
+        rows.iter().all(|row| seen.insert(columns.iter().map(|&i| row.get(i)).collect::<Vec<_>>()))
+    };
+    let combined = &candidates[..candidates.len().min(AUTO_KEY_MAX_COMBINED)];
+    let found = candidates
+        .iter()
+        .map(|&a| vec![a])
+        .find(|set| unique(set))
+        .or_else(|| {
+            combined
+                .iter()
+                .enumerate()
+                .flat_map(|(n, &a)| combined[n + 1..].iter().map(move |&b| vec![a, b]))
+                .find(|set| unique(set))
+        })
+        .or_else(|| {
+            combined
+                .iter()
+                .enumerate()
+                .flat_map(|(n, &a)| combined[n + 1..].iter().enumerate().map(move |(m, &b)| (n + 1 + m, a, b)))
+                .flat_map(|(m, a, b)| combined[m + 1..].iter().map(move |&c| vec![a, b, c]))
+                .find(|set| unique(set))
+        });
+
+    match found {
+        Some(set) => set.into_iter().map(|i| headers[i].clone()).collect(),
+        None => headers.first().cloned().into_iter().collect(),
+    }
+}
+
+/// True for column names like `id`, `Key`, `order_id`, `customerId` or `UUID`
+fn looks_like_identifier(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    [("id", "Id"), ("key", "Key"), ("uuid", "Uuid"), ("guid", "Guid")]
+        .iter()
+        .any(|(word, camel)| {
+            lower == *word
+                || lower.ends_with(&format!("_{}", word))
+                || lower.ends_with(&format!(" {}", word))
+                || name.ends_with(camel)
+        })
+}
+
+/// Build a composite key (e.g. "id1|id2") from the given field indices
+///
+/// Each field is normalized on its own with `config.key_normalization`, so
+/// `numeric` applies per key column. Fields are joined with
+/// `config.key_delimiter`; in keys of several columns, backslashes and
+/// delimiter characters inside a field are escaped with a backslash, so
+/// `("a|b", "c")` and `("a", "b|c")` stay distinct.
+fn build_composite_key(record: &ByteRecord, key_indices: &[usize], config: &CompareConfig) -> String {
+    let fields = key_indices
+        .iter()
+        .filter_map(|&i| {
+            record.get(i).and_then(|bytes| std::str::from_utf8(bytes).ok())
+        })
+        .map(|field| normalize_key_field(field, &config.key_normalization));
+    if key_indices.len() < 2 {
+        return fields.collect();
+    }
+
+    let delimiter = &config.key_delimiter;
+    let escape = |field: String| {
+        let mut escaped = String::with_capacity(field.len());
+        for c in field.chars() {
+            if c == '\\' || delimiter.contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    fields.map(escape).collect::<Vec<_>>().join(delimiter)
+}
+
+/// Normalize one key field so formatting differences do not split records
+///
+/// Numeric normalization reformats anything that parses as a number, so
+/// ` 007`, `7` and `7.0` all become `7`; other values are left as they are.
+fn normalize_key_field(field: &str, normalization: &KeyNormalization) -> String {
+    let mut value = if normalization.trim { field.trim() } else { field }.to_string();
+    if normalization.numeric {
+        let trimmed = value.trim();
+        if let Ok(n) = trimmed.parse::<i128>() {
+            value = n.to_string();
+        } else if let Ok(n) = trimmed.parse::<f64>() {
+            if n.is_finite() {
+                value = n.to_string();
+            }
+        }
+    }
+    if normalization.case_insensitive {
+        value = value.to_lowercase();
+    }
+    value
+}
+
+/// Convert an Excel cell to a string representation
 ///
 /// Handles different data types appropriately for comparison:
 /// - Numbers: Full precision (no artificial rounding)
@@ -394,16 +1652,67 @@ fn excel_cell_to_string(cell: &Data) -> String {
 
 /// Get a field value from a ByteRecord by column name
 fn get_field_value(record: &ByteRecord, col_indices: &HashMap<&str, usize>, col_name: &str) -> String {
+    field_str(record, col_indices, col_name).to_string()
+}
+
+/// Borrow a field value from a ByteRecord by column name
+fn field_str<'a>(record: &'a ByteRecord, col_indices: &HashMap<&str, usize>, col_name: &str) -> &'a str {
     col_indices
         .get(col_name)
         .and_then(|&idx| record.get(idx))
         .and_then(|bytes| std::str::from_utf8(bytes).ok())
         .unwrap_or("")
-        .to_string()
+}
+
+/// Returns true if `value` counts as missing under `config.treat_empty_as_missing`:
+/// empty, or one of `config.null_tokens` (ignoring ASCII case)
+fn is_missing(value: &str, config: &CompareConfig) -> bool {
+    value.is_empty() || config.null_tokens.iter().any(|token| token.eq_ignore_ascii_case(value))
+}
+
+/// Check if two values of a column of `column_type` are equal
+///
+/// Booleans are case-folded, numbers may carry currency symbols and thousands
+/// separators, and dates may be written in any recognized format. Values that
+/// do not parse as the column's type fall back to [`values_equal`].
+fn typed_values_equal(
+    val1: &str,
+    val2: &str,
+    column_type: ColumnType,
+    (tolerance, ulps): (f64, Option<u64>),
+    mode: ToleranceMode,
+) -> bool {
+    if val1 == val2 {
+        return true;
+    }
+
+    match column_type {
+        ColumnType::Boolean => {
+            if let (Some(b1), Some(b2)) = (parse_bool(val1), parse_bool(val2)) {
+                return b1 == b2;
+            }
+        }
+        ColumnType::Integer | ColumnType::Float => {
+            if let (Some(n1), Some(n2)) = (parse_number(val1), parse_number(val2)) {
+                return numbers_equal(n1, n2, tolerance, ulps, mode);
+            }
+        }
+        ColumnType::Date => {
+            if let (Some(d1), Some(d2)) = (parse_date(val1), parse_date(val2)) {
+                return d1 == d2;
+            }
+        }
+        ColumnType::String => {}
+    }
+
+    values_equal(val1, val2, tolerance, ulps, mode)
 }
 
 /// Check if two string values are equal, with numeric tolerance support
-fn values_equal(val1: &str, val2: &str, tolerance: f64) -> bool {
+///
+/// Numbers are compared within `ulps` units in the last place when set,
+/// otherwise within `tolerance` as selected by `mode`.
+fn values_equal(val1: &str, val2: &str, tolerance: f64, ulps: Option<u64>, mode: ToleranceMode) -> bool {
     // Direct string comparison first
     if val1 == val2 {
         return true;
@@ -411,34 +1720,898 @@ fn values_equal(val1: &str, val2: &str, tolerance: f64) -> bool {
 
     // Try numeric comparison with tolerance
     if let (Ok(n1), Ok(n2)) = (val1.parse::<f64>(), val2.parse::<f64>()) {
-        let diff = (n1 - n2).abs();
-        let max_val = n1.abs().max(n2.abs());
+        return numbers_equal(n1, n2, tolerance, ulps, mode);
+    }
 
-        // Absolute tolerance for small numbers
-        if diff <= tolerance {
-            return true;
-        }
+    false
+}
 
-        // Relative tolerance for larger numbers
-        if max_val > 0.0 && diff / max_val <= tolerance {
-            return true;
-        }
+/// Check if two numbers are equal within `ulps`, or else within `tolerance`
+fn numbers_equal(n1: f64, n2: f64, tolerance: f64, ulps: Option<u64>, mode: ToleranceMode) -> bool {
+    if let Some(max_ulps) = ulps {
+        return ulp_distance(n1, n2) <= max_ulps;
     }
 
-    false
+    let diff = (n1 - n2).abs();
+    let max_val = n1.abs().max(n2.abs());
+    let absolute = diff <= tolerance;
+    let relative = max_val > 0.0 && diff / max_val <= tolerance;
+
+    match mode {
+        ToleranceMode::Absolute => absolute,
+        // Identical zeros have no relative difference to speak of
+        ToleranceMode::Relative => relative || diff == 0.0,
+        // Absolute tolerance for small numbers, relative for larger ones
+        ToleranceMode::Either => absolute || relative,
+    }
+}
+
+/// Number of representable `f64` values between `a` and `b`
+///
+/// Maps the bit patterns onto a monotonic integer line so the distance is
+/// correct across zero (`-0.0` and `0.0` are 0 apart). NaN is never close.
+fn ulp_distance(a: f64, b: f64) -> u64 {
+    if a.is_nan() || b.is_nan() {
+        return u64::MAX;
+    }
+    let ordered = |x: f64| {
+        let bits = x.to_bits() as i64;
+        if bits < 0 { i64::MIN.wrapping_sub(bits) } else { bits }
+    };
+    ordered(a).abs_diff(ordered(b))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::{index_single_file, index_single_file_with, IndexOptions};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn write_entry(dir: &Path, name: &str, content: &str) -> FileEntry {
+        let path: PathBuf = dir.join(name);
+        fs::write(&path, content).unwrap();
+        index_single_file(&path).unwrap()
+    }
+
+    fn key_config(keys: &[&str]) -> CompareConfig {
+        CompareConfig {
+            key_columns: keys.iter().map(|k| k.to_string()).collect(),
+            ..CompareConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_three_way_classifies_each_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = write_entry(dir.path(), "base.csv", "id,name,qty\n1,Ann,1\n2,Bob,2\n3,Cy,3\n4,Di,4\n5,Ed,5\n6,Flo,6\n");
+        // 2: left edits qty; 3: both edit qty differently; 4: both make the same edit;
+        // 5: left deletes; 6: left deletes while right edits; 7: left adds
+        let left = write_entry(dir.path(), "left.csv", "id,name,qty\n1,Ann,1\n2,Bob,20\n3,Cy,30\n4,Dee,4\n7,Gus,7\n");
+        // 1: right edits name
+        let right = write_entry(dir.path(), "right.csv", "id,name,qty\n1,Anne,1\n2,Bob,2\n3,Cy,31\n4,Dee,4\n5,Ed,5\n6,Flo,60\n");
+
+        let result = compare_three_way(&base, &left, &right, &key_config(&["id"])).unwrap();
+
+        let keys = |changes: &[ThreeWayChange]| changes.iter().map(|c| c.key.clone()).collect::<Vec<_>>();
+        assert_eq!(result.unchanged_records, 0);
+        assert_eq!(keys(&result.changed_in_left), vec!["2", "5", "7"]);
+        assert_eq!(keys(&result.changed_in_right), vec!["1"]);
+        assert_eq!(keys(&result.changed_in_both), vec!["4"]);
+        assert_eq!(keys(&result.conflicts), vec!["3", "6"]);
+
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.column.as_deref(), Some("qty"));
+        assert_eq!((conflict.left.as_deref(), conflict.right.as_deref()), (Some("30"), Some("31")));
+        let deleted = &result.changed_in_left[1];
+        assert_eq!(deleted.column, None);
+        assert_eq!(deleted.left, None);
+        assert_eq!(deleted.base.as_deref(), Some("name=Ed, qty=5"));
+    }
+
+    #[test]
+    fn test_three_way_counts_unchanged_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "id,name\n1,Ann\n2,Bob\n";
+        let base = write_entry(dir.path(), "base.csv", content);
+        let left = write_entry(dir.path(), "left.csv", content);
+        let right = write_entry(dir.path(), "right.csv", "id,name\n2,Bob\n1,Ann\n");
+
+        let result = compare_three_way(&base, &left, &right, &key_config(&["id"])).unwrap();
+        assert_eq!(result.unchanged_records, 2);
+        assert!(result.is_clean());
+        assert!(result.changed_in_left.is_empty() && result.changed_in_right.is_empty());
+    }
+
+    #[test]
+    fn test_detected_delimiters_split_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id;name;city\n1;Ann;Oslo\n2;Bob;Rome\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id|name|city\n2|Bob|Roma\n1|Ann|Oslo\n");
+
+        let result = compare_structured_files(&file1, &file2, &key_config(&["id"]), &Diagnostics::new()).unwrap();
+        assert_eq!(result.common_columns, vec!["id", "name", "city"]);
+        assert_eq!(result.common_records, 2);
+        assert_eq!(result.field_mismatches.len(), 1);
+        assert_eq!(result.field_mismatches[0].column_name, "city");
+
+        // An explicit delimiter overrides the detected one
+        let mut config = key_config(&["id"]);
+        config.csv_delimiter = Some(',');
+        let result = compare_structured_files(&file1, &file1, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.common_columns, vec!["id;name;city"]);
+
+        // Non-ASCII delimiters are rejected instead of being cut to one byte
+        config.csv_delimiter = Some('§');
+        let error = compare_structured_files(&file1, &file1, &config, &Diagnostics::new()).unwrap_err();
+        assert!(format!("{:#}", error).contains("must be an ASCII character"), "{:#}", error);
+    }
+
+    #[test]
+    fn test_headerless_csv_uses_positional_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = key_config(&["col_0"]);
+        config.has_header = false;
+        let options = IndexOptions::from_config(&config);
+        let (path1, path2) = (dir.path().join("a.csv"), dir.path().join("b.csv"));
+        fs::write(&path1, "1,Ann,Oslo\n2,Bob,Rome\n3,Cy,Bern\n").unwrap();
+        fs::write(&path2, "3,Cy,Bern\n1,Ann,Lima\n2,Bob,Rome\n4,Di,Kyiv\n").unwrap();
+        let file1 = index_single_file_with(&path1, &options).unwrap();
+        let file2 = index_single_file_with(&path2, &options).unwrap();
+        assert_eq!(file1.columns, Some(vec!["col_0".to_string(), "col_1".to_string(), "col_2".to_string()]));
+
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+
+        assert_eq!(result.common_columns, vec!["col_0", "col_1", "col_2"]);
+        assert_eq!((result.file1_row_count, result.file2_row_count), (3, 4));
+        assert_eq!(result.common_records, 3);
+        assert_eq!(result.only_in_file2, 1);
+        assert_eq!(result.field_mismatches.len(), 1);
+        let mismatch = &result.field_mismatches[0];
+        assert_eq!(mismatch.column_name, "col_2");
+        assert_eq!(mismatch.sample_mismatches[0].key, "1");
+        assert_eq!(mismatch.sample_mismatches[0].value2, "Lima");
+    }
+
+    #[test]
+    fn test_column_aliases_make_renamed_columns_comparable() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "customer_id,name\n1,Ann\n2,Bob\n");
+        let file2 = write_entry(dir.path(), "b.csv", "CustomerID,Name\n2,Bob\n1,Ann\n");
+
+        let unmapped = compare_structured_files(&file1, &file2, &key_config(&["customer_id"]), &Diagnostics::new()).unwrap();
+        assert!(!unmapped.identical);
+        assert!(unmapped.common_columns.is_empty());
+        assert_eq!(unmapped.columns_only_in_file2.len(), 2);
+
+        let mut config = key_config(&["customer_id"]);
+        config.column_aliases = HashMap::from([
+            ("CustomerID".to_string(), "customer_id".to_string()),
+            ("Name".to_string(), "name".to_string()),
+        ]);
+        let mapped = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert!(mapped.identical);
+        assert_eq!(mapped.common_records, 2);
+        assert!(mapped.columns_only_in_file1.is_empty());
+        assert!(mapped.columns_only_in_file2.is_empty());
+    }
+
+    #[test]
+    fn test_redact_masks_values_but_keeps_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,ssn,city\n1,111-22-3333,Oslo\n2,444-55-6666,Rome\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,ssn,city\n1,999-88-7777,Oslo\n2,444-55-6666,Paris\n");
+
+        let plain = compare_structured_files(&file1, &file2, &key_config(&["id"]), &Diagnostics::new()).unwrap();
+
+        let mut config = key_config(&["id"]);
+        config.redact_columns = vec!["ssn".to_string()];
+        let partial = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(partial.total_field_mismatches, plain.total_field_mismatches);
+        let column = |r: &StructuredComparisonResult, name: &str| {
+            r.field_mismatches.iter().find(|c| c.column_name == name).cloned().unwrap()
+        };
+        let ssn = column(&partial, "ssn");
+        assert_eq!(ssn.mismatch_count, 1);
+        assert_eq!(ssn.sample_mismatches[0].key, "1");
+        assert_eq!(ssn.sample_mismatches[0].value1, REDACTED);
+        assert_eq!(ssn.sample_mismatches[0].value2, REDACTED);
+        assert_eq!(column(&partial, "city").sample_mismatches[0].value2, "Paris");
+
+        config.redact = true;
+        let full = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(full.total_field_mismatches, plain.total_field_mismatches);
+        assert_eq!(column(&full, "city").sample_mismatches[0].value2, REDACTED);
+    }
+
+    #[test]
+    fn test_column_tolerances_override_global_tolerance() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,price,measurement\n1,9.99,0.000001\n2,5.00,0.000002\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,price,measurement\n1,10.00,0.000001\n2,5.00,0.000003\n");
+
+        let mut config = key_config(&["id"]);
+        config.numeric_tolerance = 0.0;
+        config.column_tolerances = HashMap::from([
+            ("price".to_string(), 0.01),
+            ("measurement".to_string(), 1e-9),
+        ]);
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+
+        // A cent is within the price tolerance; a micro-unit is not within 1e-9
+        assert_eq!(result.total_field_mismatches, 1);
+        assert_eq!(result.field_mismatches.len(), 1);
+        assert_eq!(result.field_mismatches[0].column_name, "measurement");
+        assert_eq!(result.field_mismatches[0].sample_mismatches[0].key, "2");
+
+        // The global tolerance is still used for columns without an override
+        config.column_tolerances.remove("price");
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.total_field_mismatches, 2);
+    }
+
+    #[test]
+    fn test_key_normalization_matches_padded_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,name\n1001,Ann\n 1002,Bob\nX3,Cy\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,name\n1001 ,Ann\n1002,Bob\nx3,Cy\n");
+
+        let exact = compare_structured_files(&file1, &file2, &key_config(&["id"]), &Diagnostics::new()).unwrap();
+        assert_eq!(exact.common_records, 0);
+        assert_eq!(exact.only_in_file1, 3);
+
+        let mut config = key_config(&["id"]);
+        config.key_normalization = KeyNormalization { trim: true, case_insensitive: true, numeric: false };
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.common_records, 3);
+        assert_eq!((result.only_in_file1, result.only_in_file2), (0, 0));
+        assert!(result.identical);
+    }
+
+    #[test]
+    fn test_composite_keys_escape_the_delimiter() {
+        let dir = tempfile::tempdir().unwrap();
+        // Joined naively, both rows of each file have the key "a|b|c"
+        let file1 = write_entry(dir.path(), "a.csv", "k1,k2,v\n\"a|b\",c,1\na,\"b|c\",2\n");
+        let file2 = write_entry(dir.path(), "b.csv", "k1,k2,v\na,\"b|c\",2\n\"a|b\",c,1\n");
+
+        let result = compare_structured_files(&file1, &file2, &key_config(&["k1", "k2"]), &Diagnostics::new()).unwrap();
+        assert!(result.identical);
+        assert_eq!(result.common_records, 2);
+        assert!(result.duplicate_keys.is_empty());
+
+        let mut config = key_config(&["k1", "k2"]);
+        config.key_delimiter = "::".to_string();
+        let record = ByteRecord::from(vec!["a:b", "c\\d"]);
+        assert_eq!(build_composite_key(&record, &[0, 1], &config), "a\\:b::c\\\\d");
+        assert_eq!(build_composite_key(&record, &[0], &config), "a:b");
+    }
+
+    #[test]
+    fn test_empty_as_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,name,note\n1,Ann,\n2,Bob,NULL\n3,Cy,\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,name\n1,Ann\n2,Bob\n3,\n");
+
+        let strict = compare_structured_files(&file1, &file2, &key_config(&["id"]), &Diagnostics::new()).unwrap();
+        assert_eq!(strict.columns_only_in_file1, vec!["note"]);
+        assert_eq!(strict.total_field_mismatches, 1);
+
+        // The note column is empty or NULL everywhere, so it counts as absent;
+        // Cy's name really was removed
+        let mut config = key_config(&["id"]);
+        config.treat_empty_as_missing = true;
+        config.null_tokens = vec!["null".to_string()];
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert!(result.columns_only_in_file1.is_empty());
+        assert_eq!(result.total_field_mismatches, 1);
+
+        // Without the token, NULL is a value, so the note column stays a difference
+        config.null_tokens.clear();
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.columns_only_in_file1, vec!["note"]);
+    }
+
+    #[test]
+    fn test_null_tokens_match_empty_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,city\n1,NULL\n2,\n3,Oslo\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,city\n1,\n2,N/A\n3,NULL\n");
+        let mut config = key_config(&["id"]);
+        config.treat_empty_as_missing = true;
+        config.null_tokens = vec!["NULL".to_string(), "N/A".to_string()];
+
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.total_field_mismatches, 1);
+        assert_eq!(result.field_mismatches[0].sample_mismatches[0].key, "3");
+    }
+
+    #[test]
+    fn test_numeric_key_normalization() {
+        let numeric = KeyNormalization { numeric: true, ..KeyNormalization::default() };
+        assert_eq!(normalize_key_field(" 007", &numeric), "7");
+        assert_eq!(normalize_key_field("7.0", &numeric), "7");
+        assert_eq!(normalize_key_field("-0.50", &numeric), "-0.5");
+        assert_eq!(normalize_key_field("99999999999999999999", &numeric), "99999999999999999999");
+        assert_eq!(normalize_key_field(" A-1", &numeric), " A-1");
+        assert_eq!(normalize_key_field(" 007", &KeyNormalization::default()), " 007");
+    }
+
+    #[test]
+    fn test_fixed_width_files_compare_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file1 = write_entry(dir.path(), "a.txt", "0001Alice     0000100.00\n0002Bob       0000250.50\n");
+        let mut file2 = write_entry(dir.path(), "b.txt", "0002Bob       0000275.50\n0001Alice     0000100.00\n\n");
+
+        let config = CompareConfig {
+            key_columns: vec!["id".to_string()],
+            fixed_width: vec![(0, 4), (4, 14), (14, 24)],
+            fixed_width_columns: vec!["id".to_string(), "name".to_string()],
+            ..CompareConfig::default()
+        };
+        let headers = config.fixed_width_headers();
+        assert_eq!(headers, vec!["id", "name", "col_2"]);
+        crate::index::apply_fixed_width(std::slice::from_mut(&mut file1), &headers);
+        crate::index::apply_fixed_width(std::slice::from_mut(&mut file2), &headers);
+        assert_eq!(file1.file_type, FileType::FixedWidth);
+
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.common_records, 2);
+        assert_eq!((result.only_in_file1, result.only_in_file2), (0, 0));
+        assert_eq!(result.total_field_mismatches, 1);
+        let amount = &result.field_mismatches[0];
+        assert_eq!(amount.column_name, "col_2");
+        assert_eq!(amount.sample_mismatches[0].key, "0002");
+        assert_eq!(amount.sample_mismatches[0].value1, "0000250.50");
+        assert_eq!(amount.sample_mismatches[0].value2, "0000275.50");
+    }
+
+    #[test]
+    fn test_duplicate_keys_are_listed_and_matched_in_file_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,qty\n1,5\n2,7\n1,6\n3,1\n3,2\n3,3\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,qty\n3,1\n1,5\n2,7\n1,9\n3,2\n");
+
+        let mut config = key_config(&["id"]);
+        let diagnostics = Diagnostics::new();
+        let result = compare_structured_files(&file1, &file2, &config, &diagnostics).unwrap();
+
+        assert_eq!(result.duplicate_keys, vec!["1", "3"]);
+        assert_eq!(diagnostics.report().count(AnomalyKind::DuplicateKeys), 2);
+        // Key 1: (5,5) and (6,9) pair up; key 3: two pairs plus a surplus row
+        assert_eq!(result.common_records, 5);
+        assert_eq!((result.only_in_file1, result.only_in_file2), (1, 0));
+        assert_eq!((result.file1_row_count, result.file2_row_count), (6, 5));
+        assert_eq!(result.total_field_mismatches, 1);
+        let sample = &result.field_mismatches[0].sample_mismatches[0];
+        assert_eq!((sample.key.as_str(), sample.value1.as_str(), sample.value2.as_str()), ("1", "6", "9"));
+
+        // Spilling to disk keeps the same file order within a key group
+        config.external_sort = Some(1);
+        let external = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(external.duplicate_keys, result.duplicate_keys);
+        assert_eq!(external.common_records, result.common_records);
+        assert_eq!(external.total_field_mismatches, result.total_field_mismatches);
+    }
+
+    #[test]
+    fn test_typed_columns_fold_boolean_case_and_number_grouping() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,active,price\n1,TRUE,\"$1,000\"\n2,false,250\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,active,price\n1,true,1000\n2,False,$250.00\n");
+
+        let mut config = key_config(&["id"]);
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.total_field_mismatches, 4);
+        let types: Vec<_> = result.field_mismatches.iter().map(|m| (m.column_name.as_str(), m.inferred_type)).collect();
+        assert_eq!(types, vec![("active", ColumnType::Boolean), ("price", ColumnType::Float)]);
+
+        config.type_coercion.booleans = true;
+        config.type_coercion.numbers = true;
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.total_field_mismatches, 0);
+    }
+
+    #[test]
+    fn test_comment_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = key_config(&["id"]);
+        config.comment_char = Some(b'#');
+        let path1 = dir.path().join("a.csv");
+        fs::write(&path1, "# exported 2024-01-01\n# source: crm\nid,name\n1,Alice\n# page 2\n2,Bob\n").unwrap();
+        let file1 = index_single_file_with(&path1, &IndexOptions::from_config(&config)).unwrap();
+        let file2 = write_entry(dir.path(), "b.csv", "id,name\n1,Alice\n2,Bob\n");
+        let diagnostics = Diagnostics::new();
+        let result = compare_structured_files(&file1, &file2, &config, &diagnostics).unwrap();
+        assert_eq!(result.common_records, 2);
+        assert_eq!(result.total_field_mismatches, 0);
+        assert_eq!(diagnostics.report().count(AnomalyKind::RaggedRows), 0);
+
+        let mut counter = CommentCounter::new(std::fs::File::open(&file1.path).unwrap(), Some(b'#'));
+        std::io::copy(&mut counter, &mut std::io::sink()).unwrap();
+        assert_eq!(counter.lines, 3);
+    }
+
+    #[test]
+    fn test_both_files_parse_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv = |scale: usize| -> String {
+            let rows: String = (0..100_000).map(|i| format!("{},item {},{}\n", i, i % 97, i * scale)).collect();
+            format!("id,name,value\n{}", rows)
+        };
+        let file1 = write_entry(dir.path(), "a.csv", &csv(1));
+        let file2 = write_entry(dir.path(), "b.csv", &csv(2));
+        let config = key_config(&["id"]);
+        let pool = |threads| rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let (single, pair) = (pool(1), pool(2));
+
+        // Same result whether the second parse runs alongside the first or after it
+        let compare = || compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        let (serial, parallel) = (single.install(compare), pair.install(compare));
+        assert_eq!(serial.common_records, 100_000);
+        assert_eq!(
+            (serial.common_records, serial.total_field_mismatches, serial.similarity_score),
+            (parallel.common_records, parallel.total_field_mismatches, parallel.similarity_score)
+        );
+    }
+
+    #[test]
+    fn test_compare_records_matches_file_based_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,name,qty\n2,nut,20\n1,bolt,10\n3,gear,5\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,name,qty\n1,bolt,10\n2,nut,25\n4,cog,1\n");
+        let config = key_config(&["id"]);
+        let from_files = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+
+        let rows = |rows: &[[&str; 3]]| -> Vec<Vec<String>> {
+            rows.iter().map(|r| r.iter().map(|v| v.to_string()).collect()).collect()
+        };
+        let headers = || vec!["id".to_string(), "name".to_string(), "qty".to_string()];
+        let in_memory = compare_records(
+            headers(),
+            rows(&[["2", "nut", "20"], ["1", "bolt", "10"], ["3", "gear", "5"]]),
+            headers(),
+            rows(&[["1", "bolt", "10"], ["2", "nut", "25"], ["4", "cog", "1"]]),
+            &config,
+        )
+        .unwrap();
+
+        assert_eq!((in_memory.common_records, in_memory.only_in_file1, in_memory.only_in_file2), (2, 1, 1));
+        assert_eq!(
+            serde_json::to_value(&in_memory.field_mismatches).unwrap(),
+            serde_json::to_value(&from_files.field_mismatches).unwrap()
+        );
+        assert_eq!(in_memory.similarity_score, from_files.similarity_score);
+        assert_eq!(in_memory.common_columns, from_files.common_columns);
+        assert_eq!(in_memory.identical, from_files.identical);
+        assert!(in_memory.file1_path.is_empty());
+    }
+
+    #[test]
+    fn test_structured_patch_turns_file1_into_file2() {
+        let headers = || vec!["id".to_string(), "name".to_string(), "qty".to_string()];
+        let row = |id: usize, name: &str, qty: usize| vec![id.to_string(), name.to_string(), qty.to_string()];
+        // Every even record changes qty, more than the 5 samples kept per column
+        let rows1: Vec<Vec<String>> = (1..=14).map(|i| row(i, "part", i)).collect();
+        let mut rows2: Vec<Vec<String>> = (1..=14)
+            .filter(|i| *i != 3)
+            .map(|i| row(i, if i == 5 { "bolt" } else { "part" }, if i % 2 == 0 { i * 10 } else { i }))
+            .collect();
+        rows2.push(row(20, "new", 1));
+        let config = CompareConfig {
+            structured_patch: true,
+            ..key_config(&["id"])
+        };
+        let result = compare_records(headers(), rows1.clone(), headers(), rows2.clone(), &config).unwrap();
+        let patch = result.patch.as_ref().unwrap();
+
+        assert_eq!(patch.removed_keys.len(), result.only_in_file1);
+        assert_eq!(patch.added_keys.len(), result.only_in_file2);
+        assert_eq!(patch.updated.len(), result.total_field_mismatches);
+        assert_eq!(patch.updated.len(), 8);
+        assert_eq!(patch.removed_keys, vec!["3"]);
+        assert_eq!(patch.added_keys, vec!["20"]);
+
+        // Applying the patch to file 1 yields file 2
+        let by_key = |rows: Vec<Vec<String>>| -> BTreeMap<String, Vec<String>> {
+            rows.into_iter().map(|r| (r[0].clone(), r)).collect()
+        };
+        let mut patched = by_key(rows1);
+        let target = by_key(rows2);
+        for key in &patch.removed_keys {
+            patched.remove(key);
+        }
+        for key in &patch.added_keys {
+            patched.insert(key.clone(), target[key].clone());
+        }
+        for update in &patch.updated {
+            let column = headers().iter().position(|h| *h == update.column).unwrap();
+            let record = patched.get_mut(&update.key).unwrap();
+            assert_eq!(record[column], update.from);
+            record[column] = update.to.clone();
+        }
+        assert_eq!(patched, target);
+
+        // Without the option nothing extra is collected
+        let plain = compare_records(headers(), target.into_values(), headers(), Vec::new(), &key_config(&["id"])).unwrap();
+        assert!(plain.patch.is_none());
+    }
+
+    #[test]
+    fn test_full_detail_keeps_every_mismatch() {
+        let headers = || vec!["id".to_string(), "qty".to_string()];
+        let rows = |qty: usize| (1..=20).map(move |i| vec![i.to_string(), (i * qty).to_string()]);
+        let compare = |full_mismatch_detail| {
+            let config = CompareConfig {
+                full_mismatch_detail,
+                ..key_config(&["id"])
+            };
+            let result = compare_records(headers(), rows(1), headers(), rows(2), &config).unwrap();
+            result.field_mismatches.into_iter().next().unwrap()
+        };
+
+        let full = compare(true);
+        assert_eq!(full.mismatch_count, 20);
+        assert_eq!(full.all_mismatches.len(), 20);
+        assert_eq!(full.sample_mismatches.len(), 5);
+        assert_eq!(full.mismatches().len(), 20);
+
+        let sampled = compare(false);
+        assert_eq!(sampled.mismatch_count, 20);
+        assert!(sampled.all_mismatches.is_empty());
+        assert_eq!(sampled.mismatches().len(), 5);
+    }
+
+    #[test]
+    fn test_profile_reports_shifted_mean() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,amount,region\n1,10,north\n2,20,south\n3,30,\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,amount,region\n4,40,north\n5,50,north\n6,60,east\n");
+        let config = CompareConfig {
+            profile_columns: true,
+            ..key_config(&["id"])
+        };
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        let profile = |col: &str| result.column_profiles.iter().find(|p| p.column_name == col).unwrap();
+
+        // No key lines up, yet the shift in the amounts is reported
+        assert_eq!(result.common_records, 0);
+        let amount = profile("amount");
+        assert_eq!((amount.file1.mean, amount.file2.mean), (Some(20.0), Some(50.0)));
+        assert_eq!(amount.mean_delta, Some(30.0));
+        assert_eq!((amount.min_delta, amount.max_delta), (Some(30.0), Some(30.0)));
+
+        let region = profile("region");
+        assert_eq!((region.file1.null_count, region.file2.null_count), (1, 0));
+        assert_eq!(region.distinct_count_delta, 0);
+        assert!((region.null_rate_delta + 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(region.file1.mean, None);
+    }
+
+    #[test]
+    fn test_profile_counts_every_row_including_duplicate_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,amount\n1,10\n1,11\n1,12\n2,20\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,amount\n1,10\n2,20\n2,\n3,30\n");
+        let config = CompareConfig {
+            profile_columns: true,
+            ..key_config(&["id"])
+        };
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.duplicate_keys, vec!["1", "2"]);
+
+        let amount = result.column_profiles.iter().find(|p| p.column_name == "amount").unwrap();
+        assert_eq!((amount.file1.count, amount.file2.count), (result.file1_row_count, result.file2_row_count));
+        assert_eq!((amount.file1.count, amount.file2.count), (4, 4));
+        assert_eq!((amount.file1.null_count, amount.file2.null_count), (0, 1));
+        assert_eq!((amount.file1.distinct_count, amount.file2.distinct_count), (4, 3));
+    }
+
+    #[test]
+    fn test_distinct_counter_switches_to_an_estimate() {
+        let mut counter = DistinctCounter::default();
+        for i in 0..DistinctCounter::EXACT_LIMIT as u64 {
+            counter.insert(xxh3_64_with_seed(&i.to_le_bytes(), 0));
+            counter.insert(xxh3_64_with_seed(&i.to_le_bytes(), 0));
+        }
+        assert_eq!(counter.count(), DistinctCounter::EXACT_LIMIT);
+
+        for i in 0..200_000u64 {
+            counter.insert(xxh3_64_with_seed(&i.to_le_bytes(), 0));
+        }
+        assert!(matches!(counter, DistinctCounter::Sketch(_)));
+        let error = (counter.count() as f64 - 200_000.0).abs() / 200_000.0;
+        assert!(error < 0.05, "estimated {} distinct values", counter.count());
+    }
+
+    #[test]
+    fn test_fraction_sampling_keeps_the_same_keys_from_both_files() {
+        let headers = || vec!["id".to_string(), "qty".to_string()];
+        // File 2 lists the records in another order, with one extra record
+        let rows1: Vec<Vec<String>> = (0..2000).map(|i| vec![format!("k{}", i), "1".to_string()]).collect();
+        let mut rows2: Vec<Vec<String>> = rows1.iter().rev().cloned().collect();
+        rows2.push(vec!["extra".to_string(), "1".to_string()]);
+
+        let config = CompareConfig {
+            sample: Some("fraction:0.1".parse().unwrap()),
+            ..key_config(&["id"])
+        };
+        let result = compare_records(headers(), rows1, headers(), rows2, &config).unwrap();
+
+        assert!(result.sampled);
+        assert_eq!(result.sample_size, result.file1_row_count + result.file2_row_count);
+        // Roughly a tenth of the keys, and every sampled key of file 1 found in file 2
+        assert!((100..300).contains(&result.file1_row_count), "{}", result.file1_row_count);
+        assert_eq!(result.only_in_file1, 0);
+        assert_eq!(result.common_records, result.file1_row_count);
+        assert!(result.only_in_file2 <= 1);
+    }
+
+    #[test]
+    fn test_sample_spec_parsing() {
+        assert_eq!("first:100".parse(), Ok(SampleSpec::FirstN(100)));
+        assert_eq!("5%".parse(), Ok(SampleSpec::Fraction(0.05)));
+        assert_eq!("random:50:7".parse(), Ok(SampleSpec::Random { n: 50, seed: 7 }));
+        assert_eq!("random:50".parse(), Ok(SampleSpec::Random { n: 50, seed: 0 }));
+        assert!("fraction:1.5".parse::<SampleSpec>().is_err());
+
+        // Random sampling keeps the same keys regardless of file order
+        let keyed = |i: usize| KeyedRecord { key: format!("k{}", i), record: ByteRecord::new() };
+        let pick = |order: Vec<usize>| {
+            let mut sampler = RecordSampler::new(SampleSpec::Random { n: 10, seed: 7 }, false);
+            assert!(order.into_iter().all(|i| sampler.offer(keyed(i)).is_none()));
+            let mut keys: Vec<String> = sampler.finish().into_iter().map(|r| r.key).collect();
+            keys.sort();
+            keys
+        };
+        let forward = pick((0..500).collect());
+        assert_eq!(forward.len(), 10);
+        assert_eq!(forward, pick((0..500).rev().collect()));
+    }
+
+    #[test]
+    fn test_positional_compares_rows_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "name,qty\nbolt,10\nnut,20\nbolt,30\n");
+        let file2 = write_entry(dir.path(), "b.csv", "name,qty\nbolt,10\nnut,25\nscrew,30\nwasher,5\n");
+
+        let config = CompareConfig {
+            positional: true,
+            ..CompareConfig::default()
+        };
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+
+        assert_eq!(result.common_records, 3);
+        assert_eq!(result.only_in_file1, 0);
+        assert_eq!(result.only_in_file2, 1);
+        assert_eq!(result.total_field_mismatches, 2);
+        let sample = |col: &str| {
+            let column = result.field_mismatches.iter().find(|c| c.column_name == col).unwrap();
+            column.sample_mismatches[0].clone()
+        };
+        assert_eq!(sample("qty").key, "row 2");
+        assert_eq!((sample("qty").value1.as_str(), sample("qty").value2.as_str()), ("20", "25"));
+        // The first column is compared too; it is not a key in positional mode
+        assert_eq!(sample("name").key, "row 3");
+        assert_eq!(sample("name").value2, "screw");
+    }
+
+    #[test]
+    fn test_positional_finds_mismatches_hidden_by_colliding_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        // Two exports of the same query; the first column repeats, and row 2 changed region
+        let file1 = write_entry(dir.path(), "a.csv", "region,sales\neast,10\neast,20\nwest,30\n");
+        let file2 = write_entry(dir.path(), "b.csv", "region,sales\neast,10\nwest,20\nwest,30\n");
+
+        // By key, the rows regroup: west 30 is paired with west 20 and the real change is an add and a remove
+        let by_key = compare_structured_files(&file1, &file2, &CompareConfig::default(), &Diagnostics::new()).unwrap();
+        assert_eq!((by_key.only_in_file1, by_key.only_in_file2), (1, 1));
+        assert_eq!(by_key.field_mismatches[0].column_name, "sales");
+        assert_eq!(by_key.duplicate_keys, vec!["east", "west"]);
+
+        let config = CompareConfig {
+            positional: true,
+            ..CompareConfig::default()
+        };
+        let positional = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!((positional.only_in_file1, positional.only_in_file2), (0, 0));
+        assert_eq!(positional.total_field_mismatches, 1);
+        let region = &positional.field_mismatches[0];
+        assert_eq!(region.column_name, "region");
+        let sample = &region.sample_mismatches[0];
+        assert_eq!((sample.key.as_str(), sample.value1.as_str(), sample.value2.as_str()), ("row 2", "east", "west"));
+    }
+
+    #[test]
+    fn test_auto_key_picks_unique_id_over_repeated_first_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "team,score,id\nred,10,7\nred,20,3\nblue,30,5\n");
+        let file2 = write_entry(dir.path(), "b.csv", "team,score,id\nblue,30,5\nred,25,3\nred,10,7\n");
+
+        let config = CompareConfig { auto_key: true, ..CompareConfig::default() };
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.inferred_key_columns, vec!["id"]);
+        assert_eq!(result.common_records, 3);
+        assert_eq!(result.total_field_mismatches, 1);
+        assert_eq!(result.field_mismatches[0].sample_mismatches[0].key, "3");
+
+        // Without a unique column, the smallest unique combination is used
+        let headers: Vec<String> = ["region", "year", "note"].iter().map(|h| h.to_string()).collect();
+        let rows: Vec<ByteRecord> = [["east", "2024", "x"], ["east", "2025", "x"], ["west", "2024", "y"]]
+            .iter()
+            .map(|row| ByteRecord::from(row.to_vec()))
+            .collect();
+        assert_eq!(infer_key_columns(&headers, &rows, &[]), vec!["region", "year"]);
+    }
+
+    #[test]
+    fn test_result_lists_keys_only_in_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(dir.path(), "a.csv", "id,name\n1,Ann\n2,Bob\n3,Cy\n");
+        let file2 = write_entry(dir.path(), "b.csv", "id,name\n1,Ann\n3,Cy\n4,Di\n5,Ed\n");
+
+        let result = compare_structured_files(&file1, &file2, &CompareConfig::default(), &Diagnostics::new()).unwrap();
+        assert_eq!(result.only_in_file1_keys, vec!["2"]);
+        assert_eq!(result.only_in_file2_keys, vec!["4", "5"]);
+        assert!(!result.only_in_keys_truncated);
+
+        let config = CompareConfig { max_listed_keys: 1, ..CompareConfig::default() };
+        let result = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert_eq!(result.only_in_file2_keys, vec!["4"]);
+        assert_eq!(result.only_in_file2, 2);
+        assert!(result.only_in_keys_truncated);
+    }
+
+    #[test]
+    fn test_external_sort_matches_in_memory_comparison() {
+        let dir = tempfile::tempdir().unwrap();
+        // Rows in scrambled key order, with one duplicate key in file 1
+        let mut csv1 = String::from("id,name,amount\n");
+        let mut csv2 = String::from("id,name,amount\n");
+        for i in 0..2000 {
+            let id = (i * 7919) % 2000;
+            csv1.push_str(&format!("{},item {},{}\n", id, id, id * 3));
+            if id % 10 != 0 {
+                let amount = if id % 97 == 0 { id * 3 + 1 } else { id * 3 };
+                csv2.push_str(&format!("{},item {},{}\n", id, id, amount));
+            }
+        }
+        csv1.push_str("42,item 42 again,126\n");
+        csv2.push_str("5000,extra,0\n");
+        let file1 = write_entry(dir.path(), "a.csv", &csv1);
+        let file2 = write_entry(dir.path(), "b.csv", &csv2);
+
+        let in_memory_diagnostics = Diagnostics::new();
+        let in_memory = compare_structured_files(&file1, &file2, &key_config(&["id"]), &in_memory_diagnostics).unwrap();
+
+        // A 4 KB chunk forces file 1 (about 40 KB) to spill many runs
+        let mut config = key_config(&["id"]);
+        config.external_sort = Some(4096);
+        let external_diagnostics = Diagnostics::new();
+        let external = compare_structured_files(&file1, &file2, &config, &external_diagnostics).unwrap();
+
+        assert_eq!(external.file1_row_count, 2001);
+        assert_eq!(external.common_records, in_memory.common_records);
+        assert_eq!(external.only_in_file1, in_memory.only_in_file1);
+        assert_eq!(external.only_in_file2, in_memory.only_in_file2);
+        assert_eq!(external.total_field_mismatches, in_memory.total_field_mismatches);
+        assert_eq!(
+            serde_json::to_value(&external.field_mismatches).unwrap(),
+            serde_json::to_value(&in_memory.field_mismatches).unwrap()
+        );
+        assert_eq!(external_diagnostics.report().count(AnomalyKind::DuplicateKeys), 1);
+        assert_eq!(in_memory_diagnostics.report().count(AnomalyKind::DuplicateKeys), 1);
+    }
+
+    /// Write a minimal one-sheet .xlsx whose sheet data is `rows_xml`
+    fn write_xlsx(dir: &Path, name: &str, rows_xml: &str) -> FileEntry {
+        use std::io::Write;
+        let parts = [
+            (
+                "[Content_Types].xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#.to_string(),
+            ),
+            (
+                "_rels/.rels",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#.to_string(),
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#.to_string(),
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#.to_string(),
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{}</sheetData></worksheet>"#,
+                    rows_xml
+                ),
+            ),
+        ];
+
+        let path = dir.join(name);
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        for (part, content) in parts {
+            zip.start_file(part, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        index_single_file(&path).unwrap()
+    }
+
+    #[test]
+    fn test_excel_formulas_mode_detects_changed_formula() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = r#"<row r="1"><c r="A1" t="inlineStr"><is><t>id</t></is></c><c r="B1" t="inlineStr"><is><t>qty</t></is></c><c r="C1" t="inlineStr"><is><t>price</t></is></c><c r="D1" t="inlineStr"><is><t>total</t></is></c></row>"#;
+        let row = |formula: &str| {
+            format!(
+                r#"<row r="2"><c r="A2"><v>1</v></c><c r="B2"><v>2</v></c><c r="C2"><v>3</v></c><c r="D2"><f>{}</f><v>6</v></c></row>"#,
+                formula
+            )
+        };
+        // Same cached result, different formula
+        let file1 = write_xlsx(dir.path(), "a.xlsx", &format!("{}{}", header, row("B2*C2")));
+        let file2 = write_xlsx(dir.path(), "b.xlsx", &format!("{}{}", header, row("B2+B2+B2")));
+
+        let values = compare_structured_files(&file1, &file2, &key_config(&["id"]), &Diagnostics::new()).unwrap();
+        assert!(values.identical);
+
+        let mut config = key_config(&["id"]);
+        config.excel_formulas = true;
+        let formulas = compare_structured_files(&file1, &file2, &config, &Diagnostics::new()).unwrap();
+        assert!(!formulas.identical);
+        assert_eq!(formulas.total_field_mismatches, 1);
+        let total = &formulas.field_mismatches[0];
+        assert_eq!(total.column_name, "total");
+        assert_eq!(total.sample_mismatches[0].value1, "=B2*C2");
+        assert_eq!(total.sample_mismatches[0].value2, "=B2+B2+B2");
+    }
 
     #[test]
     fn test_values_equal() {
-        assert!(values_equal("hello", "hello", 0.0001));
-        assert!(!values_equal("hello", "world", 0.0001));
-        assert!(values_equal("1.0", "1.0", 0.0001));
-        assert!(values_equal("1.0000", "1.0001", 0.001));
-        assert!(!values_equal("1.0", "2.0", 0.0001));
+        assert!(values_equal("hello", "hello", 0.0001, None, ToleranceMode::Either));
+        assert!(!values_equal("hello", "world", 0.0001, None, ToleranceMode::Either));
+        assert!(values_equal("1.0", "1.0", 0.0001, None, ToleranceMode::Either));
+        assert!(values_equal("1.0000", "1.0001", 0.001, None, ToleranceMode::Either));
+        assert!(!values_equal("1.0", "2.0", 0.0001, None, ToleranceMode::Either));
+    }
+
+    #[test]
+    fn test_values_equal_ulp() {
+        let one_ulp = f64::from_bits(1.0f64.to_bits() + 1).to_string();
+        let five_ulps = f64::from_bits(1.0f64.to_bits() + 5).to_string();
+
+        assert!(values_equal("1.0", &one_ulp, 0.0, Some(1), ToleranceMode::Either));
+        assert!(!values_equal("1.0", &one_ulp, 0.0, Some(0), ToleranceMode::Either));
+        assert!(!values_equal("1.0", &five_ulps, 0.0, Some(4), ToleranceMode::Either));
+        assert!(values_equal("1.0", &five_ulps, 0.0, Some(5), ToleranceMode::Either));
+        // 0.1 + 0.2 lands one ULP above 0.3
+        assert!(values_equal("0.3", &(0.1 + 0.2).to_string(), 0.0, Some(1), ToleranceMode::Either));
+        // The ULP check replaces the absolute/relative tolerance
+        assert!(!values_equal("1.0", "1.0001", 0.01, Some(4), ToleranceMode::Either));
+    }
+
+    #[test]
+    fn test_tolerance_modes() {
+        // 1% apart on a large number: far outside 0.05 absolute, within 5% relative
+        assert!(values_equal("1000", "1010", 0.05, None, ToleranceMode::Relative));
+        assert!(!values_equal("1000", "1010", 0.05, None, ToleranceMode::Absolute));
+        assert!(values_equal("1000", "1010", 0.05, None, ToleranceMode::Either));
+        // 0.04 apart near zero: within 0.05 absolute, but 80% relative
+        assert!(values_equal("0.01", "0.05", 0.05, None, ToleranceMode::Absolute));
+        assert!(!values_equal("0.01", "0.05", 0.05, None, ToleranceMode::Relative));
+        assert!(values_equal("0", "0", 0.05, None, ToleranceMode::Relative));
+    }
+
+    #[test]
+    fn test_ulp_distance_crosses_zero() {
+        assert_eq!(ulp_distance(0.0, -0.0), 0);
+        assert_eq!(ulp_distance(f64::from_bits(1), -f64::from_bits(1)), 2);
+        assert_eq!(ulp_distance(1.0, f64::NAN), u64::MAX);
     }
 
     #[test]
@@ -446,7 +2619,7 @@ mod tests {
         assert_eq!(excel_cell_to_string(&Data::Empty), "");
         assert_eq!(excel_cell_to_string(&Data::String("test".to_string())), "test");
         assert_eq!(excel_cell_to_string(&Data::Int(42)), "42");
-        assert_eq!(excel_cell_to_string(&Data::Float(3.14)), "3.14");
+        assert_eq!(excel_cell_to_string(&Data::Float(2.5)), "2.5");
         assert_eq!(excel_cell_to_string(&Data::Float(42.0)), "42");
         assert_eq!(excel_cell_to_string(&Data::Bool(true)), "TRUE");
         assert_eq!(excel_cell_to_string(&Data::Bool(false)), "FALSE");
@@ -455,12 +2628,12 @@ mod tests {
     #[test]
     fn test_merge_join_ordering() {
         // Test that the merge-join algorithm correctly handles sorted data
-        let mut records1 = vec![
+        let mut records1 = [
             KeyedRecord { key: "a".to_string(), record: ByteRecord::new() },
             KeyedRecord { key: "c".to_string(), record: ByteRecord::new() },
             KeyedRecord { key: "e".to_string(), record: ByteRecord::new() },
         ];
-        let mut records2 = vec![
+        let mut records2 = [
             KeyedRecord { key: "b".to_string(), record: ByteRecord::new() },
             KeyedRecord { key: "c".to_string(), record: ByteRecord::new() },
             KeyedRecord { key: "d".to_string(), record: ByteRecord::new() },
@@ -489,4 +2662,99 @@ mod tests {
         assert_eq!(only1, 2);   // "a" and "e"
         assert_eq!(only2, 2);   // "b" and "d"
     }
+
+    #[test]
+    fn test_flatten_json_record_nested() {
+        let value: Value = serde_json::from_str(
+            r#"{"id": 1, "user": {"address": {"city": "Oslo"}}, "items": [{"sku": "A1"}, {"sku": "B2"}], "note": null}"#,
+        )
+        .unwrap();
+
+        let fields = flatten_json_record(&value);
+
+        assert_eq!(
+            fields,
+            vec![
+                ("id".to_string(), "1".to_string()),
+                ("user.address.city".to_string(), "Oslo".to_string()),
+                ("items.0.sku".to_string(), "A1".to_string()),
+                ("items.1.sku".to_string(), "B2".to_string()),
+                ("note".to_string(), "null".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_json_nested_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(
+            dir.path(),
+            "a.json",
+            r#"[{"id": 1, "user": {"address": {"city": "Oslo"}}}, {"id": 2, "user": {"address": {"city": "Rome"}}}]"#,
+        );
+        let file2 = write_entry(
+            dir.path(),
+            "b.json",
+            r#"[{"id": 2, "user": {"address": {"city": "Milan"}}}, {"id": 1, "user": {"address": {"city": "Oslo"}}}]"#,
+        );
+        assert_eq!(file1.file_type, FileType::Json);
+
+        let result = compare_structured_files(&file1, &file2, &key_config(&["id"]), &Diagnostics::new()).unwrap();
+
+        assert_eq!(result.common_records, 2);
+        assert_eq!(result.total_field_mismatches, 1);
+        assert_eq!(result.field_mismatches[0].column_name, "user.address.city");
+        assert_eq!(result.field_mismatches[0].sample_mismatches[0].key, "2");
+        assert_eq!(result.field_mismatches[0].sample_mismatches[0].value1, "Rome");
+        assert_eq!(result.field_mismatches[0].sample_mismatches[0].value2, "Milan");
+        assert!(!result.identical);
+    }
+
+    #[test]
+    fn test_compare_json_missing_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(
+            dir.path(),
+            "a.json",
+            r#"[{"id": "a", "qty": 1}, {"id": "b", "qty": 2}, {"id": "c", "qty": 3}]"#,
+        );
+        // NDJSON: "a" lost its qty field, "c" is gone and "d" is new
+        let file2 = write_entry(
+            dir.path(),
+            "b.ndjson",
+            "{\"id\": \"a\"}\n{\"id\": \"b\", \"qty\": 2}\n\n{\"id\": \"d\", \"qty\": 4}\n",
+        );
+        assert_eq!(file2.file_type, FileType::Json);
+
+        let result = compare_structured_files(&file1, &file2, &key_config(&["id"]), &Diagnostics::new()).unwrap();
+
+        assert_eq!(result.common_records, 2);
+        assert_eq!(result.only_in_file1, 1);
+        assert_eq!(result.only_in_file2, 1);
+        assert_eq!(result.field_mismatches.len(), 1);
+        let sample = &result.field_mismatches[0].sample_mismatches[0];
+        assert_eq!((sample.key.as_str(), sample.value1.as_str(), sample.value2.as_str()), ("a", "1", ""));
+    }
+
+    #[test]
+    fn test_compare_json_differing_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        let file1 = write_entry(
+            dir.path(),
+            "a.json",
+            r#"[{"id": 1, "name": "x", "contact": {"email": "x@example.com"}}]"#,
+        );
+        let file2 = write_entry(
+            dir.path(),
+            "b.json",
+            r#"[{"id": 1, "name": "x", "contact": {"phone": "555"}}]"#,
+        );
+
+        let result = compare_structured_files(&file1, &file2, &CompareConfig::default(), &Diagnostics::new()).unwrap();
+
+        assert_eq!(result.common_records, 1);
+        assert_eq!(result.columns_only_in_file1, vec!["contact.email".to_string()]);
+        assert_eq!(result.columns_only_in_file2, vec!["contact.phone".to_string()]);
+        assert_eq!(result.total_field_mismatches, 0);
+    }
 }
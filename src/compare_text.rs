@@ -7,16 +7,24 @@
 //! Uses `TextDiff::diff_slices` to compare lines directly without joining them
 //! into a single massive string. This eliminates OOM crashes on files >500MB.
 
-use crate::fingerprint::read_normalized_lines;
-use crate::types::{CompareConfig, FileEntry, SimilarityAlgorithm, TextComparisonResult};
-use anyhow::Result;
+use crate::diagnostics::UnreadableFile;
+use crate::fingerprint::{read_normalized_lines, read_text_decoded};
+use crate::types::{
+    CompareConfig, DiffStats, FileEntry, InlineEdit, RenameHint, SimilarityAlgorithm,
+    TextComparisonResult, WhitespaceGlyphs, REDACTED,
+};
+use anyhow::{Context, Result};
 use log::warn;
 use regex::Regex;
-use similar::{Algorithm, ChangeTag, TextDiff};
+use similar::{Algorithm, ChangeTag, DiffTag, TextDiff};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::path::Path;
 use strsim::jaro_winkler;
 
+/// Similarity a text pair needs before a rename is looked for
+const RENAME_MIN_SIMILARITY: f64 = 0.5;
+
 /// Compare two text files and produce a detailed result
 ///
 /// This function uses vector/slice-based comparison to avoid memory issues
@@ -27,18 +35,144 @@ pub fn compare_text_files(
     config: &CompareConfig,
 ) -> Result<TextComparisonResult> {
     // Read and normalize content
-    let mut lines1 = read_normalized_lines(&file1.path, &config.normalization)?;
-    let mut lines2 = read_normalized_lines(&file2.path, &config.normalization)?;
-
-    // Apply regex filtering if specified
-    if let Some(ref pattern) = config.ignore_regex {
-        let re = compile_ignore_regex(pattern);
-        if let Some(ref regex) = re {
-            lines1 = apply_regex_filter(&lines1, regex);
-            lines2 = apply_regex_filter(&lines2, regex);
+    let mut lines1 = read_normalized_lines(&file1.path, &config.normalization)
+        .with_context(|| UnreadableFile(file1.path.clone()))?;
+    let mut lines2 = read_normalized_lines(&file2.path, &config.normalization)
+        .with_context(|| UnreadableFile(file2.path.clone()))?;
+
+    // Apply regex filtering if specified (invalid patterns are skipped)
+    let regexes: Vec<Regex> = config
+        .ignore_regexes
+        .iter()
+        .filter_map(|pattern| compile_ignore_regex(pattern))
+        .collect();
+    if !regexes.is_empty() {
+        let replacement = config.ignore_regex_replacement.as_deref().unwrap_or(IGNORED);
+        lines1 = apply_regex_filter(&lines1, &regexes, replacement);
+        lines2 = apply_regex_filter(&lines2, &regexes, replacement);
+    }
+
+    Ok(compare_lines(file1, file2, &lines1, &lines2, config))
+}
+
+/// Compare two symlinks indexed with `SymlinkMode::CompareTarget` by target
+///
+/// Each link is a one-line text whose line is the path it points to. Fails if
+/// only one of the two entries is a link.
+pub fn compare_link_targets(
+    file1: &FileEntry,
+    file2: &FileEntry,
+    config: &CompareConfig,
+) -> Result<TextComparisonResult> {
+    match (&file1.link_target, &file2.link_target) {
+        (Some(target1), Some(target2)) => {
+            Ok(compare_lines(file1, file2, std::slice::from_ref(target1), std::slice::from_ref(target2), config))
         }
+        _ => anyhow::bail!(
+            "Cannot compare a symlink with a regular file: {} vs {}",
+            file1.path.display(),
+            file2.path.display()
+        ),
+    }
+}
+
+/// Build the text comparison result for two sets of normalized lines
+fn compare_lines(
+    file1: &FileEntry,
+    file2: &FileEntry,
+    lines1: &[String],
+    lines2: &[String],
+    config: &CompareConfig,
+) -> TextComparisonResult {
+    let stats = compute_diff_stats(lines1, lines2, config);
+
+    // Generate unified diff format (also uses slice-based diff), unless only
+    // the numbers were asked for
+    let (detailed_diff, diff_truncated, whitespace_glyphs) = if config.summary_only {
+        (String::new(), false, WhitespaceGlyphs::default())
+    } else {
+        generate_unified_diff_from_slices(
+            &file1.path.display().to_string(),
+            &file2.path.display().to_string(),
+            lines1,
+            lines2,
+            config,
+        )
+    };
+
+    // Word-level highlights are meaningless once values are masked
+    let inline_edits = if config.inline_diff && !config.summary_only && !config.redact {
+        compute_inline_edits(lines1, lines2, config.show_whitespace, config.max_line_length)
+    } else {
+        Vec::new()
+    };
+
+    // Create linked ID
+    let linked_id = format!(
+        "{}:{}",
+        &file1.content_hash[..16.min(file1.content_hash.len())],
+        &file2.content_hash[..16.min(file2.content_hash.len())]
+    );
+
+    let identical = stats.only_in_file1 == 0 && stats.only_in_file2 == 0;
+
+    // Values are masked with `redact`, so a rename would leak them
+    let rename = if config.detect_rename
+        && !config.redact
+        && !identical
+        && stats.similarity_score >= RENAME_MIN_SIMILARITY
+    {
+        detect_rename(lines1, lines2)
+    } else {
+        None
+    };
+
+    TextComparisonResult {
+        linked_id,
+        file1_path: file1.path.display().to_string(),
+        file2_path: file2.path.display().to_string(),
+        file1_size: file1.size,
+        file2_size: file2.size,
+        file1_line_count: lines1.len(),
+        file2_line_count: lines2.len(),
+        common_lines: stats.common_lines,
+        only_in_file1: stats.only_in_file1,
+        only_in_file2: stats.only_in_file2,
+        similarity_score: stats.similarity_score,
+        different_positions: stats.different_positions,
+        detailed_diff,
+        diff_truncated,
+        inline_edits,
+        whitespace_glyphs,
+        rename,
+        identical,
     }
+}
 
+/// Compute line counts and similarity for two sets of normalized lines
+///
+/// This is the cheap half of a text comparison: it runs the Myers diff and the
+/// configured similarity algorithm but never builds a diff string, so callers
+/// that only need the numbers can skip the cost of `detailed_diff`.
+///
+/// With `config.order_insensitive_text`, both sides are sorted first, which
+/// turns the diff into a multiset comparison for every algorithm. Positions in
+/// sorted order say nothing about the files, so none are reported.
+pub fn compute_diff_stats(lines1: &[String], lines2: &[String], config: &CompareConfig) -> DiffStats {
+    if config.order_insensitive_text {
+        let (mut sorted1, mut sorted2) = (lines1.to_vec(), lines2.to_vec());
+        sorted1.sort_unstable();
+        sorted2.sort_unstable();
+        return DiffStats {
+            different_positions: String::new(),
+            ..line_diff_stats(&sorted1, &sorted2, config.similarity_algorithm)
+        };
+    }
+    line_diff_stats(lines1, lines2, config.similarity_algorithm)
+}
+
+/// Diff two line sequences in order and score them with `algorithm`
+fn line_diff_stats(lines1: &[String], lines2: &[String], algorithm: SimilarityAlgorithm) -> DiffStats {
     let refs1: Vec<&str> = lines1.iter().map(|s| s.as_str()).collect();
     let refs2: Vec<&str> = lines2.iter().map(|s| s.as_str()).collect();
 
@@ -53,9 +187,6 @@ pub fn compare_text_files(
     let mut only_in_file1 = 0;
     let mut only_in_file2 = 0;
     let mut different_positions = Vec::new();
-    let mut detailed_diff = String::new();
-    let mut diff_bytes = 0;
-    let mut diff_truncated = false;
 
     for (idx, change) in diff.iter_all_changes().enumerate() {
         match change.tag() {
@@ -65,40 +196,16 @@ pub fn compare_text_files(
             ChangeTag::Delete => {
                 only_in_file1 += 1;
                 different_positions.push(idx);
-
-                // Add to detailed diff if under limit
-                if diff_bytes < config.max_diff_bytes {
-                    let line = format!("-{}", change.value());
-                    diff_bytes += line.len();
-                    let _ = write!(detailed_diff, "{}", line);
-                    if !line.ends_with('\n') {
-                        detailed_diff.push('\n');
-                    }
-                } else {
-                    diff_truncated = true;
-                }
             }
             ChangeTag::Insert => {
                 only_in_file2 += 1;
                 different_positions.push(idx);
-
-                // Add to detailed diff if under limit
-                if diff_bytes < config.max_diff_bytes {
-                    let line = format!("+{}", change.value());
-                    diff_bytes += line.len();
-                    let _ = write!(detailed_diff, "{}", line);
-                    if !line.ends_with('\n') {
-                        detailed_diff.push('\n');
-                    }
-                } else {
-                    diff_truncated = true;
-                }
             }
         }
     }
 
     // Calculate similarity score
-    let similarity_score = match config.similarity_algorithm {
+    let similarity_score = match algorithm {
         SimilarityAlgorithm::Diff => {
             let total = common_lines + only_in_file1 + only_in_file2;
             if total > 0 {
@@ -129,8 +236,8 @@ pub fn compare_text_files(
             let text2 = lines2.join("\n");
             strsim::sorensen_dice(&text1, &text2)
         }
-        SimilarityAlgorithm::Jaccard => calculate_jaccard_similarity(&lines1, &lines2),
-        SimilarityAlgorithm::Cosine => calculate_cosine_similarity(&lines1, &lines2),
+        SimilarityAlgorithm::Jaccard => calculate_jaccard_similarity(lines1, lines2),
+        SimilarityAlgorithm::Cosine => calculate_cosine_similarity(lines1, lines2),
         SimilarityAlgorithm::RatcliffObershelp => {
             // Using similar's ratio() which roughly approximates Ratcliff/Obershelp 2.0*M/T
             // but is highly optimized (unlike a naive manual implementation)
@@ -143,66 +250,41 @@ pub fn compare_text_files(
         SimilarityAlgorithm::SmithWaterman => {
             // Full Smith-Waterman is O(N*M) and will hang on large files.
             // We use a token-based local alignment approximation here for safety.
-            calculate_token_smith_waterman(&lines1, &lines2)
+            calculate_token_smith_waterman(lines1, lines2)
         }
-        SimilarityAlgorithm::Lcs => calculate_lcs_similarity(&lines1, &lines2),
-        SimilarityAlgorithm::Hamming => calculate_hamming_similarity(&lines1, &lines2),
-        SimilarityAlgorithm::NGram => calculate_ngram_similarity(&lines1, &lines2),
-        SimilarityAlgorithm::TfIdf => calculate_tfidf_cosine_similarity(&lines1, &lines2),
+        SimilarityAlgorithm::Lcs => calculate_lcs_similarity(lines1, lines2),
+        SimilarityAlgorithm::Hamming => calculate_hamming_similarity(lines1, lines2),
+        SimilarityAlgorithm::NGram => calculate_ngram_similarity(lines1, lines2),
+        SimilarityAlgorithm::TfIdf => calculate_tfidf_cosine_similarity(lines1, lines2),
     };
 
-    // Generate unified diff format (also uses slice-based diff)
-    let unified_diff = generate_unified_diff_from_slices(
-        &file1.path.display().to_string(),
-        &file2.path.display().to_string(),
-        &lines1,
-        &lines2,
-        config.max_diff_bytes,
-    );
-
-    // Create linked ID
-    let linked_id = format!(
-        "{}:{}",
-        &file1.content_hash[..16.min(file1.content_hash.len())],
-        &file2.content_hash[..16.min(file2.content_hash.len())]
-    );
-
-    // Encode different positions as ranges
-    let positions_str = encode_ranges(&different_positions);
-
-    let identical = only_in_file1 == 0 && only_in_file2 == 0;
-
-    Ok(TextComparisonResult {
-        linked_id,
-        file1_path: file1.path.display().to_string(),
-        file2_path: file2.path.display().to_string(),
-        file1_line_count: lines1.len(),
-        file2_line_count: lines2.len(),
+    DiffStats {
         common_lines,
         only_in_file1,
         only_in_file2,
         similarity_score,
-        different_positions: positions_str,
-        detailed_diff: if unified_diff.0.is_empty() {
-            detailed_diff
-        } else {
-            unified_diff.0
-        },
-        diff_truncated: diff_truncated || unified_diff.1,
-        identical,
-    })
+        different_positions: encode_ranges(&different_positions),
+    }
 }
 
 /// Generate unified diff format output from line slices
 ///
 /// Uses `diff_slices` to avoid constructing massive strings for large files.
+/// Output stops at `config.max_diff_bytes`, and lines longer than
+/// `config.max_line_length` characters are cut short by [`truncate_line`].
+/// With `show_whitespace`, changed lines are passed through [`visualize_whitespace`]
+/// and the glyphs it substituted are returned.
+/// With `redact`, every line's content is replaced by [`REDACTED`]; hunk headers
+/// and `+`/`-` markers are kept so the shape of the change is still visible.
 fn generate_unified_diff_from_slices(
     file1_name: &str,
     file2_name: &str,
     lines1: &[String],
     lines2: &[String],
-    max_bytes: usize,
-) -> (String, bool) {
+    config: &CompareConfig,
+) -> (String, bool, WhitespaceGlyphs) {
+    let max_bytes = config.max_diff_bytes;
+
     // Convert &[String] to Vec<&str> for diff_slices compatibility
     let refs1: Vec<&str> = lines1.iter().map(|s| s.as_str()).collect();
     let refs2: Vec<&str> = lines2.iter().map(|s| s.as_str()).collect();
@@ -213,6 +295,7 @@ fn generate_unified_diff_from_slices(
 
     let mut output = String::new();
     let mut truncated = false;
+    let mut glyphs = WhitespaceGlyphs::default();
 
     // Header
     let _ = writeln!(output, "--- {}", file1_name);
@@ -237,7 +320,30 @@ fn generate_unified_diff_from_slices(
                 ChangeTag::Insert => "+",
                 ChangeTag::Equal => " ",
             };
-            let _ = write!(output, "{}{}", prefix, change.value());
+            if config.redact {
+                let _ = write!(output, "{}{}", prefix, REDACTED);
+            } else {
+                let (line, cut) = truncate_line(change.value(), config.max_line_length);
+                if config.show_whitespace && change.tag() != ChangeTag::Equal {
+                    let (visible, offsets) = visualize_whitespace_glyphs(line);
+                    let _ = write!(output, "{}{}", prefix, visible);
+                    match (change.tag(), change.old_index(), change.new_index()) {
+                        _ if offsets.is_empty() => {}
+                        (ChangeTag::Delete, Some(old), _) => {
+                            glyphs.removed.insert(old + 1, offsets);
+                        }
+                        (_, _, Some(new)) => {
+                            glyphs.added.insert(new + 1, offsets);
+                        }
+                        _ => {}
+                    }
+                } else {
+                    let _ = write!(output, "{}{}", prefix, line);
+                }
+                if cut > 0 {
+                    let _ = write!(output, "... ({} chars truncated)", cut);
+                }
+            }
             // Lines from diff_slices don't have trailing newlines, so always add one
             output.push('\n');
         }
@@ -247,7 +353,239 @@ fn generate_unified_diff_from_slices(
         output.push_str("\n... [diff truncated] ...\n");
     }
 
-    (output, truncated)
+    (output, truncated, glyphs)
+}
+
+/// Generate a patch that turns file 1 into file 2
+///
+/// Unlike `detailed_diff`, the patch is built from the raw file contents with
+/// no normalization, truncation or redaction, so `patch -p1` or `git apply`
+/// reproduces file 2 byte for byte (a missing final newline is marked with
+/// `\ No newline at end of file`). `name1` and `name2` are the paths written
+/// after the `a/` and `b/` prefixes. Identical files give an empty patch.
+///
+/// Files are decoded like the comparison decodes them, so a Latin-1 or
+/// UTF-16 pair gets a UTF-8 patch.
+pub fn generate_patch(path1: &Path, path2: &Path, name1: &str, name2: &str) -> Result<String> {
+    let (text1, _) = read_text_decoded(path1).with_context(|| format!("Failed to read {}", path1.display()))?;
+    let (text2, _) = read_text_decoded(path2).with_context(|| format!("Failed to read {}", path2.display()))?;
+
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .diff_lines(&text1, &text2);
+
+    Ok(diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", name1), &format!("b/{}", name2))
+        .to_string())
+}
+
+/// Cut a line down to `max_chars` characters
+///
+/// Returns the kept prefix and the number of characters dropped. A limit of
+/// 0 keeps the whole line.
+fn truncate_line(line: &str, max_chars: usize) -> (&str, usize) {
+    if max_chars == 0 {
+        return (line, 0);
+    }
+    match line.char_indices().nth(max_chars) {
+        Some((end, _)) => (&line[..end], line[end..].chars().count()),
+        None => (line, 0),
+    }
+}
+
+/// Find the changed words within each modified line
+///
+/// Removed and added lines of a replaced block are paired in order and diffed
+/// word by word. With `show_whitespace`, offsets refer to the visualized lines
+/// so they line up with `detailed_diff`. Pairs involving a line longer than
+/// `max_line_length` are skipped, since the diff only shows part of them.
+fn compute_inline_edits(
+    lines1: &[String],
+    lines2: &[String],
+    show_whitespace: bool,
+    max_line_length: usize,
+) -> Vec<InlineEdit> {
+    let refs1: Vec<&str> = lines1.iter().map(|s| s.as_str()).collect();
+    let refs2: Vec<&str> = lines2.iter().map(|s| s.as_str()).collect();
+
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .diff_slices(&refs1, &refs2);
+
+    let mut edits = Vec::new();
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag != DiffTag::Replace {
+            continue;
+        }
+        for (old_idx, new_idx) in old_range.zip(new_range) {
+            let too_long = |line: &str| truncate_line(line, max_line_length).1 > 0;
+            if too_long(refs1[old_idx]) || too_long(refs2[new_idx]) {
+                continue;
+            }
+            let (old, new) = if show_whitespace {
+                (visualize_whitespace(refs1[old_idx]), visualize_whitespace(refs2[new_idx]))
+            } else {
+                (refs1[old_idx].to_string(), refs2[new_idx].to_string())
+            };
+            let (removed, added) = word_edit_ranges(&old, &new);
+            edits.push(InlineEdit {
+                old_line: old_idx + 1,
+                new_line: new_idx + 1,
+                removed,
+                added,
+            });
+        }
+    }
+
+    edits
+}
+
+/// Infer a find-and-replace that explains most of the changes
+///
+/// Removed and added lines of each replaced block are paired in order and
+/// split into identifier tokens. Where a pair has the same number of tokens,
+/// every position holding different identifiers is a candidate substitution.
+/// The most frequent one is returned if it makes up a majority of all
+/// substitutions found.
+fn detect_rename(lines1: &[String], lines2: &[String]) -> Option<RenameHint> {
+    let refs1: Vec<&str> = lines1.iter().map(|s| s.as_str()).collect();
+    let refs2: Vec<&str> = lines2.iter().map(|s| s.as_str()).collect();
+
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .diff_slices(&refs1, &refs2);
+
+    let mut substitutions: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut total = 0;
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag != DiffTag::Replace {
+            continue;
+        }
+        for (old_idx, new_idx) in old_range.zip(new_range) {
+            let (old, new) = (tokenize(refs1[old_idx]), tokenize(refs2[new_idx]));
+            if old.len() != new.len() {
+                continue;
+            }
+            for (a, b) in old.into_iter().zip(new) {
+                if a != b && is_identifier(a) && is_identifier(b) {
+                    *substitutions.entry((a, b)).or_default() += 1;
+                    total += 1;
+                }
+            }
+        }
+    }
+
+    // Highest count wins; ties go to the alphabetically first pair
+    let ((from, to), occurrences) = substitutions
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))?;
+    (occurrences * 2 > total).then(|| RenameHint {
+        from: from.to_string(),
+        to: to.to_string(),
+        occurrences,
+    })
+}
+
+/// Split a line into identifier runs and single other characters
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        let word = c.is_alphanumeric() || c == '_';
+        match (word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push(&line[s..i]);
+                start = None;
+            }
+            _ => {}
+        }
+        if !word {
+            tokens.push(&line[i..i + c.len_utf8()]);
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&line[s..]);
+    }
+    tokens
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Half-open byte ranges within a line
+type ByteRanges = Vec<(usize, usize)>;
+
+/// Byte ranges removed from `old` and added to `new` by a word diff
+///
+/// Adjacent changed tokens are merged into a single range.
+fn word_edit_ranges(old: &str, new: &str) -> (ByteRanges, ByteRanges) {
+    fn push_range(ranges: &mut ByteRanges, start: usize, end: usize) {
+        match ranges.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut pos_old, mut pos_new) = (0, 0);
+
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let len = change.value().len();
+        match change.tag() {
+            ChangeTag::Equal => {
+                pos_old += len;
+                pos_new += len;
+            }
+            ChangeTag::Delete => {
+                push_range(&mut removed, pos_old, pos_old + len);
+                pos_old += len;
+            }
+            ChangeTag::Insert => {
+                push_range(&mut added, pos_new, pos_new + len);
+                pos_new += len;
+            }
+        }
+    }
+
+    (removed, added)
+}
+
+/// Make whitespace in a line visible
+///
+/// Spaces are rendered as `·` and tabs as `→`; a trailing `¶` marks lines
+/// that end in whitespace.
+fn visualize_whitespace(line: &str) -> String {
+    visualize_whitespace_glyphs(line).0
+}
+
+/// [`visualize_whitespace`], also returning the byte offsets of the glyphs it put in
+fn visualize_whitespace_glyphs(line: &str) -> (String, Vec<usize>) {
+    let mut visible = String::with_capacity(line.len());
+    let mut offsets = Vec::new();
+    for c in line.chars() {
+        let glyph = match c {
+            ' ' => '·',
+            '\t' => '→',
+            _ => {
+                visible.push(c);
+                continue;
+            }
+        };
+        offsets.push(visible.len());
+        visible.push(glyph);
+    }
+    if line.ends_with([' ', '\t']) {
+        offsets.push(visible.len());
+        visible.push('¶');
+    }
+    (visible, offsets)
 }
 
 /// Encode a list of positions as ranges (e.g., "1-5,8,10-15")
@@ -303,14 +641,23 @@ fn compile_ignore_regex(pattern: &str) -> Option<Regex> {
     }
 }
 
-/// Apply regex filter to lines, replacing matches with `<IGNORED>`
+/// Default replacement for `ignore_regex` matches
+const IGNORED: &str = "<IGNORED>";
+
+/// Apply regex filters to lines, replacing matches with `replacement`
 ///
 /// This allows comparing files while ignoring specific content like timestamps,
-/// generated IDs, or other dynamic values.
-fn apply_regex_filter(lines: &[String], regex: &Regex) -> Vec<String> {
+/// generated IDs, or other dynamic values. The regexes run in order, each on
+/// the output of the previous one. The replacement may refer to capture
+/// groups (`$1`, `${name}`) to keep part of each match.
+fn apply_regex_filter(lines: &[String], regexes: &[Regex], replacement: &str) -> Vec<String> {
     lines
         .iter()
-        .map(|line| regex.replace_all(line, "<IGNORED>").into_owned())
+        .map(|line| {
+            regexes.iter().fold(line.clone(), |line, regex| {
+                regex.replace_all(&line, replacement).into_owned()
+            })
+        })
         .collect()
 }
 
@@ -610,6 +957,7 @@ fn calculate_tfidf_cosine_similarity(lines1: &[String], lines2: &[String]) -> f6
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::UnicodeForm;
 
     #[test]
     fn test_encode_ranges() {
@@ -618,10 +966,354 @@ mod tests {
         assert_eq!(encode_ranges(&[]), "");
     }
 
+    #[test]
+    fn test_show_whitespace_tabs_vs_spaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("tabs.py");
+        let path2 = dir.path().join("spaces.py");
+        std::fs::write(&path1, "def f():\n\treturn 1\n").unwrap();
+        std::fs::write(&path2, "def f():\n    return 1 \n").unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+
+        let plain = compare_text_files(&file1, &file2, &CompareConfig::default()).unwrap();
+        assert!(plain.detailed_diff.contains("-\treturn 1\n"));
+
+        let config = CompareConfig {
+            show_whitespace: true,
+            ..CompareConfig::default()
+        };
+        let visible = compare_text_files(&file1, &file2, &config).unwrap();
+        assert!(visible.detailed_diff.contains("-→return·1\n"));
+        assert!(visible.detailed_diff.contains("+····return·1·¶\n"));
+        assert_eq!(visible.whitespace_glyphs.removed[&2], vec![0, 9]);
+        assert_eq!(visible.whitespace_glyphs.added[&2], vec![0, 2, 4, 6, 14, 17, 19]);
+        assert!(plain.whitespace_glyphs.is_empty());
+
+        // Unchanged context lines are left alone
+        assert!(visible.detailed_diff.contains(" def f():\n"));
+
+        // Glyphs already in the file are not reported as whitespace
+        std::fs::write(&path1, "a → b\n").unwrap();
+        std::fs::write(&path2, "a → c\n").unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+        let visible = compare_text_files(&file1, &file2, &config).unwrap();
+        assert!(visible.detailed_diff.contains("-a·→·b\n"));
+        assert_eq!(visible.whitespace_glyphs.removed[&1], vec![1, 6]);
+    }
+
+    #[test]
+    fn test_inline_edit_covers_changed_word() {
+        let lines1 = vec!["header".to_string(), "the quick brown fox".to_string()];
+        let lines2 = vec!["header".to_string(), "the quick red fox".to_string()];
+
+        let edits = compute_inline_edits(&lines1, &lines2, false, 0);
+        assert_eq!(
+            edits,
+            vec![InlineEdit {
+                old_line: 2,
+                new_line: 2,
+                removed: vec![(10, 15)],
+                added: vec![(10, 13)],
+            }]
+        );
+        assert_eq!(&lines1[1][10..15], "brown");
+        assert_eq!(&lines2[1][10..13], "red");
+    }
+
+    #[test]
+    fn test_long_lines_truncated_in_diff() {
+        let long = "x".repeat(1024 * 1024);
+        let lines1 = vec!["short".to_string(), long.clone()];
+        let lines2 = vec!["short".to_string(), format!("{}y", long)];
+        let config = CompareConfig {
+            max_line_length: 1000,
+            max_diff_bytes: usize::MAX,
+            ..CompareConfig::default()
+        };
+
+        let (diff, _, _) = generate_unified_diff_from_slices("a", "b", &lines1, &lines2, &config);
+        let removed = diff.lines().find(|l| l.starts_with('-') && !l.starts_with("---")).unwrap();
+        let added = diff.lines().find(|l| l.starts_with('+') && !l.starts_with("+++")).unwrap();
+        assert_eq!(removed, format!("-{}... ({} chars truncated)", "x".repeat(1000), 1024 * 1024 - 1000));
+        assert!(added.ends_with(&format!("... ({} chars truncated)", 1024 * 1024 + 1 - 1000)));
+        assert!(diff.len() < 4096);
+
+        assert_eq!(truncate_line("héllo", 2), ("hé", 3));
+        assert_eq!(truncate_line("héllo", 0), ("héllo", 0));
+    }
+
+    #[test]
+    fn test_redact_masks_diff_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("a.env");
+        let path2 = dir.path().join("b.env");
+        std::fs::write(&path1, "HOST=db\nPASSWORD=hunter2\n").unwrap();
+        std::fs::write(&path2, "HOST=db\nPASSWORD=letmein\n").unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+
+        let config = CompareConfig {
+            redact: true,
+            ..CompareConfig::default()
+        };
+        let result = compare_text_files(&file1, &file2, &config).unwrap();
+        assert!(!result.detailed_diff.contains("hunter2"));
+        assert!(!result.detailed_diff.contains("letmein"));
+        assert!(result.detailed_diff.contains("-***\n+***\n"));
+        assert_eq!(result.only_in_file1, 1);
+        assert_eq!(result.only_in_file2, 1);
+    }
+
+    #[test]
+    fn test_detect_rename_finds_renamed_identifier() {
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("a.rs");
+        let path2 = dir.path().join("b.rs");
+        let source = "fn load_config(path: &str) -> Config {\n    let raw = read(path);\n    parse(raw)\n}\n\nfn main() {\n    let cfg = load_config(\"app.toml\");\n    run(cfg);\n}\n\n// load_config is cached\n";
+        std::fs::write(&path1, source).unwrap();
+        std::fs::write(&path2, source.replace("load_config", "read_settings")).unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+
+        let config = CompareConfig {
+            detect_rename: true,
+            ..CompareConfig::default()
+        };
+        let result = compare_text_files(&file1, &file2, &config).unwrap();
+        let rename = result.rename.unwrap();
+        assert_eq!((rename.from.as_str(), rename.to.as_str()), ("load_config", "read_settings"));
+        assert_eq!(rename.occurrences, 3);
+        assert_eq!(rename.to_string(), "appears to be a rename: `load_config` → `read_settings` (3 occurrences)");
+
+        // Off by default
+        let result = compare_text_files(&file1, &file2, &CompareConfig::default()).unwrap();
+        assert!(result.rename.is_none());
+    }
+
+    #[test]
+    fn test_ignore_head_lines_skips_banner() {
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("a.txt");
+        let path2 = dir.path().join("b.txt");
+        let body = "alpha\nbeta\ngamma\n";
+        std::fs::write(&path1, format!("# generated 2024-01-01\n# by build 17\n\n{}", body)).unwrap();
+        std::fs::write(&path2, format!("# generated 2024-06-30\n# by build 42\n\n{}", body)).unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+
+        let differs = compare_text_files(&file1, &file2, &CompareConfig::default()).unwrap();
+        assert!(!differs.identical);
+
+        let mut config = CompareConfig::default();
+        config.normalization.ignore_head_lines = 3;
+        let result = compare_text_files(&file1, &file2, &config).unwrap();
+        assert!(result.identical);
+        assert_eq!(result.common_lines, 3);
+
+        // More ignored lines than the file has leaves nothing to compare
+        config.normalization.ignore_tail_lines = 10;
+        assert!(read_normalized_lines(&path1, &config.normalization).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unicode_normalization_matches_composed_and_decomposed_accents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("mac.txt");
+        let path2 = dir.path().join("linux.txt");
+        // "café" with a decomposed (NFD) and a precomposed (NFC) é
+        std::fs::write(&path1, "cafe\u{301}\n").unwrap();
+        std::fs::write(&path2, "caf\u{e9}\n").unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+
+        let raw = compare_text_files(&file1, &file2, &CompareConfig::default()).unwrap();
+        assert!(!raw.identical);
+
+        let mut config = CompareConfig::default();
+        config.normalization.unicode_normalize = Some(UnicodeForm::Nfc);
+        let result = compare_text_files(&file1, &file2, &config).unwrap();
+        assert!(result.identical);
+        assert_eq!(result.common_lines, 1);
+    }
+
+    #[test]
+    fn test_order_insensitive_text_compares_line_multisets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("a.conf");
+        let path2 = dir.path().join("b.conf");
+        std::fs::write(&path1, "port = 80\nhost = a\nuser = x\nuser = x\n").unwrap();
+        std::fs::write(&path2, "user = x\nport = 80\nuser = x\nhost = a\n").unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+
+        let positional = compare_text_files(&file1, &file2, &CompareConfig::default()).unwrap();
+        assert!(positional.similarity_score < 1.0);
+        assert!(!positional.identical);
+
+        let config = CompareConfig { order_insensitive_text: true, ..CompareConfig::default() };
+        let result = compare_text_files(&file1, &file2, &config).unwrap();
+        assert_eq!(result.similarity_score, 1.0);
+        assert!(result.identical);
+        assert_eq!((result.common_lines, result.only_in_file1, result.only_in_file2), (4, 0, 0));
+        // The report still shows where the lines moved
+        assert!(result.detailed_diff.contains("+user = x"));
+
+        // A duplicated line counts once per occurrence
+        let lines = |text: &str| text.lines().map(String::from).collect::<Vec<_>>();
+        let stats = compute_diff_stats(&lines("b\na\na"), &lines("a\nb\nc"), &config);
+        assert_eq!((stats.common_lines, stats.only_in_file1, stats.only_in_file2), (2, 1, 1));
+    }
+
+    #[test]
+    fn test_ignore_regex_replacement_keeps_capture_group() {
+        let regex = compile_ignore_regex(r"^\d{4}-\d{2}-\d{2}T[\d:.]+Z (.*)$").unwrap();
+        let lines = vec![
+            "2024-03-01T10:15:00.123Z ERROR disk full".to_string(),
+            "no timestamp here".to_string(),
+        ];
+
+        let regexes = [regex];
+        let masked = apply_regex_filter(&lines, &regexes, "<TS> $1");
+        assert_eq!(masked, vec!["<TS> ERROR disk full", "no timestamp here"]);
+        assert_eq!(apply_regex_filter(&lines, &regexes, IGNORED)[0], "<IGNORED>");
+
+        // Different timestamps compare equal, different messages still differ
+        let dir = tempfile::tempdir().unwrap();
+        let path1 = dir.path().join("a.log");
+        let path2 = dir.path().join("b.log");
+        std::fs::write(&path1, "2024-03-01T10:15:00Z started\n2024-03-01T10:15:02Z ERROR disk full\n").unwrap();
+        std::fs::write(&path2, "2024-06-09T08:00:00Z started\n2024-06-09T08:00:07Z ERROR disk almost full\n").unwrap();
+        let file1 = crate::index::index_single_file(&path1).unwrap();
+        let file2 = crate::index::index_single_file(&path2).unwrap();
+
+        let config = CompareConfig {
+            ignore_regexes: vec![r"^\d{4}-\d{2}-\d{2}T[\d:.]+Z (.*)$".to_string()],
+            ignore_regex_replacement: Some("<TS> $1".to_string()),
+            ..CompareConfig::default()
+        };
+        let result = compare_text_files(&file1, &file2, &config).unwrap();
+        assert_eq!(result.common_lines, 1);
+        assert!(result.detailed_diff.contains("-<TS> ERROR disk full\n+<TS> ERROR disk almost full\n"));
+    }
+
+    #[test]
+    fn test_multiple_ignore_regexes_mask_same_line() {
+        let regexes: Vec<Regex> = [r"\d{4}-\d{2}-\d{2}", r"\b0x[0-9a-f]+\b"]
+            .iter()
+            .map(|p| compile_ignore_regex(p).unwrap())
+            .collect();
+        let lines = vec!["2024-03-01 request 0xdeadbeef done".to_string()];
+
+        let masked = apply_regex_filter(&lines, &regexes, IGNORED);
+        assert_eq!(masked, vec!["<IGNORED> request <IGNORED> done"]);
+
+        // Each pattern alone leaves the other value in place
+        assert_eq!(apply_regex_filter(&lines, &regexes[..1], IGNORED)[0], "<IGNORED> request 0xdeadbeef done");
+        assert_eq!(apply_regex_filter(&lines, &regexes[1..], IGNORED)[0], "2024-03-01 request <IGNORED> done");
+    }
+
+    fn stats_fixture() -> (Vec<String>, Vec<String>) {
+        let lines1: Vec<String> = (0..20_000).map(|i| format!("line {}", i)).collect();
+        let lines2: Vec<String> = (0..20_000)
+            .map(|i| if i % 50 == 0 { format!("changed {}", i) } else { format!("line {}", i) })
+            .collect();
+        (lines1, lines2)
+    }
+
+    #[test]
+    fn test_diff_stats_skip_diff_construction() {
+        let (lines1, lines2) = stats_fixture();
+        let config = CompareConfig::default();
+
+        let stats = compute_diff_stats(&lines1, &lines2, &config);
+        assert_eq!(stats.only_in_file1, 400);
+        assert_eq!(stats.only_in_file2, 400);
+        assert_eq!(stats.common_lines, 19_600);
+
+        // The counts agree with the full diff they skip building
+        let unlimited = CompareConfig {
+            max_diff_bytes: usize::MAX,
+            ..CompareConfig::default()
+        };
+        let (diff, truncated, _) = generate_unified_diff_from_slices("a", "b", &lines1, &lines2, &unlimited);
+        assert!(!truncated);
+        let count = |prefix: char| diff.lines().skip(2).filter(|l| l.starts_with(prefix)).count();
+        assert_eq!((count('-'), count('+')), (stats.only_in_file1, stats.only_in_file2));
+    }
+
+    /// Timing check, run with `cargo test -- --ignored` on an otherwise idle machine
+    #[test]
+    #[ignore]
+    fn test_diff_stats_are_faster_than_full_diff() {
+        let (lines1, lines2) = stats_fixture();
+        let config = CompareConfig::default();
+
+        // Best of three to keep scheduler noise out of the comparison
+        let best_of = |run: &dyn Fn()| {
+            (0..3)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    run();
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let unlimited = CompareConfig {
+            max_diff_bytes: usize::MAX,
+            ..CompareConfig::default()
+        };
+        let stats_only = best_of(&|| {
+            std::hint::black_box(compute_diff_stats(&lines1, &lines2, &config));
+        });
+        let with_diff = best_of(&|| {
+            std::hint::black_box(compute_diff_stats(&lines1, &lines2, &config));
+            std::hint::black_box(generate_unified_diff_from_slices(
+                "a", "b", &lines1, &lines2, &unlimited,
+            ));
+        });
+        assert!(
+            stats_only < with_diff,
+            "stats-only {:?} should beat full diff {:?}",
+            stats_only,
+            with_diff
+        );
+    }
+
+    #[test]
+    fn test_every_similarity_algorithm_parses_and_scores() {
+        use clap::ValueEnum;
+
+        let lines1: Vec<String> = ["alpha beta", "gamma delta", "epsilon zeta"].map(String::from).to_vec();
+        // Differ on the first line: strsim's Jaro-Winkler saturates at 1.0 on long common prefixes
+        let lines2: Vec<String> = ["omega beta", "gamma delta", "epsilon zeta"].map(String::from).to_vec();
+
+        for &algorithm in SimilarityAlgorithm::value_variants() {
+            let name = algorithm.to_possible_value().unwrap().get_name().to_string();
+            assert_eq!(ValueEnum::from_str(&name, false), Ok(algorithm), "{}", name);
+            assert_eq!(name.parse(), Ok(algorithm));
+
+            let config = CompareConfig {
+                similarity_algorithm: algorithm,
+                ..CompareConfig::default()
+            };
+            let same = compute_diff_stats(&lines1, &lines1, &config).similarity_score;
+            let changed = compute_diff_stats(&lines1, &lines2, &config).similarity_score;
+            assert!((same - 1.0).abs() < 1e-9, "{} scored identical input {}", name, same);
+            assert!(changed < 1.0 && changed > 0.0, "{} scored changed input {}", name, changed);
+        }
+        assert_eq!("smith-waterman".parse(), Ok(SimilarityAlgorithm::SmithWaterman));
+        assert_eq!("TF-IDF".parse(), Ok(SimilarityAlgorithm::TfIdf));
+        assert_eq!("ngram".parse(), Ok(SimilarityAlgorithm::NGram));
+        assert!("soundex".parse::<SimilarityAlgorithm>().unwrap_err().contains("n-gram"));
+    }
+
     #[test]
     fn test_diff_slices_basic() {
-        let lines1 = vec!["line1".to_string(), "line2".to_string(), "line3".to_string()];
-        let lines2 = vec!["line1".to_string(), "modified".to_string(), "line3".to_string()];
+        let lines1 = ["line1".to_string(), "line2".to_string(), "line3".to_string()];
+        let lines2 = ["line1".to_string(), "modified".to_string(), "line3".to_string()];
         
         // Convert to &str slices for diff_slices
         let refs1: Vec<&str> = lines1.iter().map(|s| s.as_str()).collect();
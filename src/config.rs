@@ -0,0 +1,116 @@
+//! JSON config files for the compare command
+//!
+//! A config file holds any subset of [`CompareConfig`]'s fields, so a team can
+//! commit `compareit.json` instead of repeating dozens of flags. Lines whose
+//! first non-blank characters are `//` are comments. Flags given on the
+//! command line override the file, even when they repeat a default value.
+
+use crate::types::CompareConfig;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Default file name written by `init`
+pub const DEFAULT_CONFIG_FILE: &str = "compareit.json";
+
+/// Comments written above the most commonly edited fields by [`config_template`]
+const FIELD_COMMENTS: &[(&str, &str)] = &[
+    ("mode", "auto, text or structured"),
+    ("pairing", "same-path, same-name, all-vs-all or by-content-hash"),
+    ("key_columns", "Columns identifying a record in CSV/Excel/JSON files, e.g. [\"id\"]"),
+    ("numeric_tolerance", "Numbers closer than this compare as equal (see tolerance_mode)"),
+    ("column_tolerances", "Per-column tolerances, e.g. {\"price\": 0.01}"),
+    ("normalization", "Text normalization applied before diffing"),
+    ("similarity_algorithm", "Text similarity metric, e.g. ratcliff-obershelp or tf-idf"),
+    ("exclude_patterns", "Glob patterns of files to skip, e.g. [\"**/*.log\"]"),
+    ("ignore_columns", "Columns left out of structured comparisons"),
+    ("results_base", "Output locations; relative paths resolve from the working directory"),
+];
+
+/// Read a config file, keeping defaults for the fields it leaves out
+pub fn load_config(path: &Path) -> Result<CompareConfig> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read config file {}", path.display()))?;
+    serde_json::from_str(&strip_comments(&text))
+        .with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+/// Apply the command-line settings given explicitly on top of a file config
+///
+/// `cli` is the config built from the actual command line and `fields` the
+/// fields its explicit flags set, as dotted paths like
+/// `normalization.ignore_case`. Only those fields are copied, so
+/// `--ignore-case` keeps the file's other normalization options.
+pub fn apply_overrides(file: CompareConfig, cli: &CompareConfig, fields: &[String]) -> Result<CompareConfig> {
+    let mut merged = serde_json::to_value(file)?;
+    let cli = serde_json::to_value(cli)?;
+    for field in fields {
+        let unknown = || format!("Unknown config field {}", field);
+        let value = cli.pointer(&format!("/{}", field.replace('.', "/"))).with_context(unknown)?;
+        let (parent, key) = match field.rsplit_once('.') {
+            Some((parent, key)) => (format!("/{}", parent.replace('.', "/")), key),
+            None => (String::new(), field.as_str()),
+        };
+        merged
+            .pointer_mut(&parent)
+            .and_then(|p| p.as_object_mut())
+            .with_context(unknown)?
+            .insert(key.to_string(), value.clone());
+    }
+    serde_json::from_value(merged).context("Failed to merge command-line flags into the config file")
+}
+
+/// Render `config` as a config file, with comments on the main fields
+pub fn config_template(config: &CompareConfig) -> Result<String> {
+    let json = serde_json::to_string_pretty(config)?;
+    let mut out = String::from(
+        "// CompareIt config: use with `CompareIt compare <path1> <path2> --config <this file>`.\n\
+         // Fields can be deleted to keep their defaults; command-line flags override them.\n",
+    );
+    for line in json.lines() {
+        let key = line.strip_prefix("  \"").and_then(|rest| rest.split_once('"')).map(|(key, _)| key);
+        if let Some((_, comment)) = key.and_then(|key| FIELD_COMMENTS.iter().find(|(field, _)| *field == key)) {
+            out.push_str(&format!("  // {}\n", comment));
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Blank out `//` comment lines, keeping line numbers for parse errors
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .map(|line| if line.trim_start().starts_with("//") { "" } else { line })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_round_trips() {
+        let config = CompareConfig {
+            key_columns: vec!["id".to_string()],
+            ..CompareConfig::default()
+        };
+        let template = config_template(&config).unwrap();
+        assert!(template.contains("  // Columns identifying a record"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_CONFIG_FILE);
+        fs::write(&path, template).unwrap();
+        let loaded = load_config(&path).unwrap();
+        assert_eq!(loaded.key_columns, vec!["id"]);
+    }
+
+    #[test]
+    fn test_single_ignore_regex_is_accepted() {
+        let config: CompareConfig = serde_json::from_str(r#"{"ignore_regex": "\\d{4}-\\d{2}-\\d{2}"}"#).unwrap();
+        assert_eq!(config.ignore_regexes, vec![r"\d{4}-\d{2}-\d{2}"]);
+
+        let config: CompareConfig = serde_json::from_str(r#"{"ignore_regexes": ["a", "b"]}"#).unwrap();
+        assert_eq!(config.ignore_regexes, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Run diagnostics
+//!
+//! Pipeline stages report data-quality issues (oversized files, encoding
+//! failures, ragged rows, duplicate keys, unparseable structured files) into a
+//! shared [`Diagnostics`] sink instead of only logging them. At the end of a run
+//! the sink is turned into an [`AnomalyReport`] for the summary and HTML report.
+
+use crate::types::{Anomaly, AnomalyKind, AnomalyReport};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Thread-safe collector for anomalies found during a run
+///
+/// Stages running inside rayon iterators record into the same sink, so all
+/// access goes through a mutex.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    anomalies: Mutex<Vec<Anomaly>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an anomaly for a file
+    pub fn record(&self, kind: AnomalyKind, path: &Path, detail: impl Into<String>) {
+        let anomaly = Anomaly {
+            kind,
+            path: path.display().to_string(),
+            detail: detail.into(),
+        };
+        self.anomalies.lock().unwrap().push(anomaly);
+    }
+
+    /// Discard all recorded anomalies
+    pub fn clear(&self) {
+        self.anomalies.lock().unwrap().clear();
+    }
+
+    /// Aggregate everything recorded so far into a report
+    pub fn report(&self) -> AnomalyReport {
+        AnomalyReport::from_anomalies(self.anomalies.lock().unwrap().clone())
+    }
+}
+
+/// Error context naming the input file that could not be read
+///
+/// Comparisons wrap each side's read errors in it, so a failed pair can be
+/// traced back to the file at fault with [`failed_file`].
+#[derive(Debug)]
+pub struct UnreadableFile(pub PathBuf);
+
+impl fmt::Display for UnreadableFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to read {}", self.0.display())
+    }
+}
+
+/// The input file an error was raised reading, if it carries [`UnreadableFile`]
+pub fn failed_file(err: &anyhow::Error) -> Option<&Path> {
+    err.downcast_ref::<UnreadableFile>().map(|file| file.0.as_path())
+}
+
+/// Returns true if an error was caused by content that could not be decoded
+///
+/// Covers `std::io` invalid-data errors (raised by line readers on invalid
+/// UTF-8) and CSV UTF-8 errors.
+pub fn is_encoding_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(io_err) = cause.downcast_ref::<io::Error>() {
+            return io_err.kind() == io::ErrorKind::InvalidData;
+        }
+        if let Some(csv_err) = cause.downcast_ref::<csv::Error>() {
+            return matches!(csv_err.kind(), csv::ErrorKind::Utf8 { .. });
+        }
+        false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_report_counts_and_orders_anomalies() {
+        let diagnostics = Diagnostics::new();
+        diagnostics.record(AnomalyKind::DuplicateKeys, Path::new("b.csv"), "2 duplicate keys");
+        diagnostics.record(AnomalyKind::OversizedFile, Path::new("big.txt"), "too large");
+        diagnostics.record(AnomalyKind::DuplicateKeys, Path::new("a.csv"), "1 duplicate key");
+
+        let report = diagnostics.report();
+        assert_eq!(report.count(AnomalyKind::DuplicateKeys), 2);
+        assert_eq!(report.count(AnomalyKind::OversizedFile), 1);
+        assert_eq!(report.count(AnomalyKind::RaggedRows), 0);
+        assert_eq!(report.anomalies[0].kind, AnomalyKind::OversizedFile);
+        assert_eq!(report.anomalies[1].path, "a.csv");
+
+        diagnostics.clear();
+        assert!(diagnostics.report().is_empty());
+    }
+
+    #[test]
+    fn test_is_encoding_error() {
+        let io_err = io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+        let err = Err::<(), _>(io_err).context("Failed to read lines").unwrap_err();
+        assert!(is_encoding_error(&err));
+
+        let not_found = anyhow::Error::new(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert!(!is_encoding_error(&not_found));
+    }
+
+    #[test]
+    fn test_failed_file_is_found_under_later_context() {
+        // "b.csv" contains "a.csv" as a substring, so only the typed context tells them apart
+        let err = Err::<(), _>(io::Error::new(io::ErrorKind::InvalidData, "bad byte"))
+            .with_context(|| UnreadableFile(PathBuf::from("data/b.csv")))
+            .context("Comparing data/a.csv with data/b.csv")
+            .unwrap_err();
+        assert_eq!(failed_file(&err), Some(Path::new("data/b.csv")));
+        assert_eq!(format!("{:#}", err), "Comparing data/a.csv with data/b.csv: Failed to read data/b.csv: bad byte");
+
+        assert_eq!(failed_file(&anyhow::anyhow!("Key column id not found")), None);
+    }
+}
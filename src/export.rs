@@ -3,13 +3,25 @@
 //! This module handles writing comparison results to various formats:
 //! - JSONL (streaming, one result per line)
 //! - CSV (summary format)
-//! - Patch/artifact files
+//! - Markdown (for PR descriptions and wiki pages)
+//! - JUnit XML (for CI pipelines)
+//! - shields.io badge JSON
+//! - Patch/artifact files, optionally mirrored into the input's folder layout
 
-use crate::types::{ComparisonResult, ComparisonSummary};
+use crate::compare_text::generate_patch;
+use crate::match_files::relative_path;
+use crate::types::{
+    AnomalyReport, BlockingStats, ColumnRollup, ComparisonResult, ComparisonSummary, FileSide,
+    StructuredComparisonResult, TextComparisonResult,
+};
 use anyhow::{Context, Result};
-use std::fs::{self, File};
+use log::warn;
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+use std::fs::{self, File, OpenOptions};
 use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// Export results to JSONL format (one JSON object per line)
 pub fn export_jsonl(results: &[ComparisonResult], output_path: &Path) -> Result<()> {
@@ -26,6 +38,139 @@ pub fn export_jsonl(results: &[ComparisonResult], output_path: &Path) -> Result<
     Ok(())
 }
 
+/// Read back the results of an earlier, possibly interrupted, JSONL export
+///
+/// Reading stops at the first line that is incomplete or does not parse; the
+/// file is truncated there so new results can be appended cleanly.
+pub fn recover_jsonl(path: &Path) -> Result<Vec<ComparisonResult>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut results = Vec::new();
+    let mut valid_len = 0;
+    for line in content.split_inclusive('\n') {
+        if !line.ends_with('\n') {
+            break;
+        }
+        if !line.trim().is_empty() {
+            match serde_json::from_str::<ComparisonResult>(line) {
+                Ok(result) => results.push(result),
+                Err(_) => break,
+            }
+        }
+        valid_len += line.len();
+    }
+
+    if valid_len < content.len() {
+        warn!(
+            "Discarding {} bytes of incomplete results at the end of {}",
+            content.len() - valid_len,
+            path.display()
+        );
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_len(valid_len as u64))
+            .with_context(|| format!("Failed to truncate {}", path.display()))?;
+    }
+
+    Ok(results)
+}
+
+/// Destination for results as they are produced
+///
+/// The engine writes each result as soon as its comparison finishes, from
+/// whichever worker thread produced it, so implementations must be thread-safe.
+pub trait ResultSink: Send + Sync {
+    /// Record one result
+    fn write(&self, result: &ComparisonResult) -> Result<()>;
+}
+
+/// Writes results to a JSONL file one line at a time
+///
+/// Each line is flushed as soon as it is written, so an interrupted run
+/// loses at most the result it was writing. Safe to share across threads.
+pub struct JsonlSink {
+    writer: Mutex<BufWriter<File>>,
+    /// Only results below this similarity are written
+    threshold: Option<f64>,
+}
+
+impl JsonlSink {
+    /// Create `path`, replacing any earlier contents
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self::from_file(file))
+    }
+
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(Self::from_file(file))
+    }
+
+    fn from_file(file: File) -> Self {
+        Self {
+            writer: Mutex::new(BufWriter::new(file)),
+            threshold: None,
+        }
+    }
+
+    /// Skip results at or above `threshold` similarity (errors are always written)
+    pub fn below_threshold(mut self, threshold: Option<f64>) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl ResultSink for JsonlSink {
+    fn write(&self, result: &ComparisonResult) -> Result<()> {
+        if self.threshold.is_some_and(|t| !result.is_below_threshold(t)) {
+            return Ok(());
+        }
+        let json = serde_json::to_string(result)?;
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(writer, "{}", json)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Merge a new run's results into the results of an earlier run
+///
+/// A new result replaces the earlier result for the same pair; earlier
+/// results for pairs the new run did not produce are kept, ahead of the new
+/// ones. Pairs are matched by their file paths rather than `linked_id`,
+/// which is derived from the file contents and so changes when a file is
+/// edited.
+pub fn merge_results(
+    previous: Vec<ComparisonResult>,
+    new: Vec<ComparisonResult>,
+) -> Vec<ComparisonResult> {
+    let replaced: HashSet<(String, String)> = new
+        .iter()
+        .map(|r| {
+            let (file1, file2) = r.file_paths();
+            (file1.to_string(), file2.to_string())
+        })
+        .collect();
+
+    let mut merged: Vec<ComparisonResult> = previous
+        .into_iter()
+        .filter(|r| {
+            let (file1, file2) = r.file_paths();
+            !replaced.contains(&(file1.to_string(), file2.to_string()))
+        })
+        .collect();
+    merged.extend(new);
+    merged
+}
+
 /// Export results to CSV summary format
 pub fn export_csv(results: &[ComparisonResult], output_path: &Path) -> Result<()> {
     let file = File::create(output_path)
@@ -109,6 +254,79 @@ pub fn export_csv(results: &[ComparisonResult], output_path: &Path) -> Result<()
                     mismatch_str,
                 ])?;
             }
+            ComparisonResult::Permissions {
+                file1_path,
+                file2_path,
+                identical,
+                ..
+            } => {
+                let sim_str = if *identical { "1.0000" } else { "0.0000" };
+                let common_str = if *identical { "1" } else { "0" };
+                let mismatch_str = if *identical { "0" } else { "1" };
+                writer.write_record([
+                    file1_path.as_str(),
+                    file1_path.as_str(),
+                    file2_path.as_str(),
+                    "permissions",
+                    sim_str,
+                    &identical.to_string(),
+                    "1",
+                    "1",
+                    common_str,
+                    "0",
+                    "0",
+                    mismatch_str,
+                ])?;
+            }
+            ComparisonResult::Binary {
+                linked_id,
+                file1_path,
+                file2_path,
+                file1_size,
+                file2_size,
+                differing_bytes,
+                byte_similarity,
+                identical,
+                ..
+            } => {
+                // Counts are bytes: common = same-valued offsets
+                let common = file1_size.max(file2_size).saturating_sub(*differing_bytes);
+                writer.write_record([
+                    linked_id.as_str(),
+                    file1_path.as_str(),
+                    file2_path.as_str(),
+                    "binary",
+                    &format!("{:.4}", byte_similarity),
+                    &identical.to_string(),
+                    &file1_size.to_string(),
+                    &file2_size.to_string(),
+                    &common.to_string(),
+                    &file1_size.saturating_sub(*file2_size).to_string(),
+                    &file2_size.saturating_sub(*file1_size).to_string(),
+                    &differing_bytes.to_string(),
+                ])?;
+            }
+            ComparisonResult::OnlyInOne(r) => {
+                let (file1_path, file2_path) = result.file_paths();
+                let (count1, count2) = match r.side {
+                    FileSide::Set1 => (r.size.to_string(), String::new()),
+                    FileSide::Set2 => (String::new(), r.size.to_string()),
+                };
+                writer.write_record([
+                    "",
+                    file1_path,
+                    file2_path,
+                    "only_in_one",
+                    "0.0000",
+                    "false",
+                    &count1,
+                    &count2,
+                    "",
+                    "",
+                    "",
+                    "",
+                ])?;
+            }
             ComparisonResult::Error {
                 file1_path,
                 file2_path,
@@ -137,17 +355,30 @@ pub fn export_csv(results: &[ComparisonResult], output_path: &Path) -> Result<()
 }
 
 /// Write patch files for text comparison results
-pub fn write_patches(results: &[ComparisonResult], output_dir: &Path) -> Result<()> {
+///
+/// By default each patch is the result's `detailed_diff`. With `patch_roots`
+/// (the two compared roots), patches are instead rebuilt from the raw files
+/// by [`generate_patch`] with paths relative to those roots, so they apply
+/// cleanly with `patch -p1` or `git apply`.
+pub fn write_patches(
+    results: &[ComparisonResult],
+    output_dir: &Path,
+    patch_roots: Option<(&Path, &Path)>,
+) -> Result<()> {
     let patches_dir = output_dir.join("patches");
     fs::create_dir_all(&patches_dir)?;
 
     for result in results {
         if let ComparisonResult::Text(r) = result {
-            if !r.detailed_diff.is_empty() && !r.identical {
+            if r.identical {
+                continue;
+            }
+            let patch = text_patch(r, patch_roots);
+            if !patch.is_empty() {
                 let filename = sanitize_filename(&r.linked_id) + ".diff";
                 let path = patches_dir.join(&filename);
 
-                fs::write(&path, &r.detailed_diff)
+                fs::write(&path, &patch)
                     .with_context(|| format!("Failed to write patch {}", path.display()))?;
             }
         }
@@ -156,7 +387,87 @@ pub fn write_patches(results: &[ComparisonResult], output_dir: &Path) -> Result<
     Ok(())
 }
 
+/// Patch for one differing text pair, as described on [`write_patches`]
+///
+/// A pair whose files can no longer be read or decoded gets no patch (an
+/// empty string) and a warning, rather than failing the finished run.
+fn text_patch(r: &TextComparisonResult, patch_roots: Option<(&Path, &Path)>) -> String {
+    match patch_roots {
+        Some((root1, root2)) => {
+            let (path1, path2) = (Path::new(&r.file1_path), Path::new(&r.file2_path));
+            generate_patch(
+                path1,
+                path2,
+                &patch_path_name(relative_path(path1, root1)),
+                &patch_path_name(relative_path(path2, root2)),
+            )
+            .unwrap_or_else(|e| {
+                warn!("Skipping the patch for {} and {}: {:#}", r.file1_path, r.file2_path, e);
+                String::new()
+            })
+        }
+        None => r.detailed_diff.clone(),
+    }
+}
+
+/// Write each differing pair's artifact under `mirror_dir` at the path of its
+/// first file relative to `root1`
+///
+/// Text pairs get `<path>.diff` and structured pairs `<path>.json`, so
+/// `src/main.rs` ends up at `<mirror_dir>/src/main.rs.diff`. When one file is
+/// paired several times, later artifacts add the other file's name
+/// (`main.rs.vs-app.rs.diff`). Patches follow `patch_roots` like
+/// [`write_patches`].
+pub fn write_mirror(
+    results: &[ComparisonResult],
+    mirror_dir: &Path,
+    root1: &Path,
+    patch_roots: Option<(&Path, &Path)>,
+) -> Result<()> {
+    let mut used: HashSet<PathBuf> = HashSet::new();
+
+    for result in results {
+        let (file1, file2, extension, content) = match result {
+            ComparisonResult::Text(r) if !r.identical => {
+                (&r.file1_path, &r.file2_path, "diff", text_patch(r, patch_roots))
+            }
+            ComparisonResult::Structured(r) if !r.identical => {
+                (&r.file1_path, &r.file2_path, "json", serde_json::to_string_pretty(r)?)
+            }
+            _ => continue,
+        };
+        if content.is_empty() {
+            continue;
+        }
+
+        let relative = relative_path(Path::new(file1), root1);
+        let name = relative.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut path = mirror_dir.join(relative).with_file_name(format!("{}.{}", name, extension));
+        if !used.insert(path.clone()) {
+            let other = Path::new(file2).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            path = path.with_file_name(format!("{}.vs-{}.{}", name, sanitize_filename(&other), extension));
+            used.insert(path.clone());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Path as written in a patch header, always with forward slashes
+fn patch_path_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
 /// Write mismatch artifacts for structured comparison results
+///
+/// Results carrying a [`crate::types::StructuredPatch`] also get `<id>.patch.json`.
 pub fn write_mismatch_artifacts(results: &[ComparisonResult], output_dir: &Path) -> Result<()> {
     let mismatches_dir = output_dir.join("mismatches");
     fs::create_dir_all(&mismatches_dir)?;
@@ -170,6 +481,10 @@ pub fn write_mismatch_artifacts(results: &[ComparisonResult], output_dir: &Path)
                 let json = serde_json::to_string_pretty(r)?;
                 fs::write(&path, json)
                     .with_context(|| format!("Failed to write mismatch {}", path.display()))?;
+
+                if r.patch.is_some() {
+                    export_structured_patch(r, &mismatches_dir.join(sanitize_filename(&r.linked_id) + ".patch.json"))?;
+                }
             }
         }
     }
@@ -177,6 +492,19 @@ pub fn write_mismatch_artifacts(results: &[ComparisonResult], output_dir: &Path)
     Ok(())
 }
 
+/// Write the records to add, remove and update to turn file 1 into file 2
+///
+/// The result must have been compared with `structured_patch` set, which
+/// collects every difference instead of samples.
+pub fn export_structured_patch(result: &StructuredComparisonResult, path: &Path) -> Result<()> {
+    let patch = result
+        .patch
+        .as_ref()
+        .with_context(|| format!("No patch was collected for {} (enable structured_patch)", result.file1_path))?;
+    let json = serde_json::to_string_pretty(patch)?;
+    fs::write(path, json).with_context(|| format!("Failed to write patch {}", path.display()))
+}
+
 /// Sanitize a string for use as a filename
 fn sanitize_filename(s: &str) -> String {
     s.chars()
@@ -213,19 +541,34 @@ pub fn calculate_summary(
 ) -> ComparisonSummary {
     let mut identical = 0;
     let mut different = 0;
+    let mut structural = 0;
     let mut errors = 0;
+    let mut only_in_set1 = 0;
+    let mut only_in_set2 = 0;
     let mut similarities = Vec::new();
+    let mut weighted_sum = 0.0;
+    let mut total_weight = 0u64;
 
     for result in results {
         match result {
             ComparisonResult::Error { .. } => errors += 1,
+            ComparisonResult::OnlyInOne(r) => match r.side {
+                FileSide::Set1 => only_in_set1 += 1,
+                FileSide::Set2 => only_in_set2 += 1,
+            },
             _ => {
                 if result.is_identical() {
                     identical += 1;
                 } else {
                     different += 1;
+                    if result.is_structural_change() {
+                        structural += 1;
+                    }
                 }
                 similarities.push(result.similarity_score());
+                let weight = result.combined_size();
+                weighted_sum += result.similarity_score() * weight as f64;
+                total_weight += weight;
             }
         }
     }
@@ -236,6 +579,13 @@ pub fn calculate_summary(
         similarities.iter().sum::<f64>() / similarities.len() as f64
     };
 
+    // Without any sizes (e.g. empty files) fall back to the plain average
+    let weighted_average_similarity = if total_weight > 0 {
+        weighted_sum / total_weight as f64
+    } else {
+        average_similarity
+    };
+
     let min_similarity = similarities.iter().cloned().fold(f64::INFINITY, f64::min);
     let max_similarity = similarities.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
 
@@ -255,11 +605,16 @@ pub fn calculate_summary(
     ComparisonSummary {
         total_files_set1: total1,
         total_files_set2: total2,
-        pairs_compared: results.len(),
+        pairs_compared: results.len() - only_in_set1 - only_in_set2,
+        only_in_set1,
+        only_in_set2,
         identical_pairs: identical,
         different_pairs: different,
+        content_diff_pairs: different - structural,
+        structural_diff_pairs: structural,
         error_pairs: errors,
         average_similarity: if average_similarity.is_nan() { 0.0 } else { average_similarity },
+        weighted_average_similarity,
         min_similarity: if min_similarity.is_infinite() { 0.0 } else { min_similarity },
         max_similarity: if max_similarity.is_infinite() { 0.0 } else { max_similarity },
         execution_time_ms: exec_time,
@@ -268,15 +623,681 @@ pub fn calculate_summary(
         total_data_processed_bytes: data_bytes,
         comparison_mode: mode,
         similarity_algorithm: algo,
+        hash_algorithm: None,
+        anomalies: AnomalyReport::default(),
+        cancelled: false,
+        first_difference: None,
+        blocking: BlockingStats::default(),
+    }
+}
+
+/// Sum field mismatches by column name across all structured results
+///
+/// Answers "column `amount` mismatched in 1,204 records across 37 files" for
+/// folders of files sharing a schema. Sorted by mismatch count, most first.
+pub fn calculate_column_rollup(results: &[ComparisonResult]) -> Vec<ColumnRollup> {
+    let mut by_column: HashMap<&str, ColumnRollup> = HashMap::new();
+
+    for result in results {
+        if let ComparisonResult::Structured(r) = result {
+            for mismatch in r.field_mismatches.iter().filter(|m| m.mismatch_count > 0) {
+                let rollup = by_column.entry(&mismatch.column_name).or_insert_with(|| ColumnRollup {
+                    column_name: mismatch.column_name.clone(),
+                    mismatch_count: 0,
+                    files_affected: 0,
+                });
+                rollup.mismatch_count += mismatch.mismatch_count;
+                rollup.files_affected += 1;
+            }
+        }
+    }
+
+    let mut rollup: Vec<ColumnRollup> = by_column.into_values().collect();
+    rollup.sort_by(|a, b| {
+        b.mismatch_count
+            .cmp(&a.mismatch_count)
+            .then_with(|| a.column_name.cmp(&b.column_name))
+    });
+    rollup
+}
+
+/// Export the summary and per-pair details as Markdown
+///
+/// Starts with a summary table, then one section per result: similarity and
+/// status, a fenced `diff` block for differing text pairs (the diff is
+/// already capped at `max_diff_bytes` when it is computed) and a mismatch
+/// count table for differing structured pairs.
+pub fn export_markdown(
+    results: &[ComparisonResult],
+    summary: &ComparisonSummary,
+    output_path: &Path,
+) -> Result<()> {
+    let file = File::create(output_path)
+        .with_context(|| format!("Failed to create {}", output_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(render_markdown(results, summary).as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn render_markdown(results: &[ComparisonResult], summary: &ComparisonSummary) -> String {
+    let mut md = String::from("# CompareIt Results\n\n");
+    md.push_str("| Metric | Value |\n|---|---|\n");
+    md.push_str(&format!("| Pairs Compared | {} |\n", summary.pairs_compared));
+    md.push_str(&format!("| Identical | {} |\n", summary.identical_pairs));
+    md.push_str(&format!("| Different | {} |\n", summary.different_pairs));
+    md.push_str(&format!("| Errors | {} |\n", summary.error_pairs));
+    md.push_str(&format!("| Avg Similarity | {:.1}% |\n", summary.average_similarity * 100.0));
+    if summary.only_in_set1 + summary.only_in_set2 > 0 {
+        md.push_str(&format!(
+            "| Only in Path 1 / 2 | {} / {} |\n",
+            summary.only_in_set1, summary.only_in_set2
+        ));
+    }
+
+    if !results.is_empty() {
+        md.push_str("\n## Pairs\n");
+    }
+    for result in results {
+        let (file1, file2) = result.file_paths();
+        let status = pair_status(result);
+
+        if let ComparisonResult::OnlyInOne(r) = result {
+            md.push_str(&format!("\n### `{}`\n\n- Status: {}\n", r.path, status));
+            continue;
+        }
+        md.push_str(&format!("\n### `{}` ↔ `{}`\n\n", file1, file2));
+        md.push_str(&format!(
+            "- Similarity: {:.1}%\n- Status: {}\n",
+            result.similarity_score() * 100.0,
+            status
+        ));
+
+        match result {
+            ComparisonResult::Text(r) if !r.identical && !r.detailed_diff.is_empty() => {
+                let fence = markdown_fence(&r.detailed_diff);
+                md.push_str(&format!("\n{}diff\n{}", fence, r.detailed_diff));
+                if !r.detailed_diff.ends_with('\n') {
+                    md.push('\n');
+                }
+                md.push_str(&format!("{}\n", fence));
+            }
+            ComparisonResult::Structured(r) if !r.field_mismatches.is_empty() => {
+                md.push_str("\n| Column | Mismatches |\n|---|---|\n");
+                for column in &r.field_mismatches {
+                    md.push_str(&format!(
+                        "| {} | {} |\n",
+                        column.column_name.replace('|', "\\|"),
+                        column.mismatch_count
+                    ));
+                }
+            }
+            ComparisonResult::Error { error, .. } => {
+                md.push_str(&format!("- Error: {}\n", error));
+            }
+            _ => {}
+        }
     }
+
+    md
+}
+
+/// Status of a pair as shown in Markdown and XLSX exports
+fn pair_status(result: &ComparisonResult) -> &'static str {
+    match result {
+        ComparisonResult::Error { .. } => "Error",
+        ComparisonResult::OnlyInOne(r) => match r.side {
+            FileSide::Set1 => "Only in path 1",
+            FileSide::Set2 => "Only in path 2",
+        },
+        _ if result.is_identical() => "Identical",
+        _ => "Different",
+    }
+}
+
+/// A backtick fence longer than any backtick run inside `content`
+fn markdown_fence(content: &str) -> String {
+    let longest = content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Write the run summary, including process statistics, as pretty JSON
+///
+/// A small file dashboards can read instead of parsing the whole JSONL; it
+/// deserializes back into a [`ComparisonSummary`].
+pub fn export_summary_json(summary: &ComparisonSummary, output_path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(summary)?;
+    fs::write(output_path, json).with_context(|| format!("Failed to write {}", output_path.display()))?;
+    Ok(())
+}
+
+/// Write a shields.io endpoint badge summarizing the run
+///
+/// The JSON is consumed by `https://img.shields.io/endpoint?url=...`. The
+/// message is the average similarity (plus the error count, if any) and the
+/// color goes from green to red as similarity drops; any error makes it red.
+pub fn export_badge(summary: &ComparisonSummary, output_path: &Path) -> Result<()> {
+    let json = serde_json::to_string(&badge_json(summary))?;
+    fs::write(output_path, json).with_context(|| format!("Failed to write {}", output_path.display()))?;
+    Ok(())
+}
+
+fn badge_json(summary: &ComparisonSummary) -> serde_json::Value {
+    let similarity = summary.average_similarity;
+    let (message, color) = if summary.pairs_compared == 0 {
+        ("no pairs".to_string(), "lightgrey")
+    } else {
+        let mut message = format!("{:.0}% match", similarity * 100.0);
+        if summary.error_pairs > 0 {
+            message.push_str(&format!(
+                ", {} error{}",
+                summary.error_pairs,
+                if summary.error_pairs == 1 { "" } else { "s" }
+            ));
+        }
+        let color = match similarity {
+            _ if summary.error_pairs > 0 => "red",
+            s if s >= 0.95 => "green",
+            s if s >= 0.8 => "yellow",
+            s if s >= 0.5 => "orange",
+            _ => "red",
+        };
+        (message, color)
+    };
+
+    serde_json::json!({
+        "schemaVersion": 1,
+        "label": "compareit",
+        "message": message,
+        "color": color,
+    })
+}
+
+/// Number of diff lines included in a JUnit failure message
+const JUNIT_SNIPPET_LINES: usize = 20;
+
+/// Export results as JUnit XML so CI systems can gate on them
+///
+/// Every result becomes a `<testcase>` named `file1 vs file2`. Identical
+/// pairs pass; differing pairs carry a `<failure>` with the similarity and a
+/// short snippet of what differs; comparisons that failed carry an `<error>`.
+/// Files found on only one side had nothing to compare against and are
+/// `<skipped>`, so failures and errors match the summary's different and
+/// error counts.
+pub fn export_junit(results: &[ComparisonResult], output_path: &Path) -> Result<()> {
+    let file = File::create(output_path)
+        .with_context(|| format!("Failed to create {}", output_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(render_junit(results).as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn render_junit(results: &[ComparisonResult]) -> String {
+    let errors = results
+        .iter()
+        .filter(|r| matches!(r, ComparisonResult::Error { .. }))
+        .count();
+    let skipped = results
+        .iter()
+        .filter(|r| matches!(r, ComparisonResult::OnlyInOne(_)))
+        .count();
+    let failures = results.iter().filter(|r| !r.is_identical()).count() - errors - skipped;
+    let counts = format!(
+        "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\"",
+        results.len(),
+        failures,
+        errors,
+        skipped
+    );
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<testsuites name=\"CompareIt\" {}>\n", counts));
+    xml.push_str(&format!("  <testsuite name=\"CompareIt\" {}>\n", counts));
+
+    for result in results {
+        let (file1, file2) = result.file_paths();
+        let (classname, name) = match result {
+            ComparisonResult::Text(_) => ("text", format!("{} vs {}", file1, file2)),
+            ComparisonResult::Structured(_) => ("structured", format!("{} vs {}", file1, file2)),
+            ComparisonResult::HashOnly { .. } => ("hash", format!("{} vs {}", file1, file2)),
+            ComparisonResult::Binary { .. } => ("binary", format!("{} vs {}", file1, file2)),
+            ComparisonResult::Permissions { .. } => ("permissions", format!("{} vs {}", file1, file2)),
+            ComparisonResult::OnlyInOne(r) => ("unmatched", r.path.clone()),
+            ComparisonResult::Error { .. } => ("error", format!("{} vs {}", file1, file2)),
+        };
+        xml.push_str(&format!(
+            "    <testcase classname=\"compareit.{}\" name=\"{}\"",
+            classname,
+            escape_xml(&name)
+        ));
+
+        match result {
+            ComparisonResult::Error { error, .. } => {
+                xml.push_str(&format!(
+                    ">\n      <error message=\"{}\" type=\"error\"/>\n    </testcase>\n",
+                    escape_xml(error)
+                ));
+            }
+            ComparisonResult::OnlyInOne(r) => {
+                let side = match r.side {
+                    FileSide::Set1 => "path 1",
+                    FileSide::Set2 => "path 2",
+                };
+                xml.push_str(&format!(
+                    ">\n      <skipped message=\"Only in {}\"/>\n    </testcase>\n",
+                    side
+                ));
+            }
+            _ if result.is_identical() => xml.push_str("/>\n"),
+            _ => {
+                xml.push_str(&format!(
+                    ">\n      <failure message=\"Files differ (similarity {:.1}%)\" type=\"different\">{}</failure>\n    </testcase>\n",
+                    result.similarity_score() * 100.0,
+                    escape_xml(&junit_snippet(result))
+                ));
+            }
+        }
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Short description of how a differing pair differs
+fn junit_snippet(result: &ComparisonResult) -> String {
+    match result {
+        ComparisonResult::Text(r) => {
+            let mut snippet: String = r
+                .detailed_diff
+                .lines()
+                .take(JUNIT_SNIPPET_LINES)
+                .map(|line| format!("{}\n", line))
+                .collect();
+            if r.detailed_diff.lines().nth(JUNIT_SNIPPET_LINES).is_some() {
+                snippet.push_str("...\n");
+            }
+            snippet
+        }
+        ComparisonResult::Structured(r) => {
+            let mut snippet = format!(
+                "{} common records, {} only in file 1, {} only in file 2\n",
+                r.common_records, r.only_in_file1, r.only_in_file2
+            );
+            for column in &r.field_mismatches {
+                snippet.push_str(&format!("{}: {} mismatches\n", column.column_name, column.mismatch_count));
+            }
+            snippet
+        }
+        ComparisonResult::Binary {
+            differing_bytes,
+            first_diff_offset,
+            ..
+        } => match first_diff_offset {
+            Some(offset) => format!("{} bytes differ, first at offset {}\n", differing_bytes, offset),
+            None => format!("{} bytes differ\n", differing_bytes),
+        },
+        ComparisonResult::HashOnly { .. } => "Content hashes differ\n".to_string(),
+        ComparisonResult::Permissions { file1_mode, file2_mode, .. } => {
+            format!("{}\n", describe_permission_change(*file1_mode, *file2_mode))
+        }
+        ComparisonResult::OnlyInOne(_) | ComparisonResult::Error { .. } => String::new(),
+    }
+}
+
+/// Describe how two files' permission bits differ, e.g. `mode 0644 vs 0666 (world-writable in file 2)`
+pub fn describe_permission_change(mode1: Option<u32>, mode2: Option<u32>) -> String {
+    let octal = |mode: Option<u32>| mode.map_or_else(|| "unknown".to_string(), |m| format!("{:04o}", m));
+    let world_writable = |mode: Option<u32>| mode.is_some_and(|m| m & 0o002 != 0);
+    let mut description = format!("mode {} vs {}", octal(mode1), octal(mode2));
+    match (world_writable(mode1), world_writable(mode2)) {
+        (true, false) => description.push_str(" (world-writable in file 1)"),
+        (false, true) => description.push_str(" (world-writable in file 2)"),
+        _ => {}
+    }
+    description
+}
+
+/// Escape text for an XML attribute or element
+///
+/// Control characters that XML 1.0 does not allow are replaced with U+FFFD.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Export results as a SARIF 2.1.0 log
+///
+/// Code review tools show each SARIF result as an annotation on file 2:
+/// differing text pairs point at their first differing line, every sampled
+/// field mismatch of a structured pair becomes its own result carrying the
+/// record key, and other differing pairs are reported against the whole
+/// file. The log is checked for SARIF's required fields before writing.
+pub fn export_sarif(results: &[ComparisonResult], output_path: &Path) -> Result<()> {
+    let sarif = render_sarif(results);
+    validate_sarif(&sarif)?;
+
+    let json = serde_json::to_string_pretty(&sarif)?;
+    fs::write(output_path, json)
+        .with_context(|| format!("Failed to write SARIF to {}", output_path.display()))?;
+    Ok(())
+}
+
+/// SARIF log for [`export_sarif`]
+fn render_sarif(results: &[ComparisonResult]) -> serde_json::Value {
+    let mut sarif_results = Vec::new();
+
+    for result in results {
+        if result.is_identical() {
+            continue;
+        }
+        let (file1, file2) = result.file_paths();
+        match result {
+            ComparisonResult::Text(r) => {
+                // Positions are 0-based indices into the change list; every
+                // change before the first difference is an equal line
+                let first = r
+                    .different_positions
+                    .split([',', '-'])
+                    .next()
+                    .and_then(|p| p.parse::<usize>().ok())
+                    .unwrap_or(0);
+                let line = (first + 1).min(r.file2_line_count.max(1));
+                sarif_results.push(sarif_result(
+                    "text-difference",
+                    format!(
+                        "Differs from {} ({} lines only in this file, {} only in the other; similarity {:.1}%)",
+                        file1,
+                        r.only_in_file2,
+                        r.only_in_file1,
+                        r.similarity_score * 100.0
+                    ),
+                    file2,
+                    Some(line),
+                ));
+            }
+            ComparisonResult::Structured(r) => {
+                let mut samples = 0;
+                for column in &r.field_mismatches {
+                    for sample in column.mismatches() {
+                        let mut entry = sarif_result(
+                            "field-mismatch",
+                            format!(
+                                "Column '{}' of record '{}' is '{}' here but '{}' in {}",
+                                column.column_name, sample.key, sample.value2, sample.value1, file1
+                            ),
+                            file2,
+                            None,
+                        );
+                        entry["partialFingerprints"] = serde_json::json!({ "recordKey": sample.key });
+                        entry["properties"] = serde_json::json!({
+                            "column": column.column_name,
+                            "key": sample.key,
+                        });
+                        sarif_results.push(entry);
+                        samples += 1;
+                    }
+                }
+                // Record or column changes without sampled field mismatches
+                if samples == 0 {
+                    sarif_results.push(sarif_result(
+                        "structured-difference",
+                        format!(
+                            "Differs from {} ({} records only in this file, {} only in the other)",
+                            file1, r.only_in_file2, r.only_in_file1
+                        ),
+                        file2,
+                        None,
+                    ));
+                }
+            }
+            ComparisonResult::HashOnly { .. }
+            | ComparisonResult::Binary { .. }
+            | ComparisonResult::Permissions { .. } => {
+                sarif_results.push(sarif_result(
+                    "file-difference",
+                    format!("Differs from {}", file1),
+                    file2,
+                    None,
+                ));
+            }
+            // Nothing to annotate: no second file, or no comparison at all
+            ComparisonResult::OnlyInOne(_) | ComparisonResult::Error { .. } => {}
+        }
+    }
+
+    let rule = |id: &str, text: &str| serde_json::json!({ "id": id, "shortDescription": { "text": text } });
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "CompareIt",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [
+                        rule("text-difference", "Text file differs"),
+                        rule("field-mismatch", "Structured field value differs"),
+                        rule("structured-difference", "Structured file differs"),
+                        rule("file-difference", "File differs"),
+                    ],
+                }
+            },
+            "results": sarif_results,
+        }]
+    })
+}
+
+/// One SARIF result located in `path`, optionally at a 1-based line
+fn sarif_result(rule_id: &str, message: String, path: &str, line: Option<usize>) -> serde_json::Value {
+    let mut location = serde_json::json!({
+        "physicalLocation": { "artifactLocation": { "uri": sarif_uri(path) } }
+    });
+    if let Some(line) = line {
+        location["physicalLocation"]["region"] = serde_json::json!({ "startLine": line });
+    }
+    serde_json::json!({
+        "ruleId": rule_id,
+        "level": "warning",
+        "message": { "text": message },
+        "locations": [location],
+    })
+}
+
+/// Path as a SARIF URI reference: forward slashes, other reserved
+/// characters percent-encoded
+fn sarif_uri(path: &str) -> String {
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.replace('\\', "/").bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Check the fields SARIF 2.1.0 requires of a log and of each result
+fn validate_sarif(sarif: &serde_json::Value) -> Result<()> {
+    anyhow::ensure!(sarif["version"] == "2.1.0", "SARIF log must have version 2.1.0");
+    let runs = sarif["runs"].as_array().context("SARIF log must have a runs array")?;
+    for run in runs {
+        anyhow::ensure!(run["tool"]["driver"]["name"].is_string(), "SARIF run must name its tool driver");
+        for result in run["results"].as_array().context("SARIF run must have a results array")? {
+            anyhow::ensure!(
+                result["message"]["text"].as_str().is_some_and(|text| !text.is_empty()),
+                "SARIF result must have a message"
+            );
+            anyhow::ensure!(result["ruleId"].is_string(), "SARIF result must have a ruleId");
+            for location in result["locations"].as_array().into_iter().flatten() {
+                let physical = &location["physicalLocation"];
+                anyhow::ensure!(
+                    physical["artifactLocation"]["uri"].as_str().is_some_and(|uri| !uri.is_empty()),
+                    "SARIF location must have a URI"
+                );
+                if let Some(line) = physical["region"].get("startLine") {
+                    anyhow::ensure!(line.as_u64().is_some_and(|l| l >= 1), "SARIF startLine must be at least 1");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Longest string an XLSX cell can hold, in characters
+const XLSX_MAX_CELL_CHARS: usize = 32_767;
+
+/// Rows in an XLSX worksheet, including the header row
+const XLSX_MAX_ROWS: u32 = 1_048_576;
+
+/// Export results as an XLSX workbook
+///
+/// Writes a "Summary" sheet with the run metrics, an "All Pairs" sheet with
+/// one row per result and a "Field Mismatches" sheet with every sampled
+/// field mismatch of the structured pairs. Values are always written as text
+/// (never formulas); cells over Excel's length limit are truncated, and a
+/// sheet that would overflow the row limit ends with a note instead.
+pub fn export_xlsx(
+    results: &[ComparisonResult],
+    summary: &ComparisonSummary,
+    output_path: &Path,
+) -> Result<()> {
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold();
+
+    let sheet = workbook.add_worksheet().set_name("Summary")?;
+    write_xlsx_header(sheet, &["Metric", "Value"], &header)?;
+    let metrics = [
+        ("Files in Path 1", summary.total_files_set1 as f64),
+        ("Files in Path 2", summary.total_files_set2 as f64),
+        ("Pairs Compared", summary.pairs_compared as f64),
+        ("Identical", summary.identical_pairs as f64),
+        ("Different", summary.different_pairs as f64),
+        ("Errors", summary.error_pairs as f64),
+        ("Only in Path 1", summary.only_in_set1 as f64),
+        ("Only in Path 2", summary.only_in_set2 as f64),
+        ("Avg Similarity", summary.average_similarity),
+        ("Size-Weighted Similarity", summary.weighted_average_similarity),
+    ];
+    for (row, (metric, value)) in (1..).zip(metrics) {
+        sheet.write_string(row, 0, metric)?;
+        sheet.write_number(row, 1, value)?;
+    }
+
+    let sheet = workbook.add_worksheet().set_name("All Pairs")?;
+    write_xlsx_header(sheet, &["File 1", "File 2", "Status", "Similarity", "Type"], &header)?;
+    for (idx, result) in results.iter().enumerate() {
+        let row = idx as u32 + 1;
+        if xlsx_rows_exhausted(sheet, row, results.len() - idx)? {
+            break;
+        }
+        let (file1, file2) = result.file_paths();
+        let kind = match result {
+            ComparisonResult::Text(_) => "text",
+            ComparisonResult::Structured(_) => "structured",
+            ComparisonResult::HashOnly { .. } | ComparisonResult::Binary { .. } => "binary",
+            ComparisonResult::Permissions { .. } => "permissions",
+            ComparisonResult::OnlyInOne(_) => "unmatched",
+            ComparisonResult::Error { .. } => "error",
+        };
+        write_xlsx_text(sheet, row, 0, file1)?;
+        write_xlsx_text(sheet, row, 1, file2)?;
+        sheet.write_string(row, 2, pair_status(result))?;
+        sheet.write_number(row, 3, result.similarity_score())?;
+        sheet.write_string(row, 4, kind)?;
+    }
+
+    let sheet = workbook.add_worksheet().set_name("Field Mismatches")?;
+    write_xlsx_header(sheet, &["File", "Column", "Key", "Value 1", "Value 2"], &header)?;
+    let mismatches: Vec<_> = results
+        .iter()
+        .filter_map(|result| match result {
+            ComparisonResult::Structured(r) => Some(r),
+            _ => None,
+        })
+        .flat_map(|r| {
+            r.field_mismatches.iter().flat_map(move |column| {
+                column.mismatches().iter().map(move |sample| (&r.file1_path, &column.column_name, sample))
+            })
+        })
+        .collect();
+    for (idx, (file, column, sample)) in mismatches.iter().enumerate() {
+        let row = idx as u32 + 1;
+        if xlsx_rows_exhausted(sheet, row, mismatches.len() - idx)? {
+            break;
+        }
+        write_xlsx_text(sheet, row, 0, file)?;
+        write_xlsx_text(sheet, row, 1, column)?;
+        write_xlsx_text(sheet, row, 2, &sample.key)?;
+        write_xlsx_text(sheet, row, 3, &sample.value1)?;
+        write_xlsx_text(sheet, row, 4, &sample.value2)?;
+    }
+
+    workbook
+        .save(output_path)
+        .with_context(|| format!("Failed to write XLSX workbook to {}", output_path.display()))?;
+    Ok(())
+}
+
+/// Write a bold header row
+fn write_xlsx_header(sheet: &mut Worksheet, titles: &[&str], format: &Format) -> Result<()> {
+    for (col, title) in (0..).zip(titles) {
+        sheet.write_string_with_format(0, col, *title, format)?;
+    }
+    Ok(())
+}
+
+/// Write a text cell, truncated to the XLSX cell length limit
+fn write_xlsx_text(sheet: &mut Worksheet, row: u32, col: u16, value: &str) -> Result<()> {
+    let value = match value.char_indices().nth(XLSX_MAX_CELL_CHARS) {
+        Some((end, _)) => &value[..end],
+        None => value,
+    };
+    sheet.write_string(row, col, value)?;
+    Ok(())
+}
+
+/// On the last row of a sheet, write a note about the `remaining` rows that
+/// do not fit and return true
+fn xlsx_rows_exhausted(sheet: &mut Worksheet, row: u32, remaining: usize) -> Result<bool> {
+    if row + 1 < XLSX_MAX_ROWS || remaining <= 1 {
+        return Ok(false);
+    }
+    warn!("XLSX sheet is full; {} rows were left out", remaining);
+    sheet.write_string(row, 0, format!("{} more rows left out (sheet row limit)", remaining))?;
+    Ok(true)
 }
 
 /// Export all artifacts (JSONL, CSV, patches, mismatches)
+///
+/// `patch_roots` is passed on to [`write_patches`]. With `mirror` (a mirror
+/// directory and the first compared root), artifacts are also written there
+/// by [`write_mirror`].
 pub fn export_all(
     results: &[ComparisonResult],
     jsonl_path: Option<&Path>,
     csv_path: Option<&Path>,
     output_dir: Option<&Path>,
+    patch_roots: Option<(&Path, &Path)>,
+    mirror: Option<(&Path, &Path)>,
 ) -> Result<()> {
     if let Some(path) = jsonl_path {
         export_jsonl(results, path)?;
@@ -288,9 +1309,482 @@ pub fn export_all(
 
     if let Some(dir) = output_dir {
         fs::create_dir_all(dir)?;
-        write_patches(results, dir)?;
+        write_patches(results, dir, patch_roots)?;
         write_mismatch_artifacts(results, dir)?;
     }
 
+    if let Some((mirror_dir, root1)) = mirror {
+        write_mirror(results, mirror_dir, root1, patch_roots)?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        ColumnMismatch, ColumnType, FieldMismatch, FileType, StructuredComparisonResult, TextComparisonResult, UnmatchedFile,
+        WhitespaceGlyphs,
+    };
+
+    fn text_result(name: &str, size: u64, similarity_score: f64) -> ComparisonResult {
+        ComparisonResult::Text(TextComparisonResult {
+            linked_id: name.to_string(),
+            file1_path: format!("a/{}", name),
+            file2_path: format!("b/{}", name),
+            file1_size: size,
+            file2_size: size,
+            file1_line_count: 0,
+            file2_line_count: 0,
+            common_lines: 0,
+            only_in_file1: 0,
+            only_in_file2: 0,
+            similarity_score,
+            different_positions: String::new(),
+            detailed_diff: String::new(),
+            diff_truncated: false,
+            inline_edits: Vec::new(),
+            whitespace_glyphs: WhitespaceGlyphs::default(),
+            rename: None,
+            identical: similarity_score == 1.0,
+        })
+    }
+
+    /// A differing structured result for `name`, with `rows` records on each side
+    fn structured_result(name: &str, rows: usize, field_mismatches: Vec<ColumnMismatch>) -> StructuredComparisonResult {
+        StructuredComparisonResult {
+            linked_id: name.to_string(),
+            file1_path: format!("a/{}", name),
+            file2_path: format!("b/{}", name),
+            file1_size: 0,
+            file2_size: 0,
+            file1_row_count: rows,
+            file2_row_count: rows,
+            common_records: rows,
+            only_in_file1: 0,
+            only_in_file2: 0,
+            similarity_score: 0.5,
+            total_field_mismatches: field_mismatches.iter().map(|m| m.mismatch_count).sum(),
+            field_mismatches,
+            columns_only_in_file1: Vec::new(),
+            columns_only_in_file2: Vec::new(),
+            common_columns: Vec::new(),
+            duplicate_keys: Vec::new(),
+            identical: false,
+            patch: None,
+            column_profiles: Vec::new(),
+            only_in_file1_keys: Vec::new(),
+            only_in_file2_keys: Vec::new(),
+            only_in_keys_truncated: false,
+            inferred_key_columns: Vec::new(),
+            sampled: false,
+            sample_size: 0,
+        }
+    }
+
+    #[test]
+    fn test_mirror_places_artifacts_at_relative_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root1 = Path::new("/data/left");
+        let with_paths = |mut result: ComparisonResult, file1: &str, file2: &str| {
+            if let ComparisonResult::Text(ref mut r) = result {
+                r.file1_path = format!("{}/{}", root1.display(), file1);
+                r.file2_path = format!("/data/right/{}", file2);
+                r.detailed_diff = format!("--- a\n+++ b\n-{}\n", file1);
+            }
+            result
+        };
+        let results = vec![
+            with_paths(text_result("main", 10, 0.5), "src/main.rs", "src/main.rs"),
+            with_paths(text_result("lib", 10, 0.5), "src/lib.rs", "src/lib.rs"),
+            with_paths(text_result("lib2", 10, 0.4), "src/lib.rs", "src/core.rs"),
+            with_paths(text_result("same", 10, 1.0), "README.md", "README.md"),
+        ];
+
+        let mirror = dir.path().join("mirror");
+        export_all(&results, None, None, None, None, Some((&mirror, root1))).unwrap();
+
+        assert_eq!(fs::read_to_string(mirror.join("src/main.rs.diff")).unwrap(), "--- a\n+++ b\n-src/main.rs\n");
+        assert!(mirror.join("src/lib.rs.diff").exists());
+        assert!(mirror.join("src/lib.rs.vs-core.rs.diff").exists());
+        // Identical pairs have nothing to mirror
+        assert!(!mirror.join("README.md.diff").exists());
+    }
+
+    #[test]
+    fn test_markdown_has_summary_and_one_fence_per_text_diff() {
+        let with_diff = |name: &str, diff: &str| {
+            let mut result = text_result(name, 10, 0.5);
+            if let ComparisonResult::Text(ref mut r) = result {
+                r.detailed_diff = diff.to_string();
+            }
+            result
+        };
+        let name_mismatch = ColumnMismatch {
+            column_name: "name".to_string(),
+            inferred_type: ColumnType::String,
+            mismatch_count: 1,
+            sample_mismatches: Vec::new(),
+            all_mismatches: Vec::new(),
+        };
+        let structured = StructuredComparisonResult {
+            similarity_score: 1.0,
+            common_columns: vec!["id".to_string(), "name".to_string()],
+            ..structured_result("people.csv", 2, vec![name_mismatch])
+        };
+
+        let results = vec![
+            with_diff("one.txt", "--- a\n+++ b\n-old\n+new\n"),
+            text_result("same.txt", 10, 1.0),
+            with_diff("two.md", "--- a\n+++ b\n-```\n+~~~\n"),
+            ComparisonResult::Structured(structured),
+        ];
+        let summary = calculate_summary(&results, 4, 4, None);
+        let md = render_markdown(&results, &summary);
+
+        assert!(md.starts_with("# CompareIt Results\n"));
+        assert!(md.contains("| Pairs Compared | 4 |"));
+        assert!(md.contains("| Identical | 1 |"));
+        assert!(md.contains("| Different | 3 |"));
+        assert!(md.contains("### `a/one.txt` ↔ `b/one.txt`"));
+        // One fence per differing text pair; the second needs a longer fence
+        let fences: Vec<&str> = md.lines().filter(|l| l.ends_with("`diff")).collect();
+        assert_eq!(fences, ["```diff", "````diff"]);
+        assert!(md.contains("| name | 1 |"));
+    }
+
+    #[test]
+    fn test_weighted_similarity_favors_large_files() {
+        let mut results: Vec<ComparisonResult> = (0..20)
+            .map(|i| text_result(&format!("tiny{}.txt", i), 100, 1.0))
+            .collect();
+        results.push(text_result("huge.log", 1_000_000, 0.1));
+
+        let summary = calculate_summary(&results, 21, 21, None);
+        assert!(summary.average_similarity > 0.9);
+        assert!(summary.weighted_average_similarity < 0.11);
+        assert!(summary.weighted_average_similarity > 0.1);
+    }
+
+    /// Minimal `patch -p1`: applies each hunk to `original`, checking that
+    /// every context and removed line matches and the hunk counts add up
+    fn apply_patch(original: &str, patch: &str) -> String {
+        let old: Vec<&str> = original.split_inclusive('\n').collect();
+        let mut lines = patch.split_inclusive('\n').peekable();
+        assert!(lines.next().unwrap().starts_with("--- a/"));
+        assert!(lines.next().unwrap().starts_with("+++ b/"));
+
+        let range = |r: &str| -> (usize, usize) {
+            let mut parts = r[1..].split(',');
+            let start = parts.next().unwrap().parse().unwrap();
+            (start, parts.next().map_or(1, |len| len.parse().unwrap()))
+        };
+        let mut out = String::new();
+        let mut pos = 0;
+        while let Some(header) = lines.next() {
+            let ranges: Vec<&str> = header.trim_end().trim_matches('@').trim().split(' ').collect();
+            let (old_start, old_len) = range(ranges[0]);
+            let (_, new_len) = range(ranges[1]);
+            let hunk_start = if old_len == 0 { old_start } else { old_start - 1 };
+            out.push_str(&old[pos..hunk_start].concat());
+            pos = hunk_start;
+
+            let (mut old_seen, mut new_seen) = (0, 0);
+            while old_seen < old_len || new_seen < new_len {
+                let (tag, mut body) = lines.next().unwrap().split_at(1);
+                if lines.peek().is_some_and(|l| l.starts_with("\\ No newline")) {
+                    lines.next();
+                    body = body.strip_suffix('\n').unwrap();
+                }
+                if tag != "+" {
+                    assert_eq!(old[pos], body);
+                    pos += 1;
+                    old_seen += 1;
+                }
+                if tag != "-" {
+                    out.push_str(body);
+                    new_seen += 1;
+                }
+            }
+        }
+        out.push_str(&old[pos..].concat());
+        out
+    }
+
+    #[test]
+    fn test_patch_compatible_patch_reproduces_file2() {
+        let dir = tempfile::tempdir().unwrap();
+        let (root1, root2) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(root1.join("src")).unwrap();
+        fs::create_dir_all(root2.join("src")).unwrap();
+
+        let mut before: Vec<String> = (1..=40).map(|i| format!("line {}\n", i)).collect();
+        before[4] = "windows line\r\n".to_string();
+        let mut after = before.clone();
+        after[1] = "line 2 changed  \n".to_string();
+        after.insert(20, "\tinserted\n".to_string());
+        after.pop();
+        after.push("last line without newline".to_string());
+        let (before, after) = (before.concat(), after.concat());
+        fs::write(root1.join("src/notes.txt"), &before).unwrap();
+        fs::write(root2.join("src/notes.txt"), &after).unwrap();
+
+        let mut result = text_result("notes.txt", 10, 0.5);
+        if let ComparisonResult::Text(ref mut r) = result {
+            r.file1_path = root1.join("src/notes.txt").display().to_string();
+            r.file2_path = root2.join("src/notes.txt").display().to_string();
+            r.detailed_diff = "--- normalized\n".to_string();
+        }
+        let out = dir.path().join("out");
+        write_patches(&[result], &out, Some((&root1, &root2))).unwrap();
+
+        let patch = fs::read_to_string(out.join("patches/notes.txt.diff")).unwrap();
+        assert!(patch.starts_with("--- a/src/notes.txt\n+++ b/src/notes.txt\n"));
+        assert!(patch.contains("\\ No newline at end of file"));
+        assert_eq!(patch.matches("@@ -").count(), 3);
+        assert_eq!(apply_patch(&before, &patch), after);
+    }
+
+    #[test]
+    fn test_patch_compatible_decodes_latin1_and_skips_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (root1, root2) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&root1).unwrap();
+        fs::create_dir_all(&root2).unwrap();
+        // "café" in windows-1252
+        fs::write(root1.join("menu.txt"), b"caf\xe9\nsoup\n").unwrap();
+        fs::write(root2.join("menu.txt"), b"caf\xe9\nsalad\n").unwrap();
+
+        let pair = |name: &str, linked_id: &str| {
+            let mut result = text_result(linked_id, 10, 0.5);
+            if let ComparisonResult::Text(ref mut r) = result {
+                r.file1_path = root1.join(name).display().to_string();
+                r.file2_path = root2.join(name).display().to_string();
+            }
+            result
+        };
+        let out = dir.path().join("out");
+        let results = [pair("menu.txt", "menu"), pair("gone.txt", "gone")];
+        write_patches(&results, &out, Some((&root1, &root2))).unwrap();
+
+        let patch = fs::read_to_string(out.join("patches/menu.diff")).unwrap();
+        assert!(patch.contains(" café\n-soup\n+salad\n"), "{}", patch);
+        assert!(!out.join("patches/gone.diff").exists());
+    }
+
+    #[test]
+    fn test_junit_failures_match_summary_and_parse_back() {
+        use quick_xml::events::Event;
+
+        let mut different = text_result("a<b>&\"c\".txt", 10, 0.5);
+        if let ComparisonResult::Text(ref mut r) = different {
+            r.detailed_diff = "--- a\n+++ b\n@@ -1 +1 @@\n-<old> & 'x'\n+new\u{1}\n".to_string();
+        }
+        let results = vec![
+            text_result("same.txt", 10, 1.0),
+            different,
+            ComparisonResult::Error {
+                file1_path: "x.csv".to_string(),
+                file2_path: "y.csv".to_string(),
+                error: "Failed to parse <row> 3 & more".to_string(),
+            },
+            ComparisonResult::OnlyInOne(UnmatchedFile {
+                path: "new.txt".to_string(),
+                side: FileSide::Set2,
+                size: 3,
+                file_type: FileType::Text,
+            }),
+        ];
+        let summary = calculate_summary(&results, 3, 4, None);
+
+        let xml = render_junit(&results);
+        let mut reader = quick_xml::Reader::from_str(&xml);
+        let (mut testcases, mut failing, mut skipped) = (0, 0, 0);
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) | Event::Empty(e) => {
+                    for attr in e.attributes() {
+                        assert!(attr.unwrap().decode_and_unescape_value(&reader).is_ok());
+                    }
+                    match e.name().as_ref() {
+                        b"testcase" => testcases += 1,
+                        b"failure" | b"error" => failing += 1,
+                        b"skipped" => skipped += 1,
+                        _ => {}
+                    }
+                }
+                Event::Text(t) => assert!(t.unescape().is_ok()),
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        assert_eq!(testcases, results.len());
+        assert_eq!(failing, summary.different_pairs + summary.error_pairs);
+        assert_eq!(skipped, summary.only_in_set2);
+        assert!(xml.contains(r#"failures="1" errors="1" skipped="1""#));
+    }
+
+    #[test]
+    fn test_summary_json_round_trips() {
+        let results = vec![text_result("a.txt", 10, 1.0), text_result("b.txt", 10, 0.5)];
+        let stats = ProcessStats {
+            execution_time_ms: Some(1234),
+            peak_memory_usage_bytes: Some(64 * 1024 * 1024),
+            ..ProcessStats::default()
+        };
+        let summary = calculate_summary(&results, 2, 3, Some(stats));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("summary.json");
+        export_summary_json(&summary, &path).unwrap();
+        let read: ComparisonSummary = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(
+            (read.total_files_set1, read.total_files_set2, read.pairs_compared),
+            (summary.total_files_set1, summary.total_files_set2, summary.pairs_compared)
+        );
+        assert_eq!((read.identical_pairs, read.different_pairs), (1, 1));
+        assert_eq!(read.average_similarity, summary.average_similarity);
+        assert_eq!(read.execution_time_ms, Some(1234));
+        assert_eq!(read.peak_memory_usage_bytes, Some(64 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_badge_reflects_summary() {
+        let results = vec![text_result("a.txt", 10, 1.0), text_result("b.txt", 10, 0.96)];
+        let summary = calculate_summary(&results, 2, 2, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("badge.json");
+        export_badge(&summary, &path).unwrap();
+        let badge: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            badge,
+            serde_json::json!({"schemaVersion": 1, "label": "compareit", "message": "98% match", "color": "green"})
+        );
+
+        let mut with_error = results.clone();
+        with_error.push(ComparisonResult::Error {
+            file1_path: "c.txt".to_string(),
+            file2_path: "c.txt".to_string(),
+            error: "unreadable".to_string(),
+        });
+        let badge = badge_json(&calculate_summary(&with_error, 3, 3, None));
+        assert_eq!(badge["message"], "98% match, 1 error");
+        assert_eq!(badge["color"], "red");
+
+        let badge = badge_json(&calculate_summary(&[text_result("d.txt", 10, 0.6)], 1, 1, None));
+        assert_eq!(badge["color"], "orange");
+    }
+
+    #[test]
+    fn test_column_rollup_sums_across_files() {
+        let structured = |name: &str, mismatches: &[(&str, usize)]| {
+            let field_mismatches = mismatches
+                .iter()
+                .map(|&(column, count)| ColumnMismatch {
+                    column_name: column.to_string(),
+                    inferred_type: ColumnType::Float,
+                    mismatch_count: count,
+                    sample_mismatches: Vec::new(),
+                    all_mismatches: Vec::new(),
+                })
+                .collect();
+            ComparisonResult::Structured(StructuredComparisonResult {
+                common_columns: vec!["amount".to_string(), "qty".to_string()],
+                ..structured_result(name, 100, field_mismatches)
+            })
+        };
+        let results = vec![
+            structured("jan.csv", &[("amount", 40), ("qty", 3)]),
+            text_result("notes.txt", 10, 0.5),
+            structured("feb.csv", &[("amount", 2)]),
+        ];
+
+        let rollup = calculate_column_rollup(&results);
+        assert_eq!(
+            rollup,
+            vec![
+                ColumnRollup { column_name: "amount".to_string(), mismatch_count: 42, files_affected: 2 },
+                ColumnRollup { column_name: "qty".to_string(), mismatch_count: 3, files_affected: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xlsx_export_sheets() {
+        use calamine::{open_workbook_auto, Reader};
+
+        let mismatch = |key: &str| FieldMismatch {
+            key: key.to_string(),
+            value1: "=1+1".to_string(),
+            value2: "<b>&amp;</b>".to_string(),
+        };
+        let amount = ColumnMismatch {
+            column_name: "amount".to_string(),
+            inferred_type: ColumnType::String,
+            mismatch_count: 2,
+            sample_mismatches: vec![mismatch("1"), mismatch("2")],
+            all_mismatches: Vec::new(),
+        };
+        let structured = ComparisonResult::Structured(StructuredComparisonResult {
+            common_columns: vec!["id".to_string(), "amount".to_string()],
+            ..structured_result("orders.csv", 3, vec![amount])
+        });
+        let results = vec![structured, text_result("notes.txt", 10, 1.0), text_result("x.txt", 10, 0.5)];
+        let summary = calculate_summary(&results, 3, 3, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.xlsx");
+        export_xlsx(&results, &summary, &path).unwrap();
+
+        let mut workbook = open_workbook_auto(&path).unwrap();
+        assert_eq!(workbook.sheet_names(), ["Summary", "All Pairs", "Field Mismatches"]);
+        assert_eq!(workbook.worksheet_range("All Pairs").unwrap().height(), 4);
+        let mismatches = workbook.worksheet_range("Field Mismatches").unwrap();
+        assert_eq!(mismatches.height(), 3);
+        // Values stay literal text
+        assert_eq!(mismatches.get_value((1, 3)).unwrap().to_string(), "=1+1");
+        assert_eq!(mismatches.get_value((1, 4)).unwrap().to_string(), "<b>&amp;</b>");
+    }
+
+    #[test]
+    fn test_sarif_results_per_difference() {
+        let mut text = text_result("src/main rs", 10, 0.5);
+        if let ComparisonResult::Text(r) = &mut text {
+            r.different_positions = "3-4,9".to_string();
+            r.file2_line_count = 12;
+        }
+        let amount = ColumnMismatch {
+            column_name: "amount".to_string(),
+            inferred_type: ColumnType::String,
+            mismatch_count: 1,
+            sample_mismatches: vec![FieldMismatch {
+                key: "42".to_string(),
+                value1: "1".to_string(),
+                value2: "2".to_string(),
+            }],
+            all_mismatches: Vec::new(),
+        };
+        let structured = ComparisonResult::Structured(StructuredComparisonResult {
+            common_columns: vec!["id".to_string(), "amount".to_string()],
+            ..structured_result("orders.csv", 3, vec![amount])
+        });
+        let results = vec![text, text_result("same.txt", 10, 1.0), structured];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.sarif");
+        export_sarif(&results, &path).unwrap();
+        let sarif: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(sarif["version"], "2.1.0");
+        let sarif_results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(sarif_results.len(), 2);
+        let location = &sarif_results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "b/src/main%20rs");
+        assert_eq!(location["region"]["startLine"], 4);
+        assert_eq!(sarif_results[1]["ruleId"], "field-mismatch");
+        assert_eq!(sarif_results[1]["partialFingerprints"]["recordKey"], "42");
+    }
+}
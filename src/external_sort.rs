@@ -0,0 +1,225 @@
+//! External merge sort for keyed CSV records
+//!
+//! Structured comparison normally sorts every record of a file in memory.
+//! For CSVs larger than RAM, records are instead buffered into chunks of a
+//! bounded size; each full chunk is sorted by key and spilled to a temporary
+//! file as a "run". The runs are then merged back in key order with a k-way
+//! heap merge that holds only one record per run in memory.
+//!
+//! The final chunk never needs to be spilled, so input that fits in a single
+//! chunk is sorted entirely in memory without touching disk.
+
+use anyhow::{Context, Result};
+use csv::{ByteRecord, Reader, ReaderBuilder, WriterBuilder};
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Bytes in one of the megabytes `--external-sort` is given in
+pub const MEGABYTE: usize = 1024 * 1024;
+
+/// Default chunk size for `--external-sort` (256 MB)
+pub const DEFAULT_CHUNK_BYTES: usize = 256 * MEGABYTE;
+
+/// Approximate bookkeeping overhead of one buffered record, in bytes
+const RECORD_OVERHEAD: usize = 64;
+
+/// Buffers keyed records and spills them to disk as sorted runs
+pub struct RunSpiller {
+    chunk_bytes: usize,
+    chunk: Vec<(String, ByteRecord)>,
+    chunk_size: usize,
+    dir: Option<TempDir>,
+    runs: Vec<PathBuf>,
+}
+
+impl RunSpiller {
+    /// Create a spiller that keeps at most about `chunk_bytes` of records in memory
+    pub fn new(chunk_bytes: usize) -> Self {
+        Self {
+            chunk_bytes: chunk_bytes.max(1),
+            chunk: Vec::new(),
+            chunk_size: 0,
+            dir: None,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Add a record, spilling the current chunk first if it is full
+    pub fn push(&mut self, key: String, record: ByteRecord) -> Result<()> {
+        let size = key.len() + record.as_slice().len() + RECORD_OVERHEAD;
+        if !self.chunk.is_empty() && self.chunk_size + size > self.chunk_bytes {
+            self.spill()?;
+        }
+        self.chunk_size += size;
+        self.chunk.push((key, record));
+        Ok(())
+    }
+
+    /// Sort the current chunk and write it to a new run file
+    fn spill(&mut self) -> Result<()> {
+        let mut chunk = std::mem::take(&mut self.chunk);
+        self.chunk_size = 0;
+        chunk.par_sort_by(|a, b| a.0.cmp(&b.0));
+
+        let dir = match &self.dir {
+            Some(dir) => dir.path().to_path_buf(),
+            None => {
+                let dir = tempfile::tempdir().context("Failed to create a directory for sort runs")?;
+                let path = dir.path().to_path_buf();
+                self.dir = Some(dir);
+                path
+            }
+        };
+        let path = dir.join(format!("run-{}.csv", self.runs.len()));
+
+        // Each row is the key followed by the record's own fields
+        let mut writer = WriterBuilder::new()
+            .flexible(true)
+            .from_path(&path)
+            .with_context(|| format!("Failed to create sort run {}", path.display()))?;
+        for (key, record) in &chunk {
+            let mut row = ByteRecord::with_capacity(key.len() + record.as_slice().len(), record.len() + 1);
+            row.push_field(key.as_bytes());
+            row.extend(record.iter());
+            writer
+                .write_byte_record(&row)
+                .with_context(|| format!("Failed to write sort run {}", path.display()))?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write sort run {}", path.display()))?;
+
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// Finish buffering and return the records merged back in key order
+    ///
+    /// Records with equal keys keep their insertion order.
+    pub fn finish(mut self) -> Result<SortedRuns> {
+        self.chunk.par_sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Spilled runs come first so ties resolve in insertion order
+        let mut sources: Vec<RunSource> = Vec::with_capacity(self.runs.len() + 1);
+        for path in &self.runs {
+            let reader = ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_path(path)
+                .with_context(|| format!("Failed to open sort run {}", path.display()))?;
+            sources.push(RunSource::Spilled(reader));
+        }
+        sources.push(RunSource::Memory(std::mem::take(&mut self.chunk).into_iter()));
+
+        let mut merged = SortedRuns {
+            spilled_runs: self.runs.len(),
+            heads: sources.iter().map(|_| None).collect(),
+            sources,
+            heap: BinaryHeap::new(),
+            _dir: self.dir.take(),
+        };
+        for run in 0..merged.sources.len() {
+            merged.refill(run)?;
+        }
+        Ok(merged)
+    }
+}
+
+/// One sorted run: either a spilled file or the final in-memory chunk
+enum RunSource {
+    Spilled(Reader<File>),
+    Memory(std::vec::IntoIter<(String, ByteRecord)>),
+}
+
+impl RunSource {
+    fn next_record(&mut self) -> Result<Option<(String, ByteRecord)>> {
+        match self {
+            RunSource::Spilled(reader) => {
+                let mut row = ByteRecord::new();
+                if !reader.read_byte_record(&mut row).context("Failed to read a sort run")? {
+                    return Ok(None);
+                }
+                let key = String::from_utf8_lossy(row.get(0).unwrap_or_default()).into_owned();
+                let record: ByteRecord = row.iter().skip(1).collect();
+                Ok(Some((key, record)))
+            }
+            RunSource::Memory(records) => Ok(records.next()),
+        }
+    }
+}
+
+/// Iterator over all buffered records in key order
+pub struct SortedRuns {
+    spilled_runs: usize,
+    sources: Vec<RunSource>,
+    /// Next record of each run, waiting for its turn in the heap
+    heads: Vec<Option<ByteRecord>>,
+    /// Smallest (key, run) first; the run index breaks ties stably
+    heap: BinaryHeap<Reverse<(String, usize)>>,
+    /// Keeps the run files alive until the merge is dropped
+    _dir: Option<TempDir>,
+}
+
+impl SortedRuns {
+    /// Number of chunks that were spilled to disk
+    pub fn spilled_runs(&self) -> usize {
+        self.spilled_runs
+    }
+
+    /// Pull the next record of `run` into the heap
+    fn refill(&mut self, run: usize) -> Result<()> {
+        if let Some((key, record)) = self.sources[run].next_record()? {
+            self.heads[run] = Some(record);
+            self.heap.push(Reverse((key, run)));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SortedRuns {
+    type Item = Result<(String, ByteRecord)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((key, run)) = self.heap.pop()?;
+        let record = self.heads[run].take().unwrap_or_default();
+        match self.refill(run) {
+            Ok(()) => Some(Ok((key, record))),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spilled_runs_merge_in_stable_key_order() {
+        let mut spiller = RunSpiller::new(1024);
+        for i in 0..500 {
+            let key = format!("k{:03}", (i * 37) % 100);
+            let record = ByteRecord::from(vec![key.clone(), i.to_string(), "a,\"quoted\"\nvalue".to_string()]);
+            spiller.push(key, record).unwrap();
+        }
+
+        let merged = spiller.finish().unwrap();
+        assert!(merged.spilled_runs() > 1);
+
+        let records: Vec<(String, ByteRecord)> = merged.map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 500);
+        for pair in records.windows(2) {
+            assert!(pair[0].0 <= pair[1].0);
+            if pair[0].0 == pair[1].0 {
+                // Duplicates keep the order they were pushed in
+                let a: usize = std::str::from_utf8(&pair[0].1[1]).unwrap().parse().unwrap();
+                let b: usize = std::str::from_utf8(&pair[1].1[1]).unwrap().parse().unwrap();
+                assert!(a < b);
+            }
+        }
+        assert_eq!(&records[0].1[2], b"a,\"quoted\"\nvalue");
+    }
+}
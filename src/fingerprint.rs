@@ -1,93 +1,167 @@
 //! Fingerprinting for fast similarity estimation
 //!
 //! This module computes:
-//! - Blake3 content hashes for exact match detection
+//! - Content hashes (Blake3 by default) for exact match detection
 //! - Simhash fingerprints for text similarity estimation
 //! - Schema signatures for structured file matching
 
-use crate::types::{FileEntry, FileType, NormalizationOptions};
-use anyhow::Result;
+use crate::archive::resolve_entry_path;
+use crate::diagnostics::Diagnostics;
+use crate::input::{open_input, read_input};
+use crate::types::{AnomalyKind, FileEntry, FileType, HashAlgorithm, NormalizationOptions, UnicodeForm};
+use crate::ProgressReporter;
+use anyhow::{Context, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{DecoderResult, Encoding, UTF_8};
 use log::warn;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::hash::{Hash, Hasher};
-use std::io::{BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use unicode_normalization::UnicodeNormalization;
 
 /// Compute all fingerprints for a set of file entries
+///
+/// Files skipped for similarity fingerprinting because of their size are
+/// recorded in `diagnostics`. Once `cancel` is set, remaining entries are
+/// left unfingerprinted. `progress` is advanced by one for every file
+/// processed, including files that failed to fingerprint. Files found
+/// unchanged in `cache` reuse their stored fingerprints; the rest are
+/// computed and written back to it. Content hashes use `hash_algorithm`.
+#[allow(clippy::too_many_arguments)]
 pub fn compute_fingerprints(
     entries: &mut [FileEntry],
     normalization: &NormalizationOptions,
     max_file_size: u64,
+    hash_algorithm: HashAlgorithm,
+    diagnostics: &Diagnostics,
+    cancel: Option<&AtomicBool>,
+    progress: Option<&dyn ProgressReporter>,
+    cache: Option<&FingerprintCache>,
 ) {
     entries.par_iter_mut().for_each(|entry| {
-        if let Err(e) = compute_fingerprint_for_entry(entry, normalization, max_file_size) {
+        if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return;
+        }
+        // A symlink's content is its target path, hashed when it was indexed
+        if entry.link_target.is_some() {
+            if let Some(p) = progress {
+                p.inc(1);
+            }
+            return;
+        }
+        let key = cache.and_then(|_| cache_key(&entry.path));
+        let result = match cache {
+            Some(cache) if cache.restore(key.as_ref(), entry) => {
+                if entry.size > max_file_size {
+                    report_oversized(entry, max_file_size, diagnostics);
+                }
+                Ok(())
+            }
+            _ => compute_fingerprint_for_entry(entry, normalization, max_file_size, hash_algorithm, diagnostics).map(|()| {
+                if let (Some(cache), Some(key)) = (cache, key) {
+                    cache.store(key, entry);
+                }
+            }),
+        };
+        if let Err(e) = result {
             warn!(
                 "Failed to fingerprint {}: {}",
                 entry.path.display(),
                 e
             );
         }
+        if let Some(p) = progress {
+            p.inc(1);
+        }
+    });
+}
+
+/// Compute only the content hash of each entry
+///
+/// The fast path for pairing strategies that never estimate similarity:
+/// simhashes and schema signatures stay `None`, so exact-match detection
+/// works but similarity-ranked pairing does not. `cancel` and `progress`
+/// behave as in [`compute_fingerprints`].
+pub fn compute_content_hashes(
+    entries: &mut [FileEntry],
+    hash_algorithm: HashAlgorithm,
+    cancel: Option<&AtomicBool>,
+    progress: Option<&dyn ProgressReporter>,
+) {
+    entries.par_iter_mut().for_each(|entry| {
+        if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return;
+        }
+        // A symlink's content is its target path, hashed when it was indexed
+        if entry.link_target.is_none() {
+            match hash_file(&entry.path, hash_algorithm) {
+                Ok(hash) => entry.content_hash = hash,
+                Err(e) => warn!("Failed to hash {}: {}", entry.path.display(), e),
+            }
+        }
+        if let Some(p) = progress {
+            p.inc(1);
+        }
     });
 }
 
 /// Compute fingerprints for a single file entry
 ///
-/// Uses streaming Blake3 hash computation to handle files of any size with
-/// constant memory usage. Memory-intensive operations (Simhash) are skipped
-/// for files exceeding `max_file_size`.
+/// Uses a streaming content hash to handle files of any size with constant
+/// memory usage. Memory-intensive operations (Simhash) are skipped for files
+/// exceeding `max_file_size`.
 fn compute_fingerprint_for_entry(
     entry: &mut FileEntry,
     normalization: &NormalizationOptions,
     max_file_size: u64,
+    hash_algorithm: HashAlgorithm,
+    diagnostics: &Diagnostics,
 ) -> Result<()> {
-    // Get file metadata to check size
-    let metadata = fs::metadata(&entry.path)?;
-    let file_size = metadata.len();
-
-    // Streaming Blake3 hash computation - works with any file size
-    // using constant memory (reads in chunks internally)
-    let file = fs::File::open(&entry.path)?;
-    let mut reader = BufReader::new(file);
-    let mut hasher = blake3::Hasher::new();
-    
-    // Stream the file through the hasher
-    let mut buffer = [0u8; 16384]; // 16KB buffer for efficient streaming
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
-    
-    entry.content_hash = hasher.finalize().to_hex().to_string();
+    // Content size from indexing (decompressed size for gzipped files)
+    let file_size = entry.size;
+
+    entry.content_hash = hash_file(&entry.path, hash_algorithm)?;
 
     // Skip memory-intensive Simhash for large files to prevent OOM
     if file_size > max_file_size {
-        if matches!(entry.file_type, FileType::Text | FileType::Csv | FileType::Tsv) {
-            warn!(
-                "File too large for similarity fingerprinting ({} bytes > {} byte limit), using hash-only: {}",
-                file_size, max_file_size, entry.path.display()
-            );
-        }
+        report_oversized(entry, max_file_size, diagnostics);
         // Still compute schema signature for structured files (it's lightweight)
-        if let (FileType::Csv | FileType::Tsv | FileType::Excel, Some(ref columns)) = (&entry.file_type, &entry.columns) {
+        if let (FileType::Csv | FileType::Tsv | FileType::Excel | FileType::Json | FileType::FixedWidth, Some(ref columns)) = (&entry.file_type, &entry.columns) {
             entry.schema_signature = Some(compute_schema_signature(columns));
         }
         return Ok(());
     }
 
     // For files within the size limit, read content for Simhash computation
-    let content = fs::read(&entry.path)?;
+    let content = read_input(&entry.path)?;
 
     // Compute type-specific fingerprints
     match entry.file_type {
         FileType::Text => {
-            let text = String::from_utf8_lossy(&content);
-            entry.simhash = Some(compute_simhash(&text, normalization));
+            match decode_text(&content) {
+                Ok((text, encoding)) => {
+                    entry.simhash = Some(compute_simhash(&text, normalization));
+                    entry.encoding = Some(encoding.to_string());
+                }
+                Err(e) => {
+                    // Still fingerprint what we can; the comparison will surface the error
+                    warn!("Failed to decode {}: {}", entry.path.display(), e);
+                    let text = String::from_utf8_lossy(&content);
+                    entry.simhash = Some(compute_simhash(&text, normalization));
+                }
+            }
         }
-        FileType::Csv | FileType::Tsv => {
+        FileType::Csv | FileType::Tsv | FileType::Json | FileType::FixedWidth => {
             // Compute schema signature from columns
             if let Some(ref columns) = entry.columns {
                 entry.schema_signature = Some(compute_schema_signature(columns));
@@ -112,6 +186,244 @@ fn compute_fingerprint_for_entry(
     Ok(())
 }
 
+/// Hex digest of a file's contents under `algorithm`
+///
+/// The file is streamed in chunks, so any size hashes in constant memory.
+/// Gzipped files hash their decompressed bytes so they match their
+/// plaintext twins.
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
+    let mut reader = open_input(path)?;
+    let mut hasher = ContentHasher::new(algorithm);
+
+    let mut buffer = [0u8; 16384]; // 16KB buffer for efficient streaming
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(hasher.finalize_hex())
+}
+
+/// Streaming hasher for one [`HashAlgorithm`]
+enum ContentHasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+impl ContentHasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake3 => ContentHasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Sha256 => ContentHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Xxh3 => ContentHasher::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new())),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            ContentHasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+            ContentHasher::Sha256(hasher) => hasher.update(bytes),
+            ContentHasher::Xxh3(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            ContentHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            ContentHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            ContentHasher::Xxh3(hasher) => format!("{:016x}", hasher.digest()),
+        }
+    }
+}
+
+/// Warn that a text-like file is too large for similarity fingerprinting
+fn report_oversized(entry: &FileEntry, max_file_size: u64, diagnostics: &Diagnostics) {
+    if matches!(entry.file_type, FileType::Text | FileType::Csv | FileType::Tsv | FileType::Json | FileType::FixedWidth) {
+        warn!(
+            "File too large for similarity fingerprinting ({} bytes > {} byte limit), using hash-only: {}",
+            entry.size, max_file_size, entry.path.display()
+        );
+        diagnostics.record(
+            AnomalyKind::OversizedFile,
+            &entry.path,
+            format!("{} bytes exceeds the {} byte fingerprint limit; compared by hash only", entry.size, max_file_size),
+        );
+    }
+}
+
+/// Fingerprints of one file as stored in a [`FingerprintCache`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFingerprint {
+    /// Modification time in nanoseconds since the Unix epoch
+    mtime: u64,
+    /// Size on disk in bytes
+    size: u64,
+    /// Size limit the fingerprints were computed under; larger files have no simhash
+    max_file_size: u64,
+    content_hash: String,
+    simhash: Option<u64>,
+    schema_signature: Option<String>,
+    encoding: Option<String>,
+}
+
+/// On-disk layout of a [`FingerprintCache`]
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    /// Settings the fingerprints were computed with
+    settings: String,
+    /// Entries keyed by canonical path
+    entries: HashMap<String, CachedFingerprint>,
+}
+
+/// Persistent fingerprint cache for repeated runs over the same files
+///
+/// Entries are keyed by canonical path and are only reused while the file's
+/// modification time and size are unchanged. Fingerprints depend on the
+/// normalization options and hash algorithm, so a cache written with
+/// different ones is discarded as a whole. The size limit is recorded per
+/// entry instead: a changed limit only invalidates the files it moves across.
+pub struct FingerprintCache {
+    path: PathBuf,
+    settings: String,
+    max_file_size: u64,
+    entries: Mutex<HashMap<String, CachedFingerprint>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl FingerprintCache {
+    /// Load the cache at `path`, starting empty if it is missing, unreadable,
+    /// or was written with different settings
+    pub fn load(
+        path: &Path,
+        normalization: &NormalizationOptions,
+        max_file_size: u64,
+        hash_algorithm: HashAlgorithm,
+    ) -> Self {
+        let settings = format!("{:?}/{}", normalization, hash_algorithm.name());
+        let entries = match fs::read(path) {
+            Ok(bytes) => match serde_json::from_slice::<CacheFile>(&bytes) {
+                Ok(file) if file.settings == settings => file.entries,
+                Ok(_) => HashMap::new(),
+                Err(e) => {
+                    warn!("Ignoring unreadable fingerprint cache {}: {}", path.display(), e);
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            path: path.to_path_buf(),
+            settings,
+            max_file_size,
+            entries: Mutex::new(entries),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of files whose fingerprints were reused
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of files that had to be fingerprinted
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Fill in `entry`'s fingerprints from the cache if the file is unchanged
+    ///
+    /// `key` is the file's [`cache_key`], or `None` if it has none.
+    fn restore(&self, key: Option<&CacheKey>, entry: &mut FileEntry) -> bool {
+        let oversized = entry.size > self.max_file_size;
+        let cached = key.and_then(|key| {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            entries
+                .get(&key.path)
+                .filter(|c| c.mtime == key.mtime && c.size == key.size && (entry.size > c.max_file_size) == oversized)
+                .cloned()
+        });
+        match cached {
+            Some(cached) => {
+                entry.content_hash = cached.content_hash;
+                entry.simhash = cached.simhash;
+                entry.schema_signature = cached.schema_signature;
+                entry.encoding = cached.encoding;
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// Record freshly computed fingerprints for the file `key` identifies
+    fn store(&self, key: CacheKey, entry: &FileEntry) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            key.path,
+            CachedFingerprint {
+                mtime: key.mtime,
+                size: key.size,
+                max_file_size: self.max_file_size,
+                content_hash: entry.content_hash.clone(),
+                simhash: entry.simhash,
+                schema_signature: entry.schema_signature.clone(),
+                encoding: entry.encoding.clone(),
+            },
+        );
+    }
+
+    /// Write the cache back to disk
+    pub fn save(&self) -> Result<()> {
+        let file = CacheFile {
+            settings: self.settings.clone(),
+            entries: self.entries.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        };
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create cache directory {}", parent.display()))?;
+        }
+        let json = serde_json::to_vec(&file).context("Failed to serialize fingerprint cache")?;
+        fs::write(&self.path, json)
+            .with_context(|| format!("Failed to write fingerprint cache {}", self.path.display()))
+    }
+}
+
+/// Canonical path, modification time and size identifying a file's contents
+struct CacheKey {
+    path: String,
+    /// Modification time in nanoseconds since the Unix epoch
+    mtime: u64,
+    /// Size on disk in bytes
+    size: u64,
+}
+
+/// The [`CacheKey`] of the file at `path`, if it can be canonicalized
+///
+/// Archive entries are keyed by their archive-relative path, not by the
+/// temporary copy they are read from.
+fn cache_key(path: &Path) -> Option<CacheKey> {
+    let (canonical, metadata) = match resolve_entry_path(path) {
+        Cow::Owned(unpacked) => (std::path::absolute(path).ok()?, fs::metadata(unpacked).ok()?),
+        Cow::Borrowed(_) => {
+            let canonical = fs::canonicalize(path).ok()?;
+            let metadata = fs::metadata(&canonical).ok()?;
+            (canonical, metadata)
+        }
+    };
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64;
+    Some(CacheKey { path: canonical.display().to_string(), mtime, size: metadata.len() })
+}
+
 /// Compute simhash fingerprint for text content
 ///
 /// Simhash is a locality-sensitive hash (LSH) that produces similar hashes
@@ -146,19 +458,19 @@ pub fn compute_simhash(text: &str, normalization: &NormalizationOptions) -> u64
 
     for shingle in shingles {
         let hash = hash_string(&shingle);
-        for i in 0..64 {
+        for (i, weight) in v.iter_mut().enumerate() {
             if (hash >> i) & 1 == 1 {
-                v[i] += 1;
+                *weight += 1;
             } else {
-                v[i] -= 1;
+                *weight -= 1;
             }
         }
     }
 
     // Convert to final hash
     let mut result: u64 = 0;
-    for i in 0..64 {
-        if v[i] > 0 {
+    for (i, weight) in v.iter().enumerate() {
+        if *weight > 0 {
             result |= 1 << i;
         }
     }
@@ -166,11 +478,24 @@ pub fn compute_simhash(text: &str, normalization: &NormalizationOptions) -> u64
     result
 }
 
+/// Convert `line` to the given Unicode normalization form
+fn unicode_normalize(line: &str, form: UnicodeForm) -> String {
+    match form {
+        UnicodeForm::Nfc => line.nfc().collect(),
+        UnicodeForm::Nfd => line.nfd().collect(),
+        UnicodeForm::Nfkc => line.nfkc().collect(),
+        UnicodeForm::Nfkd => line.nfkd().collect(),
+    }
+}
+
 /// Normalize text according to options
 fn normalize_text(text: &str, opts: &NormalizationOptions) -> Vec<String> {
     text.lines()
         .map(|line| {
-            let mut s = line.to_string();
+            let mut s = match opts.unicode_normalize {
+                Some(form) => unicode_normalize(line, form),
+                None => line.to_string(),
+            };
 
             // Normalize EOL (already handled by .lines())
 
@@ -260,47 +585,237 @@ pub fn simhash_similarity(hash1: u64, hash2: u64) -> f64 {
     1.0 - (distance as f64 / 64.0)
 }
 
+/// Bytes read per step when decoding a file incrementally
+const DECODE_CHUNK: usize = 64 * 1024;
+
+/// Read a text file and decode it to UTF-8
+///
+/// Returns the text and the name of the encoding it was decoded from. See
+/// [`decode_text`] for how the encoding is chosen. The file is decoded as it
+/// is read, so only the decoded text is held in memory.
+pub fn read_text_decoded(path: &Path) -> Result<(String, &'static str)> {
+    let mut text = String::new();
+    let encoding = decode_file(path, &mut text)?;
+    Ok((text, encoding))
+}
+
+/// Receives a file's text from [`decode_file`] one piece at a time
+trait DecodedText {
+    /// Take the next piece of decoded text
+    fn push(&mut self, text: &str);
+    /// Forget everything pushed so far; the file is being decoded again
+    fn restart(&mut self);
+}
+
+impl DecodedText for String {
+    fn push(&mut self, text: &str) {
+        self.push_str(text);
+    }
+
+    fn restart(&mut self) {
+        self.clear();
+    }
+}
+
+/// Decode the file at `path` to UTF-8 incrementally, returning the encoding's name
+///
+/// The encoding is chosen like [`decode_text`] does. A file without a BOM is
+/// first decoded as UTF-8; if it is not valid UTF-8, `sink` is restarted and
+/// the file decoded again in the encoding `chardetng` detects for it.
+fn decode_file(path: &Path, sink: &mut impl DecodedText) -> Result<&'static str> {
+    let mut reader = BufReader::with_capacity(DECODE_CHUNK, open_input(path)?);
+    let (encoding, bom_len) = Encoding::for_bom(reader.fill_buf()?).unwrap_or((UTF_8, 0));
+    reader.consume(bom_len);
+    if decode_stream(&mut reader, encoding, sink)? {
+        return Ok(encoding.name());
+    }
+    if bom_len > 0 {
+        return Err(malformed(encoding).into());
+    }
+
+    let mut detector = EncodingDetector::new();
+    let mut reader = BufReader::with_capacity(DECODE_CHUNK, open_input(path)?);
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        detector.feed(chunk, false);
+        let read = chunk.len();
+        reader.consume(read);
+    }
+    detector.feed(&[], true);
+    let encoding = detector.guess(None, true);
+
+    sink.restart();
+    let mut reader = BufReader::with_capacity(DECODE_CHUNK, open_input(path)?);
+    if decode_stream(&mut reader, encoding, sink)? {
+        Ok(encoding.name())
+    } else {
+        Err(malformed(encoding).into())
+    }
+}
+
+/// Decode `reader` as `encoding` into `sink`; false if it holds malformed bytes
+fn decode_stream(reader: &mut impl BufRead, encoding: &'static Encoding, sink: &mut impl DecodedText) -> io::Result<bool> {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut out = String::with_capacity(decoder.max_utf8_buffer_length(DECODE_CHUNK).unwrap_or(DECODE_CHUNK));
+    loop {
+        let input = reader.fill_buf()?;
+        let last = input.is_empty();
+        let (result, read) = decoder.decode_to_string_without_replacement(input, &mut out, last);
+        reader.consume(read);
+        sink.push(&out);
+        out.clear();
+        match result {
+            DecoderResult::InputEmpty if last => return Ok(true),
+            DecoderResult::InputEmpty | DecoderResult::OutputFull => {}
+            DecoderResult::Malformed(..) => return Ok(false),
+        }
+    }
+}
+
+/// Error for content that does not decode as `encoding`
+fn malformed(encoding: &'static Encoding) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("content is not valid {}", encoding.name()))
+}
+
+/// Decode text bytes to UTF-8, detecting their encoding
+///
+/// A byte-order mark (UTF-8, UTF-16LE/BE) wins. Without one, valid UTF-8 is
+/// taken as is and anything else goes through `chardetng` charset detection,
+/// which picks a legacy encoding such as `windows-1252` (Latin-1). Bytes that
+/// are malformed for the chosen encoding give an `InvalidData` error.
+pub fn decode_text(bytes: &[u8]) -> Result<(String, &'static str)> {
+    let (encoding, bom_len) = match Encoding::for_bom(bytes) {
+        Some(found) => found,
+        None => {
+            if let Ok(text) = std::str::from_utf8(bytes) {
+                return Ok((text.to_string(), UTF_8.name()));
+            }
+            let mut detector = EncodingDetector::new();
+            detector.feed(bytes, true);
+            (detector.guess(None, true), 0)
+        }
+    };
+
+    let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+    if had_errors {
+        return Err(malformed(encoding).into());
+    }
+    Ok((text.into_owned(), encoding.name()))
+}
+
 /// Read and normalize file content for comparison
+///
+/// The file is decoded like [`read_text_decoded`], so UTF-16 and legacy
+/// encodings compare as text, and split into lines as it is decoded. Ignored
+/// head and tail lines are dropped first, so empty lines among them count
+/// towards their number.
 pub fn read_normalized_lines(
-    path: &std::path::Path,
+    path: &Path,
     normalization: &NormalizationOptions,
 ) -> Result<Vec<String>> {
-    let file = fs::File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut lines = Vec::new();
+    let mut lines = NormalizedLines::new(normalization);
+    decode_file(path, &mut lines)?;
+    Ok(lines.finish())
+}
 
-    for line in reader.lines() {
-        let mut s = line?;
+/// Splits decoded text into normalized lines as it arrives
+///
+/// The last `ignore_tail_lines` lines are held back until the end of the
+/// file, where they are dropped.
+struct NormalizedLines<'a> {
+    normalization: &'a NormalizationOptions,
+    /// Text after the last line break seen
+    partial: String,
+    /// Lines that may still turn out to be tail lines
+    pending: VecDeque<String>,
+    /// Number of lines taken out of `pending`
+    released: usize,
+    lines: Vec<String>,
+}
 
-        // Normalize trailing whitespace
-        if normalization.ignore_trailing_ws {
-            s = s.trim_end().to_string();
-        }
+impl<'a> NormalizedLines<'a> {
+    fn new(normalization: &'a NormalizationOptions) -> Self {
+        Self { normalization, partial: String::new(), pending: VecDeque::new(), released: 0, lines: Vec::new() }
+    }
 
-        // Normalize all whitespace
-        if normalization.ignore_all_ws {
-            s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    fn line(&mut self, line: String) {
+        self.pending.push_back(line);
+        if self.pending.len() > self.normalization.ignore_tail_lines {
+            let line = self.pending.pop_front().unwrap_or_default();
+            if self.released >= self.normalization.ignore_head_lines {
+                if let Some(line) = normalize_line(&line, self.normalization) {
+                    self.lines.push(line);
+                }
+            }
+            self.released += 1;
         }
+    }
 
-        // Case insensitive
-        if normalization.ignore_case {
-            s = s.to_lowercase();
+    fn finish(mut self) -> Vec<String> {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.line(line);
         }
+        self.lines
+    }
+}
 
-        // Skip empty lines if requested
-        if normalization.skip_empty_lines && s.trim().is_empty() {
-            continue;
+impl DecodedText for NormalizedLines<'_> {
+    fn push(&mut self, mut text: &str) {
+        while let Some(end) = text.find('\n') {
+            self.partial.push_str(&text[..end]);
+            let mut line = std::mem::take(&mut self.partial);
+            if line.ends_with('\r') {
+                line.pop();
+            }
+            self.line(line);
+            text = &text[end + 1..];
         }
+        self.partial.push_str(text);
+    }
 
-        lines.push(s);
+    fn restart(&mut self) {
+        *self = Self::new(self.normalization);
     }
+}
+
+/// Apply `normalization` to one line; `None` if the line is skipped as empty
+fn normalize_line(line: &str, normalization: &NormalizationOptions) -> Option<String> {
+    let mut s = match normalization.unicode_normalize {
+        Some(form) => unicode_normalize(line, form),
+        None => line.to_string(),
+    };
 
-    Ok(lines)
+    // Normalize trailing whitespace
+    if normalization.ignore_trailing_ws {
+        s = s.trim_end().to_string();
+    }
+
+    // Normalize all whitespace
+    if normalization.ignore_all_ws {
+        s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+
+    // Case insensitive
+    if normalization.ignore_case {
+        s = s.to_lowercase();
+    }
+
+    // Skip empty lines if requested
+    if normalization.skip_empty_lines && s.trim().is_empty() {
+        return None;
+    }
+
+    Some(s)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_hamming_distance() {
@@ -315,6 +830,172 @@ mod tests {
         assert!(simhash_similarity(0, u64::MAX) < 0.1);
     }
 
+    /// Progress reporter that only counts increments
+    #[derive(Default)]
+    struct CountingReporter {
+        incs: std::sync::atomic::AtomicU64,
+    }
+
+    impl ProgressReporter for CountingReporter {
+        fn start(&self, _total: u64, _message: &str) {}
+        fn inc(&self, delta: u64) {
+            assert_eq!(delta, 1);
+            self.incs.fetch_add(delta, Ordering::SeqCst);
+        }
+        fn finish(&self, _message: &str) {}
+    }
+
+    #[test]
+    fn test_progress_advances_once_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut entries: Vec<FileEntry> = (0..25)
+            .map(|i| {
+                let path = dir.path().join(format!("f{}.txt", i));
+                fs::write(&path, format!("file number {}\n", i)).unwrap();
+                crate::index::index_single_file(&path).unwrap()
+            })
+            .collect();
+
+        let progress = CountingReporter::default();
+        compute_fingerprints(
+            &mut entries,
+            &NormalizationOptions::default(),
+            u64::MAX,
+            HashAlgorithm::Blake3,
+            &Diagnostics::new(),
+            None,
+            Some(&progress),
+            None,
+        );
+
+        assert_eq!(progress.incs.load(Ordering::SeqCst), 25);
+        assert!(entries.iter().all(|e| e.simhash.is_some()));
+    }
+
+    #[test]
+    fn test_cache_recomputes_only_touched_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                fs::write(&path, format!("contents of {}\n", name)).unwrap();
+                path
+            })
+            .collect();
+        let index = || -> Vec<FileEntry> {
+            paths.iter().map(|p| crate::index::index_single_file(p).unwrap()).collect()
+        };
+        let cache_path = dir.path().join("cache").join("fingerprints.json");
+        let normalization = NormalizationOptions::default();
+        let run = || {
+            let cache = FingerprintCache::load(&cache_path, &normalization, u64::MAX, HashAlgorithm::Blake3);
+            let mut entries = index();
+            compute_fingerprints(&mut entries, &normalization, u64::MAX, HashAlgorithm::Blake3, &Diagnostics::new(), None, None, Some(&cache));
+            cache.save().unwrap();
+            (entries, cache.hits(), cache.misses())
+        };
+
+        let (first, hits, misses) = run();
+        assert_eq!((hits, misses), (0, 2));
+
+        let (second, hits, misses) = run();
+        assert_eq!((hits, misses), (2, 0));
+        assert_eq!(second[0].content_hash, first[0].content_hash);
+        assert_eq!(second[1].simhash, first[1].simhash);
+
+        let file = fs::File::options().write(true).open(&paths[1]).unwrap();
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60)).unwrap();
+        let (third, hits, misses) = run();
+        assert_eq!((hits, misses), (1, 1));
+        assert_eq!(third[1].content_hash, first[1].content_hash);
+
+        // A new size limit only invalidates the files it moves across
+        let with_limit = |max_file_size: u64| {
+            let cache = FingerprintCache::load(&cache_path, &normalization, max_file_size, HashAlgorithm::Blake3);
+            let mut entries = index();
+            compute_fingerprints(&mut entries, &normalization, max_file_size, HashAlgorithm::Blake3, &Diagnostics::new(), None, None, Some(&cache));
+            (entries, cache.hits(), cache.misses())
+        };
+        let (_, hits, misses) = with_limit(1024);
+        assert_eq!((hits, misses), (2, 0));
+        let (oversized, hits, misses) = with_limit(1);
+        assert_eq!((hits, misses), (0, 2));
+        assert!(oversized.iter().all(|e| e.simhash.is_none()));
+
+        // Different normalization settings invalidate the whole cache
+        let ignore_case = NormalizationOptions { ignore_case: true, ..NormalizationOptions::default() };
+        let cache = FingerprintCache::load(&cache_path, &ignore_case, u64::MAX, HashAlgorithm::Blake3);
+        let mut entries = index();
+        compute_fingerprints(&mut entries, &ignore_case, u64::MAX, HashAlgorithm::Blake3, &Diagnostics::new(), None, None, Some(&cache));
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    }
+
+    #[test]
+    fn test_decode_utf16le_with_bom() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.txt");
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "Name\tCity\r\nZoë\tKöln\r\n".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        fs::write(&path, bytes).unwrap();
+
+        let (_, encoding) = read_text_decoded(&path).unwrap();
+        assert_eq!(encoding, "UTF-16LE");
+        let lines = read_normalized_lines(&path, &NormalizationOptions::default()).unwrap();
+        assert_eq!(lines, vec!["Name\tCity", "Zoë\tKöln"]);
+
+        let mut entries = vec![crate::index::index_single_file(&path).unwrap()];
+        assert_eq!(entries[0].file_type, FileType::Text);
+        assert_eq!(entries[0].line_count, 2);
+        compute_fingerprints(&mut entries, &NormalizationOptions::default(), u64::MAX, HashAlgorithm::Blake3, &Diagnostics::new(), None, None, None);
+        assert_eq!(entries[0].encoding.as_deref(), Some("UTF-16LE"));
+    }
+
+    #[test]
+    fn test_decoding_streams_across_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy.txt");
+        // Valid UTF-8 for more than a chunk, then a Latin-1 byte
+        let filler: String = (0..DECODE_CHUNK / 10).map(|i| format!("line {:04}\r\n", i % 10_000)).collect();
+        let mut bytes = b"banner\n".to_vec();
+        bytes.extend(filler.as_bytes());
+        bytes.extend(b"caf\xE9\nfooter");
+        fs::write(&path, &bytes).unwrap();
+
+        let (text, encoding) = read_text_decoded(&path).unwrap();
+        assert_eq!(encoding, "windows-1252");
+        assert!(text.ends_with("café\nfooter"));
+
+        let normalization = NormalizationOptions { ignore_head_lines: 1, ignore_tail_lines: 1, ..NormalizationOptions::default() };
+        let lines = read_normalized_lines(&path, &normalization).unwrap();
+        assert_eq!(lines.len(), DECODE_CHUNK / 10 + 1);
+        assert_eq!(lines[0], "line 0000");
+        assert_eq!(lines.last().map(String::as_str), Some("café"));
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("menu.txt");
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252
+            .encode("Le café est très apprécié à Genève.\nDéjà vu, garçon, crème brûlée.\n");
+        fs::write(&path, &latin1).unwrap();
+
+        let lines = read_normalized_lines(&path, &NormalizationOptions::default()).unwrap();
+        assert_eq!(lines[0], "Le café est très apprécié à Genève.");
+        assert_eq!(lines[1], "Déjà vu, garçon, crème brûlée.");
+
+        let mut entries = vec![crate::index::index_single_file(&path).unwrap()];
+        assert_eq!(entries[0].file_type, FileType::Text);
+        compute_fingerprints(&mut entries, &NormalizationOptions::default(), u64::MAX, HashAlgorithm::Blake3, &Diagnostics::new(), None, None, None);
+        assert_eq!(entries[0].encoding.as_deref(), Some("windows-1252"));
+
+        // Plain UTF-8 is reported as such
+        assert_eq!(decode_text("café".as_bytes()).unwrap().1, "UTF-8");
+    }
+
     #[test]
     fn test_schema_signature() {
         let cols1 = vec!["a".to_string(), "b".to_string(), "c".to_string()];
@@ -325,4 +1006,19 @@ mod tests {
             compute_schema_signature(&cols2)
         );
     }
+
+    #[test]
+    fn test_hash_algorithms() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.txt");
+        fs::write(&path, "abc").unwrap();
+
+        // FIPS 180-2 test vector
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Sha256).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(hash_file(&path, HashAlgorithm::Blake3).unwrap(), blake3::hash(b"abc").to_hex().to_string());
+        assert_eq!(hash_file(&path, HashAlgorithm::Xxh3).unwrap().len(), 16);
+    }
 }
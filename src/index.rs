@@ -5,32 +5,99 @@
 //!
 //! **Phase 3 Enhancement:** Now supports Excel/OpenDocument spreadsheet detection
 //! using the `calamine` crate.
-
-use crate::types::{FileEntry, FileType};
+//!
+//! JSON files holding an array of objects, and NDJSON files holding one object
+//! per line, are detected as structured `FileType::Json`. Fixed-width files
+//! look like plain text, so they are only retyped by [`apply_fixed_width`].
+
+use crate::archive::{resolve_entry_path, unpacked_root};
+use crate::compare_structured::{flatten_json_record, positional_columns, read_json_rows};
+use crate::fingerprint::read_text_decoded;
+use crate::input::{content_extension, content_size, is_gzip_path, open_input, read_input_to_string};
+use crate::types::{CompareConfig, CsvDialect, FileEntry, FileType, SymlinkMode};
 use anyhow::{Context, Result};
 use calamine::{open_workbook_auto, Reader};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
 use globset::{Glob, GlobSetBuilder};
 use log::warn;
 use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 /// Excel/OpenDocument spreadsheet extensions supported by calamine
 const EXCEL_EXTENSIONS: &[&str] = &["xlsx", "xls", "xlsm", "xlsb", "xla", "xlam", "ods"];
 
+/// Newline-delimited JSON extensions (one object per line)
+const NDJSON_EXTENSIONS: &[&str] = &["jsonl", "ndjson"];
+
+/// Delimiters tried when sniffing a CSV-like file, in order of preference
+const DELIMITER_CANDIDATES: [char; 4] = [',', ';', '\t', '|'];
+
+/// Leading lines sampled when sniffing a CSV-like file
+const SNIFF_LINES: usize = 10;
+
+/// Which files a directory walk picks up, and how their headers are read
+#[derive(Debug, Clone, Copy)]
+pub struct IndexOptions<'a> {
+    /// Glob patterns for paths to exclude (e.g., `["*.tmp", "node_modules/"]`)
+    pub exclude_patterns: &'a [String],
+    /// Glob patterns a file must match to be indexed; empty indexes every file
+    pub include_patterns: &'a [String],
+    /// Deepest subdirectory level to descend into (0 = only the top-level files)
+    pub max_depth: Option<usize>,
+    /// Whether symlinks are skipped, followed or indexed as their target path
+    pub symlinks: SymlinkMode,
+    /// CSV/TSV lines starting with this byte are passed over when reading the header
+    pub comment_char: Option<u8>,
+    /// Whether the first CSV/TSV record is a header; without one, columns are
+    /// named by position (`col_0`, `col_1`, ...)
+    pub has_header: bool,
+}
+
+impl Default for IndexOptions<'_> {
+    fn default() -> Self {
+        Self {
+            exclude_patterns: &[],
+            include_patterns: &[],
+            max_depth: None,
+            symlinks: SymlinkMode::default(),
+            comment_char: None,
+            has_header: true,
+        }
+    }
+}
+
+impl<'a> IndexOptions<'a> {
+    pub fn from_config(config: &'a CompareConfig) -> Self {
+        Self {
+            exclude_patterns: &config.exclude_patterns,
+            include_patterns: &config.include_patterns,
+            max_depth: config.max_depth,
+            symlinks: config.symlinks,
+            comment_char: config.comment_char,
+            has_header: config.has_header,
+        }
+    }
+}
+
 /// Index files from a path (file or directory)
 ///
 /// # Arguments
 /// * `path` - The file or directory path to index
-/// * `exclude_patterns` - Glob patterns for paths to exclude (e.g., `["*.tmp", "node_modules/"]`)
-pub fn index_path(path: &Path, exclude_patterns: &[String]) -> Result<Vec<FileEntry>> {
-    if path.is_file() {
-        let entry = index_single_file(path)?;
+/// * `options` - Filters and symlink handling applied while walking a directory
+pub fn index_path(path: &Path, options: &IndexOptions) -> Result<Vec<FileEntry>> {
+    if let Some(root) = unpacked_root(path) {
+        let mut entries = index_directory(&root, options)?;
+        rebase_entries(&mut entries, &root, path);
+        Ok(entries)
+    } else if path.is_file() {
+        let entry = index_single_file_with(path, options)?;
         Ok(vec![entry])
     } else if path.is_dir() {
-        index_directory(path, exclude_patterns)
+        index_directory(path, options)
     } else {
         anyhow::bail!("Path does not exist or is not accessible: {}", path.display());
     }
@@ -38,50 +105,225 @@ pub fn index_path(path: &Path, exclude_patterns: &[String]) -> Result<Vec<FileEn
 
 /// Index all files in a directory recursively
 ///
-/// Applies exclusion patterns to filter out unwanted files and directories.
-pub fn index_directory(dir: &Path, exclude_patterns: &[String]) -> Result<Vec<FileEntry>> {
-    // Build glob set for exclusion patterns
-    let exclude_set = build_exclude_set(exclude_patterns)?;
+/// Applies exclusion patterns to filter out unwanted files and directories,
+/// and inclusion patterns to keep only the wanted files. The walk itself is
+/// sequential; type detection then runs in parallel, and entries are sorted
+/// by path so the result does not depend on thread scheduling.
+pub fn index_directory(dir: &Path, options: &IndexOptions) -> Result<Vec<FileEntry>> {
+    let paths = walk_files(dir, options)?;
+    let link_targets = options.symlinks == SymlinkMode::CompareTarget;
+
+    // Process files in parallel
+    let mut entries: Vec<FileEntry> = paths
+        .par_iter()
+        .filter_map(|p| {
+            if link_targets && p.is_symlink() {
+                index_symlink(p).ok()
+            } else {
+                index_single_file_with(p, options).ok()
+            }
+        })
+        .collect();
+
+    // Deterministic ordering by path
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(entries)
+}
+
+/// Index an explicit list of files instead of walking a directory
+///
+/// For callers that already know which files to compare (e.g. the files a
+/// commit touched). Each file gets the same type detection as [`index_path`];
+/// paths matching `exclude_patterns` are dropped. Unlike a directory walk, a
+/// listed file that cannot be indexed is an error.
+pub fn index_files(paths: &[PathBuf], exclude_patterns: &[String]) -> Result<Vec<FileEntry>> {
+    let exclude_set = build_glob_set(exclude_patterns, "exclude")?;
+
+    let mut entries: Vec<FileEntry> = paths
+        .par_iter()
+        .filter(|p| !is_excluded(exclude_set.as_ref(), p))
+        .map(|p| index_single_file(p).with_context(|| format!("Failed to index {}", p.display())))
+        .collect::<Result<_>>()?;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(entries)
+}
+
+/// Index files from a path using file metadata only
+///
+/// No file content is opened: entries carry size, extension, permission bits
+/// and modification time, but no type detection, line count or columns.
+pub fn index_path_metadata(path: &Path, options: &IndexOptions) -> Result<Vec<FileEntry>> {
+    if let Some(root) = unpacked_root(path) {
+        let mut entries = index_path_metadata(&root, options)?;
+        rebase_entries(&mut entries, &root, path);
+        Ok(entries)
+    } else if path.is_file() {
+        Ok(vec![index_metadata(path)?])
+    } else if path.is_dir() {
+        let mut entries: Vec<FileEntry> = walk_files(path, options)?
+            .par_iter()
+            .filter_map(|p| index_metadata(p).ok())
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    } else {
+        anyhow::bail!("Path does not exist or is not accessible: {}", path.display());
+    }
+}
+
+/// Give entries indexed from an unpacked archive their archive-relative paths
+fn rebase_entries(entries: &mut [FileEntry], root: &Path, archive: &Path) {
+    for entry in entries {
+        if let Ok(relative) = entry.path.strip_prefix(root) {
+            entry.path = archive.join(relative);
+        }
+    }
+}
+
+/// Build an entry for one file from its metadata alone
+fn index_metadata(path: &Path) -> Result<FileEntry> {
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
+
+    Ok(FileEntry {
+        path: path.to_path_buf(),
+        size: metadata.len(),
+        file_type: FileType::Binary,
+        extension: content_extension(path),
+        content_hash: String::new(),
+        simhash: None,
+        schema_signature: None,
+        line_count: 0,
+        columns: None,
+        encoding: None,
+        mode: permission_bits(&metadata),
+        mtime: modified_secs(&metadata),
+        link_target: None,
+        dialect: None,
+    })
+}
+
+/// Build an entry for a symlink whose content is its target path
+///
+/// The link is never resolved, so dangling links are indexed too.
+fn index_symlink(path: &Path) -> Result<FileEntry> {
+    let target = fs::read_link(path)
+        .with_context(|| format!("Failed to read symlink {}", path.display()))?
+        .display()
+        .to_string();
+    let metadata = fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
+
+    Ok(FileEntry {
+        path: path.to_path_buf(),
+        size: target.len() as u64,
+        file_type: FileType::Text,
+        extension: content_extension(path),
+        content_hash: blake3::hash(target.as_bytes()).to_hex().to_string(),
+        simhash: None,
+        schema_signature: None,
+        line_count: 1,
+        columns: None,
+        encoding: None,
+        mode: None,
+        mtime: modified_secs(&metadata),
+        link_target: Some(target),
+        dialect: None,
+    })
+}
+
+/// Unix permission bits of a file
+#[cfg(unix)]
+fn permission_bits(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+/// Unix permission bits of a file
+#[cfg(not(unix))]
+fn permission_bits(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Modification time of a file in seconds since the Unix epoch
+fn modified_secs(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// Paths of all files under `dir` that no exclusion pattern matches
+///
+/// With inclusion patterns, a file must also match one of them by its full
+/// path, its path relative to `dir` or its file name. With `max_depth`, files
+/// more than that many directories below `dir` are skipped; symlinked
+/// directories count toward the depth.
+///
+/// Symlinks are handled per `options.symlinks`: skipped, returned as paths of
+/// their own ([`SymlinkMode::CompareTarget`]), or followed. When following,
+/// each directory is entered once by its canonical path, so link cycles and
+/// several links to one folder do not repeat work.
+fn walk_files(dir: &Path, options: &IndexOptions) -> Result<Vec<PathBuf>> {
+    // Build glob sets for exclusion and inclusion patterns
+    let exclude_set = build_glob_set(options.exclude_patterns, "exclude")?;
+    let include_set = build_glob_set(options.include_patterns, "include")?;
+    let included = |path: &Path| {
+        let Some(glob_set) = &include_set else { return true };
+        glob_set.is_match(path)
+            || path.strip_prefix(dir).is_ok_and(|relative| glob_set.is_match(relative))
+            || path.file_name().is_some_and(|name| glob_set.is_match(name))
+    };
+
+    // WalkDir counts `dir` itself as depth 0, so its files are at depth 1
+    let follow = options.symlinks == SymlinkMode::Follow;
+    let mut walker = WalkDir::new(dir).follow_links(follow);
+    if let Some(depth) = options.max_depth {
+        walker = walker.max_depth(depth.saturating_add(1));
+    }
 
-    let paths: Vec<PathBuf> = WalkDir::new(dir)
-        .follow_links(false)
+    let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
+    let paths: Vec<PathBuf> = walker
         .into_iter()
         .filter_entry(|e| {
-            // Check if this entry should be excluded
-            let path = e.path();
-            if let Some(ref glob_set) = exclude_set {
-                // Check both the full path and relative path
-                if glob_set.is_match(path) {
+            // Enter each directory once when links may lead back to it
+            if follow && e.file_type().is_dir() {
+                let canonical = fs::canonicalize(e.path()).unwrap_or_else(|_| e.path().to_path_buf());
+                if !visited_dirs.insert(canonical) {
                     return false;
                 }
-                // Also check with just the filename for simple patterns like "*.tmp"
-                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    if glob_set.is_match(name) {
-                        return false;
-                    }
-                }
             }
-            true
+
+            // Check if this entry should be excluded
+            !is_excluded(exclude_set.as_ref(), e.path())
         })
         .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            let file = e.file_type().is_file()
+                || (options.symlinks == SymlinkMode::CompareTarget && e.path_is_symlink());
+            file && included(e.path())
+        })
         .map(|e| e.path().to_path_buf())
         .collect();
 
-    // Process files in parallel
-    let mut entries: Vec<FileEntry> = paths
-        .par_iter()
-        .filter_map(|p| index_single_file(p).ok())
-        .collect();
-
-    // Deterministic ordering by path
-    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(paths)
+}
 
-    Ok(entries)
+/// Whether `path` matches an exclusion pattern
+fn is_excluded(exclude_set: Option<&globset::GlobSet>, path: &Path) -> bool {
+    let Some(glob_set) = exclude_set else { return false };
+    // Check the full path, then just the filename for simple patterns like "*.tmp"
+    glob_set.is_match(path)
+        || path.file_name().and_then(|n| n.to_str()).is_some_and(|name| glob_set.is_match(name))
 }
 
-/// Build a glob set from exclusion patterns
-fn build_exclude_set(patterns: &[String]) -> Result<Option<globset::GlobSet>> {
+/// Build a glob set from exclusion or inclusion patterns
+///
+/// `kind` names the patterns in warnings and errors.
+fn build_glob_set(patterns: &[String], kind: &str) -> Result<Option<globset::GlobSet>> {
     if patterns.is_empty() {
         return Ok(None);
     }
@@ -93,29 +335,46 @@ fn build_exclude_set(patterns: &[String]) -> Result<Option<globset::GlobSet>> {
                 builder.add(glob);
             }
             Err(e) => {
-                warn!("Invalid exclude pattern '{}': {}", pattern, e);
+                warn!("Invalid {} pattern '{}': {}", kind, pattern, e);
             }
         }
     }
 
-    let set = builder.build().context("Failed to build exclude glob set")?;
+    let set = builder.build().with_context(|| format!("Failed to build {} glob set", kind))?;
     Ok(Some(set))
 }
 
-/// Index a single file
+/// Treat every text entry as fixed-width records with the given column names
+///
+/// Fixed-width data has no header, so `line_count` already counts records.
+pub fn apply_fixed_width(entries: &mut [FileEntry], headers: &[String]) {
+    for entry in entries.iter_mut().filter(|e| e.file_type == FileType::Text) {
+        entry.file_type = FileType::FixedWidth;
+        entry.columns = Some(headers.to_vec());
+    }
+}
+
+/// Index a single file with the default [`IndexOptions`]
 pub fn index_single_file(path: &Path) -> Result<FileEntry> {
+    index_single_file_with(path, &IndexOptions::default())
+}
+
+/// Index a single file, reading CSV/TSV headers as `options` describe
+pub fn index_single_file_with(path: &Path, options: &IndexOptions) -> Result<FileEntry> {
     let metadata = fs::metadata(path)
         .with_context(|| format!("Failed to read metadata for {}", path.display()))?;
 
-    let size = metadata.len();
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase())
-        .unwrap_or_default();
+    // Gzipped files are sized and typed by their decompressed content, so
+    // `data.csv.gz` pairs up with `data.csv`
+    let size = if is_gzip_path(path) {
+        content_size(path).with_context(|| format!("Failed to read the gzip size of {}", path.display()))?
+    } else {
+        metadata.len()
+    };
+    let extension = content_extension(path);
 
     // Detect file type
-    let (file_type, line_count, columns) = detect_file_type(path, &extension)?;
+    let (file_type, line_count, columns, dialect) = detect_file_type(path, &extension, options)?;
 
     // Compute content hash (will be done in fingerprint stage, placeholder here)
     let content_hash = String::new();
@@ -130,50 +389,79 @@ pub fn index_single_file(path: &Path) -> Result<FileEntry> {
         schema_signature: None,
         line_count,
         columns,
+        encoding: None,
+        mode: permission_bits(&metadata),
+        mtime: modified_secs(&metadata),
+        link_target: None,
+        dialect,
     })
 }
 
+/// File type, line count, column names and (for CSV/TSV) dialect of a file
+type Detection = (FileType, usize, Option<Vec<String>>, Option<CsvDialect>);
+
 /// Detect file type by examining content and extension
-fn detect_file_type(path: &Path, extension: &str) -> Result<(FileType, usize, Option<Vec<String>>)> {
+fn detect_file_type(path: &Path, extension: &str, options: &IndexOptions) -> Result<Detection> {
     // Check for Excel/OpenDocument spreadsheet first (by extension)
-    if EXCEL_EXTENSIONS.contains(&extension) {
-        return detect_excel_type(path);
+    if EXCEL_EXTENSIONS.contains(&extension) && !is_gzip_path(path) {
+        let (file_type, rows, columns) = detect_excel_type(path)?;
+        return Ok((file_type, rows, columns, None));
+    }
+
+    // JSON record sets are structured; other JSON (config objects) stays text
+    if extension == "json" || NDJSON_EXTENSIONS.contains(&extension) {
+        if let Some((file_type, rows, columns)) = detect_json_type(path, extension) {
+            return Ok((file_type, rows, columns, None));
+        }
     }
 
     // Check extension for CSV/TSV
     let is_csv_ext = extension == "csv";
     let is_tsv_ext = extension == "tsv" || extension == "tab";
 
+    // UTF-16 text is full of null bytes, so recognize it by its BOM before
+    // the binary check below
+    if let Some(line_count) = detect_utf16_text(path) {
+        return Ok((FileType::Text, line_count, None, None));
+    }
+
     // Try to read first few KB to determine type
-    let file = fs::File::open(path)?;
-    let mut reader = BufReader::new(file);
+    let mut reader = BufReader::new(open_input(path)?);
     let mut buffer: Vec<u8> = Vec::with_capacity(8192);
 
-    // Read up to 8KB for detection
+    // Read up to 8KB for detection. Lines are read as bytes so that text in a
+    // legacy encoding (Latin-1, Windows-1252) is not mistaken for binary.
     let mut total_read = 0;
     let mut line_count = 0;
     let mut has_null_byte = false;
     let mut first_line = String::new();
+    // Leading lines not starting with the comment char, so a CSV export with
+    // leading comment lines (skipped with `--comment-char`) still indexes its
+    // real header; the first is the header, the rest help sniff the delimiter
+    let mut sample_lines: Vec<String> = Vec::new();
 
     loop {
-        let mut line = String::new();
-        match reader.read_line(&mut line) {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line) {
             Ok(0) => break, // EOF
             Ok(n) => {
                 total_read += n;
                 line_count += 1;
 
                 if line_count == 1 {
-                    first_line = line.trim().to_string();
+                    first_line = String::from_utf8_lossy(&line).trim().to_string();
+                }
+                if sample_lines.len() < SNIFF_LINES && line.first() != options.comment_char.as_ref() {
+                    sample_lines.push(String::from_utf8_lossy(&line).trim().to_string());
                 }
 
                 // Check for binary content (null bytes)
-                if line.bytes().any(|b| b == 0) {
+                if line.contains(&0) {
                     has_null_byte = true;
                     break;
                 }
 
-                buffer.extend(line.as_bytes());
+                buffer.extend(&line);
                 if total_read >= 8192 {
                     break;
                 }
@@ -187,7 +475,7 @@ fn detect_file_type(path: &Path, extension: &str) -> Result<(FileType, usize, Op
 
     // Count remaining lines if we hit 8KB limit
     if total_read >= 8192 {
-        for result in reader.lines() {
+        for result in reader.split(b'\n') {
             if result.is_ok() {
                 line_count += 1;
             }
@@ -196,36 +484,53 @@ fn detect_file_type(path: &Path, extension: &str) -> Result<(FileType, usize, Op
 
     // Determine file type
     if has_null_byte {
-        return Ok((FileType::Binary, 0, None));
+        return Ok((FileType::Binary, 0, None, None));
     }
 
-    // Check for CSV/TSV structure
+    // Check for CSV/TSV structure, sniffing semicolon- or pipe-delimited exports
     if is_csv_ext || is_tsv_ext {
-        let delimiter = if is_tsv_ext { '\t' } else { ',' };
-        if let Some(columns) = parse_header(&first_line, delimiter) {
-            let file_type = if is_tsv_ext {
-                FileType::Tsv
-            } else {
-                FileType::Csv
-            };
-            return Ok((file_type, line_count, Some(columns)));
+        let file_type = if is_tsv_ext {
+            FileType::Tsv
+        } else {
+            FileType::Csv
+        };
+        let default = CsvDialect::for_file_type(&file_type);
+        let dialect = sniff_dialect(&sample_lines, default.delimiter).unwrap_or(default);
+        if let Some(columns) = parse_header(sample_lines.first().unwrap_or(&first_line), dialect) {
+            let columns = if options.has_header { columns } else { positional_columns(columns.len()) };
+            return Ok((file_type, line_count, Some(columns), Some(dialect)));
         }
     }
 
     // Auto-detect CSV/TSV by content
     if !first_line.is_empty() {
-        // Try comma delimiter
-        if let Some(columns) = try_detect_structured(&first_line, ',') {
-            return Ok((FileType::Csv, line_count, Some(columns)));
-        }
-        // Try tab delimiter
-        if let Some(columns) = try_detect_structured(&first_line, '\t') {
-            return Ok((FileType::Tsv, line_count, Some(columns)));
+        // Try comma, then tab delimiter
+        for file_type in [FileType::Csv, FileType::Tsv] {
+            let delimiter = CsvDialect::for_file_type(&file_type).delimiter;
+            if let Some(columns) = try_detect_structured(&first_line, delimiter) {
+                let dialect = CsvDialect { delimiter, quote: detect_quote(&sample_lines, delimiter) };
+                let columns = if options.has_header { columns } else { positional_columns(columns.len()) };
+                return Ok((file_type, line_count, Some(columns), Some(dialect)));
+            }
         }
     }
 
     // Default to text
-    Ok((FileType::Text, line_count, None))
+    Ok((FileType::Text, line_count, None, None))
+}
+
+/// Count the lines of a file that starts with a UTF-16 byte-order mark
+///
+/// Returns `None` for anything else, including UTF-16 files that fail to decode.
+fn detect_utf16_text(path: &Path) -> Option<usize> {
+    let mut bom = [0u8; 2];
+    open_input(path).ok()?.read_exact(&mut bom).ok()?;
+    let (encoding, _) = Encoding::for_bom(&bom)?;
+    if encoding != UTF_16LE && encoding != UTF_16BE {
+        return None;
+    }
+    let (text, _) = read_text_decoded(path).ok()?;
+    Some(text.lines().count())
 }
 
 /// Detect Excel/OpenDocument spreadsheet type using calamine
@@ -233,7 +538,7 @@ fn detect_file_type(path: &Path, extension: &str) -> Result<(FileType, usize, Op
 /// Extracts headers from the first row of the first worksheet.
 fn detect_excel_type(path: &Path) -> Result<(FileType, usize, Option<Vec<String>>)> {
     // Open workbook using auto-detection (supports xlsx, xls, ods, etc.)
-    let mut workbook = open_workbook_auto(path)
+    let mut workbook = open_workbook_auto(resolve_entry_path(path))
         .with_context(|| format!("Failed to open Excel file: {}", path.display()))?;
 
     // Get sheet names
@@ -265,16 +570,120 @@ fn detect_excel_type(path: &Path) -> Result<(FileType, usize, Option<Vec<String>
     Ok((FileType::Excel, row_count, columns))
 }
 
-/// Parse a header line with the given delimiter
-fn parse_header(line: &str, delimiter: char) -> Option<Vec<String>> {
-    let parts: Vec<&str> = line.split(delimiter).collect();
-    if parts.len() >= 2 {
-        Some(parts.iter().map(|s| s.trim().to_string()).collect())
+/// Detect a JSON record set and extract its flattened column paths
+///
+/// A `.json` file qualifies when its top level is a non-empty array of objects;
+/// a `.jsonl`/`.ndjson` file when every non-empty line is an object. Anything
+/// else returns `None` so the file falls back to text detection.
+fn detect_json_type(path: &Path, extension: &str) -> Option<(FileType, usize, Option<Vec<String>>)> {
+    let rows = if extension == "json" {
+        let content = read_input_to_string(path).ok()?;
+        match serde_json::from_str(&content).ok()? {
+            serde_json::Value::Array(items) => items,
+            _ => return None,
+        }
+    } else {
+        read_json_rows(path).ok()?
+    };
+
+    if rows.is_empty() || !rows.iter().all(|row| row.is_object()) {
+        return None;
+    }
+
+    // Union of flattened paths, in first-seen order
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut columns: Vec<String> = Vec::new();
+    for row in &rows {
+        for (column, _) in flatten_json_record(row) {
+            if seen.insert(column.clone()) {
+                columns.push(column);
+            }
+        }
+    }
+
+    Some((FileType::Json, rows.len(), Some(columns)))
+}
+
+/// Parse a header line with the given dialect
+fn parse_header(line: &str, dialect: CsvDialect) -> Option<Vec<String>> {
+    let fields = split_fields(line, dialect);
+    if fields.len() >= 2 {
+        Some(fields)
     } else {
         None
     }
 }
 
+/// Guess the delimiter and quote character from a file's leading lines
+///
+/// Picks the candidate delimiter that splits every sampled line into the
+/// same number of fields (at least two). `preferred` wins whenever it is
+/// consistent; otherwise the candidate giving the most fields does. Returns
+/// `None` when no candidate is consistent, e.g. for a single-column file or
+/// a sample cut inside a multi-line field.
+pub fn sniff_dialect(lines: &[String], preferred: char) -> Option<CsvDialect> {
+    let lines: Vec<&String> = lines.iter().filter(|l| !l.is_empty()).take(SNIFF_LINES).collect();
+    let mut best: Option<(CsvDialect, usize)> = None;
+
+    for delimiter in DELIMITER_CANDIDATES {
+        let quote = detect_quote(&lines, delimiter);
+        let dialect = CsvDialect { delimiter, quote };
+        let mut counts = lines.iter().map(|l| split_fields(l, dialect).len());
+        let fields = counts.next()?;
+        if fields < 2 || counts.any(|c| c != fields) {
+            continue;
+        }
+        if delimiter == preferred {
+            return Some(dialect);
+        }
+        if best.is_none_or(|(_, most)| fields > most) {
+            best = Some((dialect, fields));
+        }
+    }
+
+    best.map(|(dialect, _)| dialect)
+}
+
+/// Single quotes if some field opens with one and none with a double quote
+fn detect_quote(lines: &[impl AsRef<str>], delimiter: char) -> char {
+    let opens_with = |quote: char| {
+        let after_delimiter = format!("{}{}", delimiter, quote);
+        lines.iter().any(|line| {
+            let line = line.as_ref();
+            line.starts_with(quote) || line.contains(&after_delimiter)
+        })
+    };
+    if opens_with('\'') && !opens_with('"') { '\'' } else { '"' }
+}
+
+/// Split one line into trimmed fields, honoring quoted fields
+///
+/// A doubled quote inside a quoted field stands for one quote character.
+fn split_fields(line: &str, dialect: CsvDialect) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == dialect.quote {
+            if in_quotes && chars.peek() == Some(&dialect.quote) {
+                field.push(c);
+                chars.next();
+            } else {
+                in_quotes = !in_quotes;
+            }
+        } else if c == dialect.delimiter && !in_quotes {
+            fields.push(field.trim().to_string());
+            field.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
 /// Try to detect if a line looks like a structured header
 fn try_detect_structured(line: &str, delimiter: char) -> Option<Vec<String>> {
     let parts: Vec<&str> = line.split(delimiter).collect();
@@ -304,7 +713,7 @@ mod tests {
     #[test]
     fn test_parse_header() {
         let header = "id,name,value";
-        let columns = parse_header(header, ',');
+        let columns = parse_header(header, CsvDialect::for_file_type(&FileType::Csv));
         assert_eq!(columns, Some(vec!["id".to_string(), "name".to_string(), "value".to_string()]));
     }
 
@@ -321,4 +730,187 @@ mod tests {
         assert!(EXCEL_EXTENSIONS.contains(&"ods"));
         assert!(!EXCEL_EXTENSIONS.contains(&"csv"));
     }
+
+    #[test]
+    fn test_detect_json_record_sets() {
+        let dir = tempfile::tempdir().unwrap();
+        let records = dir.path().join("records.json");
+        let config = dir.path().join("package.json");
+        let ndjson = dir.path().join("events.jsonl");
+        fs::write(&records, r#"[{"id": 1, "meta": {"tag": "a"}}, {"id": 2, "extra": true}]"#).unwrap();
+        fs::write(&config, "{\n  \"name\": \"app\",\n  \"version\": \"1.0.0\"\n}\n").unwrap();
+        fs::write(&ndjson, "{\"id\": 1}\n{\"id\": 2}\n").unwrap();
+
+        let entry = index_single_file(&records).unwrap();
+        assert_eq!(entry.file_type, FileType::Json);
+        assert_eq!(entry.line_count, 2);
+        assert_eq!(
+            entry.columns,
+            Some(vec!["id".to_string(), "meta.tag".to_string(), "extra".to_string()])
+        );
+
+        // A single configuration object is not a record set
+        assert_eq!(index_single_file(&config).unwrap().file_type, FileType::Text);
+        assert_eq!(index_single_file(&ndjson).unwrap().file_type, FileType::Json);
+    }
+
+    #[test]
+    fn test_include_and_exclude_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["reports/a.csv", "reports/tmp/b.csv", "reports/c.txt", "other/d.csv"] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "id,value\n1,2\n").unwrap();
+        }
+        let indexed = |include: &[&str], exclude: &[&str]| -> Vec<String> {
+            let include: Vec<String> = include.iter().map(|p| p.to_string()).collect();
+            let exclude: Vec<String> = exclude.iter().map(|p| p.to_string()).collect();
+            let options = IndexOptions { exclude_patterns: &exclude, include_patterns: &include, ..IndexOptions::default() };
+            index_path(dir.path(), &options)
+                .unwrap()
+                .iter()
+                .map(|e| e.path.strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect()
+        };
+
+        assert_eq!(indexed(&[], &[]).len(), 4);
+        assert_eq!(indexed(&["*.csv"], &[]), vec!["other/d.csv", "reports/a.csv", "reports/tmp/b.csv"]);
+        assert_eq!(indexed(&["*.csv"], &["tmp"]), vec!["other/d.csv", "reports/a.csv"]);
+        assert_eq!(indexed(&["reports/**/*.csv"], &[]), vec!["reports/a.csv", "reports/tmp/b.csv"]);
+    }
+
+    #[test]
+    fn test_max_depth_limits_recursion() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["top.txt", "a/one.txt", "a/b/two.txt", "a/b/c/three.txt"] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "line\n").unwrap();
+        }
+        let names = |max_depth| -> Vec<String> {
+            let options = IndexOptions { max_depth, ..IndexOptions::default() };
+            index_path(dir.path(), &options)
+                .unwrap()
+                .iter()
+                .map(|e| e.path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        // A symlink back to the root is not followed, so it cannot loop
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("a/loop")).unwrap();
+
+        assert_eq!(names(Some(0)), vec!["top.txt"]);
+        assert_eq!(names(Some(1)), vec!["one.txt", "top.txt"]);
+        assert_eq!(names(None).len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a")).unwrap();
+        fs::write(dir.path().join("a/real.txt"), "data\n").unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("a/loop")).unwrap();
+        std::os::unix::fs::symlink("a/real.txt", dir.path().join("link.txt")).unwrap();
+        let indexed = |symlinks| -> Vec<(String, Option<String>)> {
+            let options = IndexOptions { symlinks, ..IndexOptions::default() };
+            index_path(dir.path(), &options)
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.path.strip_prefix(dir.path()).unwrap().display().to_string(), e.link_target))
+                .collect()
+        };
+
+        assert_eq!(indexed(SymlinkMode::Skip), vec![("a/real.txt".to_string(), None)]);
+        // The loop back to the root is entered once, not forever
+        assert_eq!(
+            indexed(SymlinkMode::Follow),
+            vec![("a/real.txt".to_string(), None), ("link.txt".to_string(), None)]
+        );
+        let targets = indexed(SymlinkMode::CompareTarget);
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[2], ("link.txt".to_string(), Some("a/real.txt".to_string())));
+    }
+
+    #[test]
+    fn test_index_files_uses_only_listed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.csv", "b.txt", "c.txt", "skip.tmp"] {
+            fs::write(dir.path().join(name), "id,name\n1,x\n").unwrap();
+        }
+        let listed: Vec<PathBuf> = ["b.txt", "a.csv", "skip.tmp"].iter().map(|n| dir.path().join(n)).collect();
+
+        let entries = index_files(&listed, &["*.tmp".to_string()]).unwrap();
+
+        let names: Vec<_> = entries.iter().map(|e| e.path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, vec!["a.csv", "b.txt"]);
+        assert_eq!(entries[0].file_type, FileType::Csv);
+        assert!(index_files(&[dir.path().join("missing.txt")], &[]).is_err());
+    }
+
+    #[test]
+    fn test_parallel_indexing_matches_serial() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..300 {
+            let sub = dir.path().join(format!("d{}", i % 7));
+            fs::create_dir_all(&sub).unwrap();
+            let (name, content) = match i % 3 {
+                0 => (format!("f{}.csv", i), format!("id,value\n{},x\n", i)),
+                1 => (format!("f{}.txt", i), "line\n".repeat(i % 5 + 1)),
+                _ => (format!("f{}.json", i), format!("[{{\"id\": {}}}]", i)),
+            };
+            fs::write(sub.join(name), content).unwrap();
+        }
+        let options = IndexOptions::default();
+
+        let parallel = index_path(dir.path(), &options).unwrap();
+        let mut serial: Vec<FileEntry> = walk_files(dir.path(), &options)
+            .unwrap()
+            .iter()
+            .map(|p| index_single_file(p).unwrap())
+            .collect();
+        serial.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(parallel.len(), 300);
+        assert_eq!(serde_json::to_value(&parallel).unwrap(), serde_json::to_value(&serial).unwrap());
+    }
+
+    #[test]
+    fn test_leading_comment_lines_use_the_configured_comment_char() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        fs::write(&path, "; exported 2024-01-01\nid,name\n1,Ann\n").unwrap();
+
+        let options = IndexOptions { comment_char: Some(b';'), ..IndexOptions::default() };
+        let entry = index_single_file_with(&path, &options).unwrap();
+        assert_eq!(entry.columns, Some(vec!["id".to_string(), "name".to_string()]));
+
+        // Without a comment char, the comment line is read as the header
+        let entry = index_single_file(&path).unwrap();
+        assert_ne!(entry.columns, Some(vec!["id".to_string(), "name".to_string()]));
+    }
+
+    #[test]
+    fn test_sniffs_semicolon_and_pipe_delimiters() {
+        let dir = tempfile::tempdir().unwrap();
+        let semicolon = dir.path().join("eu.csv");
+        fs::write(&semicolon, "id;name;price\n1;Ann;2,50\n2;\"Bob; Jr\";3,00\n").unwrap();
+        let pipe = dir.path().join("export.csv");
+        fs::write(&pipe, "id|name\n1|'Ann|Lee'\n").unwrap();
+
+        let entry = index_single_file(&semicolon).unwrap();
+        assert_eq!(entry.file_type, FileType::Csv);
+        assert_eq!(entry.columns, Some(vec!["id".to_string(), "name".to_string(), "price".to_string()]));
+        assert_eq!(entry.dialect, Some(CsvDialect { delimiter: ';', quote: '"' }));
+
+        let entry = index_single_file(&pipe).unwrap();
+        assert_eq!(entry.columns.map(|c| c.len()), Some(2));
+        assert_eq!(entry.dialect, Some(CsvDialect { delimiter: '|', quote: '\'' }));
+
+        // A plain comma CSV keeps the comma even if another candidate is consistent
+        let lines = vec!["a,b|c".to_string(), "1,2|3".to_string()];
+        assert_eq!(sniff_dialect(&lines, ',').map(|d| d.delimiter), Some(','));
+        assert_eq!(sniff_dialect(&["single".to_string()], ','), None);
+    }
 }
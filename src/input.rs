@@ -0,0 +1,164 @@
+//! Opening input files, with transparent gzip decompression
+//!
+//! A file ending in `.gz` is read through a gzip decoder, so `data.csv.gz`
+//! is indexed, fingerprinted and compared as if it were `data.csv`. A path
+//! of `-` stands for standard input, which is buffered to a temporary file.
+
+use crate::archive::resolve_entry_path;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Length of the smallest gzip file: a 10-byte header and an 8-byte trailer
+const GZIP_MIN_LEN: u64 = 18;
+
+/// True if the path has a `.gz` extension
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("gz"))
+}
+
+/// Lowercased extension that describes the file's content
+///
+/// For `report.csv.gz` this is `csv`; for `report.csv` it is also `csv`.
+pub fn content_extension(path: &Path) -> String {
+    let path = if is_gzip_path(path) {
+        Path::new(path.file_stem().unwrap_or_default())
+    } else {
+        path
+    };
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Open a file for reading, decompressing it if it is gzipped
+pub fn open_input(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let file = BufReader::new(File::open(resolve_entry_path(path))?);
+    if is_gzip_path(path) {
+        Ok(Box::new(GzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Read a whole file into memory, decompressing it if it is gzipped
+pub fn read_input(path: &Path) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open_input(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read a whole UTF-8 file into a string, decompressing it if it is gzipped
+pub fn read_input_to_string(path: &Path) -> io::Result<String> {
+    let mut text = String::new();
+    open_input(path)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Size of the file's content in bytes, after decompression
+///
+/// A gzipped file is not decompressed: its size is read from the ISIZE field
+/// of the gzip trailer, which records the uncompressed size modulo 4 GiB.
+pub fn content_size(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(resolve_entry_path(path))?;
+    let size = file.metadata()?.len();
+    if !is_gzip_path(path) {
+        return Ok(size);
+    }
+    if size < GZIP_MIN_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "file is too short to be gzipped"));
+    }
+    let mut trailer = [0u8; 4];
+    file.seek(SeekFrom::End(-4))?;
+    file.read_exact(&mut trailer)?;
+    Ok(u32::from_le_bytes(trailer) as u64)
+}
+
+/// True if the path is `-`, which stands for standard input
+pub fn is_stdin_path(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Copy a stream into a temporary file so it can be indexed like any other
+///
+/// The file takes the content extension of `like`, so piped CSV compared
+/// against `data.csv` is parsed as CSV. It is deleted when the returned
+/// handle is dropped.
+pub fn buffer_to_temp_file(mut reader: impl Read, like: &Path) -> io::Result<NamedTempFile> {
+    let extension = content_extension(like);
+    let suffix = if extension.is_empty() { String::new() } else { format!(".{}", extension) };
+    let mut file = tempfile::Builder::new().prefix("stdin-").suffix(&suffix).tempfile()?;
+    io::copy(&mut reader, file.as_file_mut())?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare_structured::compare_structured_files;
+    use crate::diagnostics::Diagnostics;
+    use crate::fingerprint::compute_fingerprints;
+    use crate::index::index_single_file;
+    use crate::types::{CompareConfig, ComparisonResult, FileType};
+    use crate::ComparisonEngine;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_gzipped_csv_matches_plaintext_twin() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "id,name\n1,Ann\n2,Bob\n";
+        let plain = dir.path().join("people.csv");
+        std::fs::write(&plain, content).unwrap();
+        let gz = dir.path().join("people.csv.gz");
+        let mut encoder = GzEncoder::new(File::create(&gz).unwrap(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let mut entries = vec![index_single_file(&plain).unwrap(), index_single_file(&gz).unwrap()];
+        assert_eq!(entries[1].file_type, FileType::Csv);
+        assert_eq!(entries[1].extension, "csv");
+        assert_eq!(entries[1].size, content.len() as u64);
+
+        let config = CompareConfig::default();
+        let diagnostics = Diagnostics::new();
+        compute_fingerprints(&mut entries, &config.normalization, u64::MAX, config.hash_algorithm, &diagnostics, None, None, None);
+        assert_eq!(entries[0].content_hash, entries[1].content_hash);
+
+        let result = compare_structured_files(&entries[0], &entries[1], &config, &diagnostics).unwrap();
+        assert!(result.identical);
+    }
+
+    #[test]
+    fn test_stdin_buffer_diffs_like_the_file_it_came_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old.txt"), dir.path().join("new.txt"));
+        std::fs::write(&old, "alpha\nbeta\ngamma\n").unwrap();
+        std::fs::write(&new, "alpha\nBETA\ngamma\ndelta\n").unwrap();
+
+        assert!(is_stdin_path(Path::new("-")));
+        let piped = buffer_to_temp_file(File::open(&old).unwrap(), &new).unwrap();
+        assert_eq!(content_extension(piped.path()), "txt");
+
+        let config = CompareConfig::default();
+        let engine = ComparisonEngine::new(&config);
+        let (from_stdin, _) = engine.run_in_memory(piped.path(), &new).unwrap();
+        let (from_files, _) = engine.run_in_memory(&old, &new).unwrap();
+
+        let (ComparisonResult::Text(a), ComparisonResult::Text(b)) = (&from_stdin[0], &from_files[0]) else {
+            panic!("expected text results");
+        };
+        assert_eq!(a.linked_id, b.linked_id);
+        assert_eq!(a.different_positions, b.different_positions);
+        // Only the `---` header, which names the file, differs
+        let hunks = |diff: &str| diff.lines().skip(2).collect::<Vec<_>>().join("\n");
+        assert_eq!(hunks(&a.detailed_diff), hunks(&b.detailed_diff));
+        assert_eq!((a.only_in_file1, a.only_in_file2), (1, 2));
+    }
+}
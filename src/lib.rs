@@ -3,34 +3,55 @@
 //! This library provides core functionality for comparing files and folders.
 //! It supports both CLI and UI interfaces by sharing the same high-performance engine.
 
+pub mod archive;
+pub mod column_types;
+pub mod compare_binary;
 pub mod compare_structured;
 pub mod compare_text;
+pub mod config;
+pub mod diagnostics;
 pub mod export;
+pub mod external_sort;
 pub mod fingerprint;
 pub mod index;
+pub mod input;
 pub mod match_files;
 pub mod report;
 pub mod types;
+pub mod watch;
 
 use anyhow::{Context, Result};
 use chrono::Local;
+use log::{info, warn};
 use rayon::prelude::*;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use sysinfo::{ProcessRefreshKind, RefreshKind, System};
 
+use crate::compare_binary::compare_binary_files;
 use crate::compare_structured::compare_structured_files;
-use crate::compare_text::compare_text_files;
-use crate::export::{calculate_summary, export_all, ProcessStats};
-use crate::fingerprint::compute_fingerprints;
-use crate::index::index_path;
-use crate::match_files::generate_candidates;
-use crate::report::generate_html_report;
+use crate::compare_text::{compare_link_targets, compare_text_files};
+use crate::diagnostics::{failed_file, is_encoding_error, Diagnostics};
+use crate::archive::{extract_archive, is_archive_path, ExtractedArchive};
+use crate::export::{
+    calculate_summary, export_all, export_badge, export_jsonl, export_junit, export_markdown, export_sarif,
+    export_summary_json, export_xlsx, merge_results, recover_jsonl, JsonlSink, ProcessStats, ResultSink,
+};
+use crate::fingerprint::{compute_content_hashes, compute_fingerprints, FingerprintCache};
+use crate::index::{apply_fixed_width, index_path, index_path_metadata, index_single_file_with, IndexOptions};
+use crate::match_files::{
+    find_unmatched, generate_candidates, locate_file_in_set, preflight_warnings, BlockingRules,
+};
+use crate::report::{generate_html_report, load_results_from_jsonl, write_html_diffs};
 use crate::types::{
-    CandidatePair, CompareConfig, CompareMode, ComparisonResult, FileEntry, FileType,
+    AnomalyKind, AnomalyReport, BlockingStats, CandidatePair, CompareConfig, CompareMode, ComparisonResult,
+    ComparisonSummary, FileDirStrategy, FileEntry, FileType, PairingStrategy, ReferenceRanking,
 };
 
 /// Trait for reporting progress during long-running operations
@@ -48,10 +69,61 @@ impl ProgressReporter for NoopProgressReporter {
     fn finish(&self, _message: &str) {}
 }
 
+/// Throughput and remaining time of a progress stage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressRate {
+    pub items_per_sec: f64,
+    /// Estimated seconds until the stage completes; `None` until the first item is done
+    pub eta_seconds: Option<f64>,
+}
+
+impl ProgressRate {
+    /// Estimate from `current` of `total` items done `elapsed` after the stage started
+    pub fn new(current: u64, total: u64, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        let items_per_sec = if secs > 0.0 { current as f64 / secs } else { 0.0 };
+        let eta_seconds = (items_per_sec > 0.0).then(|| total.saturating_sub(current) as f64 / items_per_sec);
+        Self { items_per_sec, eta_seconds }
+    }
+}
+
+/// Start time of the current progress stage, for reporters that show a rate and ETA
+pub struct ProgressClock(Mutex<Instant>);
+
+impl ProgressClock {
+    pub fn new() -> Self {
+        Self(Mutex::new(Instant::now()))
+    }
+
+    /// Start timing a new stage; call from [`ProgressReporter::start`]
+    pub fn restart(&self) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// Rate and ETA of `current` of `total` items since the last restart
+    pub fn rate(&self, current: u64, total: u64) -> ProgressRate {
+        ProgressRate::new(current, total, self.0.lock().unwrap_or_else(|e| e.into_inner()).elapsed())
+    }
+}
+
+impl Default for ProgressClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Core comparison engine that can be used by both CLI and UI
 pub struct ComparisonEngine<'a> {
     pub config: &'a CompareConfig,
     pub progress: Option<&'a dyn ProgressReporter>,
+    /// Cancellation flag; once set, remaining work is skipped and partial results returned
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Sink for data-quality anomalies found during the current run
+    diagnostics: Diagnostics,
+    /// Blocking-rule rejections from the most recent run
+    blocking: Mutex<BlockingStats>,
+    /// Pair that stopped the most recent fail-fast run
+    first_difference: Mutex<Option<(String, String)>>,
 }
 
 impl<'a> ComparisonEngine<'a> {
@@ -59,6 +131,10 @@ impl<'a> ComparisonEngine<'a> {
         Self {
             config,
             progress: None,
+            cancel: None,
+            diagnostics: Diagnostics::new(),
+            blocking: Mutex::new(BlockingStats::default()),
+            first_difference: Mutex::new(None),
         }
     }
 
@@ -67,12 +143,48 @@ impl<'a> ComparisonEngine<'a> {
         self
     }
 
-    /// Run the full comparison pipeline
-    pub fn run(&self, path1: &PathBuf, path2: &PathBuf) -> Result<Vec<ComparisonResult>> {
-        // ─────────────────────────────────────────────────────────────
-        // Start timing for process statistics
-        // ─────────────────────────────────────────────────────────────
-        let start_time = Instant::now();
+    /// Attach a cancellation flag that can be set from another thread
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// True if the cancellation flag has been set
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    /// Anomalies recorded during the most recent run
+    pub fn anomaly_report(&self) -> AnomalyReport {
+        self.diagnostics.report()
+    }
+
+    /// Potential pairs rejected by each blocking rule during the most recent run
+    pub fn blocking_stats(&self) -> BlockingStats {
+        self.blocking.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The pair that stopped the most recent run, when `config.fail_fast` is set
+    pub fn first_difference(&self) -> Option<(String, String)> {
+        self.first_difference.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Run the full comparison pipeline and export results to disk
+    ///
+    /// Builds on [`run_in_memory`](Self::run_in_memory), then writes artifacts,
+    /// `summary.json` and the HTML report (unless `no_html`) into the results
+    /// directory. Results are streamed to
+    /// the JSONL as they are produced, so a crash keeps everything compared so
+    /// far. With `resume` set, the JSONL is appended to and pairs already
+    /// recorded there by an earlier run are skipped.
+    pub fn run(&self, path1: &Path, path2: &Path) -> Result<Vec<ComparisonResult>> {
+        if self.config.resume && self.config.output_root.is_none() && self.config.output_jsonl.is_none() {
+            anyhow::bail!("Resuming needs a fixed output location (output root or JSONL path)");
+        }
+        if self.config.patch_compatible && self.config.redact {
+            anyhow::bail!("Patch-compatible output contains raw file contents and cannot be redacted");
+        }
+        let _archives = self.unpack_archives(path1, path2)?;
 
         // Set up results directory
         // If output_root is set, use it directly (no subfolder).
@@ -88,11 +200,217 @@ impl<'a> ComparisonEngine<'a> {
         };
 
         let (auto_jsonl_path, auto_html_path, auto_artifacts_dir) = get_auto_export_paths(&results_dir);
+        let auto_summary_path = get_auto_summary_path(&results_dir);
+        let jsonl_path = self.config.output_jsonl.as_deref().unwrap_or(&auto_jsonl_path);
+        let artifacts_path = self.config.output_dir.as_deref().unwrap_or(&auto_artifacts_dir);
+        let summary_path = self.config.output_summary.as_deref().unwrap_or(&auto_summary_path);
+
+        // Read before the results JSONL is recreated, as it may be the same file
+        let merge_base = match self.config.merge_results.as_deref() {
+            Some(path) => Some(load_results_from_jsonl(path)?),
+            None => None,
+        };
+
+        let (results, summary) = if self.config.resume {
+            // Every pair is recorded so a later resume can skip it
+            let previous = if jsonl_path.exists() { recover_jsonl(jsonl_path)? } else { Vec::new() };
+            let sink = JsonlSink::open(jsonl_path)?;
+            self.run_pipeline(path1, path2, None, previous, Some(&sink))?
+        } else {
+            let sink = JsonlSink::create(jsonl_path)?.below_threshold(self.config.min_similarity_to_report);
+            self.run_pipeline(path1, path2, None, Vec::new(), Some(&sink))?
+        };
+        // Merge before applying the report threshold, so pairs it omits still
+        // replace their earlier results and count towards the summary
+        let merging = merge_base.is_some() && summary.first_difference.is_none();
+        let (mut results, summary) = match merge_base {
+            Some(previous) if merging => {
+                let results = merge_results(previous, results);
+                let summary = recount_summary(&results, summary);
+                (results, summary)
+            }
+            _ => (results, summary),
+        };
+        self.retain_below_threshold(&mut results);
+        if summary.first_difference.is_some() {
+            return Ok(results);
+        }
+        if merging {
+            export_jsonl(&results, jsonl_path)?;
+        }
+
+        // Export the remaining formats (the JSONL has been streamed already)
+        export_all(
+            &results,
+            None,
+            self.config.output_csv.as_deref(),
+            Some(artifacts_path),
+            self.config.patch_compatible.then_some((path1, path2)),
+            self.config.mirror_output.as_deref().map(|dir| (dir, path1)),
+        )?;
+        if let Some(path) = self.config.output_md.as_deref() {
+            export_markdown(&results, &summary, path)?;
+        }
+        if let Some(path) = self.config.output_junit.as_deref() {
+            export_junit(&results, path)?;
+        }
+        if let Some(path) = self.config.output_xlsx.as_deref() {
+            export_xlsx(&results, &summary, path)?;
+        }
+        if let Some(path) = self.config.output_sarif.as_deref() {
+            export_sarif(&results, path)?;
+        }
+        if let Some(path) = self.config.output_badge.as_deref() {
+            export_badge(&summary, path)?;
+        }
+        export_summary_json(&summary, summary_path)?;
+
+        if self.config.split_diffs {
+            write_html_diffs(&results, artifacts_path, self.config.report_theme)?;
+        }
+        if !self.config.no_html {
+            generate_html_report(
+                &results,
+                &summary,
+                &auto_html_path,
+                Some(artifacts_path),
+                self.config.split_diffs,
+                self.config.report_theme,
+                self.config.report_max_rows,
+            )?;
+        }
+
+        Ok(results)
+    }
+
+    /// Run indexing, fingerprinting, candidate generation and comparison
+    /// without writing anything to disk
+    ///
+    /// Returns the results together with the run summary (including process
+    /// statistics and anomalies). Useful for embedders that only need the data.
+    /// If the run is cancelled, the results compared so far are returned and
+    /// `summary.cancelled` is set.
+    pub fn run_in_memory(
+        &self,
+        path1: &Path,
+        path2: &Path,
+    ) -> Result<(Vec<ComparisonResult>, ComparisonSummary)> {
+        let _archives = self.unpack_archives(path1, path2)?;
+        let (mut results, summary) = self.run_pipeline(path1, path2, None, Vec::new(), None)?;
+        self.retain_below_threshold(&mut results);
+        Ok((results, summary))
+    }
+
+    /// Like [`run_in_memory`](Self::run_in_memory), but with the two file sets
+    /// already indexed (e.g. by [`index_files`](crate::index::index_files))
+    ///
+    /// `root1` and `root2` are the folders the entries live under; pairing by
+    /// relative path resolves against them. Nothing is walked or re-indexed.
+    pub fn run_with_indexes(
+        &self,
+        root1: &Path,
+        root2: &Path,
+        files1: Vec<FileEntry>,
+        files2: Vec<FileEntry>,
+    ) -> Result<(Vec<ComparisonResult>, ComparisonSummary)> {
+        let (mut results, summary) = self.run_pipeline(root1, root2, Some((files1, files2)), Vec::new(), None)?;
+        self.retain_below_threshold(&mut results);
+        Ok((results, summary))
+    }
+
+    /// Index, fingerprint and pair the two paths without comparing anything
+    ///
+    /// Returns the pairs a full run would compare, with their estimated
+    /// similarity, so pairing and blocking settings can be checked before an
+    /// expensive run.
+    pub fn plan(&self, path1: &Path, path2: &Path) -> Result<Vec<CandidatePair>> {
+        let _archives = self.unpack_archives(path1, path2)?;
+        let (mut files1, mut files2) = self.index_sets(path1, path2, None)?;
+        self.fingerprint_sets(path1, path2, &mut files1, &mut files2);
+        let mut blocking = BlockingStats::default();
+        Ok(self.build_candidates(path1, path2, &files1, &files2, &mut blocking))
+    }
+
+    /// With `follow_archives`, unpack archive paths so they are compared like folders
+    ///
+    /// The unpacked entries are deleted when the returned archives are dropped.
+    fn unpack_archives(&self, path1: &Path, path2: &Path) -> Result<Vec<ExtractedArchive>> {
+        [path1, path2]
+            .into_iter()
+            .filter(|path| self.follows_archive(path))
+            .map(extract_archive)
+            .collect()
+    }
+
+    /// True if `path` is an archive compared entry by entry
+    fn follows_archive(&self, path: &Path) -> bool {
+        self.config.follow_archives && path.is_file() && is_archive_path(path)
+    }
+
+    /// True if `path` is compared as a single file rather than a folder
+    fn is_single_file(&self, path: &Path) -> bool {
+        path.is_file() && !self.follows_archive(path)
+    }
+
+    /// Shared body of [`run`](Self::run) and [`run_in_memory`](Self::run_in_memory)
+    ///
+    /// `indexes`, when given, replaces indexing `path1` and `path2`.
+    /// Pairs whose paths match a result in `previous` are not compared again;
+    /// `previous` is included in the returned results and summary. Each new
+    /// result is written to `sink` as soon as it is produced. Every pair is
+    /// returned; callers apply the report threshold.
+    ///
+    /// With `threads` configured, every parallel stage runs inside a
+    /// dedicated thread pool of that size instead of the global one.
+    fn run_pipeline(
+        &self,
+        path1: &Path,
+        path2: &Path,
+        indexes: Option<(Vec<FileEntry>, Vec<FileEntry>)>,
+        previous: Vec<ComparisonResult>,
+        sink: Option<&dyn ResultSink>,
+    ) -> Result<(Vec<ComparisonResult>, ComparisonSummary)> {
+        match self.config.threads {
+            Some(threads) => {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .context("Failed to build thread pool")?;
+                pool.install(|| self.run_stages(path1, path2, indexes, previous, sink))
+            }
+            None => self.run_stages(path1, path2, indexes, previous, sink),
+        }
+    }
+
+    /// Index, fingerprint, pair and compare on the current thread pool
+    fn run_stages(
+        &self,
+        path1: &Path,
+        path2: &Path,
+        indexes: Option<(Vec<FileEntry>, Vec<FileEntry>)>,
+        previous: Vec<ComparisonResult>,
+        sink: Option<&dyn ResultSink>,
+    ) -> Result<(Vec<ComparisonResult>, ComparisonSummary)> {
+        // ─────────────────────────────────────────────────────────────
+        // Start timing for process statistics
+        // ─────────────────────────────────────────────────────────────
+        let start_time = Instant::now();
+        self.diagnostics.clear();
 
         // Stage 1: Index files
         if let Some(p) = self.progress { p.start(0, "Indexing files..."); }
-        let mut files1 = index_path(path1, &self.config.exclude_patterns).context("Failed to index path1")?;
-        let mut files2 = index_path(path2, &self.config.exclude_patterns).context("Failed to index path2")?;
+        let (mut files1, mut files2) = self.index_sets(path1, path2, indexes)?;
+
+        // Pre-flight: flag inputs that cannot produce useful pairs before the
+        // expensive stages run
+        let rules = BlockingRules::from_config(self.config);
+        let warnings = preflight_warnings(&files1, &files2, &rules, self.is_single_file(path1) == self.is_single_file(path2));
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+        if self.config.strict && !warnings.is_empty() {
+            anyhow::bail!("Pre-flight check failed: {}", warnings.join("; "));
+        }
 
         // ─────────────────────────────────────────────────────────────
         // Calculate total data size for statistics
@@ -105,43 +423,93 @@ impl<'a> ComparisonEngine<'a> {
             p.start((files1.len() + files2.len()) as u64, "Computing fingerprints..."); 
         }
         
-        // Calculate max fingerprint size (dynamic or configured)
-        let max_size = self.config.max_fingerprint_size.unwrap_or_else(|| {
-            // Default to 5% of total system memory, capped strictly at 2GB to be safe
-            // This is much better than the hardcoded 100MB limit
-            let mut sys = System::new_all();
-            sys.refresh_memory();
-            let total_mem = sys.total_memory();
-            // sysinfo reports in bytes (despite some older docs saying KB)
-            // 5% of RAM
-            let calc_limit = total_mem / 20; 
-            // Cap at 2GB to avoid extreme cases
-            calc_limit.min(2 * 1024 * 1024 * 1024)
-        });
-
-        compute_fingerprints(&mut files1, &self.config.normalization, max_size);
-        if let Some(p) = self.progress { p.inc(files1.len() as u64); }
-        
-        compute_fingerprints(&mut files2, &self.config.normalization, max_size);
+        self.fingerprint_sets(path1, path2, &mut files1, &mut files2);
         if let Some(p) = self.progress { p.finish("Fingerprinting complete"); }
 
         // Stage 3: Generate candidate pairs
         if let Some(p) = self.progress { p.start(0, "Generating candidates..."); }
-        let candidates = generate_candidates(&files1, &files2, self.config);
+        let mut blocking = BlockingStats::default();
+        let mut candidates = if self.is_cancelled() {
+            Vec::new()
+        } else {
+            self.build_candidates(path1, path2, &files1, &files2, &mut blocking)
+        };
+
+        // Files the pairing left alone. Searching for one file in a folder
+        // is expected to leave most of the folder unpaired, so skip it there.
+        let searching = self.config.file_dir_strategy == FileDirStrategy::Search && self.is_single_file(path1) != self.is_single_file(path2);
+        let mut unmatched: Vec<ComparisonResult> = if searching || self.is_cancelled() {
+            Vec::new()
+        } else {
+            find_unmatched(&files1, &files2, &candidates)
+                .into_iter()
+                .map(ComparisonResult::OnlyInOne)
+                .collect()
+        };
+
+        // Skip pairs (and unmatched files) an earlier run already recorded
+        if !previous.is_empty() {
+            let completed: HashSet<(&str, &str)> = previous.iter().map(|r| r.file_paths()).collect();
+            candidates.retain(|pair| {
+                let key = (pair.file1.path.display().to_string(), pair.file2.path.display().to_string());
+                !completed.contains(&(key.0.as_str(), key.1.as_str()))
+            });
+            unmatched.retain(|r| !completed.contains(&r.file_paths()));
+        }
 
         // Stage 4: Exact comparison
         if let Some(p) = self.progress { 
             p.start(candidates.len() as u64, "Comparing files..."); 
         }
 
-        let results: Vec<ComparisonResult> = candidates
+        // With fail-fast, the first non-identical result trips this flag,
+        // which then short-circuits the remaining pairs like a cancellation.
+        // Unmatched files already make the trees differ.
+        let first_difference: Mutex<Option<(String, String)>> = Mutex::new(None);
+        let tripped = AtomicBool::new(false);
+        let trip = |result: &ComparisonResult| {
+            let (file1, file2) = result.file_paths();
+            let mut first = first_difference.lock().unwrap_or_else(|e| e.into_inner());
+            first.get_or_insert_with(|| (file1.to_string(), file2.to_string()));
+            tripped.store(true, Ordering::Relaxed);
+        };
+        if self.config.fail_fast {
+            if let Some(result) = unmatched.first() {
+                trip(result);
+            }
+        }
+
+        let write_error: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+        let new_results: Vec<ComparisonResult> = candidates
             .par_iter()
-            .map(|pair| {
+            .filter_map(|pair| {
+                // Skip the remaining pairs once cancelled or failed fast
+                if self.is_cancelled() || tripped.load(Ordering::Relaxed) {
+                    return None;
+                }
                 let result = self.compare_pair(pair);
+                if self.config.fail_fast && !result.is_identical() {
+                    trip(&result);
+                }
+                if let Some(Err(e)) = sink.map(|s| s.write(&result)) {
+                    write_error.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert(e);
+                }
                 if let Some(p) = self.progress { p.inc(1); }
-                result
+                Some(result)
             })
             .collect();
+        if let Some(e) = write_error.into_inner().unwrap_or_else(|e| e.into_inner()) {
+            return Err(e.context("Failed to append results"));
+        }
+        if let Some(sink) = sink {
+            for result in &unmatched {
+                sink.write(result).context("Failed to append results")?;
+            }
+        }
+
+        let mut results = previous;
+        results.extend(new_results);
+        results.extend(unmatched);
 
         if let Some(p) = self.progress { p.finish("Comparison complete"); }
 
@@ -182,32 +550,236 @@ impl<'a> ComparisonEngine<'a> {
         };
 
         // Calculate summary with process stats
-        let summary = calculate_summary(&results, files1.len(), files2.len(), Some(process_stats));
+        let mut summary = calculate_summary(&results, files1.len(), files2.len(), Some(process_stats));
+        summary.anomalies = self.diagnostics.report();
+        summary.hash_algorithm = Some(self.config.hash_algorithm.name().to_string());
+        summary.cancelled = self.is_cancelled();
+        summary.first_difference = first_difference.into_inner().unwrap_or_else(|e| e.into_inner());
+        if let Some((file1, file2)) = &summary.first_difference {
+            info!("Stopped at the first difference: {} vs {}", file1, file2);
+        }
+        *self.first_difference.lock().unwrap_or_else(|e| e.into_inner()) = summary.first_difference.clone();
+        if let Some((rule, count)) = blocking.top_rule() {
+            info!(
+                "Blocking rules rejected {} of {} potential pairs; most by {} ({})",
+                blocking.total_rejected(),
+                blocking.pairs_checked,
+                rule,
+                count
+            );
+        }
+        *self.blocking.lock().unwrap_or_else(|e| e.into_inner()) = blocking.clone();
+        summary.blocking = blocking;
 
-        // Export results
-        let jsonl_path = self.config.output_jsonl.as_deref().unwrap_or(&auto_jsonl_path);
-        let artifacts_path = self.config.output_dir.as_deref().unwrap_or(&auto_artifacts_dir);
-        
-        export_all(
-            &results,
-            Some(jsonl_path),
-            self.config.output_csv.as_deref(),
-            Some(artifacts_path),
-        )?;
+        Ok((results, summary))
+    }
 
-        // Always generate HTML report
-        generate_html_report(&results, &summary, &auto_html_path, Some(artifacts_path))?;
+    /// Drop the pairs at or above the report threshold, if one is set
+    ///
+    /// Only the returned results are filtered; summaries still count every pair.
+    fn retain_below_threshold(&self, results: &mut Vec<ComparisonResult>) {
+        if let Some(threshold) = self.config.min_similarity_to_report {
+            let before = results.len();
+            results.retain(|r| r.is_below_threshold(threshold));
+            info!(
+                "Omitted {} pair(s) at or above {:.1}% similarity",
+                before - results.len(),
+                threshold * 100.0
+            );
+        }
+    }
 
-        Ok(results)
+    /// Compare one candidate against several references and rank them
+    ///
+    /// Runs [`run_in_memory`](Self::run_in_memory) once per reference and
+    /// returns the references ordered from most to least similar. Nothing is
+    /// written to disk.
+    pub fn compare_many(&self, candidate: &Path, references: &[PathBuf]) -> Result<Vec<ReferenceRanking>> {
+        let mut rankings = Vec::with_capacity(references.len());
+        for reference in references {
+            if self.is_cancelled() {
+                break;
+            }
+            let (results, summary) = self
+                .run_in_memory(candidate, reference)
+                .with_context(|| format!("Failed to compare against {}", reference.display()))?;
+            rankings.push(ReferenceRanking {
+                reference: reference.display().to_string(),
+                score: best_match_score(&results, summary.total_files_set1),
+                summary,
+            });
+        }
+
+        rankings.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(rankings)
+    }
+
+    /// Index both sides, or take the already indexed `indexes`
+    fn index_sets(
+        &self,
+        path1: &Path,
+        path2: &Path,
+        indexes: Option<(Vec<FileEntry>, Vec<FileEntry>)>,
+    ) -> Result<(Vec<FileEntry>, Vec<FileEntry>)> {
+        let (mut files1, mut files2) = match indexes {
+            Some(indexes) => indexes,
+            None => {
+                // A permissions audit never opens the files
+                let index = if self.config.compare_permissions_only { index_path_metadata } else { index_path };
+                let options = IndexOptions::from_config(self.config);
+                (
+                    index(path1, &options).context("Failed to index path1")?,
+                    index(path2, &options).context("Failed to index path2")?,
+                )
+            }
+        };
+        if !self.config.fixed_width.is_empty() {
+            let headers = self.config.fixed_width_headers();
+            apply_fixed_width(&mut files1, &headers);
+            apply_fixed_width(&mut files2, &headers);
+        }
+        Ok((files1, files2))
+    }
+
+    /// Compute fingerprints for both sides, through the cache when configured
+    ///
+    /// With `no_fingerprint`, only content hashes are computed unless the
+    /// pairing ranks files by similarity.
+    fn fingerprint_sets(&self, path1: &Path, path2: &Path, files1: &mut [FileEntry], files2: &mut [FileEntry]) {
+        if self.config.no_fingerprint && !self.config.compare_permissions_only {
+            let searching = self.config.file_dir_strategy == FileDirStrategy::Search && self.is_single_file(path1) != self.is_single_file(path2);
+            if self.config.pairing == PairingStrategy::AllVsAll || searching {
+                warn!("--no-fingerprint is ignored when pairing ranks files by similarity");
+            } else {
+                let cancel = self.cancel.as_deref();
+                compute_content_hashes(files1, self.config.hash_algorithm, cancel, self.progress);
+                compute_content_hashes(files2, self.config.hash_algorithm, cancel, self.progress);
+                return;
+            }
+        }
+
+        // Calculate max fingerprint size (dynamic or configured)
+        let max_size = self.config.max_fingerprint_size.unwrap_or_else(|| {
+            // Default to 5% of total system memory, capped strictly at 2GB to be safe
+            // This is much better than the hardcoded 100MB limit
+            let mut sys = System::new_all();
+            sys.refresh_memory();
+            let total_mem = sys.total_memory();
+            // sysinfo reports in bytes (despite some older docs saying KB)
+            // 5% of RAM
+            let calc_limit = total_mem / 20;
+            // Cap at 2GB to avoid extreme cases
+            calc_limit.min(2 * 1024 * 1024 * 1024)
+        });
+
+        let cancel = self.cancel.as_deref();
+        let cache = self
+            .config
+            .cache_path
+            .as_deref()
+            .filter(|_| !self.config.compare_permissions_only)
+            .map(|path| FingerprintCache::load(path, &self.config.normalization, max_size, self.config.hash_algorithm));
+        if !self.config.compare_permissions_only {
+            let hash = self.config.hash_algorithm;
+            compute_fingerprints(files1, &self.config.normalization, max_size, hash, &self.diagnostics, cancel, self.progress, cache.as_ref());
+            compute_fingerprints(files2, &self.config.normalization, max_size, hash, &self.diagnostics, cancel, self.progress, cache.as_ref());
+        }
+        if let Some(cache) = &cache {
+            info!("Fingerprint cache: {} reused, {} computed", cache.hits(), cache.misses());
+            if let Err(e) = cache.save() {
+                warn!("{:#}", e);
+            }
+        }
+    }
+
+    /// Generate candidate pairs, handling the asymmetric file-vs-directory case
+    ///
+    /// When exactly one of the paths is a file and the strategy is
+    /// [`FileDirStrategy::Search`], that file is compared against the top-K
+    /// most similar compatible files on the other side instead of going
+    /// through the regular pairing strategy.
+    fn build_candidates(
+        &self,
+        path1: &Path,
+        path2: &Path,
+        files1: &[FileEntry],
+        files2: &[FileEntry],
+        stats: &mut BlockingStats,
+    ) -> Vec<CandidatePair> {
+        if self.config.file_dir_strategy == FileDirStrategy::Search {
+            let rules = BlockingRules::from_config(self.config);
+            let located = match (self.is_single_file(path1), self.is_single_file(path2)) {
+                (true, false) => files1
+                    .first()
+                    .map(|target| locate_file_in_set(target, files2, self.config.top_k, true, &rules, stats)),
+                (false, true) => files2
+                    .first()
+                    .map(|target| locate_file_in_set(target, files1, self.config.top_k, false, &rules, stats)),
+                _ => None,
+            };
+
+            if let Some(mut pairs) = located {
+                if let Some(max) = self.config.max_pairs {
+                    pairs.truncate(max);
+                }
+                return pairs;
+            }
+        }
+
+        generate_candidates(files1, files2, path1, path2, self.config, stats)
+    }
+
+    /// Compare two binary files byte by byte, or by hash if either is too large
+    fn compare_binary_pair(&self, pair: &CandidatePair) -> ComparisonResult {
+        let (file1, file2) = (&pair.file1, &pair.file2);
+        if file1.size.max(file2.size) > self.config.max_binary_diff_bytes {
+            return ComparisonResult::HashOnly {
+                linked_id: format!(
+                    "{}:{}",
+                    &file1.content_hash[..16.min(file1.content_hash.len())],
+                    &file2.content_hash[..16.min(file2.content_hash.len())]
+                ),
+                file1_path: file1.path.display().to_string(),
+                file2_path: file2.path.display().to_string(),
+                file1_size: file1.size,
+                file2_size: file2.size,
+                identical: !file1.content_hash.is_empty() && file1.content_hash == file2.content_hash,
+            };
+        }
+
+        compare_binary_files(file1, file2).unwrap_or_else(|e| ComparisonResult::Error {
+            file1_path: file1.path.display().to_string(),
+            file2_path: file2.path.display().to_string(),
+            error: format!("{:#}", e),
+        })
     }
 
     /// Compare a single candidate pair
     pub fn compare_pair(&self, pair: &CandidatePair) -> ComparisonResult {
+        if self.config.compare_permissions_only {
+            return compare_permissions(&pair.file1, &pair.file2);
+        }
+
         // Quick check for identical files
         if pair.exact_hash_match {
             return create_identical_result(&pair.file1, &pair.file2);
         }
 
+        // Symlinks indexed by target are compared by where they point
+        if pair.file1.link_target.is_some() || pair.file2.link_target.is_some() {
+            return compare_link_targets(&pair.file1, &pair.file2, self.config)
+                .map(ComparisonResult::Text)
+                .unwrap_or_else(|e| ComparisonResult::Error {
+                    file1_path: pair.file1.path.display().to_string(),
+                    file2_path: pair.file2.path.display().to_string(),
+                    error: format!("{:#}", e),
+                });
+        }
+
+        if pair.file1.file_type == FileType::Binary || pair.file2.file_type == FileType::Binary {
+            return self.compare_binary_pair(pair);
+        }
+
         // Determine comparison mode
         let mode = match self.config.mode {
             CompareMode::Auto => auto_detect_mode(&pair.file1, &pair.file2),
@@ -215,39 +787,100 @@ impl<'a> ComparisonEngine<'a> {
             CompareMode::Structured => CompareMode::Structured,
         };
 
-        match mode {
-            CompareMode::Text => {
-                match compare_text_files(&pair.file1, &pair.file2, self.config) {
-                    Ok(result) => ComparisonResult::Text(result),
-                    Err(e) => ComparisonResult::Error {
-                        file1_path: pair.file1.path.display().to_string(),
-                        file2_path: pair.file2.path.display().to_string(),
-                        error: e.to_string(),
-                    },
-                }
-            }
+        let outcome = match mode {
             CompareMode::Structured => {
-                match compare_structured_files(&pair.file1, &pair.file2, self.config) {
-                    Ok(result) => ComparisonResult::Structured(result),
-                    Err(e) => ComparisonResult::Error {
-                        file1_path: pair.file1.path.display().to_string(),
-                        file2_path: pair.file2.path.display().to_string(),
-                        error: e.to_string(),
-                    },
-                }
+                compare_structured_files(&pair.file1, &pair.file2, self.config, &self.diagnostics)
+                    .map(ComparisonResult::Structured)
             }
-            CompareMode::Auto => {
-                // Fallback to text if auto-detection fails
-                match compare_text_files(&pair.file1, &pair.file2, self.config) {
-                    Ok(result) => ComparisonResult::Text(result),
-                    Err(e) => ComparisonResult::Error {
-                        file1_path: pair.file1.path.display().to_string(),
-                        file2_path: pair.file2.path.display().to_string(),
-                        error: e.to_string(),
-                    },
-                }
+            // Text, plus the fallback if auto-detection fails
+            CompareMode::Text | CompareMode::Auto => {
+                compare_text_files(&pair.file1, &pair.file2, self.config).map(ComparisonResult::Text)
+            }
+        };
+
+        outcome.unwrap_or_else(|e| {
+            if let Some(path) = failed_file(&e) {
+                self.record_failure(path, mode, &e);
             }
+            ComparisonResult::Error {
+                file1_path: pair.file1.path.display().to_string(),
+                file2_path: pair.file2.path.display().to_string(),
+                error: format!("{:#}", e),
+            }
+        })
+    }
+
+    /// Record a failure reading the file at `path` as an anomaly when it points at bad data
+    ///
+    /// Decoding failures are reported as encoding errors; any other failure in
+    /// structured mode means the file could not be parsed.
+    fn record_failure(&self, path: &Path, mode: CompareMode, error: &anyhow::Error) {
+        let kind = if is_encoding_error(error) {
+            AnomalyKind::EncodingError
+        } else if mode == CompareMode::Structured {
+            AnomalyKind::UnparseableFile
+        } else {
+            return;
+        };
+        self.diagnostics.record(kind, path, format!("{:#}", error));
+    }
+}
+
+/// Average of each first-set file's best similarity, over `total_files` files
+///
+/// Unlike `average_similarity`, this does not reward a reference for matching
+/// only a few candidate files well: unmatched files contribute 0.
+fn best_match_score(results: &[ComparisonResult], total_files: usize) -> f64 {
+    if total_files == 0 {
+        return 0.0;
+    }
+    let mut best: HashMap<&str, f64> = HashMap::new();
+    for result in results {
+        if matches!(result, ComparisonResult::Error { .. } | ComparisonResult::OnlyInOne(_)) {
+            continue;
         }
+        let score = best.entry(result.file_paths().0).or_insert(0.0);
+        *score = score.max(result.similarity_score());
+    }
+    best.values().sum::<f64>() / total_files as f64
+}
+
+/// Compare exactly two files
+///
+/// The shortcut for embedders that already know which files to compare: both
+/// are indexed and hashed, then compared with
+/// [`ComparisonEngine::compare_pair`]. Nothing is paired or written to disk,
+/// and a failed comparison is returned as [`ComparisonResult::Error`]. Use
+/// [`ComparisonEngine`] for folders, progress or anomaly reports.
+pub fn compare_files(path1: &Path, path2: &Path, config: &CompareConfig) -> Result<ComparisonResult> {
+    let options = IndexOptions::from_config(config);
+    let index =
+        |path: &Path| index_single_file_with(path, &options).with_context(|| format!("Failed to index {}", path.display()));
+    let mut files = [index(path1)?, index(path2)?];
+    if !config.fixed_width.is_empty() {
+        apply_fixed_width(&mut files, &config.fixed_width_headers());
+    }
+    compute_content_hashes(&mut files, config.hash_algorithm, None, None);
+
+    let [file1, file2] = files;
+    let exact_hash_match = !file1.content_hash.is_empty() && file1.content_hash == file2.content_hash;
+    let pair = CandidatePair {
+        file1,
+        file2,
+        estimated_similarity: if exact_hash_match { 1.0 } else { 0.0 },
+        exact_hash_match,
+    };
+    Ok(ComparisonEngine::new(config).compare_pair(&pair))
+}
+
+/// Compare the permission bits of two files, without reading them
+pub fn compare_permissions(file1: &FileEntry, file2: &FileEntry) -> ComparisonResult {
+    ComparisonResult::Permissions {
+        file1_path: file1.path.display().to_string(),
+        file2_path: file2.path.display().to_string(),
+        file1_mode: file1.mode,
+        file2_mode: file2.mode,
+        identical: file1.mode == file2.mode,
     }
 }
 
@@ -255,9 +888,8 @@ impl<'a> ComparisonEngine<'a> {
 pub fn auto_detect_mode(file1: &FileEntry, file2: &FileEntry) -> CompareMode {
     if file1.file_type.is_structured() && file2.file_type.is_structured() {
         CompareMode::Structured
-    } else if file1.file_type == FileType::Binary || file2.file_type == FileType::Binary {
-        CompareMode::Text // Will fall through to hash-only
     } else {
+        // Binary files also land here and fall through to hash-only
         CompareMode::Text
     }
 }
@@ -284,6 +916,8 @@ pub fn create_identical_result(file1: &FileEntry, file2: &FileEntry) -> Comparis
             linked_id,
             file1_path: file1.path.display().to_string(),
             file2_path: file2.path.display().to_string(),
+            file1_size: file1.size,
+            file2_size: file2.size,
             file1_row_count: file1.line_count,
             file2_row_count: file2.line_count,
             common_records: file1.line_count,
@@ -295,13 +929,24 @@ pub fn create_identical_result(file1: &FileEntry, file2: &FileEntry) -> Comparis
             columns_only_in_file1: vec![],
             columns_only_in_file2: vec![],
             common_columns: file1.columns.clone().unwrap_or_default(),
+            duplicate_keys: vec![],
             identical: true,
+            patch: None,
+            column_profiles: Vec::new(),
+            only_in_file1_keys: Vec::new(),
+            only_in_file2_keys: Vec::new(),
+            only_in_keys_truncated: false,
+            inferred_key_columns: Vec::new(),
+            sampled: false,
+            sample_size: 0,
         })
     } else {
         ComparisonResult::Text(crate::types::TextComparisonResult {
             linked_id,
             file1_path: file1.path.display().to_string(),
             file2_path: file2.path.display().to_string(),
+            file1_size: file1.size,
+            file2_size: file2.size,
             file1_line_count: file1.line_count,
             file2_line_count: file2.line_count,
             common_lines: file1.line_count,
@@ -311,6 +956,9 @@ pub fn create_identical_result(file1: &FileEntry, file2: &FileEntry) -> Comparis
             different_positions: String::new(),
             detailed_diff: String::new(),
             diff_truncated: false,
+            inline_edits: Vec::new(),
+            whitespace_glyphs: crate::types::WhitespaceGlyphs::default(),
+            rename: None,
             identical: true,
         })
     }
@@ -352,6 +1000,29 @@ pub fn ensure_results_dir(base_path: &Path) -> Result<PathBuf> {
     Ok(run_folder)
 }
 
+/// Summary of `results` that keeps the run-level parts of `summary`
+///
+/// Counts and similarities are recalculated; process statistics, anomalies
+/// and blocking statistics still describe the run that produced `summary`.
+fn recount_summary(results: &[ComparisonResult], summary: ComparisonSummary) -> ComparisonSummary {
+    let stats = ProcessStats {
+        execution_time_ms: summary.execution_time_ms,
+        processing_speed_mb_per_sec: summary.processing_speed_mb_per_sec,
+        peak_memory_usage_bytes: summary.peak_memory_usage_bytes,
+        total_data_processed_bytes: summary.total_data_processed_bytes,
+        comparison_mode: summary.comparison_mode,
+        similarity_algorithm: summary.similarity_algorithm,
+    };
+    ComparisonSummary {
+        hash_algorithm: summary.hash_algorithm,
+        anomalies: summary.anomalies,
+        cancelled: summary.cancelled,
+        first_difference: summary.first_difference,
+        blocking: summary.blocking,
+        ..calculate_summary(results, summary.total_files_set1, summary.total_files_set2, Some(stats))
+    }
+}
+
 /// Get full paths for automatic export files within the run directory
 pub fn get_auto_export_paths(run_dir: &Path) -> (PathBuf, PathBuf, PathBuf) {
     let jsonl_path = run_dir.join("results.jsonl");
@@ -359,3 +1030,800 @@ pub fn get_auto_export_paths(run_dir: &Path) -> (PathBuf, PathBuf, PathBuf) {
     let artifacts_dir = run_dir.join("artifacts");
     (jsonl_path, html_path, artifacts_dir)
 }
+
+/// Get the full path of the run summary JSON within the run directory
+pub fn get_auto_summary_path(run_dir: &Path) -> PathBuf {
+    run_dir.join("summary.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fingerprint::hash_file;
+    use crate::types::{FileSide, HashAlgorithm, UnmatchedFile};
+
+    #[test]
+    fn test_run_collects_anomaly_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+
+        // Oversized: both sides exceed the fingerprint limit below
+        fs::write(left.join("big.txt"), "old line\n".repeat(3000)).unwrap();
+        fs::write(right.join("big.txt"), "new line\n".repeat(3000)).unwrap();
+        // Encoding: declared UTF-8 (BOM) but containing invalid bytes
+        fs::write(left.join("notes.txt"), "plain ascii\n").unwrap();
+        let mut broken = b"\xef\xbb\xbf".to_vec();
+        broken.extend_from_slice(b"caf\xe9\n");
+        fs::write(right.join("notes.txt"), broken).unwrap();
+        // Malformed: ragged rows on the left
+        fs::write(left.join("people.csv"), "id,name\n1,ann\n2,bob,extra\n3\n").unwrap();
+        fs::write(right.join("people.csv"), "id,name\n1,ann\n2,bea\n3,cy\n").unwrap();
+        // Duplicate keys on the left
+        fs::write(left.join("orders.csv"), "id,amount\n1,5\n1,6\n2,7\n").unwrap();
+        fs::write(right.join("orders.csv"), "id,amount\n1,5\n2,8\n").unwrap();
+
+        let config = CompareConfig {
+            pairing: PairingStrategy::SameName,
+            output_root: Some(dir.path().join("out")),
+            max_fingerprint_size: Some(16 * 1024),
+            ..CompareConfig::default()
+        };
+        let engine = ComparisonEngine::new(&config);
+        let results = engine.run(&left, &right).unwrap();
+        assert_eq!(results.len(), 4);
+
+        let report = engine.anomaly_report();
+        assert_eq!(report.count(AnomalyKind::OversizedFile), 2);
+        assert_eq!(report.count(AnomalyKind::EncodingError), 1);
+        assert_eq!(report.count(AnomalyKind::RaggedRows), 1);
+        assert_eq!(report.count(AnomalyKind::DuplicateKeys), 1);
+        assert_eq!(report.count(AnomalyKind::UnparseableFile), 0);
+
+        let encoding = report
+            .anomalies
+            .iter()
+            .find(|a| a.kind == AnomalyKind::EncodingError)
+            .unwrap();
+        assert!(encoding.path.ends_with("notes.txt"));
+        assert!(encoding.path.contains("right"));
+
+        let html = fs::read_to_string(dir.path().join("out").join("report.html")).unwrap();
+        assert!(html.contains("Data Quality"));
+        assert!(html.contains("Duplicate keys"));
+    }
+
+    #[test]
+    fn test_failure_is_recorded_against_the_failing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // The good file's path is a prefix of the broken one's
+        let (good, broken) = (dir.path().join("notes.txt"), dir.path().join("notes.txt.txt"));
+        fs::write(&good, "plain ascii\n").unwrap();
+        fs::write(&broken, b"\xef\xbb\xbfcaf\xe9\n").unwrap();
+
+        let config = CompareConfig { mode: CompareMode::Text, ..CompareConfig::default() };
+        let engine = ComparisonEngine::new(&config);
+        engine.run_in_memory(&good, &broken).unwrap();
+
+        let report = engine.anomaly_report();
+        assert_eq!(report.count(AnomalyKind::EncodingError), 1);
+        assert_eq!(report.anomalies[0].path, broken.display().to_string());
+    }
+
+    #[test]
+    fn test_run_in_memory_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        fs::write(&left, "one\ntwo\n").unwrap();
+        fs::write(&right, "one\nthree\n").unwrap();
+
+        let config = CompareConfig {
+            results_base: dir.path().join("results"),
+            ..CompareConfig::default()
+        };
+        let (results, summary) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(summary.pairs_compared, 1);
+        assert_eq!(summary.different_pairs, 1);
+        assert!(summary.execution_time_ms.is_some());
+        assert!(!config.results_base.exists());
+    }
+
+    #[test]
+    fn test_compare_files_routes_each_file_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &[u8]| {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let config = CompareConfig::default();
+
+        let (a, b) = (write("a.txt", b"one\ntwo\n"), write("b.txt", b"one\nthree\n"));
+        let result = compare_files(&a, &b, &config).unwrap();
+        assert!(matches!(result, ComparisonResult::Text(ref r) if !r.identical), "{:?}", result);
+
+        let (a, b) = (write("a.csv", b"id,qty\n1,5\n2,6\n"), write("b.csv", b"id,qty\n1,5\n2,7\n"));
+        let result = compare_files(&a, &b, &config).unwrap();
+        assert!(matches!(result, ComparisonResult::Structured(ref r) if r.total_field_mismatches == 1), "{:?}", result);
+
+        let (a, b) = (write("a.bin", &[0, 1, 2, 3, 0, 255]), write("b.bin", &[0, 1, 9, 3, 0, 255]));
+        let result = compare_files(&a, &b, &config).unwrap();
+        assert!(matches!(result, ComparisonResult::Binary { differing_bytes: 1, .. }), "{:?}", result);
+
+        assert!(compare_files(&a, &dir.path().join("missing.bin"), &config).is_err());
+    }
+
+    /// Progress reporter that sets the cancel flag after a number of increments
+    struct CancelAfter {
+        flag: Arc<AtomicBool>,
+        remaining: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ProgressReporter for CancelAfter {
+        fn start(&self, _total: u64, _message: &str) {}
+        fn inc(&self, _delta: u64) {
+            if self.remaining.fetch_sub(1, Ordering::SeqCst) <= 1 {
+                self.flag.store(true, Ordering::SeqCst);
+            }
+        }
+        fn finish(&self, _message: &str) {}
+    }
+
+    /// Progress reporter that times its stage with a [`ProgressClock`], like the desktop app's
+    struct ClockReporter {
+        clock: ProgressClock,
+        total: std::sync::atomic::AtomicU64,
+        current: std::sync::atomic::AtomicU64,
+        rates: Mutex<Vec<ProgressRate>>,
+    }
+
+    impl ProgressReporter for ClockReporter {
+        fn start(&self, total: u64, _message: &str) {
+            self.clock.restart();
+            self.total.store(total, Ordering::SeqCst);
+        }
+        fn inc(&self, delta: u64) {
+            let current = self.current.fetch_add(delta, Ordering::SeqCst) + delta;
+            let rate = self.clock.rate(current, self.total.load(Ordering::SeqCst));
+            self.rates.lock().unwrap().push(rate);
+        }
+        fn finish(&self, _message: &str) {}
+    }
+
+    #[test]
+    fn test_eta_decreases_as_progress_increases() {
+        let reporter = ClockReporter {
+            clock: ProgressClock::new(),
+            total: Default::default(),
+            current: Default::default(),
+            rates: Mutex::new(Vec::new()),
+        };
+        reporter.start(4, "Comparing");
+        for _ in 0..4 {
+            // Each item takes a second: move the stage start one second back
+            *reporter.clock.0.lock().unwrap() -= Duration::from_secs(1);
+            reporter.inc(1);
+        }
+
+        let rates = reporter.rates.lock().unwrap();
+        let etas: Vec<f64> = rates.iter().map(|r| r.eta_seconds.unwrap()).collect();
+        for (eta, expected) in etas.iter().zip([3.0, 2.0, 1.0, 0.0]) {
+            assert!((eta - expected).abs() < 0.1, "{:?}", etas);
+        }
+        assert!(rates.iter().all(|r| (r.items_per_sec - 1.0).abs() < 0.1), "{:?}", rates);
+
+        // A restart times the next stage from scratch
+        reporter.start(4, "Reporting");
+        assert_eq!(reporter.clock.rate(0, 4).eta_seconds, None);
+        assert!(reporter.clock.rate(1, 4).items_per_sec > 10.0);
+    }
+
+    /// Sink that crashes the run after a fixed number of writes
+    struct CrashAfter {
+        inner: JsonlSink,
+        remaining: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ResultSink for CrashAfter {
+        fn write(&self, result: &ComparisonResult) -> Result<()> {
+            let remaining = self.remaining.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+            if remaining.is_err() {
+                panic!("simulated crash");
+            }
+            self.inner.write(result)
+        }
+    }
+
+    #[test]
+    fn test_streamed_jsonl_survives_a_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        for i in 0..20 {
+            fs::write(left.join(format!("f{}.txt", i)), format!("left {}\n", i)).unwrap();
+            fs::write(right.join(format!("f{}.txt", i)), format!("right {}\n", i)).unwrap();
+        }
+
+        let jsonl = dir.path().join("results.jsonl");
+        let sink = CrashAfter {
+            inner: JsonlSink::create(&jsonl).unwrap(),
+            remaining: std::sync::atomic::AtomicUsize::new(5),
+        };
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            ..CompareConfig::default()
+        };
+        let engine = ComparisonEngine::new(&config);
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            engine.run_pipeline(&left, &right, None, Vec::new(), Some(&sink))
+        }));
+        assert!(outcome.is_err());
+
+        // Everything written before the crash is complete, parseable JSON
+        let content = fs::read_to_string(&jsonl).unwrap();
+        assert!(content.ends_with('\n'));
+        let results: Vec<ComparisonResult> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.file_paths().0.contains("left")));
+    }
+
+    #[test]
+    fn test_resume_skips_pairs_already_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(left.join(name), format!("{} old\n", name)).unwrap();
+            fs::write(right.join(name), format!("{} new\n", name)).unwrap();
+        }
+
+        let out = dir.path().join("out");
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            output_root: Some(out.clone()),
+            resume: true,
+            ..CompareConfig::default()
+        };
+        ComparisonEngine::new(&config).run(&left, &right).unwrap();
+
+        // Simulate a crash: one complete result on disk, the next cut off mid-line
+        let jsonl = out.join("results.jsonl");
+        let full = fs::read_to_string(&jsonl).unwrap();
+        let first = full.lines().next().unwrap().to_string();
+        let second = full.lines().nth(1).unwrap();
+        fs::write(&jsonl, format!("{}\n{}", first, &second[..second.len() / 2])).unwrap();
+
+        let results = ComparisonEngine::new(&config).run(&left, &right).unwrap();
+        assert_eq!(results.len(), 3);
+
+        let resumed = fs::read_to_string(&jsonl).unwrap();
+        let lines: Vec<&str> = resumed.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], first); // kept, not re-compared
+        let pairs: HashSet<(String, String)> = lines
+            .iter()
+            .map(|line| {
+                let result: ComparisonResult = serde_json::from_str(line).unwrap();
+                let (a, b) = result.file_paths();
+                (a.to_string(), b.to_string())
+            })
+            .collect();
+        assert_eq!(pairs.len(), 3);
+    }
+
+    #[test]
+    fn test_merge_replaces_recompared_pairs_and_keeps_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        for name in ["a.txt", "b.txt"] {
+            fs::write(left.join(name), format!("{}\n", name)).unwrap();
+            fs::write(right.join(name), format!("{}\n", name)).unwrap();
+        }
+
+        // An earlier run saw a.txt differ, and c.txt which is now gone
+        let stale = |name: &str| ComparisonResult::Error {
+            file1_path: left.join(name).to_string_lossy().into_owned(),
+            file2_path: right.join(name).to_string_lossy().into_owned(),
+            error: "stale".to_string(),
+        };
+        let previous = dir.path().join("previous.jsonl");
+        export_jsonl(&[stale("a.txt"), stale("c.txt")], &previous).unwrap();
+
+        let out = dir.path().join("out");
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            output_root: Some(out.clone()),
+            merge_results: Some(previous),
+            no_html: true,
+            ..CompareConfig::default()
+        };
+        let results = ComparisonEngine::new(&config).run(&left, &right).unwrap();
+        assert_eq!(results.len(), 3);
+
+        let merged = load_results_from_jsonl(&out.join("results.jsonl")).unwrap();
+        assert_eq!(merged.len(), 3);
+        let status = |name: &str| {
+            merged
+                .iter()
+                .find(|r| r.file_paths().0.ends_with(name))
+                .map(|r| matches!(r, ComparisonResult::Error { .. }))
+        };
+        assert_eq!(status("a.txt"), Some(false)); // replaced by the new result
+        assert_eq!(status("b.txt"), Some(false));
+        assert_eq!(status("c.txt"), Some(true)); // kept
+    }
+
+    #[test]
+    fn test_merge_with_threshold_counts_omitted_pairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("a.txt"), "same\n").unwrap();
+        fs::write(right.join("a.txt"), "same\n").unwrap();
+        fs::write(left.join("b.txt"), "one\n").unwrap();
+        fs::write(right.join("b.txt"), "two\n").unwrap();
+
+        // An earlier run failed on a.txt, which is now identical
+        let previous = dir.path().join("previous.jsonl");
+        let stale = ComparisonResult::Error {
+            file1_path: left.join("a.txt").to_string_lossy().into_owned(),
+            file2_path: right.join("a.txt").to_string_lossy().into_owned(),
+            error: "stale".to_string(),
+        };
+        export_jsonl(&[stale], &previous).unwrap();
+
+        let out = dir.path().join("out");
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            output_root: Some(out.clone()),
+            merge_results: Some(previous),
+            min_similarity_to_report: Some(0.8),
+            no_html: true,
+            ..CompareConfig::default()
+        };
+        let results = ComparisonEngine::new(&config).run(&left, &right).unwrap();
+
+        // The identical a.txt replaces the stale error but is not reported
+        assert_eq!(results.len(), 1);
+        assert!(results[0].file_paths().0.ends_with("b.txt"));
+        let summary: ComparisonSummary =
+            serde_json::from_str(&fs::read_to_string(out.join("summary.json")).unwrap()).unwrap();
+        assert_eq!((summary.pairs_compared, summary.identical_pairs, summary.error_pairs), (2, 1, 0));
+    }
+
+    #[test]
+    fn test_exact_match_pairing_with_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("report.txt"), "quarterly numbers\n").unwrap();
+        fs::write(right.join("renamed.txt"), "quarterly numbers\n").unwrap();
+
+        let config = CompareConfig {
+            hash_algorithm: HashAlgorithm::Sha256,
+            ..CompareConfig::default()
+        };
+        let (results, summary) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_identical());
+        assert_eq!(summary.hash_algorithm.as_deref(), Some("sha256"));
+        // linked_id is built from SHA-256 prefixes
+        let digest = hash_file(&left.join("report.txt"), HashAlgorithm::Sha256).unwrap();
+        assert_eq!(results[0].linked_id(), format!("{}:{}", &digest[..16], &digest[..16]));
+    }
+
+    #[test]
+    fn test_no_fingerprint_hashes_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("same.txt"), "alpha\nbeta\n").unwrap();
+        fs::write(right.join("same.txt"), "alpha\nbeta\n").unwrap();
+        fs::write(left.join("changed.csv"), "id,name\n1,a\n").unwrap();
+        fs::write(right.join("changed.csv"), "id,name\n1,b\n").unwrap();
+
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            no_fingerprint: true,
+            ..CompareConfig::default()
+        };
+        let engine = ComparisonEngine::new(&config);
+        let pairs = engine.plan(&left, &right).unwrap();
+
+        assert_eq!(pairs.len(), 2);
+        for pair in &pairs {
+            for entry in [&pair.file1, &pair.file2] {
+                assert!(entry.simhash.is_none());
+                assert!(entry.schema_signature.is_none());
+                assert!(!entry.content_hash.is_empty());
+            }
+            assert_eq!(pair.exact_hash_match, pair.file1.path.ends_with("same.txt"));
+        }
+
+        // Per-pair comparison is unaffected
+        let (results, summary) = engine.run_in_memory(&left, &right).unwrap();
+        assert_eq!(summary.identical_pairs, 1);
+        assert_eq!(summary.different_pairs, 1);
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_threshold_reports_only_pairs_below_cutoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        let lines: String = (1..=10).map(|i| format!("line {}\n", i)).collect();
+        fs::write(left.join("same.txt"), &lines).unwrap();
+        fs::write(right.join("same.txt"), &lines).unwrap();
+        fs::write(left.join("close.txt"), &lines).unwrap();
+        fs::write(right.join("close.txt"), lines.replace("line 5\n", "line five\n")).unwrap();
+        fs::write(left.join("drift.txt"), &lines).unwrap();
+        fs::write(right.join("drift.txt"), "something else\nentirely\n").unwrap();
+
+        let out = dir.path().join("out");
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            output_root: Some(out.clone()),
+            min_similarity_to_report: Some(0.8),
+            ..CompareConfig::default()
+        };
+        let (_, summary) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+        let results = ComparisonEngine::new(&config).run(&left, &right).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].file_paths().0.ends_with("drift.txt"));
+        assert!(results.iter().all(|r| r.similarity_score() < 0.8));
+        assert_eq!(summary.pairs_compared, 3);
+        assert_eq!(summary.identical_pairs, 1);
+
+        let exported = fs::read_to_string(out.join("results.jsonl")).unwrap();
+        assert_eq!(exported.lines().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_permissions_audit_flags_mode_change_without_reading() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("deploy.sh"), "echo hi\n").unwrap();
+        fs::write(right.join("deploy.sh"), "echo hi\n").unwrap();
+        fs::set_permissions(left.join("deploy.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(right.join("deploy.sh"), fs::Permissions::from_mode(0o757)).unwrap();
+        // Different, malformed content with equal modes: would be flagged if read
+        fs::write(left.join("data.csv"), "id,name\n1,ann,extra\n").unwrap();
+        fs::write(right.join("data.csv"), [0xff, 0xfe, 0x00, b'\n']).unwrap();
+        for side in [&left, &right] {
+            fs::set_permissions(side.join("data.csv"), fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        let config = CompareConfig {
+            compare_permissions_only: true,
+            ..CompareConfig::default()
+        };
+        let engine = ComparisonEngine::new(&config);
+        let (results, summary) = engine.run_in_memory(&left, &right).unwrap();
+
+        assert_eq!(summary.pairs_compared, 2);
+        assert_eq!(summary.identical_pairs, 1);
+        assert!(engine.anomaly_report().anomalies.is_empty());
+        let flagged: Vec<_> = results.iter().filter(|r| !r.is_identical()).collect();
+        assert_eq!(flagged.len(), 1);
+        let ComparisonResult::Permissions { file1_path, file1_mode, file2_mode, .. } = flagged[0] else {
+            panic!("expected a permissions result");
+        };
+        assert!(file1_path.ends_with("deploy.sh"));
+        assert_eq!((*file1_mode, *file2_mode), (Some(0o755), Some(0o757)));
+        assert_eq!(
+            crate::export::describe_permission_change(*file1_mode, *file2_mode),
+            "mode 0755 vs 0757 (world-writable in file 2)"
+        );
+    }
+
+    #[test]
+    fn test_summary_separates_content_and_structural_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        let write = |name: &str, old: &str, new: &str| {
+            fs::write(left.join(name), old).unwrap();
+            fs::write(right.join(name), new).unwrap();
+        };
+        // Content: a value changed, a line edited in place
+        write("prices.csv", "id,price\n1,10\n2,20\n", "id,price\n1,10\n2,25\n");
+        write("notes.txt", "alpha\nbeta\n", "alpha\ngamma\n");
+        // Structure: a column added, a line added
+        write("people.csv", "id,name\n1,ann\n", "id,name,email\n1,ann,a@x\n");
+        write("log.txt", "one\ntwo\n", "one\ntwo\nthree\n");
+        write("same.txt", "unchanged\n", "unchanged\n");
+
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            ..CompareConfig::default()
+        };
+        let (_, summary) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+
+        assert_eq!(summary.identical_pairs, 1);
+        assert_eq!(summary.different_pairs, 4);
+        assert_eq!(summary.content_diff_pairs, 2);
+        assert_eq!(summary.structural_diff_pairs, 2);
+    }
+
+    #[test]
+    fn test_extra_file_reported_as_unmatched() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("shared.txt"), "same\n").unwrap();
+        fs::write(right.join("shared.txt"), "same\n").unwrap();
+        fs::write(right.join("extra.txt"), "only on the right\n").unwrap();
+
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            ..CompareConfig::default()
+        };
+        let (results, summary) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+
+        let unmatched: Vec<&UnmatchedFile> = results
+            .iter()
+            .filter_map(|r| match r {
+                ComparisonResult::OnlyInOne(file) => Some(file),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0].path, right.join("extra.txt").display().to_string());
+        assert_eq!(unmatched[0].side, FileSide::Set2);
+        assert_eq!(summary.pairs_compared, 1);
+        assert_eq!(summary.identical_pairs, 1);
+        assert_eq!(summary.only_in_set1, 0);
+        assert_eq!(summary.only_in_set2, 1);
+    }
+
+    #[test]
+    fn test_single_thread_matches_default_pool() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        for i in 0..8 {
+            fs::write(left.join(format!("f{}.txt", i)), format!("line a{}\nline b\n", i)).unwrap();
+            fs::write(right.join(format!("f{}.txt", i)), format!("line a{}\nline c\n", i)).unwrap();
+        }
+        fs::write(left.join("t.csv"), "id,v\n1,x\n2,y\n").unwrap();
+        fs::write(right.join("t.csv"), "id,v\n1,x\n2,z\n").unwrap();
+
+        let run = |threads: Option<usize>| -> Vec<String> {
+            let config = CompareConfig { threads, ..CompareConfig::default() };
+            let (results, _) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+            let mut lines: Vec<String> = results.iter().map(|r| serde_json::to_string(r).unwrap()).collect();
+            lines.sort();
+            lines
+        };
+
+        assert_eq!(run(Some(1)), run(None));
+    }
+
+    #[test]
+    fn test_cancel_stops_comparison_early() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        let pairs = 200;
+        for i in 0..pairs {
+            fs::write(left.join(format!("f{}.txt", i)), format!("left {}\n", i)).unwrap();
+            fs::write(right.join(format!("f{}.txt", i)), format!("right {}\n", i)).unwrap();
+        }
+
+        let flag = Arc::new(AtomicBool::new(false));
+        // One increment per fingerprinted file, then a few pairs
+        let progress = CancelAfter {
+            flag: flag.clone(),
+            remaining: std::sync::atomic::AtomicUsize::new(2 * pairs + 3),
+        };
+        let config = CompareConfig {
+            pairing: PairingStrategy::SameName,
+            ..CompareConfig::default()
+        };
+        let engine = ComparisonEngine::new(&config)
+            .with_progress(&progress)
+            .with_cancel(flag.clone());
+        let (results, summary) = engine.run_in_memory(&left, &right).unwrap();
+
+        assert!(summary.cancelled);
+        assert!(!results.is_empty());
+        assert!(results.len() < pairs, "expected partial results, got {}", results.len());
+    }
+
+    #[test]
+    fn test_fail_fast_stops_at_first_difference() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        let pairs = 50;
+        for i in 0..pairs {
+            fs::write(left.join(format!("f{}.txt", i)), format!("left {}\n", i)).unwrap();
+            fs::write(right.join(format!("f{}.txt", i)), format!("right {}\n", i)).unwrap();
+        }
+
+        // One thread, so nothing else is in flight when the first pair differs
+        let config = CompareConfig {
+            pairing: PairingStrategy::SameName,
+            fail_fast: true,
+            threads: Some(1),
+            output_root: Some(dir.path().join("out")),
+            ..CompareConfig::default()
+        };
+        let engine = ComparisonEngine::new(&config);
+        let results = engine.run(&left, &right).unwrap();
+
+        assert_eq!(results.len(), 1);
+        let (file1, file2) = results[0].file_paths();
+        assert_eq!(engine.first_difference(), Some((file1.to_string(), file2.to_string())));
+        assert!(!dir.path().join("out").join("report.html").exists());
+
+        // Identical trees run to completion
+        let (results, summary) = engine.run_in_memory(&left, &left).unwrap();
+        assert_eq!(results.len(), pairs);
+        assert_eq!(summary.first_difference, None);
+        assert_eq!(engine.first_difference(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_compared_by_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        std::os::unix::fs::symlink("releases/v1", left.join("current")).unwrap();
+        std::os::unix::fs::symlink("releases/v2", right.join("current")).unwrap();
+
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            symlinks: crate::types::SymlinkMode::CompareTarget,
+            ..CompareConfig::default()
+        };
+        let (results, _) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+
+        assert_eq!(results.len(), 1);
+        let ComparisonResult::Text(text) = &results[0] else { panic!("expected a text result") };
+        assert!(!text.identical);
+        assert!(text.detailed_diff.contains("-releases/v1"));
+        assert!(text.detailed_diff.contains("+releases/v2"));
+    }
+
+    #[test]
+    fn test_run_with_indexes_compares_only_listed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        for side in [&left, &right] {
+            fs::create_dir_all(side).unwrap();
+            fs::write(side.join("changed.txt"), "same\n").unwrap();
+            fs::write(side.join("other.txt"), "same\n").unwrap();
+        }
+        let files1 = crate::index::index_files(&[left.join("changed.txt")], &[]).unwrap();
+        let files2 = crate::index::index_files(&[right.join("changed.txt")], &[]).unwrap();
+
+        let config = CompareConfig { pairing: PairingStrategy::SamePath, ..CompareConfig::default() };
+        let (results, summary) = ComparisonEngine::new(&config)
+            .run_with_indexes(&left, &right, files1, files2)
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].file_paths().0.ends_with("changed.txt"));
+        assert_eq!(summary.total_files_set1, 1);
+    }
+
+    #[test]
+    fn test_plan_matches_compared_pairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("a.txt"), "one\ntwo\n").unwrap();
+        fs::write(right.join("a.txt"), "one\ntwo\n").unwrap();
+        fs::write(left.join("b.txt"), "alpha\nbeta\n").unwrap();
+        fs::write(right.join("b.txt"), "alpha\ngamma\n").unwrap();
+        fs::write(left.join("only.txt"), "x\n").unwrap();
+
+        let config = CompareConfig::default();
+        let engine = ComparisonEngine::new(&config);
+        let plan = engine.plan(&left, &right).unwrap();
+        let (results, _) = engine.run_in_memory(&left, &right).unwrap();
+
+        let compared = results.iter().filter(|r| !matches!(r, ComparisonResult::OnlyInOne(_))).count();
+        assert_eq!(plan.len(), compared);
+        assert_eq!(plan.iter().filter(|p| p.exact_hash_match).count(), 1);
+    }
+
+    #[test]
+    fn test_run_locates_file_in_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir_all(&folder).unwrap();
+
+        let body: String = (0..40).map(|i| format!("invoice line {} total {}\n", i, i * 7)).collect();
+        let target = dir.path().join("invoice.txt");
+        fs::write(&target, &body).unwrap();
+        fs::write(folder.join("copy_with_edit.txt"), body.replace("line 3 ", "line three ")).unwrap();
+        fs::write(folder.join("poem.txt"), "roses are red\nviolets are blue\n".repeat(20)).unwrap();
+        fs::write(folder.join("notes.md"), "# meeting notes\n- nothing to report\n".repeat(20)).unwrap();
+
+        let config = CompareConfig {
+            top_k: 2,
+            output_root: Some(dir.path().join("out")),
+            ..CompareConfig::default()
+        };
+        let results = ComparisonEngine::new(&config).run(&target, &folder).unwrap();
+
+        assert_eq!(results.len(), 2);
+        let best = results
+            .iter()
+            .max_by(|a, b| a.similarity_score().total_cmp(&b.similarity_score()))
+            .unwrap();
+        let (file1, file2) = best.file_paths();
+        assert!(file1.ends_with("invoice.txt"));
+        assert!(file2.ends_with("copy_with_edit.txt"));
+        assert!(best.similarity_score() > 0.9);
+    }
+
+    #[test]
+    fn test_compare_many_ranks_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let write_set = |name: &str, files: &[(&str, String)]| {
+            let folder = dir.path().join(name);
+            fs::create_dir_all(&folder).unwrap();
+            for (file, body) in files {
+                fs::write(folder.join(file), body).unwrap();
+            }
+            folder
+        };
+        let body = |tag: &str, changed: usize| -> String {
+            (0..30)
+                .map(|i| if i < changed { format!("{} edited {}\n", tag, i) } else { format!("{} line {}\n", tag, i) })
+                .collect()
+        };
+
+        let candidate = write_set("candidate", &[("a.txt", body("alpha", 0)), ("b.txt", body("beta", 0))]);
+        let far = write_set("far", &[("a.txt", "something else entirely\n".repeat(5))]);
+        let exact = write_set("exact", &[("a.txt", body("alpha", 0)), ("b.txt", body("beta", 0))]);
+        let close = write_set("close", &[("a.txt", body("alpha", 3)), ("b.txt", body("beta", 0))]);
+
+        let config = CompareConfig::default();
+        let rankings = ComparisonEngine::new(&config)
+            .compare_many(&candidate, &[far.clone(), exact.clone(), close.clone()])
+            .unwrap();
+
+        let order: Vec<&str> = rankings.iter().map(|r| r.reference.as_str()).collect();
+        assert_eq!(
+            order,
+            [exact.display().to_string(), close.display().to_string(), far.display().to_string()]
+        );
+        assert_eq!(rankings[0].score, 1.0);
+        assert_eq!(rankings[0].summary.identical_pairs, 2);
+        assert!(rankings[1].score > rankings[2].score);
+    }
+}
@@ -3,28 +3,40 @@
 //! This is a thin CLI wrapper around the CompareIt library.
 //! All core logic is in lib.rs for sharing with the UI.
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
+use tempfile::NamedTempFile;
 
 // Import from our library crate
 use compare_it::{
     ComparisonEngine, ProgressReporter,
-    export::calculate_summary,
-    report::{generate_html_report, load_results_from_jsonl},
+    compare_structured::compare_three_way,
+    config::{apply_overrides, config_template, load_config, DEFAULT_CONFIG_FILE},
+    export::{calculate_column_rollup, calculate_summary},
+    external_sort::{DEFAULT_CHUNK_BYTES, MEGABYTE},
+    index::{index_single_file_with, IndexOptions},
+    input::{buffer_to_temp_file, is_stdin_path},
+    report::{generate_html_report, generate_three_way_report, load_results_from_jsonl},
+    watch::watch_paths,
     types::{
         self,
-        CompareConfig, CompareMode, ComparisonResult,
-        NormalizationOptions, PairingStrategy, SimilarityAlgorithm,
-        TextComparisonResult,
+        CompareConfig, CompareMode, ComparisonResult, FileDirStrategy, FileSide, HashAlgorithm, KeyNormalization,
+        NormalizationOptions, PairingStrategy, ReportTheme, SampleSpec, SimilarityAlgorithm, SymlinkMode, ToleranceMode,
+        TextComparisonResult, TypeCoercion, UnicodeForm,
     },
 };
 
+/// Fingerprint cache file under the results base, used by `--cache` without a path and by `--watch`
+const DEFAULT_CACHE_FILE: &str = "fingerprint-cache.json";
+
 /// CompareIt - High-performance file comparison tool
 #[derive(Parser)]
 #[command(name = "CompareIt")]
@@ -35,13 +47,14 @@ struct Cli {
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Compare files or folders
     Compare {
-        /// First file or folder path
+        /// First file or folder path (`-` reads a file from stdin)
         path1: PathBuf,
 
-        /// Second file or folder path
+        /// Second file or folder path (`-` reads a file from stdin)
         path2: PathBuf,
 
         /// Comparison mode (auto, text, structured)
@@ -56,57 +69,300 @@ enum Commands {
         #[arg(long, default_value = "3")]
         topk: usize,
 
+        /// File-vs-folder behavior (search the folder for the file, or apply --pairing)
+        #[arg(long, default_value = "search")]
+        file_vs_dir: FileDirStrategy,
+
         /// Maximum number of pairs to compare
         #[arg(long)]
         max_pairs: Option<usize>,
 
+        /// Maximum pairs any one file contributes in all-vs-all mode (caps --topk per file)
+        #[arg(long)]
+        max_pairs_per_file: Option<usize>,
+
+        /// Drop all-vs-all candidates whose estimated similarity is below this (0.0-1.0)
+        #[arg(long = "min-candidate-sim", value_parser = parse_threshold, default_value_t = 0.0)]
+        min_candidate_sim: f64,
+
+        /// Largest size ratio between paired files, either way round (default 10; 'inf' disables the check)
+        #[arg(long, value_parser = parse_size_ratio, default_value_t = 10.0)]
+        size_ratio_max: f64,
+
+        /// Let these extensions pair with each other, on top of the built-in groups (e.g. kt,java; repeatable)
+        #[arg(long = "ext-group", value_name = "EXTS", value_parser = parse_extension_group)]
+        ext_group: Vec<Vec<String>>,
+
+        /// Pair files regardless of extension
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        allow_any_extension: bool,
+
+        /// Only report pairs less similar than this (0.0-1.0); others still count in the summary
+        #[arg(long, value_parser = parse_threshold)]
+        threshold: Option<f64>,
+
         /// Key columns for structured comparison (comma-separated)
         #[arg(short, long, value_delimiter = ',')]
         key: Vec<String>,
 
+        /// Infer key columns from the data when --key is not given (prefers unique columns named like id)
+        #[arg(long, conflicts_with = "key")]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        auto_key: bool,
+
+        /// Trim whitespace around key values before matching records
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        key_trim: bool,
+
+        /// Match key values case-insensitively
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        key_ignore_case: bool,
+
+        /// Match numeric key values by number (007, 7 and 7.0 are one key)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        key_numeric: bool,
+
+        /// Separator between the values of a multi-column key in reports (default '|')
+        #[arg(long, default_value = "|", value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        key_delimiter: String,
+
+        /// Compare numeric columns ignoring currency symbols and thousands separators ($1,000 matches 1000)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        coerce_numbers: bool,
+
+        /// Compare boolean columns case-insensitively (TRUE matches true)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        coerce_booleans: bool,
+
+        /// Compare date columns as dates, whatever their format (2024-01-01 matches 2024/01/01)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        coerce_dates: bool,
+
+        /// Read text files as fixed-width records with these character ranges (e.g. 0-10,10-20)
+        #[arg(long, value_name = "RANGES", value_delimiter = ',', value_parser = parse_fixed_width_range)]
+        fixed_width: Vec<(usize, usize)>,
+
+        /// Column names for --fixed-width fields (comma-separated; default col_0, col_1, ...)
+        #[arg(long, value_delimiter = ',', requires = "fixed_width")]
+        columns: Vec<String>,
+
+        /// Map a file2 column onto a file1 column (file2col=file1col, repeatable)
+        #[arg(long = "map-column", value_parser = parse_column_alias)]
+        map_column: Vec<(String, String)>,
+
+        /// Compare CSV rows by position (row N vs row N) instead of by key
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        positional: bool,
+
+        /// Write every added, removed and changed record of structured pairs to mismatches/<id>.patch.json
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        structured_patch: bool,
+
+        /// Keep every field mismatch of structured pairs in the results, not just 5 samples per column
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        full_detail: bool,
+
+        /// List at most this many keys per side of records only in one structured file (0 = none)
+        #[arg(long, value_name = "N", default_value = "1000")]
+        max_listed_keys: usize,
+
+        /// Profile shared columns of structured pairs (nulls, distinct values, min/max/mean) and report shifts
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        profile: bool,
+
+        /// Compare only a sample of structured records: first:N, fraction:F, P% or random:N[:SEED]
+        #[arg(long, value_name = "SPEC")]
+        sample: Option<SampleSpec>,
+
+        #[arg(
+            long,
+            value_name = "MB",
+            num_args = 0..=1,
+            help = format!(
+                "Sort large CSVs on disk in chunks of MB megabytes (default {}) to bound memory",
+                DEFAULT_CHUNK_BYTES / MEGABYTE
+            )
+        )]
+        external_sort: Option<Option<usize>>,
+
+        /// Compare Excel formula cells by formula text instead of computed value
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        excel_formulas: bool,
+
+        /// Skip CSV/TSV lines starting with this character (e.g. '#')
+        #[arg(long, value_parser = parse_comment_char)]
+        comment_char: Option<u8>,
+
+        /// Field delimiter for CSV/TSV files (e.g. ';', '|' or 'tab'); detected per file by default
+        #[arg(long, value_parser = parse_delimiter)]
+        delimiter: Option<char>,
+
+        /// CSV/TSV files have no header row; columns are named col_0, col_1, ... (e.g. --key col_0)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        no_header: bool,
+
         /// Numeric tolerance for structured comparison
         #[arg(long, default_value = "0.0001")]
         numeric_tol: f64,
 
-        /// Similarity algorithm (diff, char-jaro)
+        /// Whether numeric tolerances bound the absolute difference, the relative difference, or either
+        #[arg(long, value_enum, default_value = "either")]
+        tolerance_mode: ToleranceMode,
+
+        /// Treat numbers within N units-in-the-last-place as equal (replaces --numeric-tol)
+        #[arg(long)]
+        numeric_ulp: Option<u64>,
+
+        /// Per-column numeric tolerances overriding --numeric-tol (column=tol, comma-separated)
+        #[arg(long = "numeric-tol-for", value_delimiter = ',', value_parser = parse_column_tolerance)]
+        numeric_tol_for: Vec<(String, f64)>,
+
+        /// Similarity algorithm for text pairs (see the possible values below)
         #[arg(long, default_value = "diff")]
         similarity: SimilarityAlgorithm,
 
         /// Normalize line endings
         #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
         ignore_eol: bool,
 
         /// Ignore trailing whitespace
         #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
         ignore_trailing_ws: bool,
 
         /// Ignore all whitespace
         #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
         ignore_all_ws: bool,
 
         /// Case-insensitive comparison
         #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
         ignore_case: bool,
 
         /// Skip empty lines
         #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
         skip_empty_lines: bool,
 
+        /// Unicode normalization applied to text lines, so accents composed and decomposed differently match
+        #[arg(long, value_enum, value_name = "FORM")]
+        unicode_normalize: Option<UnicodeForm>,
+
+        /// Ignore the first N lines of each text file (e.g. a generated banner)
+        #[arg(long, value_name = "N", default_value = "0")]
+        ignore_head_lines: usize,
+
+        /// Ignore the last N lines of each text file (e.g. a signature)
+        #[arg(long, value_name = "N", default_value = "0")]
+        ignore_tail_lines: usize,
+
         /// Maximum bytes for detailed diff output
         #[arg(long, default_value = "1048576")]
         max_diff_bytes: usize,
 
+        /// Maximum characters shown per diff line (0 = unlimited)
+        #[arg(long, default_value = "10000")]
+        max_line_length: usize,
+
+        /// Largest binary file compared byte by byte (bigger ones are hash-only)
+        #[arg(long, default_value = "67108864")]
+        max_binary_diff_bytes: u64,
+
+        /// Only compute counts and similarity for text files (no detailed diffs)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        summary_only: bool,
+
+        /// Write patch artifacts from the raw files with a/ b/ paths, ready for `git apply`
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        patch_compatible: bool,
+
+        /// Show whitespace on changed diff lines (spaces as ·, tabs as →, trailing ¶)
+        #[arg(long, alias = "diff-whitespace-visible")]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        show_whitespace: bool,
+
+        /// Highlight changed words within modified lines in the HTML report
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        inline_diff: bool,
+
+        /// Report when a text pair differs only by a consistent rename (e.g. oldName -> newName)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        detect_rename: bool,
+
+        /// Compare text files as sets of lines, so reordered but present lines count as equal
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        ignore_line_order: bool,
+
+        /// Mask all values in mismatch samples and diffs (keys and counts are kept)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        redact: bool,
+
+        /// Mask values only in these columns (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        redact_columns: Vec<String>,
+
         /// Exclude patterns (glob syntax, e.g., "*.tmp", "node_modules/")
         #[arg(long, value_delimiter = ',')]
         exclude: Vec<String>,
 
+        /// Only index files matching these patterns (glob syntax, e.g., "*.csv", "reports/**")
+        #[arg(long, value_delimiter = ',')]
+        include: Vec<String>,
+
+        /// Only index files up to this many folders deep (0 = just the top-level files)
+        #[arg(long)]
+        max_depth: Option<usize>,
+
+        /// How to treat symlinks in folders: skip them, follow them, or compare their target paths
+        #[arg(long, value_enum, default_value = "skip")]
+        symlinks: SymlinkMode,
+
+        /// Compare .zip, .tar, .tar.gz and .tgz paths entry by entry instead of as single files
+        #[arg(long = "archives")]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        follow_archives: bool,
+
         /// Columns to ignore in structured comparison (comma-separated)
         #[arg(long, value_delimiter = ',')]
         ignore_columns: Vec<String>,
 
-        /// Regex pattern for lines to ignore in text comparison
+        /// Treat empty and absent fields as equal, so an empty column only one file has is no difference
         #[arg(long)]
-        ignore_regex: Option<String>,
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        empty_as_missing: bool,
+
+        /// Values that also count as empty with --empty-as-missing (comma-separated, e.g. NULL,N/A)
+        #[arg(long, value_delimiter = ',', requires = "empty_as_missing")]
+        null_tokens: Vec<String>,
+
+        /// Regex pattern for content to ignore in text comparison (repeatable, applied in order)
+        #[arg(long)]
+        ignore_regex: Vec<String>,
+
+        /// Replacement for --ignore-regex matches; may use capture groups like $1 (default <IGNORED>)
+        #[arg(long, requires = "ignore_regex")]
+        ignore_regex_replace: Option<String>,
 
         /// Output JSONL file path
         #[arg(long)]
@@ -116,10 +372,38 @@ enum Commands {
         #[arg(long)]
         out_csv: Option<PathBuf>,
 
+        /// Output Markdown summary path (for PR descriptions and wikis)
+        #[arg(long)]
+        out_md: Option<PathBuf>,
+
+        /// Output JUnit XML path (each pair is a test case that fails if the files differ)
+        #[arg(long)]
+        out_junit: Option<PathBuf>,
+
+        /// Output XLSX workbook path (Summary, All Pairs and Field Mismatches sheets)
+        #[arg(long)]
+        out_xlsx: Option<PathBuf>,
+
+        /// Output SARIF 2.1.0 path (differences as code-scanning annotations on the second file)
+        #[arg(long)]
+        out_sarif: Option<PathBuf>,
+
+        /// Write a shields.io endpoint badge JSON (e.g. "98% match") to this path
+        #[arg(long)]
+        badge: Option<PathBuf>,
+
+        /// Output summary JSON path (default: summary.json in the results directory)
+        #[arg(long)]
+        out_summary: Option<PathBuf>,
+
         /// Output directory for patches and artifacts
         #[arg(long)]
         out_dir: Option<PathBuf>,
 
+        /// Also write each pair's diff/mismatch file under DIR at its relative input path
+        #[arg(long, value_name = "DIR")]
+        mirror_output: Option<PathBuf>,
+
         /// Base directory for automatic results (each run creates a unique subfolder)
         #[arg(short = 'B', long, default_value = "output")]
         results_base: PathBuf,
@@ -128,8 +412,127 @@ enum Commands {
         #[arg(short = 'o', long)]
         output: Option<PathBuf>,
 
+        /// Number of worker threads (default: one per CPU core; 1 = sequential)
+        #[arg(long)]
+        threads: Option<usize>,
+
+        /// Reuse fingerprints of unchanged files across runs (default file: <results-base>/fingerprint-cache.json)
+        #[arg(long, value_name = "FILE", num_args = 0..=1)]
+        cache: Option<Option<PathBuf>>,
+
+        /// Content hash algorithm for exact-match detection and linked IDs
+        #[arg(long, value_enum, default_value = "blake3")]
+        hash_algo: HashAlgorithm,
+
+        /// Hash file contents only, skipping similarity fingerprints (ignored with all-vs-all pairing)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        no_fingerprint: bool,
+
+        /// Abort if the pre-flight check warns that the inputs look incomparable
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        strict: bool,
+
+        /// Stop at the first pair that differs and exit non-zero, skipping the remaining pairs and exports
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        fail_fast: bool,
+
+        /// Audit permission bits of matched files only, without reading their content
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        compare_permissions_only: bool,
+
+        /// Resume an interrupted run, skipping pairs already in its results JSONL (needs -o or --out-jsonl)
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        resume: bool,
+
+        /// Merge this run's results into an earlier results JSONL, replacing results for re-compared pairs
+        #[arg(long, value_name = "JSONL", conflicts_with = "resume")]
+        merge: Option<PathBuf>,
+
         /// Verbose output
         #[arg(short, long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        verbose: bool,
+
+        /// Print only the summary table and export paths, skipping per-pair details
+        #[arg(short, long, conflicts_with = "verbose")]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        quiet: bool,
+
+        /// Skip generating the HTML report
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        no_html: bool,
+
+        /// Write each text diff as a standalone HTML page in the artifacts directory and link to it from the report
+        #[arg(long)]
+        #[arg(action = ArgAction::Set, num_args = 0..=1, require_equals = true, value_name = "BOOL", default_value_t = false, default_missing_value = "true")]
+        split_diffs: bool,
+
+        /// Color theme of the HTML report
+        #[arg(long, value_enum, default_value = "dark")]
+        theme: ReportTheme,
+
+        /// Rows per page of the HTML report's results table
+        #[arg(long, default_value = "1000")]
+        report_max_rows: usize,
+
+        /// List the candidate pairs the run would compare, then stop without comparing or exporting
+        #[arg(long)]
+        plan: bool,
+
+        /// After the first run, re-run whenever a file under either path changes (Ctrl-C to stop)
+        #[arg(long, conflicts_with = "plan")]
+        watch: bool,
+
+        /// JSON config file with settings for this command (flags override it; see `init`)
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
+
+    /// Rank several reference files or folders by similarity to one candidate
+    CompareMany {
+        /// Candidate file or folder
+        candidate: PathBuf,
+
+        /// Reference files or folders to rank
+        #[arg(required = true, num_args = 1..)]
+        references: Vec<PathBuf>,
+
+        /// Comparison mode (auto, text, structured)
+        #[arg(short, long, default_value = "auto")]
+        mode: CompareMode,
+
+        /// Pairing strategy for folders (same-path, same-name, all-vs-all)
+        #[arg(long, default_value = "all-vs-all")]
+        pairing: PairingStrategy,
+
+        /// Top-K candidates per file in all-vs-all mode
+        #[arg(long, default_value = "3")]
+        topk: usize,
+
+        /// Key columns for structured comparison (comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        key: Vec<String>,
+
+        /// Similarity algorithm for text pairs (see the possible values below)
+        #[arg(long, default_value = "diff")]
+        similarity: SimilarityAlgorithm,
+
+        /// Exclude patterns (glob syntax, e.g., "*.tmp", "node_modules/")
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
+
+        /// Columns to ignore in structured comparison (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        ignore_columns: Vec<String>,
+
+        /// Show the full summary for every reference
+        #[arg(short, long)]
         verbose: bool,
     },
 
@@ -146,6 +549,53 @@ enum Commands {
         /// Path to artifacts directory (for linking)
         #[arg(long)]
         artifacts: Option<PathBuf>,
+
+        /// Color theme of the HTML report
+        #[arg(long, value_enum, default_value = "dark")]
+        theme: ReportTheme,
+
+        /// Rows per page of the HTML report's results table
+        #[arg(long, default_value = "1000")]
+        report_max_rows: usize,
+    },
+
+    /// Compare two edited copies of a structured file against their common ancestor
+    ThreeWay {
+        /// Common ancestor (base) file
+        base: PathBuf,
+
+        /// First edited copy
+        left: PathBuf,
+
+        /// Second edited copy
+        right: PathBuf,
+
+        /// Key columns identifying records (comma-separated)
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        key: Vec<String>,
+
+        /// Columns to ignore (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        ignore_columns: Vec<String>,
+
+        /// Output HTML file path
+        #[arg(long, default_value = "three_way_report.html")]
+        html: PathBuf,
+
+        /// Color theme of the HTML report
+        #[arg(long, value_enum, default_value = "dark")]
+        theme: ReportTheme,
+    },
+
+    /// Write a commented config file with the default compare settings
+    Init {
+        /// Config file to create
+        #[arg(default_value = DEFAULT_CONFIG_FILE)]
+        path: PathBuf,
+
+        /// Overwrite the file if it already exists
+        #[arg(long)]
+        force: bool,
     },
 }
 
@@ -153,78 +603,184 @@ fn main() -> Result<()> {
     // Initialize logger (controlled by RUST_LOG env var)
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
 
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     match cli.command {
-        Commands::Compare {
-            path1,
-            path2,
+        command @ Commands::Compare { .. } => {
+            let CompareInvocation { path1, path2, config_file, plan, watch, mut config } = compare_invocation(command);
+            if let Some(file) = config_file {
+                let explicit = matches.subcommand_matches("compare").map(explicit_config_fields).unwrap_or_default();
+                config = apply_overrides(load_config(&file)?, &config, &explicit)?;
+            }
+            // Watch mode caches fingerprints by default so re-runs only rehash what changed
+            if watch && config.cache_path.is_none() {
+                config.cache_path = Some(config.results_base.join(DEFAULT_CACHE_FILE));
+            }
+
+            if watch && (is_stdin_path(&path1) || is_stdin_path(&path2)) {
+                anyhow::bail!("--watch cannot read from standard input");
+            }
+            // Held until the comparison finishes; the temp file is deleted on drop
+            let (path1, path2, _stdin_file) = resolve_stdin_paths(path1, path2)?;
+            if plan {
+                run_plan(&path1, &path2, &config)?;
+            } else {
+                run_compare(&path1, &path2, &config)?;
+                if watch {
+                    run_watch(&path1, &path2, &config)?;
+                }
+            }
+        }
+
+        Commands::CompareMany {
+            candidate,
+            references,
             mode,
             pairing,
             topk,
-            max_pairs,
             key,
-            numeric_tol,
             similarity,
-            ignore_eol,
-            ignore_trailing_ws,
-            ignore_all_ws,
-            ignore_case,
-            skip_empty_lines,
-            max_diff_bytes,
             exclude,
             ignore_columns,
-            ignore_regex,
-            out_jsonl,
-            out_csv,
-            out_dir,
-            results_base,
-            output,
             verbose,
         } => {
             let config = CompareConfig {
                 mode,
                 pairing,
                 top_k: topk,
-                max_pairs,
                 key_columns: key,
-                numeric_tolerance: numeric_tol,
-                normalization: NormalizationOptions {
-                    ignore_eol,
-                    ignore_trailing_ws,
-                    ignore_all_ws,
-                    ignore_case,
-                    skip_empty_lines,
-                },
                 similarity_algorithm: similarity,
-                max_diff_bytes,
-                output_jsonl: out_jsonl,
-                output_csv: out_csv,
-                output_dir: out_dir,
-                results_base,
-                output_root: output,
-                verbose,
                 exclude_patterns: exclude,
                 ignore_columns,
-                ignore_regex,
-                max_fingerprint_size: None, // Use dynamic calculation based on system RAM
+                verbose,
+                ..CompareConfig::default()
             };
 
-            run_compare(&path1, &path2, &config)?;
+            run_compare_many(&candidate, &references, &config)?;
         }
 
         Commands::Report {
             input,
             html,
             artifacts,
+            theme,
+            report_max_rows,
         } => {
-            run_report(&input, &html, artifacts.as_deref())?;
+            run_report(&input, &html, artifacts.as_deref(), theme, report_max_rows)?;
+        }
+
+        Commands::ThreeWay {
+            base,
+            left,
+            right,
+            key,
+            ignore_columns,
+            html,
+            theme,
+        } => {
+            let config = CompareConfig {
+                key_columns: key,
+                ignore_columns,
+                ..CompareConfig::default()
+            };
+            run_three_way(&base, &left, &right, &config, &html, theme)?;
+        }
+
+        Commands::Init { path, force } => {
+            if path.exists() && !force {
+                anyhow::bail!("{} already exists (use --force to overwrite it)", path.display());
+            }
+            let template = config_template(&default_compare_config())?;
+            std::fs::write(&path, template).with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("{} {}", style("✓ Wrote config").green().bold(), path.display());
+            println!("  Use it with: CompareIt compare <path1> <path2> --config {}", path.display());
         }
     }
 
     Ok(())
 }
 
+/// Parse a `--map-column file2col=file1col` argument
+fn parse_column_alias(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => {
+            Ok((from.trim().to_string(), to.trim().to_string()))
+        }
+        _ => Err(format!("expected FILE2_COLUMN=FILE1_COLUMN, got '{}'", s)),
+    }
+}
+
+/// Parse one `--fixed-width start-end` character range
+fn parse_fixed_width_range(s: &str) -> Result<(usize, usize), String> {
+    let parsed = s
+        .split_once('-')
+        .and_then(|(start, end)| Some((start.trim().parse::<usize>().ok()?, end.trim().parse::<usize>().ok()?)));
+    match parsed {
+        Some((start, end)) if start < end => Ok((start, end)),
+        _ => Err(format!("expected START-END with START < END, got '{}'", s)),
+    }
+}
+
+/// Parse a `--numeric-tol-for column=tolerance` entry
+fn parse_column_tolerance(s: &str) -> Result<(String, f64), String> {
+    match s.split_once('=') {
+        Some((column, tol)) if !column.trim().is_empty() => match tol.trim().parse::<f64>() {
+            Ok(tolerance) if tolerance >= 0.0 => Ok((column.trim().to_string(), tolerance)),
+            _ => Err(format!("expected a non-negative tolerance for '{}', got '{}'", column.trim(), tol)),
+        },
+        _ => Err(format!("expected COLUMN=TOLERANCE, got '{}'", s)),
+    }
+}
+
+/// Parse a `--threshold` similarity cutoff between 0 and 1
+fn parse_threshold(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(format!("expected a similarity between 0.0 and 1.0, got '{}'", s)),
+    }
+}
+
+/// Parse a `--size-ratio-max` value, which must be at least 1 (or 'inf')
+fn parse_size_ratio(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if value >= 1.0 => Ok(value),
+        _ => Err(format!("expected a ratio of at least 1 (or 'inf'), got '{}'", s)),
+    }
+}
+
+/// Parse an `--ext-group` list such as `kt,java` or `.kt,.java`
+fn parse_extension_group(s: &str) -> Result<Vec<String>, String> {
+    let group: Vec<String> = s
+        .split(',')
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    if group.len() < 2 {
+        return Err(format!("expected at least two comma-separated extensions, got '{}'", s));
+    }
+    Ok(group)
+}
+
+/// Parse a `--comment-char` value, which must be a single ASCII character
+fn parse_comment_char(s: &str) -> Result<u8, String> {
+    match s.as_bytes() {
+        [byte] if byte.is_ascii() => Ok(*byte),
+        _ => Err(format!("expected a single ASCII character, got '{}'", s)),
+    }
+}
+
+/// Parse a `--delimiter` value: a single ASCII character, or `tab`
+fn parse_delimiter(s: &str) -> Result<char, String> {
+    match s {
+        "tab" | "\\t" => Ok('\t'),
+        _ => match s.as_bytes() {
+            [byte] if byte.is_ascii() && !byte.is_ascii_alphanumeric() => Ok(*byte as char),
+            _ => Err(format!("expected a single non-alphanumeric character or 'tab', got '{}'", s)),
+        },
+    }
+}
+
 /// CLI-specific progress reporter using indicatif
 struct CliProgressReporter {
     bar: Mutex<Option<ProgressBar>>,
@@ -267,8 +823,390 @@ impl ProgressReporter for CliProgressReporter {
     }
 }
 
+/// A parsed `compare` subcommand: what to compare, and how
+struct CompareInvocation {
+    path1: PathBuf,
+    path2: PathBuf,
+    config_file: Option<PathBuf>,
+    plan: bool,
+    watch: bool,
+    config: CompareConfig,
+}
+
+/// Build the comparison config from the `compare` subcommand's flags
+fn compare_invocation(command: Commands) -> CompareInvocation {
+    let Commands::Compare {
+        path1,
+        path2,
+        mode,
+        pairing,
+        topk,
+        file_vs_dir,
+        max_pairs,
+        max_pairs_per_file,
+        min_candidate_sim,
+        size_ratio_max,
+        ext_group,
+        allow_any_extension,
+        threshold,
+        key,
+        auto_key,
+        key_trim,
+        key_ignore_case,
+        key_numeric,
+        key_delimiter,
+        coerce_numbers,
+        coerce_booleans,
+        coerce_dates,
+        fixed_width,
+        columns,
+        map_column,
+        positional,
+        structured_patch,
+        full_detail,
+        max_listed_keys,
+        profile,
+        sample,
+        external_sort,
+        excel_formulas,
+        comment_char,
+        delimiter,
+        no_header,
+        numeric_tol,
+        tolerance_mode,
+        numeric_ulp,
+        numeric_tol_for,
+        similarity,
+        ignore_eol,
+        ignore_trailing_ws,
+        ignore_all_ws,
+        ignore_case,
+        skip_empty_lines,
+        unicode_normalize,
+        ignore_head_lines,
+        ignore_tail_lines,
+        max_diff_bytes,
+        max_line_length,
+        max_binary_diff_bytes,
+        summary_only,
+        patch_compatible,
+        show_whitespace,
+        inline_diff,
+        detect_rename,
+        ignore_line_order,
+        redact,
+        redact_columns,
+        exclude,
+        include,
+        max_depth,
+        symlinks,
+        follow_archives,
+        ignore_columns,
+        empty_as_missing,
+        null_tokens,
+        ignore_regex,
+        ignore_regex_replace,
+        out_jsonl,
+        out_csv,
+        out_md,
+        out_junit,
+        out_xlsx,
+        out_sarif,
+        badge,
+        out_summary,
+        out_dir,
+        mirror_output,
+        results_base,
+        output,
+        threads,
+        cache,
+        hash_algo,
+        no_fingerprint,
+        strict,
+        fail_fast,
+        compare_permissions_only,
+        resume,
+        merge,
+        verbose,
+        quiet,
+        no_html,
+        split_diffs,
+        theme,
+        report_max_rows,
+        plan,
+        watch,
+        config: config_file,
+    } = command
+    else {
+        unreachable!("compare_invocation called with another subcommand");
+    };
+
+    let cache_path = cache.map(|path| path.unwrap_or_else(|| results_base.join(DEFAULT_CACHE_FILE)));
+    let config = CompareConfig {
+        mode,
+        pairing,
+        top_k: topk,
+        file_dir_strategy: file_vs_dir,
+        max_pairs,
+        max_pairs_per_file,
+        min_candidate_similarity: min_candidate_sim,
+        size_ratio_min: 1.0 / size_ratio_max,
+        size_ratio_max,
+        extension_groups: ext_group,
+        allow_any_extension,
+        min_similarity_to_report: threshold,
+        key_columns: key,
+        auto_key,
+        key_normalization: KeyNormalization {
+            trim: key_trim,
+            case_insensitive: key_ignore_case,
+            numeric: key_numeric,
+        },
+        key_delimiter,
+        type_coercion: TypeCoercion {
+            numbers: coerce_numbers,
+            booleans: coerce_booleans,
+            dates: coerce_dates,
+        },
+        fixed_width,
+        fixed_width_columns: columns,
+        column_aliases: map_column.into_iter().collect(),
+        positional,
+        structured_patch,
+        full_mismatch_detail: full_detail,
+        max_listed_keys,
+        profile_columns: profile,
+        sample,
+        external_sort: external_sort.map(|mb| mb.map_or(DEFAULT_CHUNK_BYTES, |mb| mb.saturating_mul(MEGABYTE))),
+        excel_formulas,
+        comment_char,
+        csv_delimiter: delimiter,
+        has_header: !no_header,
+        numeric_tolerance: numeric_tol,
+        tolerance_mode,
+        numeric_ulp,
+        column_tolerances: numeric_tol_for.into_iter().collect(),
+        normalization: NormalizationOptions {
+            ignore_eol,
+            ignore_trailing_ws,
+            ignore_all_ws,
+            ignore_case,
+            skip_empty_lines,
+            ignore_head_lines,
+            ignore_tail_lines,
+            unicode_normalize,
+        },
+        similarity_algorithm: similarity,
+        max_diff_bytes,
+        max_line_length,
+        max_binary_diff_bytes,
+        summary_only,
+        patch_compatible,
+        show_whitespace,
+        inline_diff,
+        detect_rename,
+        order_insensitive_text: ignore_line_order,
+        redact,
+        redact_columns,
+        output_jsonl: out_jsonl,
+        output_csv: out_csv,
+        output_md: out_md,
+        output_junit: out_junit,
+        output_xlsx: out_xlsx,
+        output_sarif: out_sarif,
+        output_badge: badge,
+        output_summary: out_summary,
+        output_dir: out_dir,
+        mirror_output,
+        results_base,
+        output_root: output,
+        resume,
+        merge_results: merge,
+        verbose,
+        quiet,
+        no_html,
+        split_diffs,
+        report_theme: theme,
+        report_max_rows,
+        exclude_patterns: exclude,
+        include_patterns: include,
+        max_depth,
+        symlinks,
+        follow_archives,
+        ignore_columns,
+        treat_empty_as_missing: empty_as_missing,
+        null_tokens,
+        ignore_regexes: ignore_regex,
+        ignore_regex_replacement: ignore_regex_replace,
+        threads,
+        max_fingerprint_size: None, // Use dynamic calculation based on system RAM
+        strict,
+        fail_fast,
+        compare_permissions_only,
+        cache_path,
+        hash_algorithm: hash_algo,
+        no_fingerprint,
+    };
+
+    CompareInvocation { path1, path2, config_file, plan, watch, config }
+}
+
+/// The config `compare` builds when no flags are given
+fn default_compare_config() -> CompareConfig {
+    compare_invocation(Cli::parse_from(["CompareIt", "compare", "-", "-"]).command).config
+}
+
+/// Config fields set by the flags given on the `compare` command line
+///
+/// These override a `--config` file even when they repeat the default value.
+fn explicit_config_fields(matches: &ArgMatches) -> Vec<String> {
+    let command = Cli::command();
+    let compare = command.find_subcommand("compare").expect("compare subcommand");
+    compare
+        .get_arguments()
+        .map(|arg| arg.get_id().as_str())
+        .filter(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+        .flat_map(config_fields)
+        .collect()
+}
+
+/// Fields of the serialized [`CompareConfig`] that a `compare` argument sets
+///
+/// Arguments named like their field are not listed; nested fields are
+/// dotted paths like `normalization.ignore_case`.
+fn config_fields(arg: &str) -> Vec<String> {
+    let fields: &[&str] = match arg {
+        "path1" | "path2" | "plan" | "watch" | "config" => &[],
+        "topk" => &["top_k"],
+        "file_vs_dir" => &["file_dir_strategy"],
+        "min_candidate_sim" => &["min_candidate_similarity"],
+        "size_ratio_max" => &["size_ratio_min", "size_ratio_max"],
+        "ext_group" => &["extension_groups"],
+        "threshold" => &["min_similarity_to_report"],
+        "key" => &["key_columns"],
+        "key_trim" => &["key_normalization.trim"],
+        "key_ignore_case" => &["key_normalization.case_insensitive"],
+        "key_numeric" => &["key_normalization.numeric"],
+        "coerce_numbers" => &["type_coercion.numbers"],
+        "coerce_booleans" => &["type_coercion.booleans"],
+        "coerce_dates" => &["type_coercion.dates"],
+        "columns" => &["fixed_width_columns"],
+        "map_column" => &["column_aliases"],
+        "full_detail" => &["full_mismatch_detail"],
+        "profile" => &["profile_columns"],
+        "delimiter" => &["csv_delimiter"],
+        "no_header" => &["has_header"],
+        "numeric_tol" => &["numeric_tolerance"],
+        "numeric_tol_for" => &["column_tolerances"],
+        "similarity" => &["similarity_algorithm"],
+        "ignore_eol" => &["normalization.ignore_eol"],
+        "ignore_trailing_ws" => &["normalization.ignore_trailing_ws"],
+        "ignore_all_ws" => &["normalization.ignore_all_ws"],
+        "ignore_case" => &["normalization.ignore_case"],
+        "skip_empty_lines" => &["normalization.skip_empty_lines"],
+        "unicode_normalize" => &["normalization.unicode_normalize"],
+        "ignore_head_lines" => &["normalization.ignore_head_lines"],
+        "ignore_tail_lines" => &["normalization.ignore_tail_lines"],
+        "ignore_line_order" => &["order_insensitive_text"],
+        "exclude" => &["exclude_patterns"],
+        "include" => &["include_patterns"],
+        "empty_as_missing" => &["treat_empty_as_missing"],
+        "ignore_regex" => &["ignore_regexes"],
+        "ignore_regex_replace" => &["ignore_regex_replacement"],
+        "out_jsonl" => &["output_jsonl"],
+        "out_csv" => &["output_csv"],
+        "out_md" => &["output_md"],
+        "out_junit" => &["output_junit"],
+        "out_xlsx" => &["output_xlsx"],
+        "out_sarif" => &["output_sarif"],
+        "badge" => &["output_badge"],
+        "out_summary" => &["output_summary"],
+        "out_dir" => &["output_dir"],
+        "output" => &["output_root"],
+        "cache" => &["cache_path"],
+        "hash_algo" => &["hash_algorithm"],
+        "merge" => &["merge_results"],
+        "theme" => &["report_theme"],
+        field => return vec![field.to_string()],
+    };
+    fields.iter().map(|field| field.to_string()).collect()
+}
+
+/// Buffer standard input to a temporary file when one of the paths is `-`
+///
+/// Returns the paths to compare and the temp file backing the stdin side.
+fn resolve_stdin_paths(path1: PathBuf, path2: PathBuf) -> Result<(PathBuf, PathBuf, Option<NamedTempFile>)> {
+    let (stdin1, stdin2) = (is_stdin_path(&path1), is_stdin_path(&path2));
+    if !stdin1 && !stdin2 {
+        return Ok((path1, path2, None));
+    }
+    if stdin1 && stdin2 {
+        anyhow::bail!("Only one path can be `-` (standard input)");
+    }
+    let other = if stdin1 { &path2 } else { &path1 };
+    if !other.is_file() {
+        anyhow::bail!("Standard input can only be compared against a single file, not {}", other.display());
+    }
+    let buffered = buffer_to_temp_file(std::io::stdin().lock(), other).context("Failed to buffer standard input")?;
+    let temp_path = buffered.path().to_path_buf();
+    Ok(if stdin1 { (temp_path, path2, Some(buffered)) } else { (path1, temp_path, Some(buffered)) })
+}
+
+/// Quiet period that ends a burst of changes in watch mode
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Set by Ctrl-C to end watch mode once the current comparison finishes
+static STOP_WATCHING: AtomicBool = AtomicBool::new(false);
+
+/// Re-run the comparison whenever a file under either path changes, until Ctrl-C
+fn run_watch(path1: &Path, path2: &Path, config: &CompareConfig) -> Result<()> {
+    ctrlc::set_handler(|| {
+        // A second Ctrl-C abandons a comparison that is still running
+        if STOP_WATCHING.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    })
+    .context("Failed to install the Ctrl-C handler")?;
+
+    // Writing results must not trigger another run when they land inside a watched folder
+    let ignore: Vec<PathBuf> = [
+        Some(&config.results_base),
+        config.output_root.as_ref(),
+        config.output_dir.as_ref(),
+        config.mirror_output.as_ref(),
+        config.cache_path.as_ref(),
+        config.output_jsonl.as_ref(),
+        config.output_csv.as_ref(),
+        config.output_md.as_ref(),
+        config.output_junit.as_ref(),
+        config.output_xlsx.as_ref(),
+        config.output_sarif.as_ref(),
+        config.output_badge.as_ref(),
+        config.output_summary.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .cloned()
+    .collect();
+
+    let waiting = || println!("\n{}", style("Watching for changes (Ctrl-C to stop)...").cyan());
+    waiting();
+    watch_paths(&[path1, path2], &ignore, WATCH_DEBOUNCE, &STOP_WATCHING, |changed| {
+        let names: Vec<String> = changed.iter().take(5).map(|p| p.display().to_string()).collect();
+        let more = changed.len().saturating_sub(names.len());
+        let more = if more > 0 { format!(" and {} more", more) } else { String::new() };
+        println!("\n{} {}{}\n", style("Changed:").yellow().bold(), names.join(", "), more);
+        if let Err(e) = run_compare(path1, path2, config) {
+            eprintln!("{} {:#}", style("Error:").red().bold(), e);
+        }
+        waiting();
+    })?;
+    println!("{}", style("Stopped watching").dim());
+    Ok(())
+}
+
 /// Run the compare command using the shared library engine
-fn run_compare(path1: &PathBuf, path2: &PathBuf, config: &CompareConfig) -> Result<()> {
+fn run_compare(path1: &Path, path2: &Path, config: &CompareConfig) -> Result<()> {
     println!("{}", style("CompareIt").cyan().bold());
     println!("{}", style("═".repeat(60)).dim());
 
@@ -278,6 +1216,9 @@ fn run_compare(path1: &PathBuf, path2: &PathBuf, config: &CompareConfig) -> Resu
     // Use the shared comparison engine
     let engine = ComparisonEngine::new(config).with_progress(&progress);
     let results = engine.run(path1, path2)?;
+    if let Some((file1, file2)) = engine.first_difference() {
+        anyhow::bail!("Stopped at the first difference: {} vs {}", file1, file2);
+    }
 
     // Calculate summary for display (engine already captured process stats in its summary)
     let summary = calculate_summary(&results, 0, 0, None);
@@ -287,11 +1228,9 @@ fn run_compare(path1: &PathBuf, path2: &PathBuf, config: &CompareConfig) -> Resu
     println!("{}", style("─".repeat(60)).dim());
     display_summary_table(&summary);
 
-    // Display detailed results
-    if !results.is_empty() {
-        println!("\n{}", style("Comparison Details").cyan().bold());
-        println!("{}", style("─".repeat(60)).dim());
-        display_results_table(&results, config.verbose);
+    // Quiet mode stops at the summary; everything else is in the exports
+    if !config.quiet {
+        display_details(&engine, &results, path1, path2, config);
     }
 
     // Show export info
@@ -304,7 +1243,7 @@ fn run_compare(path1: &PathBuf, path2: &PathBuf, config: &CompareConfig) -> Resu
         config.results_base.clone()
     };
     
-    let canonical_results = results_path.canonicalize().unwrap_or_else(|_| results_path);
+    let canonical_results = results_path.canonicalize().unwrap_or(results_path);
     println!(
         "  {} {}",
         style("Results Directory:").dim(),
@@ -312,15 +1251,262 @@ fn run_compare(path1: &PathBuf, path2: &PathBuf, config: &CompareConfig) -> Resu
     );
 
     println!("\n{}", style("✓ Complete").green().bold());
+    let hint = if config.no_html {
+        "  Check the results directory for the exported results"
+    } else {
+        "  Check the results directory for HTML report and artifacts"
+    };
+    println!("{}", style(hint).dim());
+    Ok(())
+}
+
+/// Print the notes and per-pair tables that follow the summary table
+fn display_details(
+    engine: &ComparisonEngine,
+    results: &[ComparisonResult],
+    path1: &Path,
+    path2: &Path,
+    config: &CompareConfig,
+) {
+    // Surface data-quality anomalies (details are in the HTML report)
+    let anomalies = engine.anomaly_report();
+    if !anomalies.is_empty() {
+        println!(
+            "\n{} {}",
+            style("⚠").yellow(),
+            style(format!(
+                "{} data-quality anomalies recorded (see Data Quality in the HTML report)",
+                anomalies.anomalies.len()
+            ))
+            .yellow()
+        );
+    }
+
+    // Duplicate keys make structured row matching order-dependent
+    let duplicated: Vec<&types::StructuredComparisonResult> = results
+        .iter()
+        .filter_map(|r| match r {
+            ComparisonResult::Structured(s) if !s.duplicate_keys.is_empty() => Some(s),
+            _ => None,
+        })
+        .collect();
+    if let Some(first) = duplicated.first() {
+        println!(
+            "\n{} {}",
+            style("⚠").yellow(),
+            style(format!(
+                "{} structured pair(s) have duplicate keys (e.g. '{}'); rows sharing a key were matched in file order",
+                duplicated.len(),
+                first.duplicate_keys[0]
+            ))
+            .yellow()
+        );
+    }
+
+    // Sampled pairs describe a subset of the records only
+    let sampled = results
+        .iter()
+        .filter(|r| matches!(r, ComparisonResult::Structured(s) if s.sampled))
+        .count();
+    if sampled > 0 {
+        println!(
+            "\n{} {}",
+            style("ℹ").cyan(),
+            style(format!(
+                "{} structured pair(s) were compared on a sample of their records (--sample); counts cover the sample only",
+                sampled
+            ))
+            .cyan()
+        );
+    }
+
+    // Keys picked by --auto-key, so a wrong guess is easy to spot
+    let mut inferred: Vec<&Vec<String>> = results
+        .iter()
+        .filter_map(|r| match r {
+            ComparisonResult::Structured(s) if !s.inferred_key_columns.is_empty() => Some(&s.inferred_key_columns),
+            _ => None,
+        })
+        .collect();
+    inferred.sort();
+    inferred.dedup();
+    if !inferred.is_empty() {
+        let keys: Vec<String> = inferred.iter().map(|columns| columns.join(" + ")).collect();
+        println!(
+            "\n{} {}",
+            style("ℹ").cyan(),
+            style(format!("Inferred key column(s): {} (pass --key to override)", keys.join("; "))).cyan()
+        );
+    }
+
+    // Explain a thin candidate list: which blocking rule pruned the most pairs
+    let blocking = engine.blocking_stats();
+    if let Some((rule, count)) = blocking.top_rule() {
+        println!(
+            "\n{} {}",
+            style("ℹ").cyan(),
+            style(format!(
+                "Blocking rules rejected {} of {} potential pairs; most by the {} rule ({}, {:.0}%)",
+                blocking.total_rejected(),
+                blocking.pairs_checked,
+                rule,
+                count,
+                count as f64 / blocking.pairs_checked as f64 * 100.0
+            ))
+            .dim()
+        );
+    }
+
+    // In file-vs-folder search mode, call out the closest match
+    let searching = config.file_dir_strategy == FileDirStrategy::Search && path1.is_file() != path2.is_file();
+    if searching {
+        let best = results
+            .iter()
+            .filter(|r| !matches!(r, ComparisonResult::Error { .. }))
+            .max_by(|a, b| a.similarity_score().total_cmp(&b.similarity_score()));
+        if let Some(best) = best {
+            let (file1, file2) = best.file_paths();
+            let found = if path1.is_file() { file2 } else { file1 };
+            println!(
+                "\n{} {} ({:.1}%)",
+                style("Best match:").cyan().bold(),
+                style(found).white().bold(),
+                best.similarity_score() * 100.0
+            );
+        }
+    }
+
+    // Display detailed results
+    if !results.is_empty() {
+        println!("\n{}", style("Comparison Details").cyan().bold());
+        println!("{}", style("─".repeat(60)).dim());
+        display_results_table(results, config.verbose);
+    }
+}
+
+/// Print the candidate pairs a compare run would produce, without comparing them
+fn run_plan(path1: &Path, path2: &Path, config: &CompareConfig) -> Result<()> {
+    println!("{}", style("CompareIt").cyan().bold());
+    println!("{}", style("═".repeat(60)).dim());
+
+    let progress = CliProgressReporter::new();
+    let engine = ComparisonEngine::new(config).with_progress(&progress);
+    let pairs = engine.plan(path1, path2)?;
+
+    println!("\n{}", style("Planned Pairs").cyan().bold());
+    println!("{}", style("─".repeat(60)).dim());
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+    table.set_header(vec![
+        Cell::new("File 1").fg(Color::Cyan),
+        Cell::new("File 2").fg(Color::Cyan),
+        Cell::new("Estimated").fg(Color::Cyan),
+        Cell::new("Exact Hash").fg(Color::Cyan),
+    ]);
+    for pair in &pairs {
+        let exact = if pair.exact_hash_match { Cell::new("yes").fg(Color::Green) } else { Cell::new("no") };
+        table.add_row(vec![
+            Cell::new(pair.file1.path.display()),
+            Cell::new(pair.file2.path.display()),
+            Cell::new(format!("{:.1}%", pair.estimated_similarity * 100.0)),
+            exact,
+        ]);
+    }
+    println!("{table}");
+
+    let exact = pairs.iter().filter(|p| p.exact_hash_match).count();
     println!(
-        "{}",
-        style("  Check the results directory for HTML report and artifacts").dim()
+        "\n{} {} pair(s), {} exact hash match(es); nothing was compared",
+        style("Plan:").cyan().bold(),
+        pairs.len(),
+        exact
     );
     Ok(())
 }
 
+/// Run the compare-many command and print the reference ranking
+fn run_compare_many(candidate: &Path, references: &[PathBuf], config: &CompareConfig) -> Result<()> {
+    println!("{}", style("CompareIt").cyan().bold());
+    println!("{}", style("═".repeat(60)).dim());
+    println!(
+        "\n{} {} against {} references",
+        style("Ranking").cyan().bold(),
+        style(candidate.display()).white().bold(),
+        references.len()
+    );
+
+    let progress = CliProgressReporter::new();
+    let engine = ComparisonEngine::new(config).with_progress(&progress);
+    let rankings = engine.compare_many(candidate, references)?;
+
+    println!("\n{}", style("Reference Ranking").cyan().bold());
+    println!("{}", style("─".repeat(60)).dim());
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+    table.set_header(vec![
+        Cell::new("#").fg(Color::Cyan),
+        Cell::new("Reference").fg(Color::Cyan),
+        Cell::new("Score").fg(Color::Cyan),
+        Cell::new("Pairs").fg(Color::Cyan),
+        Cell::new("Identical").fg(Color::Cyan),
+        Cell::new("Different").fg(Color::Cyan),
+        Cell::new("Errors").fg(Color::Cyan),
+    ]);
+    for (rank, ranking) in rankings.iter().enumerate() {
+        let score_pct = ranking.score * 100.0;
+        let score_color = if score_pct >= 90.0 {
+            Color::Green
+        } else if score_pct >= 50.0 {
+            Color::Yellow
+        } else {
+            Color::Red
+        };
+        table.add_row(vec![
+            Cell::new(rank + 1),
+            Cell::new(&ranking.reference),
+            Cell::new(format!("{:.1}% {}", score_pct, create_similarity_bar(ranking.score))).fg(score_color),
+            Cell::new(ranking.summary.pairs_compared),
+            Cell::new(ranking.summary.identical_pairs).fg(Color::Green),
+            Cell::new(ranking.summary.different_pairs).fg(Color::Yellow),
+            Cell::new(ranking.summary.error_pairs),
+        ]);
+    }
+    println!("{table}");
+
+    if config.verbose {
+        for ranking in &rankings {
+            println!("\n{}", style(&ranking.reference).white().bold());
+            display_summary_table(&ranking.summary);
+        }
+    }
+
+    if let Some(best) = rankings.first() {
+        println!(
+            "\n{} {} ({:.1}%)",
+            style("Closest reference:").cyan().bold(),
+            style(&best.reference).white().bold(),
+            best.score * 100.0
+        );
+    }
+
+    println!("\n{}", style("✓ Complete").green().bold());
+    Ok(())
+}
+
 /// Run the report command
-fn run_report(input: &PathBuf, html: &PathBuf, artifacts: Option<&std::path::Path>) -> Result<()> {
+fn run_report(
+    input: &Path,
+    html: &Path,
+    artifacts: Option<&Path>,
+    theme: ReportTheme,
+    max_rows: usize,
+) -> Result<()> {
     println!("{}", style("CompareIt Report Generator").cyan().bold());
     println!("{}", style("═".repeat(60)).dim());
 
@@ -331,7 +1517,7 @@ fn run_report(input: &PathBuf, html: &PathBuf, artifacts: Option<&std::path::Pat
     let summary = calculate_summary(&results, 0, 0, None);
 
     println!("\nGenerating HTML report...");
-    generate_html_report(&results, &summary, html, artifacts)?;
+    generate_html_report(&results, &summary, html, artifacts, false, theme, max_rows)?;
 
     println!(
         "\n{} Report generated: {}",
@@ -341,6 +1527,50 @@ fn run_report(input: &PathBuf, html: &PathBuf, artifacts: Option<&std::path::Pat
     Ok(())
 }
 
+/// Run the three-way command and print how many changes each side made
+fn run_three_way(
+    base: &Path,
+    left: &Path,
+    right: &Path,
+    config: &CompareConfig,
+    html: &Path,
+    theme: ReportTheme,
+) -> Result<()> {
+    println!("{}", style("CompareIt Three-Way").cyan().bold());
+    println!("{}", style("═".repeat(60)).dim());
+
+    let options = IndexOptions::from_config(config);
+    let result = compare_three_way(
+        &index_single_file_with(base, &options)?,
+        &index_single_file_with(left, &options)?,
+        &index_single_file_with(right, &options)?,
+        config,
+    )?;
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+    table.set_header(vec![
+        Cell::new("Change").fg(Color::Cyan),
+        Cell::new("Count").fg(Color::Cyan),
+    ]);
+    table.add_row(vec![Cell::new("Unchanged records"), Cell::new(result.unchanged_records).fg(Color::Green)]);
+    table.add_row(vec![Cell::new("Changed in left"), Cell::new(result.changed_in_left.len())]);
+    table.add_row(vec![Cell::new("Changed in right"), Cell::new(result.changed_in_right.len())]);
+    table.add_row(vec![Cell::new("Changed in both"), Cell::new(result.changed_in_both.len())]);
+    table.add_row(vec![Cell::new("Conflicts"), Cell::new(result.conflicts.len()).fg(Color::Red)]);
+    println!("{table}");
+
+    generate_three_way_report(&result, html, theme)?;
+    println!("\n{} Report generated: {}", style("✓").green(), html.display());
+
+    if !result.is_clean() {
+        println!("{}", style("  Conflicting changes need a decision before merging").yellow());
+    }
+    Ok(())
+}
+
 /// Display summary statistics table
 fn display_summary_table(summary: &types::ComparisonSummary) {
     let mut table = Table::new();
@@ -387,6 +1617,13 @@ fn display_summary_table(summary: &types::ComparisonSummary) {
         Cell::new(summary.different_pairs).fg(Color::Yellow),
         Cell::new(different_status).fg(Color::Yellow),
     ]);
+    if summary.different_pairs > 0 {
+        table.add_row(vec![
+            Cell::new("  Content / Structure"),
+            Cell::new(format!("{} / {}", summary.content_diff_pairs, summary.structural_diff_pairs)).fg(Color::Yellow),
+            Cell::new("Values changed / shape changed").fg(Color::Yellow),
+        ]);
+    }
 
     // Errors - red if any
     let error_color = if summary.error_pairs > 0 { Color::Red } else { Color::White };
@@ -397,6 +1634,15 @@ fn display_summary_table(summary: &types::ComparisonSummary) {
         Cell::new(error_status).fg(error_color),
     ]);
 
+    // Files left without a partner - blue if any
+    if summary.only_in_set1 + summary.only_in_set2 > 0 {
+        table.add_row(vec![
+            Cell::new("Only in Path 1 / 2"),
+            Cell::new(format!("{} / {}", summary.only_in_set1, summary.only_in_set2)).fg(Color::Blue),
+            Cell::new("◐ Unmatched files").fg(Color::Blue),
+        ]);
+    }
+
     // Similarity scores with visual indicator
     let avg_sim_pct = summary.average_similarity * 100.0;
     let avg_color = if avg_sim_pct >= 90.0 {
@@ -413,6 +1659,21 @@ fn display_summary_table(summary: &types::ComparisonSummary) {
         Cell::new(avg_bar).fg(avg_color),
     ]);
 
+    let weighted_sim_pct = summary.weighted_average_similarity * 100.0;
+    let weighted_color = if weighted_sim_pct >= 90.0 {
+        Color::Green
+    } else if weighted_sim_pct >= 50.0 {
+        Color::Yellow
+    } else {
+        Color::Red
+    };
+    let weighted_bar = create_similarity_bar(summary.weighted_average_similarity);
+    table.add_row(vec![
+        Cell::new("Size-Weighted Sim."),
+        Cell::new(format!("{:.1}%", weighted_sim_pct)).fg(weighted_color),
+        Cell::new(weighted_bar).fg(weighted_color),
+    ]);
+
     let min_sim_pct = summary.min_similarity * 100.0;
     let min_color = if min_sim_pct >= 90.0 {
         Color::Green
@@ -444,10 +1705,12 @@ fn display_results_table(results: &[ComparisonResult], verbose: bool) {
     let mut identical: Vec<&ComparisonResult> = Vec::new();
     let mut modified: Vec<&ComparisonResult> = Vec::new();
     let mut errors: Vec<&ComparisonResult> = Vec::new();
+    let mut unmatched: Vec<&ComparisonResult> = Vec::new();
 
     for result in results {
         match result {
             ComparisonResult::Error { .. } => errors.push(result),
+            ComparisonResult::OnlyInOne(_) => unmatched.push(result),
             _ if result.is_identical() => identical.push(result),
             _ => modified.push(result),
         }
@@ -487,6 +1750,7 @@ fn display_results_table(results: &[ComparisonResult], verbose: bool) {
 
         // Always show field-level mismatches for structured files (this is the key enhancement!)
         display_field_mismatches(&modified, verbose);
+        display_column_rollup(results, verbose);
 
         // Show text file analysis (always show for text files with differences)
         display_diff_snippets(&modified);
@@ -502,6 +1766,17 @@ fn display_results_table(results: &[ComparisonResult], verbose: bool) {
         );
         display_error_list(&errors);
     }
+
+    // Display files without a counterpart
+    if !unmatched.is_empty() {
+        println!(
+            "\n{} {} {}",
+            style("◐").blue(),
+            style("Only in One Side").blue().bold(),
+            style(format!("({})", unmatched.len())).dim()
+        );
+        display_unmatched_list(&unmatched, if verbose { unmatched.len() } else { 10 });
+    }
 }
 
 /// Display a simple list of file pairs
@@ -566,6 +1841,19 @@ fn display_detailed_table(results: &[&ComparisonResult], verbose: bool) {
                 "0".to_string(),
                 "0".to_string(),
             ),
+            ComparisonResult::Binary { file1_size, file2_size, differing_bytes, .. } => (
+                "binary",
+                file1_size.max(file2_size).saturating_sub(*differing_bytes).to_string(),
+                "-".to_string(),
+                "-".to_string(),
+            ),
+            ComparisonResult::Permissions { file1_mode, file2_mode, .. } => (
+                "perms",
+                "-".to_string(),
+                file1_mode.map_or_else(|| "-".to_string(), |m| format!("{:04o}", m)),
+                file2_mode.map_or_else(|| "-".to_string(), |m| format!("{:04o}", m)),
+            ),
+            ComparisonResult::OnlyInOne(_) => ("unmatched", "-".to_string(), "-".to_string(), "-".to_string()),
             ComparisonResult::Error { .. } => ("error", "-".to_string(), "-".to_string(), "-".to_string()),
         };
 
@@ -671,6 +1959,10 @@ fn display_diff_snippets(results: &[&ComparisonResult]) {
         }
         println!("{stats_table}");
 
+        if let Some(rename) = &result.rename {
+            println!("  {} {}", style("ℹ").blue(), rename);
+        }
+
         // Show diff preview if available
         if !result.detailed_diff.is_empty() {
             println!();
@@ -960,17 +2252,66 @@ fn display_field_mismatches(results: &[&ComparisonResult], verbose: bool) {
     }
 }
 
+/// Display mismatches per column summed across all structured files
+///
+/// Only shown when some column mismatched in more than one file; a single
+/// file's columns are already listed by [`display_field_mismatches`].
+fn display_column_rollup(results: &[ComparisonResult], verbose: bool) {
+    let rollup = calculate_column_rollup(results);
+    if rollup.iter().all(|column| column.files_affected < 2) {
+        return;
+    }
+
+    println!("\n{}", style("Column Mismatches Across Files").cyan().bold());
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+    table.set_header(vec![
+        Cell::new("Column").fg(Color::Cyan),
+        Cell::new("Mismatched Records").fg(Color::Cyan),
+        Cell::new("Files Affected").fg(Color::Cyan),
+    ]);
+
+    let limit = if verbose { rollup.len() } else { 10 };
+    for column in rollup.iter().take(limit) {
+        table.add_row(vec![
+            Cell::new(&column.column_name),
+            Cell::new(column.mismatch_count).fg(Color::Yellow),
+            Cell::new(column.files_affected),
+        ]);
+    }
+
+    println!("{table}");
+
+    if rollup.len() > limit {
+        println!(
+            "  {} {} more columns. Use {} to see all.",
+            style("ℹ").blue(),
+            rollup.len() - limit,
+            style("--verbose").cyan()
+        );
+    }
+}
+
 /// Truncate a value for display, preserving meaning
+///
+/// Lengths count characters, so multibyte values are cut between characters.
 fn truncate_value(value: &str, max_len: usize) -> String {
     let trimmed = value.trim();
-    if trimmed.len() <= max_len {
+    let len = trimmed.chars().count();
+    if len <= max_len {
         trimmed.to_string()
     } else if max_len > 6 {
         // Show beginning and end for context
         let half = (max_len - 3) / 2;
-        format!("{}...{}", &trimmed[..half], &trimmed[trimmed.len() - half..])
+        let head: String = trimmed.chars().take(half).collect();
+        let tail: String = trimmed.chars().skip(len - half).collect();
+        format!("{}...{}", head, tail)
     } else {
-        format!("{}...", &trimmed[..max_len.saturating_sub(3)])
+        let head: String = trimmed.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", head)
     }
 }
 
@@ -989,11 +2330,118 @@ fn display_error_list(results: &[&ComparisonResult]) {
     }
 }
 
+/// Display files that exist in only one of the two paths
+fn display_unmatched_list(results: &[&ComparisonResult], limit: usize) {
+    for result in results.iter().take(limit) {
+        if let ComparisonResult::OnlyInOne(file) = result {
+            let side = match file.side {
+                FileSide::Set1 => "only in path 1",
+                FileSide::Set2 => "only in path 2",
+            };
+            println!(
+                "  {} {}",
+                style(truncate_path(&file.path, 50)).dim(),
+                style(format!("({})", side)).blue()
+            );
+        }
+    }
+    if results.len() > limit {
+        println!(
+            "  {} ({} more...)",
+            style("...").dim(),
+            results.len() - limit
+        );
+    }
+}
+
 /// Truncate a path for display
+///
+/// Lengths count characters, so non-ASCII names are cut between characters.
 fn truncate_path(path: &str, max_len: usize) -> String {
-    if path.len() <= max_len {
+    let len = path.chars().count();
+    if len <= max_len {
         path.to_string()
     } else {
-        format!("...{}", &path[path.len() - max_len + 3..])
+        let tail: String = path.chars().skip(len - max_len.saturating_sub(3)).collect();
+        format!("...{}", tail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation_cuts_between_multibyte_characters() {
+        // Byte-based cuts would land inside these characters
+        assert_eq!(truncate_value("数据数据数据数据数据数据", 9), "数据数...据数据");
+        assert_eq!(truncate_value("🦀🦀🦀🦀🦀", 4), "🦀...");
+        assert_eq!(truncate_value("ok ", 4), "ok");
+        assert_eq!(truncate_path("/data/報告/2025/売上.csv", 12), "...25/売上.csv");
+        assert_eq!(truncate_path("short", 12), "short");
+    }
+
+    /// Run `compare a b <args>` against the config file at `path`, as `main` does
+    fn config_with_file(path: &Path, args: &[&str]) -> CompareConfig {
+        let argv = ["CompareIt", "compare", "a", "b"].iter().chain(args);
+        let matches = Cli::command().get_matches_from(argv);
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        let explicit = explicit_config_fields(matches.subcommand_matches("compare").unwrap());
+        apply_overrides(load_config(path).unwrap(), &compare_invocation(cli.command).config, &explicit).unwrap()
+    }
+
+    #[test]
+    fn test_flags_override_file_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("compareit.json");
+        std::fs::write(
+            &path,
+            r#"{
+                // Team defaults
+                "key_columns": ["id"],
+                "numeric_tolerance": 0.01,
+                "normalization": {"ignore_case": true},
+                "positional": true,
+                "results_base": "team-results"
+            }"#,
+        )
+        .unwrap();
+
+        let defaults = default_compare_config();
+        let merged = config_with_file(&path, &["--numeric-tol", "0.5", "--ignore-trailing-ws"]);
+        assert_eq!(merged.numeric_tolerance, 0.5);
+        assert_eq!(merged.key_columns, vec!["id"]);
+        assert!(merged.normalization.ignore_case);
+        assert!(merged.normalization.ignore_trailing_ws);
+        assert!(merged.positional);
+        assert_eq!(merged.results_base, PathBuf::from("team-results"));
+        assert_eq!(merged.pairing, defaults.pairing);
+
+        // Flags win even when they repeat the default, and booleans can be turned off
+        let merged = config_with_file(&path, &["--results-base", "output", "--positional=false", "--ignore-case=false"]);
+        assert_eq!(merged.results_base, defaults.results_base);
+        assert!(!merged.positional);
+        assert!(!merged.normalization.ignore_case);
+        assert_eq!(merged.key_columns, vec!["id"]);
+    }
+
+    #[test]
+    fn test_every_compare_flag_maps_to_config_fields() {
+        let defaults = serde_json::to_value(default_compare_config()).unwrap();
+        let compare = Cli::command().find_subcommand("compare").unwrap().clone();
+        for arg in compare.get_arguments() {
+            for field in config_fields(arg.get_id().as_str()) {
+                let pointer = format!("/{}", field.replace('.', "/"));
+                assert!(defaults.pointer(&pointer).is_some(), "--{} sets unknown field {}", arg.get_id(), field);
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_defaults_match_cli_defaults() {
+        // A config file leaving a field out must behave like not passing its flag
+        let file = serde_json::to_value(CompareConfig::default()).unwrap();
+        let cli = serde_json::to_value(default_compare_config()).unwrap();
+        assert_eq!(file, cli);
     }
 }
@@ -11,41 +11,188 @@
 //! computing expensive similarity metrics. The rules check:
 //!
 //! 1. **Extension Compatibility**: Files must have compatible extensions
-//!    (e.g., `.csv` can match `.tsv`, `.rs` can match `.py`)
-//! 2. **Size Ratio**: File sizes must be within 0.1x to 10x of each other
+//!    (e.g., `.csv` can match `.tsv`, `.rs` can match `.py`), extended by the
+//!    configured `extension_groups` or switched off with `allow_any_extension`
+//! 2. **Size Ratio**: File sizes must be within `size_ratio_min` to
+//!    `size_ratio_max` of each other (0.1x to 10x by default)
 //! 3. **File Type Compatibility**: Binary files can only match other binary files
+//!
+//! Rejections are counted per rule in [`BlockingStats`] so a run can explain
+//! why it produced few candidates. [`preflight_warnings`] spots the same
+//! problems up front, before any fingerprinting work is done.
 
 use crate::fingerprint::simhash_similarity;
-use crate::types::{CandidatePair, CompareConfig, FileEntry, FileType, PairingStrategy};
-use std::collections::{HashMap, HashSet};
+use crate::types::{
+    BlockingStats, CandidatePair, CompareConfig, FileEntry, FileSide, FileType, PairingStrategy, UnmatchedFile,
+};
+use log::info;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+/// Settings for the blocking rules, taken from a [`CompareConfig`]
+#[derive(Debug, Clone, Copy)]
+pub struct BlockingRules<'a> {
+    /// Allowed `(min, max)` of `file1.size / file2.size`; an infinite max
+    /// disables the size check
+    pub size_ratio: (f64, f64),
+    /// Extension groups treated as compatible on top of the built-in ones
+    pub extension_groups: &'a [Vec<String>],
+    /// Skip the extension check entirely
+    pub allow_any_extension: bool,
+}
+
+impl<'a> BlockingRules<'a> {
+    pub fn from_config(config: &'a CompareConfig) -> Self {
+        Self {
+            size_ratio: (config.size_ratio_min, config.size_ratio_max),
+            extension_groups: &config.extension_groups,
+            allow_any_extension: config.allow_any_extension,
+        }
+    }
+
+    /// Check if two extensions may be paired
+    ///
+    /// Same extensions always match. Otherwise both must be in one of the
+    /// built-in groups or one of `extension_groups` (matched ignoring case and
+    /// a leading dot).
+    fn extensions_compatible(&self, ext1: &str, ext2: &str) -> bool {
+        if self.allow_any_extension || extensions_compatible(ext1, ext2) {
+            return true;
+        }
+        let in_group = |group: &[String], ext: &str| {
+            group.iter().any(|g| g.trim_start_matches('.').eq_ignore_ascii_case(ext))
+        };
+        self.extension_groups
+            .iter()
+            .any(|group| in_group(group, ext1) && in_group(group, ext2))
+    }
+}
 
 /// Generate candidate pairs for comparison
+///
+/// `root1` and `root2` are the paths the two file sets were indexed from;
+/// [`PairingStrategy::SamePath`] matches files by their path relative to them.
+/// Blocking-rule rejections are added to `stats`.
 pub fn generate_candidates(
     files1: &[FileEntry],
     files2: &[FileEntry],
+    root1: &Path,
+    root2: &Path,
     config: &CompareConfig,
+    stats: &mut BlockingStats,
 ) -> Vec<CandidatePair> {
     match config.pairing {
-        PairingStrategy::SamePath => match_by_path(files1, files2),
-        PairingStrategy::SameName => match_by_name(files1, files2),
-        PairingStrategy::AllVsAll => {
-            all_vs_all_match(files1, files2, config.top_k, config.max_pairs)
+        PairingStrategy::SamePath => match_by_path(files1, files2, root1, root2),
+        // A permissions audit has no fingerprints to rank or match pairs by
+        PairingStrategy::AllVsAll | PairingStrategy::ByContentHash if config.compare_permissions_only => {
+            match_by_path(files1, files2, root1, root2)
         }
+        PairingStrategy::SameName => match_by_name(files1, files2),
+        PairingStrategy::AllVsAll => all_vs_all_match(files1, files2, config, stats),
+        PairingStrategy::ByContentHash => find_exact_hash_matches(files1, files2),
     }
 }
 
+/// Files from either set that appear in no candidate pair
+///
+/// Returned in index order, set 1 first.
+pub fn find_unmatched(
+    files1: &[FileEntry],
+    files2: &[FileEntry],
+    pairs: &[CandidatePair],
+) -> Vec<UnmatchedFile> {
+    let paired1: HashSet<&Path> = pairs.iter().map(|p| p.file1.path.as_path()).collect();
+    let paired2: HashSet<&Path> = pairs.iter().map(|p| p.file2.path.as_path()).collect();
+
+    let unmatched = |files: &[FileEntry], paired: &HashSet<&Path>, side: FileSide| -> Vec<UnmatchedFile> {
+        files
+            .iter()
+            .filter(|f| !paired.contains(f.path.as_path()))
+            .map(|f| UnmatchedFile {
+                path: f.path.display().to_string(),
+                side,
+                size: f.size,
+                file_type: f.file_type,
+            })
+            .collect()
+    };
+
+    let mut result = unmatched(files1, &paired1, FileSide::Set1);
+    result.extend(unmatched(files2, &paired2, FileSide::Set2));
+    result
+}
+
+/// Find the best matches for a single file within a set of files
+///
+/// Used when one comparison path is a file and the other a directory. Every
+/// file in `set` that passes the blocking rules is ranked by estimated
+/// similarity and the top-K are returned, best first. `target_is_first`
+/// controls which side of each pair the target file lands on so results
+/// keep the user's path order. Blocking-rule rejections are added to `stats`.
+pub fn locate_file_in_set(
+    target: &FileEntry,
+    set: &[FileEntry],
+    top_k: usize,
+    target_is_first: bool,
+    rules: &BlockingRules,
+    stats: &mut BlockingStats,
+) -> Vec<CandidatePair> {
+    let mut candidates: Vec<(&FileEntry, f64)> = set
+        .iter()
+        .filter(|f| match target_is_first {
+            true => passes_blocking_rules(target, f, rules, stats),
+            false => passes_blocking_rules(f, target, rules, stats),
+        })
+        .map(|f| (f, estimate_similarity(target, f)))
+        .collect();
+
+    // Sort by similarity (descending), path as tie-breaker for stable output
+    candidates.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.path.cmp(&b.0.path))
+    });
+
+    candidates
+        .into_iter()
+        .take(top_k)
+        .map(|(other, sim)| {
+            let (file1, file2) = if target_is_first {
+                (target.clone(), other.clone())
+            } else {
+                (other.clone(), target.clone())
+            };
+            CandidatePair {
+                exact_hash_match: !file1.content_hash.is_empty()
+                    && file1.content_hash == file2.content_hash,
+                file1,
+                file2,
+                estimated_similarity: sim,
+            }
+        })
+        .collect()
+}
+
 /// Match files by same relative path
-fn match_by_path(files1: &[FileEntry], files2: &[FileEntry]) -> Vec<CandidatePair> {
+///
+/// `dirA/sub/x.txt` pairs with `dirB/sub/x.txt` when the roots are `dirA` and
+/// `dirB`. Files with no counterpart on the other side are logged and skipped.
+fn match_by_path(
+    files1: &[FileEntry],
+    files2: &[FileEntry],
+    root1: &Path,
+    root2: &Path,
+) -> Vec<CandidatePair> {
     // Build lookup by path (relative to root)
-    let map2: HashMap<&std::path::Path, &FileEntry> = files2
+    let map2: HashMap<&Path, &FileEntry> = files2
         .iter()
-        .map(|f| (f.path.as_path(), f))
+        .map(|f| (relative_path(&f.path, root2), f))
         .collect();
 
-    files1
+    let pairs: Vec<CandidatePair> = files1
         .iter()
         .filter_map(|f1| {
-            map2.get(f1.path.as_path()).map(|f2| CandidatePair {
+            map2.get(relative_path(&f1.path, root1)).map(|f2| CandidatePair {
                 file1: f1.clone(),
                 file2: (*f2).clone(),
                 estimated_similarity: estimate_similarity(f1, f2),
@@ -53,7 +200,32 @@ fn match_by_path(files1: &[FileEntry], files2: &[FileEntry]) -> Vec<CandidatePai
                     && f1.content_hash == f2.content_hash,
             })
         })
-        .collect()
+        .collect();
+
+    let unmatched1 = files1.len() - pairs.len();
+    let unmatched2 = files2.len() - pairs.len();
+    if unmatched1 > 0 || unmatched2 > 0 {
+        info!(
+            "Same-path pairing left {} file(s) only in {} and {} only in {}",
+            unmatched1,
+            root1.display(),
+            unmatched2,
+            root2.display()
+        );
+    }
+
+    pairs
+}
+
+/// Path of `path` relative to the `root` it was indexed from
+///
+/// When the root is the file itself, the file name is used so a single file
+/// still lines up with a top-level file of the same name in a directory.
+pub fn relative_path<'a>(path: &'a Path, root: &Path) -> &'a Path {
+    match path.strip_prefix(root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => rel,
+        _ => path.file_name().map(Path::new).unwrap_or(path),
+    }
 }
 
 /// Match files by same filename (ignoring directory structure)
@@ -101,12 +273,15 @@ fn match_by_name(files1: &[FileEntry], files2: &[FileEntry]) -> Vec<CandidatePai
 /// 2. **Similarity matches**: Remaining files are matched using fingerprint similarity
 ///
 /// The function applies blocking rules to prune unlikely pairs and uses Top-K
-/// selection to limit the number of candidates per file.
+/// selection (`config.top_k`) to limit the number of candidates per file.
+/// Candidates estimated below `config.min_candidate_similarity` are dropped
+/// first; `config.max_pairs_per_file` further caps each file's similarity
+/// candidates before `config.max_pairs` truncates the combined list.
 fn all_vs_all_match(
     files1: &[FileEntry],
     files2: &[FileEntry],
-    top_k: usize,
-    max_pairs: Option<usize>,
+    config: &CompareConfig,
+    stats: &mut BlockingStats,
 ) -> Vec<CandidatePair> {
     let mut all_pairs = Vec::new();
     let mut matched_in_set1: HashSet<std::path::PathBuf> = HashSet::new();
@@ -131,7 +306,15 @@ fn all_vs_all_match(
         .filter(|f| !matched_in_set2.contains(&f.path))
         .collect();
 
-    let similarity_matches = find_similarity_matches(&unmatched1, &unmatched2, top_k);
+    let similarity_matches = find_similarity_matches(
+        &unmatched1,
+        &unmatched2,
+        config.top_k,
+        config.max_pairs_per_file,
+        config.min_candidate_similarity,
+        &BlockingRules::from_config(config),
+        stats,
+    );
     all_pairs.extend(similarity_matches);
 
     // Sort all pairs by estimated similarity (descending) for deterministic ordering
@@ -142,7 +325,7 @@ fn all_vs_all_match(
     });
 
     // Apply max_pairs cap
-    if let Some(max) = max_pairs {
+    if let Some(max) = config.max_pairs {
         all_pairs.truncate(max);
     }
 
@@ -196,26 +379,38 @@ fn find_exact_hash_matches(files1: &[FileEntry], files2: &[FileEntry]) -> Vec<Ca
 /// Find similarity-based matches for files that didn't have exact hash matches
 ///
 /// For each file in set1, finds the top-k most similar files in set2 based on
-/// fingerprint similarity. Blocking rules are applied to prune unlikely pairs.
+/// fingerprint similarity. Blocking rules are applied to prune unlikely pairs,
+/// and candidates estimated below `min_similarity` are dropped before top-k
+/// selection. No file in set1 contributes more than `max_pairs_per_file` pairs.
 fn find_similarity_matches(
     files1: &[&FileEntry],
     files2: &[&FileEntry],
     top_k: usize,
+    max_pairs_per_file: Option<usize>,
+    min_similarity: f64,
+    rules: &BlockingRules,
+    stats: &mut BlockingStats,
 ) -> Vec<CandidatePair> {
     let mut pairs = Vec::new();
+    let per_file = max_pairs_per_file.map_or(top_k, |cap| cap.min(top_k));
 
     for f1 in files1 {
         let mut candidates: Vec<(&FileEntry, f64)> = files2
             .iter()
-            .filter(|f2| passes_blocking_rules(f1, f2))
+            .filter(|f2| passes_blocking_rules(f1, f2, rules, stats))
             .map(|f2| (*f2, estimate_similarity(f1, f2)))
             .collect();
 
+        // Too dissimilar to be a real counterpart
+        let before = candidates.len();
+        candidates.retain(|(_, sim)| *sim >= min_similarity);
+        stats.rejected_low_similarity += before - candidates.len();
+
         // Sort by similarity (descending)
         candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
-        // Take top-k
-        for (f2, sim) in candidates.into_iter().take(top_k) {
+        // Take top-k, within the per-file cap
+        for (f2, sim) in candidates.into_iter().take(per_file) {
             pairs.push(CandidatePair {
                 file1: (*f1).clone(),
                 file2: f2.clone(),
@@ -229,16 +424,23 @@ fn find_similarity_matches(
 }
 
 /// Check if two files pass blocking rules for candidate consideration
-fn passes_blocking_rules(f1: &FileEntry, f2: &FileEntry) -> bool {
+///
+/// A rejected pair is counted in `stats` against the rule that rejected it.
+fn passes_blocking_rules(f1: &FileEntry, f2: &FileEntry, rules: &BlockingRules, stats: &mut BlockingStats) -> bool {
+    stats.pairs_checked += 1;
+
     // Rule 1: Same or compatible extension
-    if !extensions_compatible(&f1.extension, &f2.extension) {
+    if !rules.extensions_compatible(&f1.extension, &f2.extension) {
+        stats.rejected_extension += 1;
         return false;
     }
 
-    // Rule 2: Size ratio within threshold (0.1x to 10x)
-    if f1.size > 0 && f2.size > 0 {
+    // Rule 2: Size ratio within threshold (0.1x to 10x by default)
+    let (min_ratio, max_ratio) = rules.size_ratio;
+    if f1.size > 0 && f2.size > 0 && max_ratio.is_finite() {
         let ratio = f1.size as f64 / f2.size as f64;
-        if ratio < 0.1 || ratio > 10.0 {
+        if !(min_ratio..=max_ratio).contains(&ratio) {
+            stats.rejected_size_ratio += 1;
             return false;
         }
     }
@@ -257,11 +459,87 @@ fn passes_blocking_rules(f1: &FileEntry, f2: &FileEntry) -> bool {
     // Rule 4: Compatible file types
     match (&f1.file_type, &f2.file_type) {
         (FileType::Binary, FileType::Binary) => true,
-        (FileType::Binary, _) | (_, FileType::Binary) => false,
+        (FileType::Binary, _) | (_, FileType::Binary) => {
+            stats.rejected_file_type += 1;
+            false
+        }
         _ => true,
     }
 }
 
+/// File count ratio beyond which most files are bound to stay unmatched
+const PREFLIGHT_COUNT_RATIO: usize = 10;
+
+/// Warn about inputs that are unlikely to produce useful comparisons
+///
+/// Checks, from the indexed files alone, for two sides with no compatible
+/// extensions under `rules`, wildly different file counts (when
+/// `compare_counts` is set), and structured files that share no column across
+/// the two sides.
+pub fn preflight_warnings(
+    files1: &[FileEntry],
+    files2: &[FileEntry],
+    rules: &BlockingRules,
+    compare_counts: bool,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if files1.is_empty() || files2.is_empty() {
+        return warnings;
+    }
+
+    let extensions = |files: &[FileEntry]| -> BTreeSet<String> {
+        files.iter().map(|f| f.extension.clone()).collect()
+    };
+    let (exts1, exts2) = (extensions(files1), extensions(files2));
+    if !exts1.iter().any(|e1| exts2.iter().any(|e2| rules.extensions_compatible(e1, e2))) {
+        warnings.push(format!(
+            "path1 has only {} files, path2 has only {} files \u{2014} 0 candidate pairs likely",
+            describe_extensions(&exts1),
+            describe_extensions(&exts2)
+        ));
+    }
+
+    let (count1, count2) = (files1.len(), files2.len());
+    if compare_counts && count1.max(count2) >= PREFLIGHT_COUNT_RATIO * count1.min(count2) {
+        warnings.push(format!(
+            "path1 has {} files but path2 has {} \u{2014} most files will be left unmatched",
+            count1, count2
+        ));
+    }
+
+    let columns = |files: &[FileEntry]| -> HashSet<String> {
+        files
+            .iter()
+            .filter(|f| f.file_type.is_structured())
+            .filter_map(|f| f.columns.as_ref())
+            .flatten()
+            .cloned()
+            .collect()
+    };
+    let (columns1, columns2) = (columns(files1), columns(files2));
+    if !columns1.is_empty() && !columns2.is_empty() && columns1.is_disjoint(&columns2) {
+        warnings.push(
+            "no structured file in path1 shares a column with any in path2 \u{2014} schemas look incompatible".to_string(),
+        );
+    }
+
+    warnings
+}
+
+/// Human-readable list of extensions, e.g. `.csv` or `.csv, .tsv`
+fn describe_extensions(extensions: &BTreeSet<String>) -> String {
+    const SHOWN: usize = 4;
+    let mut names: Vec<String> = extensions
+        .iter()
+        .take(SHOWN)
+        .map(|e| if e.is_empty() { "extensionless".to_string() } else { format!(".{}", e) })
+        .collect();
+    if extensions.len() > SHOWN {
+        names.push(format!("{} other", extensions.len() - SHOWN));
+    }
+    names.join(", ")
+}
+
 /// Check if two extensions are compatible for comparison
 fn extensions_compatible(ext1: &str, ext2: &str) -> bool {
     if ext1 == ext2 {
@@ -272,7 +550,7 @@ fn extensions_compatible(ext1: &str, ext2: &str) -> bool {
     let text_exts = ["txt", "log", "md", "rst", ""];
     let csv_exts = ["csv", "tsv", "tab", "xlsx", "xls", "xlsm", "xlsb", "ods"];
     let code_exts = ["rs", "py", "js", "ts", "java", "c", "cpp", "h", "hpp", "go"];
-    let config_exts = ["json", "yaml", "yml", "toml", "ini", "cfg"];
+    let config_exts = ["json", "jsonl", "ndjson", "yaml", "yml", "toml", "ini", "cfg"];
 
     let in_same_group = |e1: &str, e2: &str, group: &[&str]| {
         group.contains(&e1) && group.contains(&e2)
@@ -315,8 +593,12 @@ fn estimate_similarity(f1: &FileEntry, f2: &FileEntry) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::{index_path, IndexOptions};
     use std::path::PathBuf;
 
+    const DEFAULT_RULES: BlockingRules<'static> =
+        BlockingRules { size_ratio: (0.1, 10.0), extension_groups: &[], allow_any_extension: false };
+
     fn make_file_entry(path: &str, hash: &str, size: u64, ext: &str, file_type: FileType) -> FileEntry {
         FileEntry {
             path: PathBuf::from(path),
@@ -328,7 +610,168 @@ mod tests {
             schema_signature: None,
             line_count: 10,
             columns: None,
+            encoding: None,
+            mode: None,
+            mtime: None,
+            link_target: None,
+            dialect: None,
+        }
+    }
+
+    #[test]
+    fn test_locate_file_in_set_ranks_best_first() {
+        let mut target = make_file_entry("report.txt", "h0", 100, "txt", FileType::Text);
+        target.simhash = Some(0);
+        let mut near = make_file_entry("dir/near.txt", "h1", 100, "txt", FileType::Text);
+        near.simhash = Some(0b1);
+        let mut far = make_file_entry("dir/far.txt", "h2", 100, "txt", FileType::Text);
+        far.simhash = Some(u64::MAX);
+        let image = make_file_entry("dir/image.png", "h3", 100, "png", FileType::Binary);
+        let set = vec![far, image, near];
+
+        let pairs = locate_file_in_set(&target, &set, 5, true, &DEFAULT_RULES, &mut BlockingStats::default());
+
+        assert_eq!(pairs.len(), 2); // the binary file is blocked
+        assert_eq!(pairs[0].file2.path, PathBuf::from("dir/near.txt"));
+        assert_eq!(pairs[0].file1.path, PathBuf::from("report.txt"));
+
+        let reversed = locate_file_in_set(&target, &set, 1, false, &DEFAULT_RULES, &mut BlockingStats::default());
+        assert_eq!(reversed.len(), 1);
+        assert_eq!(reversed[0].file1.path, PathBuf::from("dir/near.txt"));
+        assert_eq!(reversed[0].file2.path, PathBuf::from("report.txt"));
+    }
+
+    #[test]
+    fn test_same_path_matches_relative_to_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let root1 = dir.path().join("dirA");
+        let root2 = dir.path().join("dirB");
+        for (root, files) in [(&root1, ["sub/x.txt", "top.txt", "only_a.txt"]), (&root2, ["sub/x.txt", "top.txt", "only_b.txt"])] {
+            for file in files {
+                let path = root.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, format!("{}\n", file)).unwrap();
+            }
+        }
+        // Same file name in a different folder must not pair
+        std::fs::write(root2.join("sub").join("top.txt"), "top.txt\n").unwrap();
+
+        let files1 = index_path(&root1, &IndexOptions::default()).unwrap();
+        let files2 = index_path(&root2, &IndexOptions::default()).unwrap();
+        let config = CompareConfig {
+            pairing: PairingStrategy::SamePath,
+            ..CompareConfig::default()
+        };
+        let mut pairs: Vec<(PathBuf, PathBuf)> = generate_candidates(&files1, &files2, &root1, &root2, &config, &mut BlockingStats::default())
+            .into_iter()
+            .map(|p| (p.file1.path, p.file2.path))
+            .collect();
+        pairs.sort();
+
+        assert_eq!(
+            pairs,
+            vec![
+                (root1.join("sub").join("x.txt"), root2.join("sub").join("x.txt")),
+                (root1.join("top.txt"), root2.join("top.txt")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_by_content_hash_pairs_renamed_files() {
+        let files1 = vec![
+            make_file_entry("old/report.txt", "h1", 100, "txt", FileType::Text),
+            make_file_entry("old/notes.txt", "h2", 100, "txt", FileType::Text),
+        ];
+        let files2 = vec![
+            make_file_entry("new/report-final.txt", "h1", 100, "txt", FileType::Text),
+            make_file_entry("new/notes.txt", "h3", 100, "txt", FileType::Text),
+        ];
+        let (root1, root2) = (Path::new("old"), Path::new("new"));
+        let pairs_with = |pairing| {
+            let config = CompareConfig {
+                pairing,
+                ..CompareConfig::default()
+            };
+            generate_candidates(&files1, &files2, root1, root2, &config, &mut BlockingStats::default())
+        };
+
+        let by_hash = pairs_with(PairingStrategy::ByContentHash);
+        assert_eq!(by_hash.len(), 1);
+        assert_eq!(by_hash[0].file1.path, PathBuf::from("old/report.txt"));
+        assert_eq!(by_hash[0].file2.path, PathBuf::from("new/report-final.txt"));
+        assert!(by_hash[0].exact_hash_match);
+        let unmatched = find_unmatched(&files1, &files2, &by_hash);
+        assert_eq!(unmatched.iter().map(|u| u.path.as_str()).collect::<Vec<_>>(), vec!["old/notes.txt", "new/notes.txt"]);
+
+        let by_path = pairs_with(PairingStrategy::SamePath);
+        assert_eq!(by_path.len(), 1);
+        assert_eq!(by_path[0].file1.path, PathBuf::from("old/notes.txt"));
+    }
+
+    #[test]
+    fn test_relative_path_of_file_root_is_its_name() {
+        let root = Path::new("data/report.csv");
+        assert_eq!(relative_path(root, root), Path::new("report.csv"));
+        assert_eq!(relative_path(Path::new("data/sub/a.csv"), Path::new("data")), Path::new("sub/a.csv"));
+    }
+
+    #[test]
+    fn test_find_unmatched_lists_files_from_both_sides() {
+        let files1 = vec![
+            make_file_entry("a/shared.txt", "h1", 10, "txt", FileType::Text),
+            make_file_entry("a/gone.txt", "h2", 20, "txt", FileType::Text),
+        ];
+        let files2 = vec![
+            make_file_entry("b/shared.txt", "h1", 10, "txt", FileType::Text),
+            make_file_entry("b/new.csv", "h3", 30, "csv", FileType::Csv),
+        ];
+        let pairs = find_exact_hash_matches(&files1, &files2);
+
+        let unmatched = find_unmatched(&files1, &files2, &pairs);
+        assert_eq!(unmatched.len(), 2);
+        assert_eq!((unmatched[0].path.as_str(), unmatched[0].side, unmatched[0].size), ("a/gone.txt", FileSide::Set1, 20));
+        assert_eq!((unmatched[1].path.as_str(), unmatched[1].side), ("b/new.csv", FileSide::Set2));
+        assert_eq!(unmatched[1].file_type, FileType::Csv);
+    }
+
+    #[test]
+    fn test_preflight_warns_about_disjoint_extensions() {
+        let csvs: Vec<FileEntry> = (0..3)
+            .map(|i| make_file_entry(&format!("a/{}.csv", i), "h", 100, "csv", FileType::Csv))
+            .collect();
+        let jsons = vec![make_file_entry("b/0.json", "h", 100, "json", FileType::Json)];
+
+        let warnings = preflight_warnings(&csvs, &jsons, &DEFAULT_RULES, true);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0],
+            "path1 has only .csv files, path2 has only .json files \u{2014} 0 candidate pairs likely"
+        );
+
+        // Compatible extensions and comparable counts pass cleanly
+        let tsvs = vec![make_file_entry("b/0.tsv", "h", 100, "tsv", FileType::Tsv)];
+        assert!(preflight_warnings(&csvs, &tsvs, &DEFAULT_RULES, true).is_empty());
+    }
+
+    #[test]
+    fn test_preflight_warns_about_counts_and_schemas() {
+        let mut many: Vec<FileEntry> = (0..20)
+            .map(|i| make_file_entry(&format!("a/{}.csv", i), "h", 100, "csv", FileType::Csv))
+            .collect();
+        let mut few = vec![make_file_entry("b/0.csv", "h", 100, "csv", FileType::Csv)];
+        for f in &mut many {
+            f.columns = Some(vec!["id".to_string(), "name".to_string()]);
         }
+        few[0].columns = Some(vec!["sku".to_string(), "price".to_string()]);
+
+        let warnings = preflight_warnings(&many, &few, &DEFAULT_RULES, true);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("path1 has 20 files but path2 has 1"));
+        assert!(warnings[1].contains("schemas look incompatible"));
+
+        // Searching a folder for one file expects a lopsided count
+        assert_eq!(preflight_warnings(&many, &few, &DEFAULT_RULES, false).len(), 1);
     }
 
     #[test]
@@ -382,7 +825,7 @@ mod tests {
         let f1 = make_file_entry("a.txt", "h1", 100, "txt", FileType::Text);
         let f2 = make_file_entry("b.txt", "h2", 100, "txt", FileType::Text);
 
-        assert!(passes_blocking_rules(&f1, &f2));
+        assert!(passes_blocking_rules(&f1, &f2, &DEFAULT_RULES, &mut BlockingStats::default()));
     }
 
     #[test]
@@ -390,7 +833,31 @@ mod tests {
         let f1 = make_file_entry("a.txt", "h1", 100, "txt", FileType::Text);
         let f2 = make_file_entry("b.txt", "h2", 10000, "txt", FileType::Text); // 100x larger
 
-        assert!(!passes_blocking_rules(&f1, &f2));
+        assert!(!passes_blocking_rules(&f1, &f2, &DEFAULT_RULES, &mut BlockingStats::default()));
+    }
+
+    #[test]
+    fn test_size_ratio_max_is_configurable() {
+        let small = make_file_entry("app.log", "h1", 100, "log", FileType::Text);
+        let grown = make_file_entry("app.log", "h2", 2000, "log", FileType::Text); // 20x larger
+
+        assert!(!passes_blocking_rules(&small, &grown, &DEFAULT_RULES, &mut BlockingStats::default()));
+        assert!(passes_blocking_rules(&small, &grown, &BlockingRules { size_ratio: (1.0 / 30.0, 30.0), ..DEFAULT_RULES }, &mut BlockingStats::default()));
+        assert!(passes_blocking_rules(&grown, &small, &BlockingRules { size_ratio: (1.0 / 30.0, 30.0), ..DEFAULT_RULES }, &mut BlockingStats::default()));
+        assert!(passes_blocking_rules(&small, &grown, &BlockingRules { size_ratio: (0.1, f64::INFINITY), ..DEFAULT_RULES }, &mut BlockingStats::default()));
+    }
+
+    #[test]
+    fn test_extension_groups_extend_compatibility() {
+        let kotlin = make_file_entry("Main.kt", "h1", 100, "kt", FileType::Text);
+        let java = make_file_entry("Main.java", "h2", 100, "java", FileType::Text);
+        let groups = vec![vec!["kt".to_string(), ".JAVA".to_string()]];
+
+        assert!(!passes_blocking_rules(&kotlin, &java, &DEFAULT_RULES, &mut BlockingStats::default()));
+        let grouped = BlockingRules { extension_groups: &groups, ..DEFAULT_RULES };
+        assert!(passes_blocking_rules(&kotlin, &java, &grouped, &mut BlockingStats::default()));
+        let any = BlockingRules { allow_any_extension: true, ..DEFAULT_RULES };
+        assert!(passes_blocking_rules(&kotlin, &java, &any, &mut BlockingStats::default()));
     }
 
     #[test]
@@ -398,7 +865,64 @@ mod tests {
         let f1 = make_file_entry("a.bin", "h1", 100, "bin", FileType::Binary);
         let f2 = make_file_entry("b.txt", "h2", 100, "txt", FileType::Text);
 
-        assert!(!passes_blocking_rules(&f1, &f2));
+        assert!(!passes_blocking_rules(&f1, &f2, &DEFAULT_RULES, &mut BlockingStats::default()));
+    }
+
+    #[test]
+    fn test_blocking_stats_count_each_rule() {
+        let target = make_file_entry("a.txt", "h0", 100, "txt", FileType::Text);
+        let set = vec![
+            make_file_entry("ok.txt", "h1", 100, "txt", FileType::Text),
+            make_file_entry("image.png", "h2", 100, "png", FileType::Binary), // extension
+            make_file_entry("data.csv", "h3", 100, "csv", FileType::Csv),     // extension
+            make_file_entry("huge.txt", "h4", 5000, "txt", FileType::Text),   // size ratio
+            make_file_entry("blob.txt", "h5", 100, "txt", FileType::Binary),  // file type
+        ];
+
+        let mut stats = BlockingStats::default();
+        let pairs = locate_file_in_set(&target, &set, 10, true, &DEFAULT_RULES, &mut stats);
+
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(
+            stats,
+            BlockingStats {
+                pairs_checked: 5,
+                rejected_extension: 2,
+                rejected_size_ratio: 1,
+                rejected_file_type: 1,
+                rejected_low_similarity: 0,
+            }
+        );
+        assert_eq!(stats.total_rejected(), 4);
+        assert_eq!(stats.top_rule(), Some(("extension", 2)));
+    }
+
+    #[test]
+    fn test_min_candidate_similarity_drops_distant_files() {
+        let mut target = make_file_entry("a.txt", "h1", 100, "txt", FileType::Text);
+        target.simhash = Some(0);
+        let mut near = make_file_entry("near.txt", "h2", 100, "txt", FileType::Text);
+        near.simhash = Some(0b111); // 3 bits apart
+        let mut far = make_file_entry("far.txt", "h3", 100, "txt", FileType::Text);
+        far.simhash = Some(u64::MAX << 32); // 32 bits apart
+        let files1 = vec![&target];
+        let files2 = vec![&near, &far];
+
+        let all = find_similarity_matches(&files1, &files2, 3, None, 0.0, &DEFAULT_RULES, &mut BlockingStats::default());
+        assert_eq!(all.len(), 2);
+
+        let mut stats = BlockingStats::default();
+        let kept = find_similarity_matches(&files1, &files2, 3, None, 0.7, &DEFAULT_RULES, &mut stats);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].file2.path, PathBuf::from("near.txt"));
+        assert_eq!(stats.rejected_low_similarity, 1);
+        assert_eq!(stats.top_rule(), Some(("similarity", 1)));
+
+        // A tie goes to the rule listed first, whatever the counts' order
+        stats.rejected_extension = 1;
+        assert_eq!(stats.top_rule(), Some(("extension", 1)));
+        stats.rejected_file_type = 1;
+        assert_eq!(stats.top_rule(), Some(("extension", 1)));
     }
 
     #[test]
@@ -411,9 +935,32 @@ mod tests {
         let files1: Vec<&FileEntry> = vec![&f1];
         let files2: Vec<&FileEntry> = vec![&f2, &f3, &f4];
 
-        let matches = find_similarity_matches(&files1, &files2, 2);
+        let matches = find_similarity_matches(&files1, &files2, 2, None, 0.0, &DEFAULT_RULES, &mut BlockingStats::default());
 
         // Should return at most top_k matches per file in files1
         assert_eq!(matches.len(), 2);
     }
+
+    #[test]
+    fn test_max_pairs_per_file_caps_each_file() {
+        let files1: Vec<FileEntry> = (0..3)
+            .map(|i| make_file_entry(&format!("a{}.txt", i), &format!("a{}", i), 100, "txt", FileType::Text))
+            .collect();
+        let files2: Vec<FileEntry> = (0..5)
+            .map(|i| make_file_entry(&format!("b{}.txt", i), &format!("b{}", i), 100, "txt", FileType::Text))
+            .collect();
+        let refs1: Vec<&FileEntry> = files1.iter().collect();
+        let refs2: Vec<&FileEntry> = files2.iter().collect();
+
+        let matches = find_similarity_matches(&refs1, &refs2, 4, Some(2), 0.0, &DEFAULT_RULES, &mut BlockingStats::default());
+
+        for f1 in &files1 {
+            let count = matches.iter().filter(|p| p.file1.path == f1.path).count();
+            assert_eq!(count, 2);
+        }
+
+        // The cap never raises the number of candidates above top-k
+        let matches = find_similarity_matches(&refs1, &refs2, 1, Some(3), 0.0, &DEFAULT_RULES, &mut BlockingStats::default());
+        assert_eq!(matches.len(), 3);
+    }
 }
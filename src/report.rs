@@ -5,21 +5,34 @@
 //! - Dashboard with pie chart visualization
 //! - Sortable results table
 //! - Embedded side-by-side diff viewer
-//! - Structured data mismatch highlights
+//! - Structured data mismatch highlights and a per-column roll-up
+//! - Optional standalone diff pages per text pair
 
-use crate::types::{ComparisonResult, ComparisonSummary};
+use crate::export::calculate_column_rollup;
+use crate::types::{
+    ColumnProfile, ColumnRollup, ComparisonResult, ComparisonSummary, FileSide, ReportTheme, TextComparisonResult,
+    ThreeWayChange, ThreeWayResult,
+};
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::Path;
 
 /// Generate an HTML report from comparison results
+///
+/// With `split_diffs`, text diffs are not embedded; the table links to the
+/// pages written by [`write_html_diffs`] under `artifacts_dir` instead. The
+/// results table shows `max_rows` rows per page, and only the first page is
+/// in the markup, so huge runs still open quickly.
 pub fn generate_html_report(
     results: &[ComparisonResult],
     summary: &ComparisonSummary,
     output_path: &Path,
     artifacts_dir: Option<&Path>,
+    split_diffs: bool,
+    theme: ReportTheme,
+    max_rows: usize,
 ) -> Result<()> {
-    let html = build_html_report(results, summary, artifacts_dir);
+    let html = render_html_report(results, summary, artifacts_dir, split_diffs, theme, max_rows);
 
     fs::write(output_path, html)
         .with_context(|| format!("Failed to write HTML report to {}", output_path.display()))?;
@@ -27,16 +40,23 @@ pub fn generate_html_report(
     Ok(())
 }
 
-/// Build the HTML report content
-fn build_html_report(
+/// Render the HTML report to a string
+///
+/// This is what [`generate_html_report`] writes to disk; embedders serving
+/// reports over HTTP can call it directly. `artifacts_dir` only affects the
+/// links in the results table and may be `None`.
+pub fn render_html_report(
     results: &[ComparisonResult],
     summary: &ComparisonSummary,
     artifacts_dir: Option<&Path>,
+    split_diffs: bool,
+    theme: ReportTheme,
+    max_rows: usize,
 ) -> String {
     let mut html = String::new();
 
     // HTML header with embedded CSS and JS
-    html.push_str(&build_html_head());
+    html.push_str(&build_html_head(theme));
 
     // Body start
     html.push_str(r#"
@@ -48,23 +68,38 @@ fn build_html_report(
         </header>
 "#);
 
+    if summary.cancelled {
+        html.push_str(r#"
+        <p class="subtitle cancelled-note">Run was cancelled - results are partial.</p>
+"#);
+    }
+
     // Dashboard with pie chart
     html.push_str(&build_dashboard(summary));
 
     // Summary cards
     html.push_str(&build_summary_cards(summary));
 
-    // Process statistics (Run Details) - only shown if stats are available
+    // Process statistics (Run Statistics) - only shown if stats are available
     html.push_str(&build_process_stats(summary));
 
+    // Data quality anomalies - only shown if any were recorded
+    html.push_str(&build_anomaly_report(summary));
+
+    // Mismatches per column across all structured files
+    html.push_str(&build_column_rollup(&calculate_column_rollup(results)));
+
+    // Column statistics that shifted between the files (`--profile`)
+    html.push_str(&build_column_profiles(results));
+
     // Results table
-    html.push_str(&build_results_table(results, artifacts_dir));
+    html.push_str(&build_results_table(results, artifacts_dir, split_diffs, max_rows));
 
     // Diff modal
     html.push_str(&build_diff_modal());
 
     // Embedded diff data (JSON)
-    html.push_str(&build_diff_data(results));
+    html.push_str(&build_diff_data(results, split_diffs));
 
     // JavaScript
     html.push_str(&build_javascript());
@@ -78,16 +113,178 @@ fn build_html_report(
     html
 }
 
-/// Build HTML head with styles
-fn build_html_head() -> String {
-    r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>CompareIt Report</title>
-    <style>
-        :root {
+/// Write a standalone side-by-side diff page for one text pair
+///
+/// The page embeds only this pair's diff and the same CSS and renderer as
+/// the main report, so it opens without the report or a network connection.
+pub fn export_html_diff(result: &TextComparisonResult, path: &Path, theme: ReportTheme) -> Result<()> {
+    let mut html = build_html_head(theme);
+    html.push_str(&format!(
+        r#"
+<body>
+    <div class="container">
+        <header>
+            <h1>CompareIt Diff</h1>
+            <p class="subtitle">{} vs {}</p>
+        </header>
+        <div id="diff-root"></div>
+    </div>
+    <script>
+        const diffData = [{}];
+    </script>
+"#,
+        escape_html(&result.file1_path),
+        escape_html(&result.file2_path),
+        text_diff_entry(result).replace("</", "<\\/")
+    ));
+    html.push_str(&build_diff_renderer());
+    html.push_str(r#"
+    <script>
+        document.getElementById('diff-root').innerHTML = renderTextDiff(diffData[0]);
+    </script>
+</body>
+</html>
+"#);
+
+    fs::write(path, html)
+        .with_context(|| format!("Failed to write HTML diff to {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Write an [`export_html_diff`] page for each differing text pair to
+/// `diffs/<linked_id>.html` under `output_dir`
+pub fn write_html_diffs(results: &[ComparisonResult], output_dir: &Path, theme: ReportTheme) -> Result<()> {
+    let diffs_dir = output_dir.join("diffs");
+    fs::create_dir_all(&diffs_dir)
+        .with_context(|| format!("Failed to create {}", diffs_dir.display()))?;
+
+    for result in results {
+        if let ComparisonResult::Text(r) = result {
+            if r.identical || r.detailed_diff.is_empty() {
+                continue;
+            }
+            let path = diffs_dir.join(sanitize_for_filename(&r.linked_id) + ".html");
+            export_html_diff(r, &path, theme)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Generate an HTML report for a three-way comparison
+pub fn generate_three_way_report(
+    result: &ThreeWayResult,
+    output_path: &Path,
+    theme: ReportTheme,
+) -> Result<()> {
+    let mut html = build_html_head(theme);
+    html.push_str(r#"
+<body>
+    <div class="container">
+        <header>
+            <h1>CompareIt Three-Way Report</h1>
+            <p class="subtitle">Changes in two copies against their common ancestor</p>
+        </header>
+"#);
+    html.push_str(&build_three_way_section(result));
+    html.push_str(r#"
+    </div>
+</body>
+</html>
+"#);
+
+    fs::write(output_path, html)
+        .with_context(|| format!("Failed to write HTML report to {}", output_path.display()))?;
+
+    Ok(())
+}
+
+/// Build the three-way section: change counts per side, then every change
+fn build_three_way_section(result: &ThreeWayResult) -> String {
+    let buckets: [(&str, &str, &[ThreeWayChange]); 4] = [
+        ("Conflicts", "warning", &result.conflicts),
+        ("Changed in Left", "accent", &result.changed_in_left),
+        ("Changed in Right", "accent", &result.changed_in_right),
+        ("Changed in Both", "success", &result.changed_in_both),
+    ];
+
+    let mut html = format!(r#"
+        <div class="process-stats">
+            <h3>Three-Way Comparison</h3>
+            <p class="subtitle">Base: {} &middot; Left: {} &middot; Right: {}</p>
+            <div class="stats-grid">
+                <div class="stat-item">
+                    <div class="stat-value success">{}</div>
+                    <div class="stat-label">Unchanged Records</div>
+                </div>
+"#,
+        escape_html(&result.base_path),
+        escape_html(&result.left_path),
+        escape_html(&result.right_path),
+        result.unchanged_records,
+    );
+
+    for (label, class, changes) in &buckets {
+        html.push_str(&format!(r#"
+                <div class="stat-item">
+                    <div class="stat-value {}">{}</div>
+                    <div class="stat-label">{}</div>
+                </div>
+"#, class, changes.len(), label));
+    }
+
+    html.push_str(r#"
+            </div>
+            <table class="anomaly-table">
+                <thead>
+                    <tr>
+                        <th>Change</th>
+                        <th>Key</th>
+                        <th>Column</th>
+                        <th>Base</th>
+                        <th>Left</th>
+                        <th>Right</th>
+                    </tr>
+                </thead>
+                <tbody>
+"#);
+
+    // A missing value means the record does not exist on that side
+    let value = |v: &Option<String>| v.as_deref().map_or_else(|| "<em>absent</em>".to_string(), escape_html);
+    for (label, _, changes) in &buckets {
+        for change in changes.iter() {
+            html.push_str(&format!(r#"
+                    <tr>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                    </tr>
+"#,
+                label,
+                escape_html(&change.key),
+                change.column.as_deref().map_or_else(|| "<em>whole record</em>".to_string(), escape_html),
+                value(&change.base),
+                value(&change.left),
+                value(&change.right),
+            ));
+        }
+    }
+
+    html.push_str(r#"
+                </tbody>
+            </table>
+        </div>
+"#);
+
+    html
+}
+
+/// CSS variables of the dark palette
+const DARK_PALETTE: &str = "
             --bg-primary: #0d1117;
             --bg-secondary: #161b22;
             --bg-tertiary: #21262d;
@@ -100,8 +297,49 @@ fn build_html_head() -> String {
             --border: #30363d;
             --diff-add-bg: rgba(63, 185, 80, 0.15);
             --diff-del-bg: rgba(248, 81, 73, 0.15);
-        }
-        
+";
+
+/// CSS variables of the light palette
+const LIGHT_PALETTE: &str = "
+            --bg-primary: #ffffff;
+            --bg-secondary: #f6f8fa;
+            --bg-tertiary: #eaeef2;
+            --text-primary: #1f2328;
+            --text-secondary: #656d76;
+            --accent: #0969da;
+            --success: #1a7f37;
+            --warning: #9a6700;
+            --danger: #d1242f;
+            --border: #d0d7de;
+            --diff-add-bg: rgba(26, 127, 55, 0.15);
+            --diff-del-bg: rgba(209, 36, 47, 0.15);
+";
+
+/// `:root` variable block for a theme; `Auto` switches to the light palette
+/// when the viewer prefers a light color scheme
+fn build_theme_css(theme: ReportTheme) -> String {
+    match theme {
+        ReportTheme::Dark => format!("        :root {{{}        }}\n", DARK_PALETTE),
+        ReportTheme::Light => format!("        :root {{{}        }}\n", LIGHT_PALETTE),
+        ReportTheme::Auto => format!(
+            "        :root {{{}        }}\n        @media (prefers-color-scheme: light) {{\n        :root {{{}        }}\n        }}\n",
+            DARK_PALETTE, LIGHT_PALETTE
+        ),
+    }
+}
+
+/// Build HTML head with styles
+fn build_html_head(theme: ReportTheme) -> String {
+    let mut head = String::from(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>CompareIt Report</title>
+    <style>
+"#);
+    head.push_str(&build_theme_css(theme));
+    head.push_str(r#"
         * { box-sizing: border-box; margin: 0; padding: 0; }
         
         body {
@@ -117,6 +355,7 @@ fn build_html_head() -> String {
         header { margin-bottom: 2rem; }
         h1 { font-size: 2rem; font-weight: 600; color: var(--text-primary); }
         .subtitle { color: var(--text-secondary); }
+        .cancelled-note { color: var(--warning); margin-bottom: 1rem; }
         
         /* Dashboard */
         .dashboard {
@@ -232,6 +471,16 @@ fn build_html_head() -> String {
             font-size: 0.875rem;
         }
         
+        .pagination {
+            padding: 0.75rem 1rem;
+            display: flex;
+            justify-content: flex-end;
+            align-items: center;
+            gap: 0.75rem;
+            color: var(--text-secondary);
+            font-size: 0.875rem;
+        }
+        
         table { width: 100%; border-collapse: collapse; font-size: 0.875rem; }
         
         th, td {
@@ -265,6 +514,7 @@ fn build_html_head() -> String {
         .badge.identical { background: rgba(63, 185, 80, 0.2); color: var(--success); }
         .badge.different { background: rgba(210, 153, 34, 0.2); color: var(--warning); }
         .badge.error { background: rgba(248, 81, 73, 0.2); color: var(--danger); }
+        .badge.unmatched { background: rgba(88, 166, 255, 0.2); color: var(--accent); }
         
         .similarity-bar {
             width: 60px;
@@ -300,6 +550,7 @@ fn build_html_head() -> String {
             font-size: 0.75rem;
             cursor: pointer;
             font-weight: 500;
+            text-decoration: none;
         }
         
         .btn:hover { opacity: 0.9; }
@@ -410,6 +661,9 @@ fn build_html_head() -> String {
         
         .diff-line.added { background: var(--diff-add-bg); }
         .diff-line.removed { background: var(--diff-del-bg); }
+        .ws-marker { color: var(--text-secondary); opacity: 0.7; }
+        .word-del { background: rgba(248, 81, 73, 0.4); border-radius: 2px; }
+        .word-add { background: rgba(63, 185, 80, 0.4); border-radius: 2px; }
         
         /* Structured Diff */
         .struct-diff {
@@ -475,31 +729,36 @@ fn build_html_head() -> String {
         
         .stat-item .stat-value.accent { color: var(--accent); }
         .stat-item .stat-value.success { color: var(--success); }
+        .stat-item .stat-value.warning { color: var(--warning); }
+
+        .anomaly-table { width: 100%; margin-top: 1rem; border-collapse: collapse; font-size: 0.875rem; }
+        .anomaly-table th, .anomaly-table td { text-align: left; padding: 0.5rem; border-bottom: 1px solid var(--border); }
+        .anomaly-table th { color: var(--text-secondary); font-weight: 500; }
     </style>
 </head>
-"#.to_string()
+"#);
+    head
 }
 
 /// Build dashboard with pie chart
 fn build_dashboard(summary: &ComparisonSummary) -> String {
     let total = summary.pairs_compared.max(1) as f64;
-    let identical_pct = (summary.identical_pairs as f64 / total * 100.0).round();
-    let different_pct = (summary.different_pairs as f64 / total * 100.0).round();
-    let error_pct = (summary.error_pairs as f64 / total * 100.0).round();
+    let [identical_pct, different_pct, error_pct] =
+        rounded_percentages([summary.identical_pairs, summary.different_pairs, summary.error_pairs], total);
 
-    // Calculate pie chart angles (CSS conic-gradient)
-    let identical_deg = identical_pct * 3.6;
-    let different_deg = different_pct * 3.6;
-    // error_deg is implicit (fills to 360)
+    // Calculate pie chart angles (CSS conic-gradient) from the exact shares,
+    // not the rounded labels, so the error slice is exactly what remains
+    let identical_deg = (summary.identical_pairs as f64 / total * 360.0).min(360.0);
+    let different_end = (identical_deg + summary.different_pairs as f64 / total * 360.0).min(360.0);
 
     format!(r#"
         <div class="dashboard">
             <div class="pie-container">
                 <h3>Status Distribution</h3>
                 <div class="pie-chart" style="background: conic-gradient(
-                    var(--success) 0deg {identical_deg}deg,
-                    var(--warning) {identical_deg}deg {}deg,
-                    var(--danger) {}deg 360deg
+                    var(--success) 0deg {identical_deg:.2}deg,
+                    var(--warning) {identical_deg:.2}deg {different_end:.2}deg,
+                    var(--danger) {different_end:.2}deg 360deg
                 );"></div>
                 <div class="pie-legend">
                     <div class="legend-item">
@@ -517,14 +776,30 @@ fn build_dashboard(summary: &ComparisonSummary) -> String {
                 </div>
             </div>
 "#,
-        identical_deg + different_deg,
-        identical_deg + different_deg,
         summary.identical_pairs, identical_pct,
         summary.different_pairs, different_pct,
         summary.error_pairs, error_pct
     )
 }
 
+/// Whole percentages of `total` for `counts` that add up like the exact shares do
+///
+/// Largest-remainder rounding: every share is rounded down, then the points
+/// still missing go to the shares with the largest remainders (the earlier
+/// share on a tie), so three even thirds read 34/33/33 rather than 33/33/33.
+fn rounded_percentages<const N: usize>(counts: [usize; N], total: f64) -> [u64; N] {
+    let exact = counts.map(|count| count as f64 / total * 100.0);
+    let mut rounded = exact.map(|pct| pct.floor() as u64);
+    let target = exact.iter().sum::<f64>().round() as u64;
+    let mut order: Vec<usize> = (0..N).collect();
+    order.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+    let missing = target.saturating_sub(rounded.iter().sum()) as usize;
+    for &i in order.iter().take(missing) {
+        rounded[i] += 1;
+    }
+    rounded
+}
+
 /// Build summary cards
 fn build_summary_cards(summary: &ComparisonSummary) -> String {
     format!(r#"
@@ -537,9 +812,9 @@ fn build_summary_cards(summary: &ComparisonSummary) -> String {
                     <div class="label">Identical</div>
                     <div class="value success">{}</div>
                 </div>
-                <div class="summary-card">
-                    <div class="label">Different</div>
-                    <div class="value warning">{}</div>
+                <div class="summary-card" title="Content: values changed, same shape / Structural: columns added or removed, or line count or size changed">
+                    <div class="label">Different (Content / Structural)</div>
+                    <div class="value warning">{} <small>({} / {})</small></div>
                 </div>
                 <div class="summary-card">
                     <div class="label">Errors</div>
@@ -549,22 +824,42 @@ fn build_summary_cards(summary: &ComparisonSummary) -> String {
                     <div class="label">Avg Similarity</div>
                     <div class="value">{:.1}%</div>
                 </div>
+                <div class="summary-card" title="Average similarity weighted by file size">
+                    <div class="label">Size-Weighted Similarity</div>
+                    <div class="value">{:.1}%</div>
+                </div>
+                <div class="summary-card" title="Files with no counterpart in the other path (path 1 / path 2)">
+                    <div class="label">Only in One Side</div>
+                    <div class="value{}">{} / {}</div>
+                </div>
             </div>
         </div>
 "#,
         summary.pairs_compared,
         summary.identical_pairs,
         summary.different_pairs,
+        summary.content_diff_pairs,
+        summary.structural_diff_pairs,
         if summary.error_pairs > 0 { " danger" } else { "" },
         summary.error_pairs,
-        summary.average_similarity * 100.0
+        summary.average_similarity * 100.0,
+        summary.weighted_average_similarity * 100.0,
+        if summary.only_in_set1 + summary.only_in_set2 > 0 { " warning" } else { "" },
+        summary.only_in_set1,
+        summary.only_in_set2
     )
 }
 
-/// Build process statistics section (Run Details)
+/// Build process statistics section (Run Statistics)
+///
+/// Empty when the summary carries no process statistics, as for reports
+/// rebuilt from a JSONL by the `report` subcommand.
 fn build_process_stats(summary: &ComparisonSummary) -> String {
     // Only show if we have process stats
     let has_stats = summary.execution_time_ms.is_some()
+        || summary.processing_speed_mb_per_sec.is_some()
+        || summary.peak_memory_usage_bytes.is_some()
+        || summary.total_data_processed_bytes.is_some()
         || summary.comparison_mode.is_some()
         || summary.similarity_algorithm.is_some();
 
@@ -574,7 +869,7 @@ fn build_process_stats(summary: &ComparisonSummary) -> String {
 
     let mut html = String::from(r#"
         <div class="process-stats">
-            <h3>Run Details</h3>
+            <h3>Run Statistics</h3>
             <div class="stats-grid">
 "#);
 
@@ -652,8 +947,211 @@ fn build_process_stats(summary: &ComparisonSummary) -> String {
 "#, algo));
     }
 
+    // Blocking rule that pruned the most potential pairs
+    if let Some((rule, count)) = summary.blocking.top_rule() {
+        html.push_str(&format!(r#"
+                <div class="stat-item" title="{} of {} potential pairs were rejected by blocking rules">
+                    <div class="stat-value warning">{} / {}</div>
+                    <div class="stat-label">Pairs Blocked (most by {}: {})</div>
+                </div>
+"#,
+            summary.blocking.total_rejected(),
+            summary.blocking.pairs_checked,
+            summary.blocking.total_rejected(),
+            summary.blocking.pairs_checked,
+            rule,
+            count
+        ));
+    }
+
+    html.push_str(r#"
+            </div>
+        </div>
+"#);
+
+    html
+}
+
+/// Build the data quality section listing anomalies found during the run
+fn build_anomaly_report(summary: &ComparisonSummary) -> String {
+    let report = &summary.anomalies;
+    if report.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from(r#"
+        <div class="process-stats">
+            <h3>Data Quality</h3>
+            <div class="stats-grid">
+"#);
+
+    for (kind, count) in &report.counts {
+        html.push_str(&format!(r#"
+                <div class="stat-item">
+                    <div class="stat-value warning">{}</div>
+                    <div class="stat-label">{}</div>
+                </div>
+"#, count, kind.label()));
+    }
+
     html.push_str(r#"
             </div>
+            <table class="anomaly-table">
+                <thead>
+                    <tr>
+                        <th>Issue</th>
+                        <th>File</th>
+                        <th>Details</th>
+                    </tr>
+                </thead>
+                <tbody>
+"#);
+
+    for anomaly in &report.anomalies {
+        html.push_str(&format!(r#"
+                    <tr>
+                        <td>{}</td>
+                        <td class="path" title="{}">{}</td>
+                        <td>{}</td>
+                    </tr>
+"#,
+            anomaly.kind.label(),
+            escape_html(&anomaly.path),
+            escape_html(&truncate_path(&anomaly.path, 50)),
+            escape_html(&anomaly.detail),
+        ));
+    }
+
+    html.push_str(r#"
+                </tbody>
+            </table>
+        </div>
+"#);
+
+    html
+}
+
+/// Build the column roll-up section: mismatches per column summed over all
+/// structured files
+fn build_column_rollup(rollup: &[ColumnRollup]) -> String {
+    if rollup.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from(r#"
+        <div class="process-stats">
+            <h3>Column Mismatches Across Files</h3>
+            <table class="anomaly-table">
+                <thead>
+                    <tr>
+                        <th>Column</th>
+                        <th>Mismatched Records</th>
+                        <th>Files Affected</th>
+                    </tr>
+                </thead>
+                <tbody>
+"#);
+
+    for column in rollup {
+        html.push_str(&format!(r#"
+                    <tr>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                    </tr>
+"#,
+            escape_html(&column.column_name),
+            column.mismatch_count,
+            column.files_affected,
+        ));
+    }
+
+    html.push_str(r#"
+                </tbody>
+            </table>
+        </div>
+"#);
+
+    html
+}
+
+/// Build the table of profiled columns whose statistics changed
+///
+/// Empty unless some structured pair was compared with `profile_columns`.
+fn build_column_profiles(results: &[ComparisonResult]) -> String {
+    let changed: Vec<_> = results
+        .iter()
+        .filter_map(|r| match r {
+            ComparisonResult::Structured(s) => Some(s),
+            _ => None,
+        })
+        .flat_map(|s| s.column_profiles.iter().filter(|p| p.changed()).map(move |p| (&s.file1_path, p)))
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from(r#"
+        <div class="process-stats">
+            <h3>Column Profile Shifts</h3>
+            <table class="anomaly-table">
+                <thead>
+                    <tr>
+                        <th>File</th>
+                        <th>Column</th>
+                        <th>Records</th>
+                        <th>Null Rate</th>
+                        <th>Distinct</th>
+                        <th>Min</th>
+                        <th>Max</th>
+                        <th>Mean</th>
+                    </tr>
+                </thead>
+                <tbody>
+"#);
+
+    // Up to 4 decimals without trailing zeros; "-" for non-numeric columns
+    let number = |v: Option<f64>| match v {
+        Some(v) => format!("{:.4}", v).trim_end_matches('0').trim_end_matches('.').to_string(),
+        None => "-".to_string(),
+    };
+    let shift = |a: String, b: String| if a == b { a } else { format!("{} → {}", a, b) };
+    let null_rate = |p: &ColumnProfile| if p.count == 0 { 0.0 } else { p.null_count as f64 * 100.0 / p.count as f64 };
+    for (path, profile) in changed {
+        let (p1, p2) = (&profile.file1, &profile.file2);
+        let mean = match profile.mean_delta {
+            Some(delta) if delta != 0.0 => {
+                let sign = if delta > 0.0 { "+" } else { "" };
+                format!("{} ({}{})", shift(number(p1.mean), number(p2.mean)), sign, number(Some(delta)))
+            }
+            _ => shift(number(p1.mean), number(p2.mean)),
+        };
+        html.push_str(&format!(r#"
+                    <tr>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                        <td>{}</td>
+                    </tr>
+"#,
+            escape_html(path),
+            escape_html(&profile.column_name),
+            shift(p1.count.to_string(), p2.count.to_string()),
+            shift(format!("{:.1}%", null_rate(p1)), format!("{:.1}%", null_rate(p2))),
+            shift(p1.distinct_count.to_string(), p2.distinct_count.to_string()),
+            shift(number(p1.min), number(p2.min)),
+            shift(number(p1.max), number(p2.max)),
+            mean,
+        ));
+    }
+
+    html.push_str(r#"
+                </tbody>
+            </table>
         </div>
 "#);
 
@@ -678,16 +1176,22 @@ fn format_bytes(bytes: u64) -> String {
 }
 
 /// Build results table
-fn build_results_table(results: &[ComparisonResult], artifacts_dir: Option<&Path>) -> String {
+fn build_results_table(
+    results: &[ComparisonResult],
+    artifacts_dir: Option<&Path>,
+    split_diffs: bool,
+    max_rows: usize,
+) -> String {
     let mut html = String::new();
+    let mut row_cells = Vec::with_capacity(results.len());
 
-    html.push_str(r#"
+    html.push_str(&format!(r#"
         <div class="table-container">
             <div class="table-header">
                 <h2>Comparison Results</h2>
                 <input type="text" class="filter-input" id="table-filter" placeholder="Filter results...">
             </div>
-            <table id="results-table">
+            <table id="results-table" data-page-size="{}">
                 <thead>
                     <tr>
                         <th data-sort="status">Status</th>
@@ -699,7 +1203,7 @@ fn build_results_table(results: &[ComparisonResult], artifacts_dir: Option<&Path
                     </tr>
                 </thead>
                 <tbody>
-"#);
+"#, max_rows.max(1)));
 
     for (idx, result) in results.iter().enumerate() {
         let (file1, file2) = result.file_paths();
@@ -711,6 +1215,10 @@ fn build_results_table(results: &[ComparisonResult], artifacts_dir: Option<&Path
         } else {
             match result {
                 ComparisonResult::Error { .. } => ("error", "Error"),
+                ComparisonResult::OnlyInOne(r) => match r.side {
+                    FileSide::Set1 => ("unmatched", "Only in 1"),
+                    FileSide::Set2 => ("unmatched", "Only in 2"),
+                },
                 _ => ("different", "Different"),
             }
         };
@@ -726,15 +1234,27 @@ fn build_results_table(results: &[ComparisonResult], artifacts_dir: Option<&Path
         let type_str = match result {
             ComparisonResult::Text(_) => "text",
             ComparisonResult::Structured(_) => "csv",
-            ComparisonResult::HashOnly { .. } => "binary",
+            ComparisonResult::HashOnly { .. } | ComparisonResult::Binary { .. } => "binary",
+            ComparisonResult::Permissions { .. } => "permissions",
+            ComparisonResult::OnlyInOne(_) => "unmatched",
             ComparisonResult::Error { .. } => "error",
         };
 
         // Build action buttons
         let has_diff = matches!(result, ComparisonResult::Text(r) if !r.identical && !r.detailed_diff.is_empty())
-            || matches!(result, ComparisonResult::Structured(r) if !r.identical);
+            || matches!(result, ComparisonResult::Structured(r) if !r.identical)
+            || matches!(result, ComparisonResult::Binary { identical: false, .. });
 
-        let view_btn = if has_diff {
+        // Split text diffs are not embedded, so they are only viewable
+        // through a page `write_html_diffs` actually wrote
+        let split_text = split_diffs && matches!(result, ComparisonResult::Text(_));
+        let split_page = artifacts_dir
+            .filter(|_| split_text && has_diff)
+            .map(|dir| dir.join("diffs").join(sanitize_for_filename(result.linked_id()) + ".html"))
+            .filter(|page| page.is_file());
+        let view_btn = if let Some(page) = split_page {
+            format!(r#"<a class="btn" href="{}" target="_blank">View Diff</a>"#, page.display())
+        } else if has_diff && !split_text {
             format!(r#"<button class="btn" onclick="showDiff({})">View Diff</button>"#, idx)
         } else {
             String::new()
@@ -762,39 +1282,43 @@ fn build_results_table(results: &[ComparisonResult], artifacts_dir: Option<&Path
             String::new()
         };
 
-        html.push_str(&format!(
-            r#"                    <tr data-idx="{}">
-                        <td><span class="badge {}">{}</span></td>
-                        <td class="path" title="{}">{}</td>
-                        <td class="path" title="{}">{}</td>
-                        <td>
-                            <span class="similarity-bar"><span class="fill {}" style="width: {}%"></span></span>
-                            {:.1}%
-                        </td>
-                        <td>{}</td>
-                        <td>{}{}</td>
+        let cells = vec![
+            format!(r#"<td><span class="badge {}">{}</span></td>"#, status_badge, status_text),
+            format!(r#"<td class="path" title="{}">{}</td>"#, escape_html(file1), truncate_path(file1, 35)),
+            format!(r#"<td class="path" title="{}">{}</td>"#, escape_html(file2), truncate_path(file2, 35)),
+            format!(
+                r#"<td><span class="similarity-bar"><span class="fill {}" style="width: {}%"></span></span> {:.1}%</td>"#,
+                sim_class,
+                (similarity * 100.0).round(),
+                similarity * 100.0
+            ),
+            format!("<td>{}</td>", type_str),
+            format!("<td>{}{}</td>", view_btn, artifact_link),
+        ];
+
+        // Only the first page is in the markup; the script renders the rest
+        if idx < max_rows {
+            html.push_str(&format!(
+                r#"                    <tr data-idx="{}">
+                        {}
                     </tr>
 "#,
-            idx,
-            status_badge,
-            status_text,
-            escape_html(file1),
-            truncate_path(file1, 35),
-            escape_html(file2),
-            truncate_path(file2, 35),
-            sim_class,
-            (similarity * 100.0).round(),
-            similarity * 100.0,
-            type_str,
-            view_btn,
-            artifact_link
-        ));
+                idx,
+                cells.join("\n                        ")
+            ));
+        }
+        row_cells.push(cells);
     }
 
-    html.push_str(r#"                </tbody>
+    html.push_str(&format!(r#"                </tbody>
             </table>
+            <div class="pagination" id="pagination">
+                <button class="btn" id="page-prev">Previous</button>
+                <span id="page-info"></span>
+                <button class="btn" id="page-next">Next</button>
+            </div>
         </div>
-"#);
+{}"#, json_script("row-data", &row_cells)));
 
     html
 }
@@ -816,28 +1340,45 @@ fn build_diff_modal() -> String {
 }
 
 /// Build embedded diff data as JSON
-fn build_diff_data(results: &[ComparisonResult]) -> String {
+fn build_diff_data(results: &[ComparisonResult], split_diffs: bool) -> String {
     let mut data = Vec::new();
 
     for result in results {
         let entry = match result {
-            ComparisonResult::Text(r) => {
-                format!(
-                    r#"{{"type":"text","file1":"{}","file2":"{}","diff":{}}}"#,
-                    escape_json(&r.file1_path),
-                    escape_json(&r.file2_path),
-                    serde_json::to_string(&r.detailed_diff).unwrap_or_default()
-                )
-            }
+            // Split diffs live in their own pages, linked from the table
+            ComparisonResult::Text(_) if split_diffs => r#"{"type":"none"}"#.to_string(),
+            ComparisonResult::Text(r) => text_diff_entry(r),
             ComparisonResult::Structured(r) => {
                 let mismatches_json = serde_json::to_string(&r.field_mismatches).unwrap_or_default();
                 format!(
-                    r#"{{"type":"structured","file1":"{}","file2":"{}","mismatches":{},"cols_only_1":{},"cols_only_2":{}}}"#,
+                    r#"{{"type":"structured","file1":"{}","file2":"{}","mismatches":{},"cols_only_1":{},"cols_only_2":{},"keys_only_1":{},"keys_only_2":{},"keys_truncated":{}}}"#,
                     escape_json(&r.file1_path),
                     escape_json(&r.file2_path),
                     mismatches_json,
                     serde_json::to_string(&r.columns_only_in_file1).unwrap_or_default(),
-                    serde_json::to_string(&r.columns_only_in_file2).unwrap_or_default()
+                    serde_json::to_string(&r.columns_only_in_file2).unwrap_or_default(),
+                    serde_json::to_string(&r.only_in_file1_keys).unwrap_or_default(),
+                    serde_json::to_string(&r.only_in_file2_keys).unwrap_or_default(),
+                    r.only_in_keys_truncated
+                )
+            }
+            ComparisonResult::Binary {
+                file1_path,
+                file2_path,
+                file1_size,
+                file2_size,
+                differing_ranges,
+                differing_bytes,
+                ..
+            } => {
+                format!(
+                    r#"{{"type":"binary","file1":"{}","file2":"{}","size1":{},"size2":{},"ranges":{},"differing_bytes":{}}}"#,
+                    escape_json(file1_path),
+                    escape_json(file2_path),
+                    file1_size,
+                    file2_size,
+                    serde_json::to_string(differing_ranges).unwrap_or_else(|_| "[]".to_string()),
+                    differing_bytes
                 )
             }
             _ => r#"{"type":"none"}"#.to_string(),
@@ -845,38 +1386,96 @@ fn build_diff_data(results: &[ComparisonResult]) -> String {
         data.push(entry);
     }
 
+    // Parsed by the page only when a diff is first opened
     format!(
         r#"
-    <script>
-        const diffData = [{}];
-    </script>
+    <script type="application/json" id="diff-data">[{}]</script>
 "#,
-        data.join(",\n")
+        data.join(",\n").replace("</", "<\\/")
+    )
+}
+
+/// Embed `value` as a JSON data block the page's script can parse
+///
+/// `</` is escaped so values containing `</script>` cannot end the block.
+fn json_script<T: serde::Serialize>(id: &str, value: &T) -> String {
+    let json = serde_json::to_string(value).unwrap_or_else(|_| "[]".to_string());
+    format!(
+        r#"    <script type="application/json" id="{}">{}</script>
+"#,
+        id,
+        json.replace("</", "<\\/")
+    )
+}
+
+/// Diff data entry for one text pair, as read by `renderTextDiff`
+fn text_diff_entry(r: &TextComparisonResult) -> String {
+    format!(
+        r#"{{"type":"text","file1":"{}","file2":"{}","diff":{},"inline":{},"whitespace":{},"rename":{}}}"#,
+        escape_json(&r.file1_path),
+        escape_json(&r.file2_path),
+        serde_json::to_string(&r.detailed_diff).unwrap_or_default(),
+        serde_json::to_string(&r.inline_edits).unwrap_or_else(|_| "[]".to_string()),
+        serde_json::to_string(&r.whitespace_glyphs).unwrap_or_else(|_| "{}".to_string()),
+        serde_json::to_string(&r.rename).unwrap_or_else(|_| "null".to_string())
     )
 }
 
 /// Build JavaScript for interactivity
 fn build_javascript() -> String {
-    r#"
+    let mut js = String::from(r#"
     <script>
+        // Results table: rows live in #row-data and are rendered a page at a
+        // time; sorting and filtering reorder that list, not the DOM
+        const rowData = JSON.parse(document.getElementById('row-data').textContent);
+        const resultsTable = document.getElementById('results-table');
+        const pageSize = parseInt(resultsTable.dataset.pageSize, 10);
+        let rowOrder = rowData.map((_, idx) => idx);
+        let currentPage = 0;
+        
+        function cellText(idx, col) {
+            return rowData[idx][col].replace(/<[^>]*>/g, '').trim();
+        }
+        
+        function renderPage() {
+            const pages = Math.max(1, Math.ceil(rowOrder.length / pageSize));
+            currentPage = Math.min(currentPage, pages - 1);
+            const start = currentPage * pageSize;
+            resultsTable.querySelector('tbody').innerHTML = rowOrder
+                .slice(start, start + pageSize)
+                .map(idx => `<tr data-idx="${idx}">${rowData[idx].join('')}</tr>`)
+                .join('');
+            document.getElementById('page-info').textContent =
+                `Page ${currentPage + 1} of ${pages} (${rowOrder.length} results)`;
+            document.getElementById('page-prev').disabled = currentPage === 0;
+            document.getElementById('page-next').disabled = currentPage >= pages - 1;
+            document.getElementById('pagination').style.display = rowData.length > pageSize ? '' : 'none';
+        }
+        
+        document.getElementById('page-prev').addEventListener('click', () => {
+            currentPage--;
+            renderPage();
+        });
+        document.getElementById('page-next').addEventListener('click', () => {
+            currentPage++;
+            renderPage();
+        });
+        
         // Table sorting
         document.querySelectorAll('th[data-sort]').forEach(th => {
             th.addEventListener('click', () => {
-                const table = th.closest('table');
-                const tbody = table.querySelector('tbody');
-                const rows = Array.from(tbody.querySelectorAll('tr'));
                 const col = th.cellIndex;
                 const isAsc = th.classList.contains('sorted-asc');
                 
-                table.querySelectorAll('th').forEach(h => {
+                resultsTable.querySelectorAll('th').forEach(h => {
                     h.classList.remove('sorted-asc', 'sorted-desc');
                 });
                 
                 th.classList.add(isAsc ? 'sorted-desc' : 'sorted-asc');
                 
-                rows.sort((a, b) => {
-                    let aVal = a.cells[col].textContent.trim();
-                    let bVal = b.cells[col].textContent.trim();
+                rowOrder.sort((a, b) => {
+                    let aVal = cellText(a, col);
+                    let bVal = cellText(b, col);
                     
                     const aNum = parseFloat(aVal.replace('%', ''));
                     const bNum = parseFloat(bVal.replace('%', ''));
@@ -888,23 +1487,30 @@ fn build_javascript() -> String {
                     return isAsc ? bVal.localeCompare(aVal) : aVal.localeCompare(bVal);
                 });
                 
-                rows.forEach(row => tbody.appendChild(row));
+                currentPage = 0;
+                renderPage();
             });
         });
         
         // Table filtering
         document.getElementById('table-filter').addEventListener('input', (e) => {
             const filter = e.target.value.toLowerCase();
-            const rows = document.querySelectorAll('#results-table tbody tr');
-            
-            rows.forEach(row => {
-                const text = row.textContent.toLowerCase();
-                row.style.display = text.includes(filter) ? '' : 'none';
-            });
+            rowOrder = rowData
+                .map((_, idx) => idx)
+                .filter(idx => rowData[idx].some((_, col) => cellText(idx, col).toLowerCase().includes(filter)));
+            currentPage = 0;
+            renderPage();
         });
         
+        renderPage();
+        
         // Diff modal
+        let diffData = null;
+        
         function showDiff(idx) {
+            if (diffData === null) {
+                diffData = JSON.parse(document.getElementById('diff-data').textContent);
+            }
             const data = diffData[idx];
             const modal = document.getElementById('diff-modal');
             const title = document.getElementById('modal-title');
@@ -916,6 +1522,9 @@ fn build_javascript() -> String {
             } else if (data.type === 'structured') {
                 title.textContent = 'Structured Diff';
                 body.innerHTML = renderStructuredDiff(data);
+            } else if (data.type === 'binary') {
+                title.textContent = 'Binary Diff';
+                body.innerHTML = renderBinaryDiff(data);
             } else {
                 body.innerHTML = '<p>No diff available</p>';
             }
@@ -935,6 +1544,46 @@ fn build_javascript() -> String {
         document.getElementById('diff-modal').addEventListener('click', (e) => {
             if (e.target.id === 'diff-modal') closeDiff();
         });
+    </script>
+"#);
+    js.push_str(&build_diff_renderer());
+    js
+}
+
+/// Diff rendering functions shared by the report and standalone diff pages
+fn build_diff_renderer() -> String {
+    r#"
+    <script>
+        // Dim the glyphs --show-whitespace put in place of whitespace, found at
+        // the byte offsets in `glyphs`; `offset` is where `text` starts in its line
+        function renderChangedText(text, offset, glyphs) {
+            if (glyphs.size === 0) return escapeHtml(text);
+            let html = '';
+            for (const ch of text) {
+                html += glyphs.has(offset) ? `<span class="ws-marker">${ch}</span>` : escapeHtml(ch);
+                offset += new TextEncoder().encode(ch).length;
+            }
+            return html;
+        }
+        
+        // Wrap the byte ranges of an inline edit in word-add/word-del spans
+        function renderLineContent(line) {
+            if (line.type !== 'added' && line.type !== 'removed') return escapeHtml(line.content);
+            const glyphs = new Set(line.glyphs || []);
+            if (!line.ranges || line.ranges.length === 0) return renderChangedText(line.content, 0, glyphs);
+            
+            const bytes = new TextEncoder().encode(line.content);
+            const decoder = new TextDecoder();
+            const cls = line.type === 'added' ? 'word-add' : 'word-del';
+            let html = '';
+            let pos = 0;
+            for (const [start, end] of line.ranges) {
+                html += renderChangedText(decoder.decode(bytes.slice(pos, start)), pos, glyphs);
+                html += `<span class="${cls}">${renderChangedText(decoder.decode(bytes.slice(start, end)), start, glyphs)}</span>`;
+                pos = end;
+            }
+            return html + renderChangedText(decoder.decode(bytes.slice(pos)), pos, glyphs);
+        }
         
         function renderTextDiff(data) {
             const lines = data.diff.split('\n');
@@ -943,19 +1592,39 @@ fn build_javascript() -> String {
             let lineNum1 = 0;
             let lineNum2 = 0;
             
+            // Word-level edits keyed by line number, if --inline-diff was used
+            const removedRanges = {};
+            const addedRanges = {};
+            for (const edit of (data.inline || [])) {
+                removedRanges[edit.old_line] = edit.removed;
+                addedRanges[edit.new_line] = edit.added;
+            }
+            // Whitespace glyph offsets keyed by line number, if --show-whitespace was used
+            const whitespace = data.whitespace || {};
+            const removedGlyphs = whitespace.removed || {};
+            const addedGlyphs = whitespace.added || {};
+            
             for (const line of lines) {
-                if (line.startsWith('---') || line.startsWith('+++') || line.startsWith('@@')) {
+                if (line.startsWith('---') || line.startsWith('+++')) {
+                    continue;
+                }
+                
+                // Hunk headers carry the real starting line numbers
+                const hunk = line.match(/^@@ -(\d+)(?:,\d+)? \+(\d+)(?:,\d+)? @@/);
+                if (hunk) {
+                    lineNum1 = parseInt(hunk[1], 10) - 1;
+                    lineNum2 = parseInt(hunk[2], 10) - 1;
                     continue;
                 }
                 
                 if (line.startsWith('-')) {
                     lineNum1++;
-                    file1Lines.push({ num: lineNum1, content: line.slice(1), type: 'removed' });
+                    file1Lines.push({ num: lineNum1, content: line.slice(1), type: 'removed', ranges: removedRanges[lineNum1], glyphs: removedGlyphs[lineNum1] });
                     file2Lines.push({ num: '', content: '', type: 'empty' });
                 } else if (line.startsWith('+')) {
                     lineNum2++;
                     file1Lines.push({ num: '', content: '', type: 'empty' });
-                    file2Lines.push({ num: lineNum2, content: line.slice(1), type: 'added' });
+                    file2Lines.push({ num: lineNum2, content: line.slice(1), type: 'added', ranges: addedRanges[lineNum2], glyphs: addedGlyphs[lineNum2] });
                 } else if (line.startsWith(' ')) {
                     lineNum1++;
                     lineNum2++;
@@ -964,7 +1633,11 @@ fn build_javascript() -> String {
                 }
             }
             
-            return `
+            const rename = data.rename
+                ? `<p><strong>Appears to be a rename:</strong> <code>${escapeHtml(data.rename.from)}</code> → <code>${escapeHtml(data.rename.to)}</code> (${data.rename.occurrences} occurrence${data.rename.occurrences === 1 ? '' : 's'})</p>`
+                : '';
+            
+            return rename + `
                 <div class="diff-container">
                     <div class="diff-panel">
                         <div class="diff-panel-header">${escapeHtml(data.file1)}</div>
@@ -972,7 +1645,7 @@ fn build_javascript() -> String {
                             ${file1Lines.map(l => `
                                 <div class="diff-line ${l.type}">
                                     <span class="diff-line-num">${l.num}</span>
-                                    <span class="diff-line-content">${escapeHtml(l.content)}</span>
+                                    <span class="diff-line-content">${renderLineContent(l)}</span>
                                 </div>
                             `).join('')}
                         </div>
@@ -983,7 +1656,7 @@ fn build_javascript() -> String {
                             ${file2Lines.map(l => `
                                 <div class="diff-line ${l.type}">
                                     <span class="diff-line-num">${l.num}</span>
-                                    <span class="diff-line-content">${escapeHtml(l.content)}</span>
+                                    <span class="diff-line-content">${renderLineContent(l)}</span>
                                 </div>
                             `).join('')}
                         </div>
@@ -1003,7 +1676,7 @@ fn build_javascript() -> String {
                     html += `<p><strong>Columns only in File 2:</strong> ${data.cols_only_2.join(', ')}</p>`;
                 }
                 
-                return html;
+                return html + renderRecordKeys(data);
             }
             
             let html = '<div class="struct-diff"><table><thead><tr><th>Column</th><th>Mismatches</th><th>Sample Key</th><th>File 1 Value</th><th>File 2 Value</th></tr></thead><tbody>';
@@ -1022,6 +1695,47 @@ fn build_javascript() -> String {
             }
             
             html += '</tbody></table></div>';
+            return html + renderRecordKeys(data);
+        }
+        
+        // Keys of the records only one file has
+        function renderRecordKeys(data) {
+            let html = '';
+            if (data.keys_only_1 && data.keys_only_1.length > 0) {
+                html += `<p><strong>Records only in File 1:</strong> ${data.keys_only_1.map(escapeHtml).join(', ')}</p>`;
+            }
+            if (data.keys_only_2 && data.keys_only_2.length > 0) {
+                html += `<p><strong>Records only in File 2:</strong> ${data.keys_only_2.map(escapeHtml).join(', ')}</p>`;
+            }
+            if (data.keys_truncated) {
+                html += '<p><em>Record lists are truncated; raise --max-listed-keys to list more.</em></p>';
+            }
+            return html;
+        }
+        
+        function hexOffset(n) {
+            return '0x' + n.toString(16).toUpperCase().padStart(8, '0');
+        }
+        
+        function renderBinaryDiff(data) {
+            let html = `<p>${escapeHtml(data.file1)} (${data.size1} bytes) vs ${escapeHtml(data.file2)} (${data.size2} bytes): ${data.differing_bytes} differing bytes</p>`;
+            html += '<div class="struct-diff"><table><thead><tr><th>Start</th><th>End</th><th>Length</th></tr></thead><tbody>';
+            
+            for (const [start, end] of data.ranges) {
+                html += `
+                    <tr>
+                        <td><code>${hexOffset(start)}</code></td>
+                        <td><code>${hexOffset(end - 1)}</code></td>
+                        <td>${end - start}</td>
+                    </tr>
+                `;
+            }
+            
+            html += '</tbody></table></div>';
+            const shown = data.ranges.reduce((sum, [start, end]) => sum + (end - start), 0);
+            if (shown < data.differing_bytes) {
+                html += `<p>Only the first ${data.ranges.length} ranges are listed.</p>`;
+            }
             return html;
         }
         
@@ -1036,11 +1750,15 @@ fn build_javascript() -> String {
 }
 
 /// Truncate a path string for display
+///
+/// Lengths count characters, so non-ASCII names are cut between characters.
 fn truncate_path(path: &str, max_len: usize) -> String {
-    if path.len() <= max_len {
+    let len = path.chars().count();
+    if len <= max_len {
         path.to_string()
     } else {
-        format!("...{}", &path[path.len() - max_len + 3..])
+        let tail: String = path.chars().skip(len - max_len.saturating_sub(3)).collect();
+        format!("...{}", tail)
     }
 }
 
@@ -1088,3 +1806,209 @@ pub fn load_results_from_jsonl(path: &Path) -> Result<Vec<ComparisonResult>> {
 
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{calculate_summary, ProcessStats};
+    use crate::types::WhitespaceGlyphs;
+
+    #[test]
+    fn test_pie_slices_fill_exactly_360_degrees() {
+        let mut summary = calculate_summary(&[], 0, 0, None);
+        summary.pairs_compared = 3;
+        (summary.identical_pairs, summary.different_pairs, summary.error_pairs) = (1, 1, 1);
+        let html = build_dashboard(&summary);
+        assert!(html.contains("var(--success) 0deg 120.00deg"));
+        assert!(html.contains("var(--danger) 240.00deg 360deg"));
+        // The labels add up to 100%, the extra point going to the first third
+        assert!(html.contains("(1 - 34%)"));
+        assert_eq!(html.matches("- 33%)").count(), 2);
+
+        // 50.5% and 49.5% would both round up; the labels and slices still add up
+        summary.pairs_compared = 200;
+        (summary.identical_pairs, summary.different_pairs, summary.error_pairs) = (101, 99, 0);
+        let html = build_dashboard(&summary);
+        assert!(html.contains("var(--warning) 181.80deg 360.00deg"));
+        assert!(html.contains("(101 - 51%)") && html.contains("(99 - 49%)"));
+    }
+
+    #[test]
+    fn test_truncate_path_keeps_whole_characters() {
+        assert_eq!(truncate_path("C:/ユーザー/資料/🦀レポート.txt", 12), "...🦀レポート.txt");
+        assert_eq!(truncate_path("a/b.txt", 10), "a/b.txt");
+    }
+
+    #[test]
+    fn test_report_shows_run_statistics() {
+        let stats = ProcessStats {
+            execution_time_ms: Some(2500),
+            processing_speed_mb_per_sec: Some(42.0),
+            peak_memory_usage_bytes: Some(3 * 1024 * 1024),
+            total_data_processed_bytes: None,
+            comparison_mode: Some("Text".to_string()),
+            similarity_algorithm: Some("Diff".to_string()),
+        };
+        let html = render_html_report(&[], &calculate_summary(&[], 0, 0, Some(stats)), None, false, ReportTheme::Dark, 100);
+        assert!(html.contains("Run Statistics"));
+        assert!(html.contains("2.50s"));
+        assert!(html.contains("42.0 MB/s"));
+        assert!(html.contains("Memory Used"));
+
+        // Reports rebuilt from a JSONL have no process statistics
+        let html = render_html_report(&[], &calculate_summary(&[], 0, 0, None), None, false, ReportTheme::Dark, 100);
+        assert!(!html.contains("Run Statistics"));
+    }
+
+    #[test]
+    fn test_three_way_section_lists_each_bucket() {
+        let change = |key: &str| ThreeWayChange {
+            key: key.to_string(),
+            column: Some("qty".to_string()),
+            base: Some("1".to_string()),
+            left: Some("2".to_string()),
+            right: None,
+        };
+        let result = ThreeWayResult {
+            unchanged_records: 7,
+            changed_in_left: vec![change("k1")],
+            conflicts: vec![change("<k2>")],
+            ..ThreeWayResult::default()
+        };
+        let html = build_three_way_section(&result);
+        assert!(html.contains("Three-Way Comparison"));
+        assert!(html.contains("Changed in Left"));
+        assert!(html.contains("&lt;k2&gt;"));
+        assert!(html.contains("<em>absent</em>"));
+    }
+
+    #[test]
+    fn test_split_diffs_write_linked_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = TextComparisonResult {
+            linked_id: "src/app.rs".to_string(),
+            file1_path: "left/src/app.rs".to_string(),
+            file2_path: "right/src/app.rs".to_string(),
+            file1_size: 4,
+            file2_size: 4,
+            file1_line_count: 1,
+            file2_line_count: 1,
+            common_lines: 0,
+            only_in_file1: 1,
+            only_in_file2: 1,
+            similarity_score: 0.0,
+            different_positions: "1".to_string(),
+            detailed_diff: "@@ -1 +1 @@\n-old\n+new\n".to_string(),
+            diff_truncated: false,
+            inline_edits: Vec::new(),
+            whitespace_glyphs: WhitespaceGlyphs::default(),
+            rename: None,
+            identical: false,
+        };
+        let results = vec![ComparisonResult::Text(result)];
+        let summary = calculate_summary(&results, 1, 1, None);
+
+        // No link to a page that was never written
+        let html = render_html_report(&results, &summary, Some(dir.path()), true, ReportTheme::Dark, 100);
+        assert!(!html.contains("/diffs/"));
+
+        write_html_diffs(&results, dir.path(), ReportTheme::Dark).unwrap();
+
+        let page = fs::read_to_string(dir.path().join("diffs/src_app.rs.html")).unwrap();
+        assert!(page.contains("left/src/app.rs"));
+        assert!(page.contains("right/src/app.rs"));
+        assert_eq!(page.matches(r#"<div class="diff-panel">"#).count(), 2);
+        assert!(page.contains("renderTextDiff(diffData[0])"));
+
+        // The report links to the page instead of embedding the diff
+        let html = render_html_report(&results, &summary, Some(dir.path()), true, ReportTheme::Dark, 100);
+        assert!(html.contains("/diffs/src_app.rs.html"));
+        assert!(!html.contains("+new"));
+    }
+
+    #[test]
+    fn test_text_diff_entry_carries_whitespace_glyphs() {
+        let mut glyphs = WhitespaceGlyphs::default();
+        glyphs.added.insert(1, vec![1, 4]);
+        let result = TextComparisonResult {
+            linked_id: "a:b".to_string(),
+            file1_path: "a.txt".to_string(),
+            file2_path: "b.txt".to_string(),
+            file1_size: 4,
+            file2_size: 6,
+            file1_line_count: 1,
+            file2_line_count: 1,
+            common_lines: 0,
+            only_in_file1: 1,
+            only_in_file2: 1,
+            similarity_score: 0.0,
+            different_positions: "1".to_string(),
+            detailed_diff: "@@ -1 +1 @@\n-a→b\n+a·→·b\n".to_string(),
+            diff_truncated: false,
+            inline_edits: Vec::new(),
+            whitespace_glyphs: glyphs,
+            rename: None,
+            identical: false,
+        };
+        // Only the substituted spaces are marked; the literal arrow is not
+        assert!(text_diff_entry(&result).contains(r#""whitespace":{"removed":{},"added":{"1":[1,4]}}"#));
+    }
+
+    #[test]
+    fn test_render_html_report_without_artifacts() {
+        let results = vec![
+            ComparisonResult::Error {
+                file1_path: "a/x.bin".to_string(),
+                file2_path: "b/x.bin".to_string(),
+                error: "unreadable".to_string(),
+            },
+        ];
+        let summary = calculate_summary(&results, 1, 1, None);
+        let html = render_html_report(&results, &summary, None, false, ReportTheme::Dark, 100);
+
+        assert!(html.contains("Status Distribution"));
+        assert!(html.contains("<div class=\"label\">Pairs Compared</div>\n                    <div class=\"value\">1</div>"));
+        assert!(html.contains("a/x.bin"));
+        assert!(!html.contains("/patches/"));
+    }
+
+    #[test]
+    fn test_report_themes_select_palette() {
+        let summary = calculate_summary(&[], 0, 0, None);
+        let dark = render_html_report(&[], &summary, None, false, ReportTheme::Dark, 100);
+        assert!(dark.contains("--bg-primary: #0d1117;"));
+        assert!(!dark.contains("prefers-color-scheme"));
+
+        let light = render_html_report(&[], &summary, None, false, ReportTheme::Light, 100);
+        assert!(light.contains("--bg-primary: #ffffff;"));
+        assert!(!light.contains("#0d1117"));
+
+        let auto = render_html_report(&[], &summary, None, false, ReportTheme::Auto, 100);
+        let media = auto.find("@media (prefers-color-scheme: light)").unwrap();
+        assert!(auto[..media].contains("#0d1117"));
+        assert!(auto[media..].contains("--bg-primary: #ffffff;"));
+    }
+
+    #[test]
+    fn test_results_table_renders_first_page_only() {
+        let results: Vec<ComparisonResult> = (0..500)
+            .map(|i| ComparisonResult::Error {
+                file1_path: format!("a/{}.bin", i),
+                file2_path: format!("b/{}.bin", i),
+                error: "unreadable".to_string(),
+            })
+            .collect();
+        let summary = calculate_summary(&results, 500, 500, None);
+        let html = render_html_report(&results, &summary, None, false, ReportTheme::Dark, 100);
+
+        // Rendered rows, not the script's row template
+        let rows = html
+            .match_indices(r#"<tr data-idx=""#)
+            .filter(|(at, tag)| html[at + tag.len()..].starts_with(|c: char| c.is_ascii_digit()))
+            .count();
+        assert_eq!(rows, 100);
+        assert!(html.contains(r#"data-page-size="100""#));
+        // Every row is still available to the pager
+        assert!(html.contains("a/499.bin"));
+    }
+}
@@ -3,14 +3,15 @@
 //! This module defines all the shared types used across the comparison pipeline.
 //! It includes configuration structures, result types, and supporting enums.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 /// File type detected during indexing
 ///
 /// The file type determines which comparison algorithm is used:
 /// - `Text`: Line-by-line diff comparison using the Myers algorithm
-/// - `Csv`/`Tsv`/`Excel`: Key-based record comparison with field-level mismatch tracking
+/// - `Csv`/`Tsv`/`Excel`/`Json`/`FixedWidth`: Key-based record comparison with field-level mismatch tracking
 /// - `Binary`: Hash-only comparison (identical or different)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileType {
@@ -24,6 +25,16 @@ pub enum FileType {
     ///
     /// Supports .xlsx, .xls, .xlsm, .xlsb, .xla, .xlam, and .ods formats.
     Excel,
+    /// JSON array of objects or newline-delimited JSON (NDJSON)
+    ///
+    /// Each top-level object is flattened into a record whose columns are
+    /// JSON paths (e.g. `user.address.city`, `items.0.sku`).
+    Json,
+    /// Fixed-width text records, sliced into fields by character ranges
+    ///
+    /// Never detected on its own: text files are treated as fixed-width when
+    /// a column layout is configured (`CompareConfig::fixed_width`).
+    FixedWidth,
     /// Binary file - only hash comparison is performed
     Binary,
     /// Unknown or unreadable file type
@@ -31,12 +42,12 @@ pub enum FileType {
 }
 
 impl FileType {
-    /// Returns true if this is a structured file type (CSV, TSV, or Excel)
+    /// Returns true if this is a structured file type (CSV, TSV, Excel, JSON, or fixed-width)
     ///
     /// Structured files are compared using key-based record matching
     /// rather than line-by-line diffing.
     pub fn is_structured(&self) -> bool {
-        matches!(self, FileType::Csv | FileType::Tsv | FileType::Excel)
+        matches!(self, FileType::Csv | FileType::Tsv | FileType::Excel | FileType::Json | FileType::FixedWidth)
     }
 }
 
@@ -48,6 +59,7 @@ impl FileType {
 /// - **Identity**: Path, size, extension
 /// - **Fingerprints**: Content hash (exact match), simhash (similarity estimation)
 /// - **Structure**: For CSV/TSV files, schema signature and column names
+/// - **Metadata**: Permission bits and modification time
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     /// Absolute path to the file
@@ -84,14 +96,68 @@ pub struct FileEntry {
     /// Number of lines (text files) or data rows (structured files)
     pub line_count: usize,
 
-    /// Column names for structured files (CSV/TSV headers)
+    /// Column names for structured files (CSV/TSV headers, flattened JSON paths)
     pub columns: Option<Vec<String>>,
+
+    /// Character encoding detected for text files (e.g. `UTF-8`, `UTF-16LE`,
+    /// `windows-1252`)
+    ///
+    /// Set during fingerprinting; `None` for other file types and for text
+    /// files too large to fingerprint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+
+    /// Unix permission bits (`mode & 0o7777`); `None` on other platforms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+
+    /// Last modification time, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+
+    /// Target of a symbolic link indexed with [`SymlinkMode::CompareTarget`]
+    ///
+    /// The entry stands for the link itself: its content is this string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+    /// Delimiter and quote character detected for CSV/TSV files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialect: Option<CsvDialect>,
+}
+
+/// Field delimiter and quote character of a delimited text file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvDialect {
+    /// Character separating fields (`,`, `;`, tab or `|`)
+    pub delimiter: char,
+    /// Character enclosing fields that contain the delimiter
+    pub quote: char,
+}
+
+impl CsvDialect {
+    /// A dialect of the given delimiter and quote, which must both be ASCII
+    pub fn new(delimiter: char, quote: char) -> anyhow::Result<Self> {
+        for (role, c) in [("delimiter", delimiter), ("quote", quote)] {
+            if !c.is_ascii() {
+                anyhow::bail!("CSV {} must be an ASCII character, got '{}'", role, c);
+            }
+        }
+        Ok(Self { delimiter, quote })
+    }
+
+    /// The dialect assumed when none was detected: the file type's delimiter
+    /// and double quotes
+    pub fn for_file_type(file_type: &FileType) -> Self {
+        let delimiter = if *file_type == FileType::Tsv { '\t' } else { ',' };
+        Self { delimiter, quote: '"' }
+    }
 }
 
 /// Comparison mode selection
 ///
 /// Determines which algorithm is used to compare files.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
 pub enum CompareMode {
     /// Auto-detect based on file extension and content
     ///
@@ -107,7 +173,8 @@ pub enum CompareMode {
 /// Similarity scoring algorithm
 ///
 /// Determines how the `similarity_score` metric is calculated.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
 pub enum SimilarityAlgorithm {
     /// Diff-based scoring: `common / (common + only_in_1 + only_in_2)`
     ///
@@ -165,6 +232,8 @@ pub enum SimilarityAlgorithm {
     ///
     /// Compares overlapping character sequences (n-grams/shingles).
     /// More granular than word-based, catches partial word matches.
+    #[value(alias = "ngram")]
+    #[serde(alias = "ngram")]
     NGram,
     /// TF-IDF Weighted Cosine
     ///
@@ -173,10 +242,69 @@ pub enum SimilarityAlgorithm {
     TfIdf,
 }
 
+impl std::str::FromStr for SimilarityAlgorithm {
+    type Err = String;
+
+    /// Parse an algorithm by its CLI name (e.g. `tf-idf`), ignoring case
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        <Self as clap::ValueEnum>::from_str(name, true).map_err(|_| {
+            let known: Vec<String> = <Self as clap::ValueEnum>::value_variants()
+                .iter()
+                .filter_map(clap::ValueEnum::to_possible_value)
+                .map(|v| v.get_name().to_string())
+                .collect();
+            format!("Unknown similarity algorithm '{}' (expected one of: {})", name, known.join(", "))
+        })
+    }
+}
+
+/// Which records of a structured file to compare when sampling
+///
+/// Key-based specs hash the record key (the row number with `positional`),
+/// so both files keep the same keys and sampled records still pair up.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SampleSpec {
+    /// The first `n` records of each file, in file order
+    FirstN(usize),
+    /// Each record whose key hashes into this fraction (0 to 1) of the hash range
+    Fraction(f64),
+    /// The `n` records whose keys have the smallest hashes under `seed`
+    Random { n: usize, seed: u64 },
+}
+
+impl std::str::FromStr for SampleSpec {
+    type Err = String;
+
+    /// Parse `first:N`, `fraction:F`, `P%` or `random:N[:SEED]`
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected first:N, fraction:F, P% or random:N[:SEED], got '{}'", spec);
+        let fraction = |f: Option<f64>| f.filter(|f| *f > 0.0 && *f <= 1.0).map(SampleSpec::Fraction).ok_or_else(invalid);
+        let spec = spec.trim();
+        if let Some(percent) = spec.strip_suffix('%') {
+            return fraction(percent.trim().parse::<f64>().ok().map(|p| p / 100.0));
+        }
+        let (kind, rest) = spec.split_once(':').ok_or_else(invalid)?;
+        match kind {
+            "first" => rest.parse().map(SampleSpec::FirstN).map_err(|_| invalid()),
+            "fraction" => fraction(rest.parse().ok()),
+            "random" => {
+                let (n, seed) = rest.split_once(':').unwrap_or((rest, "0"));
+                match (n.parse(), seed.parse()) {
+                    (Ok(n), Ok(seed)) => Ok(SampleSpec::Random { n, seed }),
+                    _ => Err(invalid()),
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
 /// Pairing strategy for folder comparison
 ///
 /// Determines how files from two directories are matched for comparison.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
 pub enum PairingStrategy {
     /// Match files with identical relative paths
     ///
@@ -192,6 +320,94 @@ pub enum PairingStrategy {
     /// similar files across both directories.
     #[default]
     AllVsAll,
+    /// Match files with identical content, whatever their names
+    ///
+    /// Pairs only exact content-hash matches, so renamed but unchanged files
+    /// pair up and every other file is reported as only in one set.
+    ByContentHash,
+}
+
+/// Behavior when one path is a file and the other a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileDirStrategy {
+    /// Search the directory for the file
+    ///
+    /// The single file is compared against the top-K most similar compatible
+    /// files in the directory; the best match is reported first.
+    #[default]
+    Search,
+    /// Treat the file as a one-file set and apply the normal pairing strategy
+    Pairing,
+}
+
+/// Which difference a numeric tolerance bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToleranceMode {
+    /// `|a - b| <= tolerance`
+    Absolute,
+    /// `|a - b| <= tolerance * max(|a|, |b|)`
+    Relative,
+    /// Equal if either the absolute or the relative check passes
+    #[default]
+    Either,
+}
+
+/// Algorithm used for content hashes, and so for `linked_id`s
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HashAlgorithm {
+    /// Blake3: fast and cryptographically strong
+    #[default]
+    Blake3,
+    /// SHA-256, to match checksums published elsewhere
+    Sha256,
+    /// XXH3 (64-bit): fastest, for plain deduplication
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    /// Name recorded in the run summary
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Xxh3 => "xxh3",
+        }
+    }
+}
+
+/// Color palette of the HTML report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportTheme {
+    /// Dark GitHub-style palette
+    #[default]
+    Dark,
+    /// Light palette, better for printing and light-themed pages
+    Light,
+    /// Follow the viewer's `prefers-color-scheme` setting
+    Auto,
+}
+
+/// How directory walks treat symbolic links
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkMode {
+    /// Leave symlinks out of the index
+    #[default]
+    Skip,
+    /// Index what links point to, descending into linked folders
+    ///
+    /// Each folder is walked once, however many links lead to it, so link
+    /// cycles cannot recurse forever.
+    Follow,
+    /// Index each link as a text file holding its target path
+    ///
+    /// Pairs of links are compared by where they point, not by the content
+    /// behind them.
+    CompareTarget,
 }
 
 /// Text normalization options
@@ -199,6 +415,7 @@ pub enum PairingStrategy {
 /// These options are applied before comparison to reduce noise from
 /// formatting differences that may not be semantically significant.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NormalizationOptions {
     /// Normalize line endings (CRLF -> LF)
     pub ignore_eol: bool,
@@ -210,13 +427,91 @@ pub struct NormalizationOptions {
     pub ignore_case: bool,
     /// Remove empty lines from comparison
     pub skip_empty_lines: bool,
+    /// Drop this many lines from the start of each file (e.g. a banner)
+    ///
+    /// Counted in the file as written, before `skip_empty_lines` applies.
+    #[serde(default)]
+    pub ignore_head_lines: usize,
+    /// Drop this many lines from the end of each file (e.g. a signature)
+    ///
+    /// Counted in the file as written, before `skip_empty_lines` applies.
+    #[serde(default)]
+    pub ignore_tail_lines: usize,
+    /// Unicode normalization form applied to each line before any other option
+    ///
+    /// Lets text saved as decomposed accents (macOS) match the composed form.
+    pub unicode_normalize: Option<UnicodeForm>,
+}
+
+/// Unicode normalization form for text lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnicodeForm {
+    /// Canonical composition: `e` + combining acute becomes `é`
+    Nfc,
+    /// Canonical decomposition: `é` becomes `e` + combining acute
+    Nfd,
+    /// Compatibility composition: also folds ligatures, full-width forms, etc.
+    Nfkc,
+    /// Compatibility decomposition
+    Nfkd,
+}
+
+/// Normalization applied to key fields before structured records are matched
+///
+/// Values in non-key columns are compared as they are.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyNormalization {
+    /// Strip leading and trailing whitespace
+    pub trim: bool,
+    /// Match keys regardless of case
+    pub case_insensitive: bool,
+    /// Reformat numeric keys so `007`, ` 7` and `7.0` match `7`
+    pub numeric: bool,
+}
+
+/// Value type of a structured column, inferred from a sample of its values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColumnType {
+    /// Whole numbers written without a decimal point
+    Integer,
+    /// Numbers with a fractional part or decimal point
+    Float,
+    /// `true` or `false`, in any case
+    Boolean,
+    /// Dates or date-times in one of the recognized formats
+    Date,
+    /// Anything else, including columns with no values
+    #[default]
+    String,
+}
+
+/// Which inferred column types are compared by meaning rather than as text
+///
+/// All are disabled by default, so `$1,000` and `1000` differ unless numbers
+/// are enabled. A disabled type is compared as plain text (with numeric
+/// tolerance still applied to plain numbers).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypeCoercion {
+    /// Ignore currency symbols and thousands separators in numeric columns
+    pub numbers: bool,
+    /// Compare boolean columns case-insensitively (`TRUE` matches `true`)
+    pub booleans: bool,
+    /// Compare date columns as dates, whatever their format
+    pub dates: bool,
 }
 
 /// Configuration for the compare operation
 ///
 /// This struct holds all user-specified options that control how
 /// comparisons are performed and how results are output.
-#[derive(Debug, Clone)]
+///
+/// Deserializable from a JSON config file (see [`crate::config`]); fields
+/// missing from the file keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CompareConfig {
     /// Comparison mode (auto, text, or structured)
     pub mode: CompareMode,
@@ -230,22 +525,192 @@ pub struct CompareConfig {
     /// files from the second set.
     pub top_k: usize,
 
+    /// How to pair a single file against a directory
+    pub file_dir_strategy: FileDirStrategy,
+
     /// Maximum total number of pairs to compare
     ///
-    /// Useful for limiting resource usage on large directories.
+    /// Useful for limiting resource usage on large directories. Applied after
+    /// all other limits, keeping the pairs with the highest estimated
+    /// similarity, so some files may end up with no pairs at all.
     pub max_pairs: Option<usize>,
 
+    /// Maximum number of similarity pairs any one file of the first set
+    /// contributes in all-vs-all mode
+    ///
+    /// Caps `top_k` per file (the effective count is the smaller of the two),
+    /// so a budget can be spread evenly over files before `max_pairs`
+    /// truncates the total.
+    pub max_pairs_per_file: Option<usize>,
+
+    /// Minimum estimated similarity (0.0 to 1.0) for an all-vs-all candidate
+    ///
+    /// Candidates below it are dropped before top-k selection, so a file with
+    /// no real counterpart is left unmatched instead of being paired with its
+    /// least bad matches. 0.0 keeps every candidate.
+    pub min_candidate_similarity: f64,
+
+    /// Smallest allowed size ratio (file 1 / file 2) for a candidate pair
+    pub size_ratio_min: f64,
+
+    /// Largest allowed size ratio (file 1 / file 2) for a candidate pair
+    ///
+    /// Pairs outside `size_ratio_min..=size_ratio_max` are rejected by the
+    /// size-ratio blocking rule. `f64::INFINITY` disables the rule entirely.
+    pub size_ratio_max: f64,
+
+    /// Extra groups of extensions that may be paired with each other
+    ///
+    /// Merged with the built-in groups (text, tabular, code, config), e.g.
+    /// `["kt", "java"]` lets Kotlin and Java files pair in all-vs-all mode.
+    pub extension_groups: Vec<Vec<String>>,
+
+    /// Pair files regardless of extension, skipping the extension blocking rule
+    pub allow_any_extension: bool,
+
+    /// Only report pairs whose similarity is below this cutoff (0.0 to 1.0)
+    ///
+    /// Pairs at or above it are dropped from the returned and exported
+    /// results but still counted in the summary, so thousands of unchanged
+    /// files don't bury the few that drifted. Errors are always reported.
+    /// A resumed run still records every pair in its JSONL so none is
+    /// compared twice.
+    pub min_similarity_to_report: Option<f64>,
+
     /// Key columns for structured comparison
     ///
     /// Records are matched by these columns. If empty, the first column is used.
     pub key_columns: Vec<String>,
 
+    /// Infer key columns when `key_columns` is empty
+    ///
+    /// The smallest set of columns that is unique across a sample of file 1
+    /// is used, preferring columns named like `id`; see
+    /// [`infer_key_columns`](crate::compare_structured::infer_key_columns).
+    /// The chosen columns are listed on each structured result.
+    pub auto_key: bool,
+
+    /// Key normalization for structured comparison
+    pub key_normalization: KeyNormalization,
+
+    /// Type-aware value comparison for structured columns
+    pub type_coercion: TypeCoercion,
+
+    /// Column layout for fixed-width text files, as `[start, end)` character ranges
+    ///
+    /// When set, every text file is read as [`FileType::FixedWidth`] records
+    /// and compared like a CSV. Empty means text files are diffed as text.
+    pub fixed_width: Vec<(usize, usize)>,
+
+    /// Names for the `fixed_width` columns, in order
+    ///
+    /// Columns without a name are called by position, `col_0`, `col_1`, ...,
+    /// like the columns of a CSV file without a header row.
+    pub fixed_width_columns: Vec<String>,
+
+    /// Column renames applied to file 2 before structured comparison
+    ///
+    /// Maps a file 2 column name onto the equivalent file 1 name (e.g.
+    /// `CustomerID` -> `customer_id`), so differently-named columns are
+    /// compared as one. Key columns are always given in file 1 names.
+    pub column_aliases: HashMap<String, String>,
+
+    /// Compare structured rows by position instead of by key
+    ///
+    /// Row N of file 1 is compared with row N of file 2, like overlaying two
+    /// spreadsheets; extra trailing rows count as removed or added. Key columns
+    /// are ignored.
+    pub positional: bool,
+
+    /// Collect every record and field difference of structured comparisons
+    ///
+    /// Fills [`StructuredComparisonResult::patch`] with all added and removed
+    /// keys and updated fields, not just the 5 samples per column, so a
+    /// patch can be written next to each mismatch artifact.
+    pub structured_patch: bool,
+
+    /// Keep every field mismatch of structured comparisons
+    ///
+    /// Stored in [`ColumnMismatch::all_mismatches`] alongside the 5 samples
+    /// used for display, so exports and mismatch artifacts list them all.
+    pub full_mismatch_detail: bool,
+
+    /// Most keys listed per side as only in that file of a structured pair
+    ///
+    /// The counts always cover every record; past this many keys the lists
+    /// in [`StructuredComparisonResult::only_in_file1_keys`] and
+    /// `only_in_file2_keys` stop and `only_in_keys_truncated` is set. 0 lists none.
+    pub max_listed_keys: usize,
+
+    /// Profile the columns both structured files share
+    ///
+    /// Fills [`StructuredComparisonResult::column_profiles`] with counts,
+    /// nulls, distinct values and numeric ranges, so shifts show up even
+    /// when records do not line up by key.
+    pub profile_columns: bool,
+
+    /// Compare only a sample of each structured file's records
+    ///
+    /// A quick drift check for very large files: results then cover the
+    /// sample only and are marked [`StructuredComparisonResult::sampled`].
+    pub sample: Option<SampleSpec>,
+
+    /// Sort CSV/TSV records on disk in chunks of this many bytes
+    ///
+    /// Files larger than one chunk are sorted in chunks spilled to temporary
+    /// files and merged back, so keyed comparison memory stays bounded no
+    /// matter how large the file. `None` sorts every file in memory.
+    pub external_sort: Option<usize>,
+
+    /// Compare Excel formula cells by their formula instead of their value
+    ///
+    /// Catches edited formulas that happen to give the same result, at the
+    /// cost of ignoring the cached results of formula cells (so a stale or
+    /// recalculated result is not flagged) and treating equivalent but
+    /// differently written formulas as changed.
+    pub excel_formulas: bool,
+
+    /// Skip CSV/TSV lines starting with this byte (e.g. `b'#'`)
+    ///
+    /// Lets exports with leading or interspersed comment/metadata lines be
+    /// compared without those lines showing up as malformed rows.
+    pub comment_char: Option<u8>,
+
+    /// Field delimiter for CSV/TSV files, overriding the one detected per file
+    pub csv_delimiter: Option<char>,
+
+    /// Separator between the fields of a composite key (default `|`)
+    pub key_delimiter: String,
+
+    /// Whether the first line of a CSV/TSV file holds column names
+    ///
+    /// When false, every line is a record and columns are named `col_0`,
+    /// `col_1`, ... so key columns can be given by position.
+    pub has_header: bool,
+
     /// Numeric tolerance for structured comparison
     ///
-    /// Values within this tolerance are considered equal.
-    /// Both absolute and relative tolerance are checked.
+    /// Values within this tolerance are considered equal, as an absolute
+    /// or relative difference per `tolerance_mode`.
     pub numeric_tolerance: f64,
 
+    /// Whether numeric tolerances bound the absolute difference, the
+    /// relative difference, or either (default)
+    pub tolerance_mode: ToleranceMode,
+
+    /// Compare numeric fields by ULP distance instead of `numeric_tolerance`
+    ///
+    /// Two numbers are equal if at most this many representable `f64` values
+    /// (units in the last place) lie between them, which tracks how rounding
+    /// error accumulates in computed values at any magnitude.
+    pub numeric_ulp: Option<u64>,
+
+    /// Per-column numeric tolerances overriding `numeric_tolerance`
+    ///
+    /// Lets a price column use `0.01` while a measurement column uses
+    /// `1e-9`. A column listed here ignores `numeric_ulp` as well.
+    pub column_tolerances: HashMap<String, f64>,
+
     /// Text normalization options (whitespace, case, etc.)
     pub normalization: NormalizationOptions,
 
@@ -257,49 +722,280 @@ pub struct CompareConfig {
     /// Prevents memory issues with very large diffs.
     pub max_diff_bytes: usize,
 
+    /// Maximum characters shown per line in the detailed diff
+    ///
+    /// Longer lines (minified JS/JSON) are cut with a "... (N chars truncated)"
+    /// marker so a single huge line cannot bloat the report. 0 disables the limit.
+    pub max_line_length: usize,
+
+    /// Largest binary file (in bytes) compared byte by byte
+    ///
+    /// Differing binary files up to this size get a byte-level diff with the
+    /// offsets that differ; larger ones only report that their hashes differ.
+    pub max_binary_diff_bytes: u64,
+
+    /// Only compute counts and similarity for text pairs
+    ///
+    /// Skips building `detailed_diff`, which is the expensive part of a text
+    /// comparison on large files.
+    pub summary_only: bool,
+
+    /// Write `.diff` artifacts that `patch -p1` or `git apply` can apply
+    ///
+    /// Patches are rebuilt from the raw file contents (no normalization or
+    /// truncation), with `a/` and `b/` prefixed paths relative to the
+    /// compared roots, so applying one to file 1 reproduces file 2 exactly.
+    pub patch_compatible: bool,
+
+    /// Render whitespace on changed diff lines
+    ///
+    /// Spaces become `·`, tabs become `→`, and lines with trailing
+    /// whitespace end in `¶`, so whitespace-only changes are visible.
+    pub show_whitespace: bool,
+
+    /// Compute word-level edits within modified text lines
+    ///
+    /// Fills `TextComparisonResult::inline_edits` so the HTML report can
+    /// highlight the changed words. Costs an extra word diff per changed line.
+    pub inline_diff: bool,
+
+    /// Look for a consistent find-and-replace in similar text pairs
+    ///
+    /// Fills `TextComparisonResult::rename` when most changed lines differ by
+    /// the same single-token substitution (e.g. a renamed symbol).
+    pub detect_rename: bool,
+
+    /// Compare text files as multisets of lines, ignoring line order
+    ///
+    /// `common_lines` counts lines present in both files (as often as both
+    /// have them) and `only_in_fileN` the leftovers, so shuffled but otherwise
+    /// equal files are identical. `detailed_diff` still shows the positional
+    /// diff for reference.
+    pub order_insensitive_text: bool,
+
+    /// Mask every captured value in field mismatches and text diffs
+    ///
+    /// Keys, column names, line markers and counts are kept so the report
+    /// still shows *where* things changed, just not *what* they changed to.
+    pub redact: bool,
+
+    /// Columns whose values are masked in field mismatches
+    ///
+    /// Applies only to structured comparison; `redact` masks every column.
+    pub redact_columns: Vec<String>,
+
     /// Output path for JSONL results (one JSON object per line)
     pub output_jsonl: Option<PathBuf>,
 
     /// Output path for CSV summary
     pub output_csv: Option<PathBuf>,
 
+    /// Output path for a Markdown summary (summary table plus per-pair details)
+    pub output_md: Option<PathBuf>,
+
+    /// Output path for JUnit XML (one test case per pair, for CI pipelines)
+    pub output_junit: Option<PathBuf>,
+
+    /// Output path for an XLSX workbook (summary, all pairs and field mismatches sheets)
+    pub output_xlsx: Option<PathBuf>,
+
+    /// Output path for a SARIF log (differences as code-scanning annotations)
+    pub output_sarif: Option<PathBuf>,
+
+    /// Output path for a shields.io endpoint badge JSON (average similarity and errors)
+    pub output_badge: Option<PathBuf>,
+
+    /// Output path for the summary JSON (default: `summary.json` in the results directory)
+    pub output_summary: Option<PathBuf>,
+
     /// Output directory for patch files and mismatch artifacts
     pub output_dir: Option<PathBuf>,
 
     /// Base directory for automatic results (JSONL, HTML, artifacts)
     ///
-    /// Timestamped files are created within this directory.
+    /// Timestamped files are created within this directory. Defaults to
+    /// `output`, like the `--results-base` flag.
     pub results_base: PathBuf,
 
+    /// Directory mirroring the first input's tree with one artifact per pair
+    ///
+    /// Each differing pair's `.diff` or `.json` lands at its file's relative
+    /// path (e.g. `src/main.rs.diff`) instead of flat under `artifacts/`.
+    pub mirror_output: Option<PathBuf>,
+
     /// Explicit root output directory (overrides results_base)
     ///
     /// If set, results are placed directly here without creating a timestamped subfolder.
     pub output_root: Option<PathBuf>,
 
+    /// Resume an interrupted run that wrote to the same results JSONL
+    ///
+    /// Each result is appended to the JSONL as soon as its pair is compared.
+    /// On restart, pairs already recorded there are skipped. Needs
+    /// `output_root` or `output_jsonl` so both runs use the same file.
+    pub resume: bool,
+
+    /// Results JSONL of an earlier run to merge this run's results into
+    ///
+    /// [`ComparisonEngine::run`] compares every pair as usual, then writes the
+    /// earlier results it did not replace together with the new ones to its
+    /// results JSONL, summary and report.
+    ///
+    /// [`ComparisonEngine::run`]: crate::ComparisonEngine::run
+    pub merge_results: Option<PathBuf>,
+
     /// Enable verbose output (show all results, diff snippets)
     pub verbose: bool,
 
+    /// Print only the summary and export paths, without per-pair tables
+    pub quiet: bool,
+
+    /// Skip generating the HTML report in [`ComparisonEngine::run`]
+    ///
+    /// [`ComparisonEngine::run`]: crate::ComparisonEngine::run
+    pub no_html: bool,
+
+    /// Write each text diff to its own page under `diffs/` in the artifacts
+    /// directory instead of embedding it in the HTML report
+    pub split_diffs: bool,
+
+    /// Color palette of the HTML report and diff pages
+    pub report_theme: ReportTheme,
+
+    /// Rows per page of the HTML report's results table; only the first
+    /// page is rendered into the page up front
+    pub report_max_rows: usize,
+
+    /// Algorithm for content hashes (exact-match detection and `linked_id`s)
+    pub hash_algorithm: HashAlgorithm,
+
+    /// Compute only content hashes, skipping simhashes and schema signatures
+    ///
+    /// Speeds up `SamePath`, `SameName` and `ByContentHash` pairing, which
+    /// pair files by name or exact content. Ignored for `AllVsAll`, which
+    /// needs the similarity fingerprints. The fingerprint cache is not used.
+    pub no_fingerprint: bool,
+
     /// Glob patterns for files/folders to exclude from indexing
     ///
     /// Examples: `"*.tmp"`, `"node_modules"`, `".git"`
     pub exclude_patterns: Vec<String>,
 
+    /// Glob patterns a file must match to be indexed (empty = every file)
+    ///
+    /// Matched against the full path, the path relative to the compared
+    /// folder and the file name. Exclusions still win over inclusions.
+    /// Examples: `"*.csv"`, `"reports/**/*.csv"`
+    pub include_patterns: Vec<String>,
+
+    /// Deepest subdirectory level indexed below each compared folder
+    ///
+    /// 0 indexes only the folder's own files, 1 also its subfolders' files,
+    /// and so on. `None` walks the whole tree.
+    pub max_depth: Option<usize>,
+
+    /// How symbolic links found while walking folders are indexed
+    pub symlinks: SymlinkMode,
+
+    /// Compare `.zip`, `.tar`, `.tar.gz` and `.tgz` paths entry by entry
+    ///
+    /// An archive given as either compared path is compared like a folder,
+    /// its entries keeping archive-relative paths such as `dist.zip/bin/run.sh`.
+    /// Archives inside a compared folder are still compared as single files.
+    pub follow_archives: bool,
+
     /// Columns to ignore in structured comparison
     ///
     /// Useful for skipping timestamps, auto-generated IDs, etc.
     pub ignore_columns: Vec<String>,
 
-    /// Regex pattern for content to ignore in text comparison
+    /// Treat empty fields, absent fields and `null_tokens` as the same value
+    ///
+    /// A column only one file has is then no difference as long as it is
+    /// empty in every matched record (e.g. a trailing empty column).
+    pub treat_empty_as_missing: bool,
+
+    /// Values that mean "no value" under `treat_empty_as_missing` (e.g. `NULL`),
+    /// matched ignoring ASCII case
+    pub null_tokens: Vec<String>,
+
+    /// Regex patterns for content to ignore in text comparison
+    ///
+    /// Matches are replaced with `<IGNORED>` before comparison, applying the
+    /// patterns in order. Useful for filtering timestamps, UUIDs, etc.
+    /// Config files may also give a single pattern as `ignore_regex`.
+    #[serde(alias = "ignore_regex", deserialize_with = "string_or_seq")]
+    pub ignore_regexes: Vec<String>,
+
+    /// Replacement for `ignore_regexes` matches instead of `<IGNORED>`
+    ///
+    /// May reference capture groups (`$1`, `${name}`), so only part of a
+    /// match is masked: `^\S+ (.*)$` with `<TS> $1` drops a leading
+    /// timestamp but keeps the log message.
+    pub ignore_regex_replacement: Option<String>,
+
+    /// Number of worker threads for indexing, fingerprinting and comparison
     ///
-    /// Matches are replaced with `<IGNORED>` before comparison.
-    /// Useful for filtering timestamps, UUIDs, etc.
-    pub ignore_regex: Option<String>,
+    /// `None` uses rayon's global pool (one thread per core). `Some(1)` runs
+    /// everything sequentially.
+    pub threads: Option<usize>,
 
     /// Maximum file size for similarity fingerprinting (in bytes)
     ///
     /// Files larger than this will use hash-only comparison to save memory.
     /// If None, a dynamic limit based on system RAM will be calculated.
     pub max_fingerprint_size: Option<u64>,
+
+    /// Abort before fingerprinting if the pre-flight check finds problems
+    ///
+    /// Without it, problems such as disjoint extensions are only logged.
+    pub strict: bool,
+
+    /// Stop comparing at the first pair that is not identical
+    ///
+    /// Remaining pairs are skipped and [`ComparisonEngine::run`] writes no
+    /// exports beyond the streamed JSONL. Unmatched files count as a
+    /// difference too, so the run stops before comparing anything.
+    ///
+    /// [`ComparisonEngine::run`]: crate::ComparisonEngine::run
+    pub fail_fast: bool,
+
+    /// Audit permission bits only, without reading any file content
+    ///
+    /// Files are indexed from metadata alone and paired by relative path
+    /// (unless same-name pairing is chosen); every pair yields a
+    /// [`ComparisonResult::Permissions`].
+    pub compare_permissions_only: bool,
+
+    /// Fingerprint cache file reused across runs
+    ///
+    /// Files whose canonical path, modification time and size match a cached
+    /// entry skip hashing and simhashing. New fingerprints are written back.
+    pub cache_path: Option<PathBuf>,
+}
+
+impl CompareConfig {
+    /// Returns true if values of `column` must be masked in the output
+    pub fn redacts_column(&self, column: &str) -> bool {
+        self.redact || self.redact_columns.iter().any(|c| c == column)
+    }
+
+    /// Header names for the configured fixed-width columns
+    pub fn fixed_width_headers(&self) -> Vec<String> {
+        let mut headers = crate::compare_structured::positional_columns(self.fixed_width.len());
+        for (header, name) in headers.iter_mut().zip(&self.fixed_width_columns) {
+            header.clone_from(name);
+        }
+        headers
+    }
+
+    /// Numeric tolerance and ULP limit that apply to values of `column`
+    pub fn numeric_tolerance_for(&self, column: &str) -> (f64, Option<u64>) {
+        match self.column_tolerances.get(column) {
+            Some(&tolerance) => (tolerance, None),
+            None => (self.numeric_tolerance, self.numeric_ulp),
+        }
+    }
 }
 
 impl Default for CompareConfig {
@@ -308,26 +1004,107 @@ impl Default for CompareConfig {
             mode: CompareMode::Auto,
             pairing: PairingStrategy::AllVsAll,
             top_k: 3,
+            file_dir_strategy: FileDirStrategy::Search,
             max_pairs: None,
+            max_pairs_per_file: None,
+            min_candidate_similarity: 0.0,
+            size_ratio_min: 0.1,
+            size_ratio_max: 10.0,
+            extension_groups: Vec::new(),
+            allow_any_extension: false,
+            min_similarity_to_report: None,
             key_columns: Vec::new(),
+            auto_key: false,
+            key_normalization: KeyNormalization::default(),
+            type_coercion: TypeCoercion::default(),
+            fixed_width: Vec::new(),
+            fixed_width_columns: Vec::new(),
+            column_aliases: HashMap::new(),
+            positional: false,
+            structured_patch: false,
+            full_mismatch_detail: false,
+            max_listed_keys: 1000,
+            profile_columns: false,
+            sample: None,
+            external_sort: None,
+            excel_formulas: false,
+            comment_char: None,
+            csv_delimiter: None,
+            key_delimiter: "|".to_string(),
+            has_header: true,
             numeric_tolerance: 0.0001,
+            tolerance_mode: ToleranceMode::Either,
+            column_tolerances: HashMap::new(),
+            numeric_ulp: None,
             normalization: NormalizationOptions::default(),
             similarity_algorithm: SimilarityAlgorithm::Diff,
             max_diff_bytes: 1024 * 1024, // 1MB default
+            max_line_length: 10_000,
+            max_binary_diff_bytes: 64 * 1024 * 1024,
+            summary_only: false,
+            patch_compatible: false,
+            show_whitespace: false,
+            inline_diff: false,
+            detect_rename: false,
+            order_insensitive_text: false,
+            redact: false,
+            redact_columns: Vec::new(),
             output_jsonl: None,
             output_csv: None,
+            output_md: None,
+            output_junit: None,
+            output_xlsx: None,
+            output_sarif: None,
+            output_badge: None,
+            output_summary: None,
             output_dir: None,
-            results_base: PathBuf::from("results"),
+            results_base: PathBuf::from("output"),
+            mirror_output: None,
             output_root: None,
+            resume: false,
+            merge_results: None,
             verbose: false,
+            quiet: false,
+            no_html: false,
+            split_diffs: false,
+            report_theme: ReportTheme::Dark,
+            report_max_rows: 1000,
+            hash_algorithm: HashAlgorithm::Blake3,
+            no_fingerprint: false,
             exclude_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+            max_depth: None,
+            symlinks: SymlinkMode::Skip,
+            follow_archives: false,
             ignore_columns: Vec::new(),
-            ignore_regex: None,
+            treat_empty_as_missing: false,
+            null_tokens: Vec::new(),
+            ignore_regexes: Vec::new(),
+            ignore_regex_replacement: None,
+            threads: None,
             max_fingerprint_size: None,
+            strict: false,
+            fail_fast: false,
+            compare_permissions_only: false,
+            cache_path: None,
         }
     }
 }
 
+/// Deserialize a list of strings that may also be written as one string
+fn string_or_seq<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
 /// Result of comparing two files in text mode
 ///
 /// Contains line-by-line diff statistics and optionally the full diff output.
@@ -343,6 +1120,13 @@ pub struct TextComparisonResult {
     /// Path to the second file
     pub file2_path: String,
 
+    /// Size of file 1 in bytes
+    #[serde(default)]
+    pub file1_size: u64,
+    /// Size of file 2 in bytes
+    #[serde(default)]
+    pub file2_size: u64,
+
     /// Number of lines in file 1 (after normalization)
     pub file1_line_count: usize,
     /// Number of lines in file 2 (after normalization)
@@ -366,10 +1150,102 @@ pub struct TextComparisonResult {
     /// True if the diff was truncated due to size limits
     pub diff_truncated: bool,
 
+    /// Word-level changes within modified lines (only with `inline_diff`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inline_edits: Vec<InlineEdit>,
+
+    /// Where `detailed_diff` shows whitespace as glyphs (only with `show_whitespace`)
+    #[serde(default, skip_serializing_if = "WhitespaceGlyphs::is_empty")]
+    pub whitespace_glyphs: WhitespaceGlyphs,
+
+    /// Consistent token substitution explaining the changes (only with `detect_rename`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rename: Option<RenameHint>,
+
     /// True if files are byte-for-byte identical
     pub identical: bool,
 }
 
+/// A single-token substitution that most changed lines of a text pair share
+///
+/// Suggests file 2 is file 1 after a find-and-replace, such as a renamed
+/// identifier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenameHint {
+    /// Token as it appears in file 1
+    pub from: String,
+    /// Token that replaced it in file 2
+    pub to: String,
+    /// Number of places the substitution was made
+    pub occurrences: usize,
+}
+
+impl std::fmt::Display for RenameHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "appears to be a rename: `{}` → `{}` ({} occurrence{})",
+            self.from,
+            self.to,
+            self.occurrences,
+            if self.occurrences == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Glyphs that `show_whitespace` put in place of whitespace on changed lines
+///
+/// Offsets are byte offsets into the line as it appears in `detailed_diff`,
+/// so a `·`, `→` or `¶` that was already in a file can be told apart from one
+/// standing for whitespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhitespaceGlyphs {
+    /// Removed lines, by line number in file 1 (1-based)
+    pub removed: BTreeMap<usize, Vec<usize>>,
+    /// Added lines, by line number in file 2 (1-based)
+    pub added: BTreeMap<usize, Vec<usize>>,
+}
+
+impl WhitespaceGlyphs {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty()
+    }
+}
+
+/// Word-level changes between a removed line and the line that replaced it
+///
+/// Ranges are half-open byte offsets `(start, end)` into the line as it
+/// appears in `detailed_diff`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InlineEdit {
+    /// Line number of the removed line in file 1 (1-based)
+    pub old_line: usize,
+    /// Line number of the added line in file 2 (1-based)
+    pub new_line: usize,
+    /// Byte ranges removed from the old line
+    pub removed: Vec<(usize, usize)>,
+    /// Byte ranges added to the new line
+    pub added: Vec<(usize, usize)>,
+}
+
+/// Numeric outcome of a text diff, without the diff text itself
+///
+/// Returned by `compare_text::compute_diff_stats` for callers that only need
+/// counts and similarity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffStats {
+    /// Lines present in both inputs
+    pub common_lines: usize,
+    /// Lines only in the first input (removed)
+    pub only_in_file1: usize,
+    /// Lines only in the second input (added)
+    pub only_in_file2: usize,
+    /// Similarity score from the configured algorithm (0.0 to 1.0)
+    pub similarity_score: f64,
+    /// Compact representation of diff positions (e.g., "1-5,8,10-15")
+    pub different_positions: String,
+}
+
 /// Per-column mismatch statistics for structured comparison
 ///
 /// Aggregates all mismatches for a single column, with sample values
@@ -378,12 +1254,43 @@ pub struct TextComparisonResult {
 pub struct ColumnMismatch {
     /// Name of the column with mismatches
     pub column_name: String,
+    /// Type inferred for the column's values
+    #[serde(default)]
+    pub inferred_type: ColumnType,
     /// Total number of records with different values in this column
     pub mismatch_count: usize,
     /// Sample mismatches (up to 5) for inspection
     pub sample_mismatches: Vec<FieldMismatch>,
+    /// Every mismatch, when `full_mismatch_detail` is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all_mismatches: Vec<FieldMismatch>,
+}
+
+impl ColumnMismatch {
+    /// All mismatches when they were collected, otherwise the samples
+    pub fn mismatches(&self) -> &[FieldMismatch] {
+        if self.all_mismatches.is_empty() {
+            &self.sample_mismatches
+        } else {
+            &self.all_mismatches
+        }
+    }
+}
+
+/// Mismatches in one column summed across all structured comparisons
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnRollup {
+    /// Name of the column
+    pub column_name: String,
+    /// Total records with different values in this column, over all files
+    pub mismatch_count: usize,
+    /// Number of file pairs with at least one mismatch in this column
+    pub files_affected: usize,
 }
 
+/// Placeholder written in place of a redacted value
+pub const REDACTED: &str = "***";
+
 /// A single field-level mismatch sample
 ///
 /// Shows the key that identifies the record and the differing values.
@@ -411,6 +1318,13 @@ pub struct StructuredComparisonResult {
     /// Path to the second file
     pub file2_path: String,
 
+    /// Size of file 1 in bytes
+    #[serde(default)]
+    pub file1_size: u64,
+    /// Size of file 2 in bytes
+    #[serde(default)]
+    pub file2_size: u64,
+
     /// Number of data rows in file 1 (excluding header)
     pub file1_row_count: usize,
     /// Number of data rows in file 2 (excluding header)
@@ -438,8 +1352,263 @@ pub struct StructuredComparisonResult {
     /// Columns present in both files
     pub common_columns: Vec<String>,
 
+    /// Keys that occur more than once in either file (at most 100)
+    ///
+    /// Rows sharing a key are matched by their order within the file, so
+    /// counts stay stable but may pair rows that do not belong together.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_keys: Vec<String>,
+
     /// True if files are structurally identical (same records, same values)
     pub identical: bool,
+
+    /// Every difference, when `structured_patch` is set
+    ///
+    /// Not part of the JSONL results; written to its own file instead.
+    #[serde(skip)]
+    pub patch: Option<StructuredPatch>,
+
+    /// Per-column profiles of both files, when `profile_columns` is set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_profiles: Vec<ColumnProfileDiff>,
+
+    /// Keys of the records only in file 1, up to `max_listed_keys`
+    ///
+    /// Positional comparisons list `row N` instead.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only_in_file1_keys: Vec<String>,
+    /// Keys of the records only in file 2, up to `max_listed_keys`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only_in_file2_keys: Vec<String>,
+    /// True if either key list stopped at `max_listed_keys`
+    #[serde(default)]
+    pub only_in_keys_truncated: bool,
+
+    /// Key columns picked by `auto_key` for this pair (empty when not inferred)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inferred_key_columns: Vec<String>,
+
+    /// True if only a sample of the records was compared (`sample` was set)
+    ///
+    /// Row counts, mismatches and similarity then describe the sample.
+    #[serde(default)]
+    pub sampled: bool,
+    /// Records in the sample, over both files (0 when not sampled)
+    #[serde(default)]
+    pub sample_size: usize,
+}
+
+/// Aggregates of one column over all records of one file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnProfile {
+    /// Number of records, including those with duplicate keys
+    pub count: usize,
+    /// Records where the value is empty or one of the null tokens
+    pub null_count: usize,
+    /// Number of distinct non-null values; exact up to 4096, estimated above
+    pub distinct_count: usize,
+    /// Smallest value, for numeric columns
+    pub min: Option<f64>,
+    /// Largest value, for numeric columns
+    pub max: Option<f64>,
+    /// Mean of the values, for numeric columns
+    pub mean: Option<f64>,
+}
+
+/// A column's profile in both files and how it shifted from file 1 to file 2
+///
+/// Deltas are file 2 minus file 1. Numeric statistics are left out for
+/// redacted columns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnProfileDiff {
+    /// Name of the column
+    pub column_name: String,
+    /// Profile in file 1
+    pub file1: ColumnProfile,
+    /// Profile in file 2
+    pub file2: ColumnProfile,
+    /// Change in the null rate (null count / count), as a fraction
+    pub null_rate_delta: f64,
+    /// Change in the number of distinct values
+    pub distinct_count_delta: i64,
+    /// Change in the minimum, when both files have one
+    pub min_delta: Option<f64>,
+    /// Change in the maximum, when both files have one
+    pub max_delta: Option<f64>,
+    /// Change in the mean, when both files have one
+    pub mean_delta: Option<f64>,
+}
+
+impl ColumnProfileDiff {
+    /// Compare the profiles of a column in the two files
+    pub fn new(column_name: String, file1: ColumnProfile, file2: ColumnProfile) -> Self {
+        let null_rate = |p: &ColumnProfile| if p.count == 0 { 0.0 } else { p.null_count as f64 / p.count as f64 };
+        let delta = |a: Option<f64>, b: Option<f64>| Some(b? - a?);
+        Self {
+            null_rate_delta: null_rate(&file2) - null_rate(&file1),
+            distinct_count_delta: file2.distinct_count as i64 - file1.distinct_count as i64,
+            min_delta: delta(file1.min, file2.min),
+            max_delta: delta(file1.max, file2.max),
+            mean_delta: delta(file1.mean, file2.mean),
+            column_name,
+            file1,
+            file2,
+        }
+    }
+
+    /// True if any statistic differs between the files
+    pub fn changed(&self) -> bool {
+        self.file1 != self.file2
+    }
+}
+
+/// The record changes that turn file 1 of a structured comparison into file 2
+///
+/// Keys are composite keys as in [`FieldMismatch::key`] (`row N` when
+/// comparing by position). Redacted columns carry redacted values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StructuredPatch {
+    /// Keys of records only in file 2, to insert
+    pub added_keys: Vec<String>,
+    /// Keys of records only in file 1, to delete
+    pub removed_keys: Vec<String>,
+    /// Fields of matched records whose values differ, in key order
+    pub updated: Vec<FieldUpdate>,
+}
+
+/// One field to change in a [`StructuredPatch`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldUpdate {
+    /// The key value(s) identifying the record
+    pub key: String,
+    /// Column to change
+    pub column: String,
+    /// Value in file 1
+    pub from: String,
+    /// Value in file 2
+    pub to: String,
+}
+
+/// One change found by a three-way comparison
+///
+/// Values are `None` on a side where the record does not exist. For a record
+/// added or deleted as a whole, `column` is `None` and the values list the
+/// record's fields as `column=value` pairs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreeWayChange {
+    /// The key value(s) identifying this record
+    pub key: String,
+    /// Changed column, or `None` when the whole record was added or deleted
+    pub column: Option<String>,
+    /// Value in the common ancestor
+    pub base: Option<String>,
+    /// Value in the left copy
+    pub left: Option<String>,
+    /// Value in the right copy
+    pub right: Option<String>,
+}
+
+/// Result of comparing two edited copies of a structured file against their
+/// common ancestor
+///
+/// Each change is attributed to the side that made it, so a merge can take
+/// the left and right changes and only needs a decision on the conflicts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThreeWayResult {
+    /// Path to the common ancestor
+    pub base_path: String,
+    /// Path to the left copy
+    pub left_path: String,
+    /// Path to the right copy
+    pub right_path: String,
+    /// Records identical in all three files
+    pub unchanged_records: usize,
+    /// Changes made only in the left copy
+    pub changed_in_left: Vec<ThreeWayChange>,
+    /// Changes made only in the right copy
+    pub changed_in_right: Vec<ThreeWayChange>,
+    /// The same change made in both copies
+    pub changed_in_both: Vec<ThreeWayChange>,
+    /// Changes made differently in the two copies
+    pub conflicts: Vec<ThreeWayChange>,
+}
+
+impl ThreeWayResult {
+    /// True if the two copies can be merged without a decision
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// How many potential pairs each blocking rule rejected
+///
+/// Filled during candidate generation. A pair is counted against the first
+/// rule that rejects it (extension, then size ratio, then file type, then
+/// the minimum candidate similarity).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockingStats {
+    /// Potential pairs checked against the blocking rules
+    pub pairs_checked: usize,
+    /// Rejected because the extensions are not compatible
+    pub rejected_extension: usize,
+    /// Rejected because the size ratio is outside `size_ratio_min..=size_ratio_max`
+    pub rejected_size_ratio: usize,
+    /// Rejected because only one of the two files is binary
+    pub rejected_file_type: usize,
+    /// Rejected because the estimated similarity is below `min_candidate_similarity`
+    #[serde(default)]
+    pub rejected_low_similarity: usize,
+}
+
+impl BlockingStats {
+    /// True if no pair went through the blocking rules
+    pub fn is_empty(&self) -> bool {
+        self.pairs_checked == 0
+    }
+
+    /// Total number of rejected pairs
+    pub fn total_rejected(&self) -> usize {
+        self.rejected_extension + self.rejected_size_ratio + self.rejected_file_type + self.rejected_low_similarity
+    }
+
+    /// The rule that rejected the most pairs and its count, if any rejected
+    ///
+    /// Ties go to the rule listed first: extension, size ratio, file type,
+    /// then similarity.
+    pub fn top_rule(&self) -> Option<(&'static str, usize)> {
+        [
+            ("extension", self.rejected_extension),
+            ("size ratio", self.rejected_size_ratio),
+            ("file type", self.rejected_file_type),
+            ("similarity", self.rejected_low_similarity),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .rev()
+        .max_by_key(|(_, count)| *count)
+    }
+}
+
+/// Which of the two compared paths a file came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileSide {
+    /// The first path (`path1`)
+    Set1,
+    /// The second path (`path2`)
+    Set2,
+}
+
+/// A file that was not paired with any file from the other path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnmatchedFile {
+    /// Path of the file
+    pub path: String,
+    /// Which path the file belongs to
+    pub side: FileSide,
+    /// File size in bytes
+    pub size: u64,
+    /// Detected file type
+    pub file_type: FileType,
 }
 
 /// Unified comparison result enum
@@ -448,6 +1617,8 @@ pub struct StructuredComparisonResult {
 /// - `"Text"`: Line-by-line diff result
 /// - `"Structured"`: Key-based CSV/TSV result
 /// - `"HashOnly"`: Binary file hash comparison
+/// - `"Binary"`: Byte-level binary comparison
+/// - `"OnlyInOne"`: File with no counterpart on the other side
 /// - `"Error"`: Comparison failed
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -468,6 +1639,43 @@ pub enum ComparisonResult {
         file2_size: u64,
         identical: bool,
     },
+    /// Byte-level comparison for binary files
+    ///
+    /// Produced for binary files up to `max_binary_diff_bytes`; larger ones
+    /// fall back to `HashOnly`.
+    Binary {
+        linked_id: String,
+        file1_path: String,
+        file2_path: String,
+        file1_size: u64,
+        file2_size: u64,
+        /// Half-open `(start, end)` byte offsets that differ (first ranges only)
+        differing_ranges: Vec<(u64, u64)>,
+        /// Total number of differing bytes, including a length difference
+        differing_bytes: u64,
+        /// Offset of the first differing byte
+        first_diff_offset: Option<u64>,
+        /// Share of byte offsets holding the same value in both files (0.0 to 1.0)
+        byte_similarity: f64,
+        identical: bool,
+    },
+    /// Permission-bit comparison from a permissions-only audit
+    ///
+    /// Produced instead of a content comparison when
+    /// `compare_permissions_only` is set; neither file is read.
+    Permissions {
+        file1_path: String,
+        file2_path: String,
+        /// Permission bits of each file (`None` where the platform has none)
+        file1_mode: Option<u32>,
+        file2_mode: Option<u32>,
+        identical: bool,
+    },
+    /// File present on only one side
+    ///
+    /// Reported for files the pairing strategy left without a partner, so
+    /// files missing from one folder do not silently vanish from the results.
+    OnlyInOne(UnmatchedFile),
     /// Comparison failed with an error
     ///
     /// This can happen if files are unreadable, have encoding issues,
@@ -485,7 +1693,9 @@ impl ComparisonResult {
             ComparisonResult::Text(r) => &r.linked_id,
             ComparisonResult::Structured(r) => &r.linked_id,
             ComparisonResult::HashOnly { linked_id, .. } => linked_id,
-            ComparisonResult::Error { file1_path, .. } => file1_path,
+            ComparisonResult::Binary { linked_id, .. } => linked_id,
+            ComparisonResult::OnlyInOne(r) => &r.path,
+            ComparisonResult::Permissions { file1_path, .. } | ComparisonResult::Error { file1_path, .. } => file1_path,
         }
     }
 
@@ -493,28 +1703,70 @@ impl ComparisonResult {
         match self {
             ComparisonResult::Text(r) => r.similarity_score,
             ComparisonResult::Structured(r) => r.similarity_score,
-            ComparisonResult::HashOnly { identical, .. } => {
+            ComparisonResult::HashOnly { identical, .. } | ComparisonResult::Permissions { identical, .. } => {
                 if *identical {
                     1.0
                 } else {
                     0.0
                 }
             }
-            ComparisonResult::Error { .. } => 0.0,
+            ComparisonResult::Binary { byte_similarity, .. } => *byte_similarity,
+            ComparisonResult::OnlyInOne(_) | ComparisonResult::Error { .. } => 0.0,
         }
     }
 
+    /// True if this result is reported under a `threshold` similarity cutoff
+    ///
+    /// Errors are always reported.
+    pub fn is_below_threshold(&self, threshold: f64) -> bool {
+        matches!(self, ComparisonResult::Error { .. }) || self.similarity_score() < threshold
+    }
+
     pub fn is_identical(&self) -> bool {
         match self {
             ComparisonResult::Text(r) => r.identical,
             ComparisonResult::Structured(r) => r.identical,
             ComparisonResult::HashOnly { identical, .. } => *identical,
-            ComparisonResult::Error { .. } => false,
+            ComparisonResult::Binary { identical, .. } => *identical,
+            ComparisonResult::Permissions { identical, .. } => *identical,
+            ComparisonResult::OnlyInOne(_) | ComparisonResult::Error { .. } => false,
         }
     }
 
+    /// True if the two files differ in shape, not just in values
+    ///
+    /// Structured files changed shape when a column was added or removed;
+    /// text files when their line counts differ; binary files when their sizes
+    /// differ. Permission changes, unmatched files and errors never count.
+    pub fn is_structural_change(&self) -> bool {
+        match self {
+            ComparisonResult::Text(r) => r.file1_line_count != r.file2_line_count,
+            ComparisonResult::Structured(r) => !r.columns_only_in_file1.is_empty() || !r.columns_only_in_file2.is_empty(),
+            ComparisonResult::HashOnly { file1_size, file2_size, .. }
+            | ComparisonResult::Binary { file1_size, file2_size, .. } => file1_size != file2_size,
+            ComparisonResult::Permissions { .. } | ComparisonResult::OnlyInOne(_) | ComparisonResult::Error { .. } => false,
+        }
+    }
+
+    /// Combined size of both files in bytes (0 for errors and permission audits, the one file's size for unmatched files)
+    pub fn combined_size(&self) -> u64 {
+        match self {
+            ComparisonResult::Text(r) => r.file1_size + r.file2_size,
+            ComparisonResult::Structured(r) => r.file1_size + r.file2_size,
+            ComparisonResult::HashOnly { file1_size, file2_size, .. }
+            | ComparisonResult::Binary { file1_size, file2_size, .. } => file1_size + file2_size,
+            ComparisonResult::OnlyInOne(r) => r.size,
+            ComparisonResult::Permissions { .. } | ComparisonResult::Error { .. } => 0,
+        }
+    }
+
+    /// Paths of the two files; an unmatched file has an empty path on the other side
     pub fn file_paths(&self) -> (&str, &str) {
         match self {
+            ComparisonResult::OnlyInOne(r) => match r.side {
+                FileSide::Set1 => (&r.path, ""),
+                FileSide::Set2 => ("", &r.path),
+            },
             ComparisonResult::Text(r) => (&r.file1_path, &r.file2_path),
             ComparisonResult::Structured(r) => (&r.file1_path, &r.file2_path),
             ComparisonResult::HashOnly {
@@ -522,6 +1774,16 @@ impl ComparisonResult {
                 file2_path,
                 ..
             } => (file1_path, file2_path),
+            ComparisonResult::Binary {
+                file1_path,
+                file2_path,
+                ..
+            } => (file1_path, file2_path),
+            ComparisonResult::Permissions {
+                file1_path,
+                file2_path,
+                ..
+            } => (file1_path, file2_path),
             ComparisonResult::Error {
                 file1_path,
                 file2_path,
@@ -552,6 +1814,78 @@ pub struct CandidatePair {
     pub exact_hash_match: bool,
 }
 
+/// Category of a data-quality anomaly encountered during a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AnomalyKind {
+    /// File exceeded the fingerprint size limit and was only hash-compared
+    OversizedFile,
+    /// File content could not be decoded (e.g. invalid UTF-8)
+    EncodingError,
+    /// CSV/TSV rows whose field count differs from the header
+    RaggedRows,
+    /// Several records share the same key, so matching is ambiguous
+    DuplicateKeys,
+    /// Structured file could not be parsed at all
+    UnparseableFile,
+}
+
+impl AnomalyKind {
+    /// Human-readable label used in reports
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnomalyKind::OversizedFile => "Oversized file",
+            AnomalyKind::EncodingError => "Encoding failure",
+            AnomalyKind::RaggedRows => "Ragged rows",
+            AnomalyKind::DuplicateKeys => "Duplicate keys",
+            AnomalyKind::UnparseableFile => "Unparseable file",
+        }
+    }
+}
+
+/// A single data-quality issue tied to one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Anomaly {
+    /// What kind of issue this is
+    pub kind: AnomalyKind,
+    /// Path of the affected file
+    pub path: String,
+    /// Human-readable details (counts, sample key, error message)
+    pub detail: String,
+}
+
+/// Per-run aggregation of all anomalies recorded by the diagnostics sink
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyReport {
+    /// Number of anomalies per kind
+    pub counts: BTreeMap<AnomalyKind, usize>,
+    /// All anomalies, ordered by kind then path
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl AnomalyReport {
+    /// Build a report from raw anomalies, sorting them for stable output
+    pub fn from_anomalies(mut anomalies: Vec<Anomaly>) -> Self {
+        anomalies.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.path.cmp(&b.path)));
+
+        let mut counts = BTreeMap::new();
+        for anomaly in &anomalies {
+            *counts.entry(anomaly.kind).or_insert(0) += 1;
+        }
+
+        Self { counts, anomalies }
+    }
+
+    /// Number of anomalies of the given kind
+    pub fn count(&self, kind: AnomalyKind) -> usize {
+        self.counts.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// True if the run encountered no anomalies
+    pub fn is_empty(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
 /// Summary statistics for a comparison run
 ///
 /// Provides aggregate metrics for reporting and dashboards.
@@ -563,14 +1897,33 @@ pub struct ComparisonSummary {
     pub total_files_set2: usize,
     /// Number of file pairs actually compared
     pub pairs_compared: usize,
+    /// Files in the first path left without a partner
+    #[serde(default)]
+    pub only_in_set1: usize,
+    /// Files in the second path left without a partner
+    #[serde(default)]
+    pub only_in_set2: usize,
     /// Pairs where files are identical
     pub identical_pairs: usize,
     /// Pairs where files differ
     pub different_pairs: usize,
+    /// Differing pairs whose values changed but whose shape did not
+    #[serde(default)]
+    pub content_diff_pairs: usize,
+    /// Differing pairs whose shape changed: columns added or removed, or a
+    /// different line count or file size
+    #[serde(default)]
+    pub structural_diff_pairs: usize,
     /// Pairs where comparison failed
     pub error_pairs: usize,
     /// Average similarity score across all successful comparisons
     pub average_similarity: f64,
+    /// Average similarity weighted by each pair's combined file size
+    ///
+    /// Large files count more than tiny ones, giving a better "how similar
+    /// are these trees overall" figure for mixed file sizes.
+    #[serde(default)]
+    pub weighted_average_similarity: f64,
     /// Minimum similarity score (most different pair)
     pub min_similarity: f64,
     /// Maximum similarity score (most similar non-identical pair)
@@ -603,4 +1956,37 @@ pub struct ComparisonSummary {
     /// Similarity algorithm used (e.g., "Diff", "Levenshtein", "Cosine")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub similarity_algorithm: Option<String>,
+
+    /// Content hash algorithm the `linked_id`s were built from (e.g., "blake3")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_algorithm: Option<String>,
+
+    /// Data-quality anomalies encountered during the run
+    #[serde(default, skip_serializing_if = "AnomalyReport::is_empty")]
+    pub anomalies: AnomalyReport,
+
+    /// True if the run was cancelled and the results are partial
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+
+    /// The pair that stopped a fail-fast run; the results are partial
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_difference: Option<(String, String)>,
+
+    /// Potential pairs rejected by each blocking rule during pairing
+    #[serde(default, skip_serializing_if = "BlockingStats::is_empty")]
+    pub blocking: BlockingStats,
+}
+
+/// How closely a candidate resembles one reference in a 1-vs-N comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceRanking {
+    /// Reference file or folder path
+    pub reference: String,
+    /// Mean best-match similarity per candidate file (0.0 to 1.0)
+    ///
+    /// Candidate files without any match in the reference count as 0.
+    pub score: f64,
+    /// Summary of the candidate-vs-reference run
+    pub summary: ComparisonSummary,
 }
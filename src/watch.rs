@@ -0,0 +1,138 @@
+//! Watch mode: re-running a comparison when its inputs change
+//!
+//! [`watch_paths`] waits for filesystem events under the compared paths and
+//! calls back once per burst of changes. Reads (every comparison opens the
+//! files it compares) and writes under ignored paths, such as the results
+//! directory, do not count as changes.
+
+use anyhow::{Context, Result};
+use log::warn;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+/// How often the stop flag is checked while nothing changes
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Watch `paths` and call `on_change` with the changed files after each burst of changes
+///
+/// After the first change, events are collected until the paths have been
+/// quiet for `debounce`, so a tool rewriting many files triggers one call.
+/// A watched file is watched through its parent folder, so editors that save
+/// by replacing the file are still seen. Changes under `ignore` are dropped.
+/// Returns once `stop` is set.
+pub fn watch_paths(
+    paths: &[&Path],
+    ignore: &[PathBuf],
+    debounce: Duration,
+    stop: &AtomicBool,
+    mut on_change: impl FnMut(&[PathBuf]),
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start the file watcher")?;
+    let mut targets = Vec::with_capacity(paths.len());
+    for path in paths {
+        let target = absolute(path);
+        let (watched, mode) = match target.parent() {
+            Some(parent) if target.is_file() => (parent.to_path_buf(), RecursiveMode::NonRecursive),
+            _ => (target.clone(), RecursiveMode::Recursive),
+        };
+        watcher
+            .watch(&watched, mode)
+            .with_context(|| format!("Failed to watch {}", watched.display()))?;
+        targets.push(target);
+    }
+    let ignore: Vec<PathBuf> = ignore.iter().map(|p| absolute(p)).collect();
+
+    let changed_paths = |event: notify::Result<Event>| -> Vec<PathBuf> {
+        match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => event
+                .paths
+                .into_iter()
+                .filter(|p| targets.iter().any(|t| p.starts_with(t)) && !ignore.iter().any(|i| p.starts_with(i)))
+                .collect(),
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                warn!("File watcher error: {}", e);
+                Vec::new()
+            }
+        }
+    };
+
+    while !stop.load(Ordering::Relaxed) {
+        let mut changed = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => changed_paths(event),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if changed.is_empty() {
+            continue;
+        }
+        while let Ok(event) = rx.recv_timeout(debounce) {
+            changed.extend(changed_paths(event));
+        }
+        changed.sort();
+        changed.dedup();
+        on_change(&changed);
+    }
+    Ok(())
+}
+
+/// The path made absolute without resolving symlinks, as watcher events report it
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CompareConfig;
+    use crate::ComparisonEngine;
+    use std::fs;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
+
+    #[test]
+    fn test_changed_file_triggers_another_comparison() {
+        let dir = tempfile::tempdir().unwrap();
+        let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+        fs::create_dir_all(&left).unwrap();
+        fs::create_dir_all(&right).unwrap();
+        fs::write(left.join("notes.txt"), "one\ntwo\n").unwrap();
+        fs::write(right.join("notes.txt"), "one\ntwo\n").unwrap();
+
+        let config = CompareConfig::default();
+        let (initial, _) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+        assert!(initial[0].is_identical());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let watcher = {
+            let (left, right, stop) = (left.clone(), right.clone(), stop.clone());
+            thread::spawn(move || {
+                let config = CompareConfig::default();
+                watch_paths(&[&left, &right], &[], Duration::from_millis(50), &stop, |_| {
+                    let (results, _) = ComparisonEngine::new(&config).run_in_memory(&left, &right).unwrap();
+                    let _ = tx.send(results);
+                })
+            })
+        };
+
+        // Keep editing until the watcher, which starts asynchronously, reacts
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let rerun = loop {
+            assert!(Instant::now() < deadline, "no comparison after the file changed");
+            fs::write(right.join("notes.txt"), "one\nthree\n").unwrap();
+            if let Ok(results) = rx.recv_timeout(Duration::from_millis(300)) {
+                break results;
+            }
+        };
+        assert!(!rerun[0].is_identical());
+
+        stop.store(true, Ordering::Relaxed);
+        watcher.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+//! End-to-end tests of the `CompareIt` binary's terminal output
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_quiet_prints_summary_without_details() {
+    let dir = tempfile::tempdir().unwrap();
+    let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+    fs::create_dir_all(&left).unwrap();
+    fs::create_dir_all(&right).unwrap();
+    fs::write(left.join("a.txt"), "one\ntwo\n").unwrap();
+    fs::write(right.join("a.txt"), "one\nthree\n").unwrap();
+    let out = dir.path().join("out");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_CompareIt"))
+        .args(["compare", "--quiet", "--no-html", "-o"])
+        .arg(&out)
+        .arg(&left)
+        .arg(&right)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Results Summary"));
+    assert!(stdout.contains("Results Directory:"));
+    assert!(!stdout.contains("Comparison Details"));
+    assert!(out.join("results.jsonl").exists());
+    assert!(!out.join("report.html").exists());
+}
@@ -66,16 +66,23 @@ interface ProgressEvent {
   current: number;
   total: number;
   percentage: number;
+  etaSeconds: number | null;
+  itemsPerSec: number;
 }
 
 interface ComparisonSummary {
   totalFilesSet1: number;
   totalFilesSet2: number;
   pairsCompared: number;
+  onlyInSet1: number;
+  onlyInSet2: number;
   identicalPairs: number;
   differentPairs: number;
+  contentDiffPairs: number;
+  structuralDiffPairs: number;
   errorPairs: number;
   averageSimilarity: number;
+  weightedAverageSimilarity: number;
   minSimilarity: number;
   maxSimilarity: number;
 }
@@ -128,6 +135,28 @@ interface HashOnlyResult {
   file2_size: number;
 }
 
+interface BinaryResult {
+  type: "Binary";
+  linked_id: string;
+  file1_path: string;
+  file2_path: string;
+  identical: boolean;
+  file1_size: number;
+  file2_size: number;
+  differing_ranges: Array<[number, number]>;
+  differing_bytes: number;
+  first_diff_offset: number | null;
+  byte_similarity: number;
+}
+
+interface OnlyInOneResult {
+  type: "OnlyInOne";
+  path: string;
+  side: "Set1" | "Set2";
+  size: number;
+  file_type: string;
+}
+
 interface ErrorResult {
   type: "Error";
   file1_path: string;
@@ -135,7 +164,7 @@ interface ErrorResult {
   error: string;
 }
 
-type ComparisonResult = TextResult | StructuredResult | HashOnlyResult | ErrorResult;
+type ComparisonResult = TextResult | StructuredResult | HashOnlyResult | BinaryResult | OnlyInOneResult | ErrorResult;
 
 interface CompareResponse {
   success: boolean;
@@ -143,6 +172,7 @@ interface CompareResponse {
   results: ComparisonResult[];
   error: string | null;
   resultsDir: string | null;
+  cancelled: boolean;
 }
 
 interface CompareConfig {
@@ -162,8 +192,16 @@ interface CompareConfig {
   ignoreColumns?: string[];
   ignoreRegex?: string;
   resultsBase?: string;
+  similarityAlgorithm?: string;
+  maxDiffBytes?: number;
+  /** Caps the candidate set: at most this many file pairs are compared */
+  maxPairs?: number;
 }
 
+// Remaining time of a progress stage, e.g. "45s" or "2m"
+const formatEta = (seconds: number) =>
+  seconds < 60 ? `${Math.ceil(seconds)}s` : `${Math.round(seconds / 60)}m`;
+
 // Icon components (inline SVG for local-only)
 const FolderIcon = () => (
   <svg className="w-8 h-8" fill="none" stroke="currentColor" viewBox="0 0 24 24">
@@ -264,23 +302,40 @@ function App() {
         results: [],
         error: String(error),
         resultsDir: null,
+        cancelled: false,
       });
     } finally {
       setIsRunning(false);
     }
   };
 
+  // Ask the backend to stop the running comparison; partial results are returned
+  const cancelComparison = async () => {
+    await invoke<boolean>("cancel_comparison");
+  };
+
   // Get truncated path for display
   const truncatePath = (path: string, maxLen: number = 40) => {
     if (path.length <= maxLen) return path;
     return "..." + path.slice(-maxLen + 3);
   };
 
+  // Get both paths; an unmatched file has an empty path on the other side
+  const getPaths = (result: ComparisonResult): [string, string] => {
+    if (result.type === "OnlyInOne") {
+      return result.side === "Set1" ? [result.path, ""] : ["", result.path];
+    }
+    return [result.file1_path, result.file2_path];
+  };
+
   // Get status badge
   const getStatusBadge = (result: ComparisonResult) => {
     if (result.type === "Error") {
       return <span className="badge-error">Error</span>;
     }
+    if (result.type === "OnlyInOne") {
+      return <span className="badge-unmatched">Only in {result.side === "Set1" ? "1" : "2"}</span>;
+    }
     if (result.identical) {
       return <span className="badge-identical">Identical</span>;
     }
@@ -289,8 +344,9 @@ function App() {
 
   // Get similarity score
   const getSimilarity = (result: ComparisonResult): number => {
-    if (result.type === "Error") return 0;
+    if (result.type === "Error" || result.type === "OnlyInOne") return 0;
     if (result.type === "HashOnly") return result.identical ? 1 : 0;
+    if (result.type === "Binary") return result.byte_similarity;
     return result.similarity_score;
   };
 
@@ -403,6 +459,7 @@ function App() {
                       <option value="all-vs-all">All-vs-All (Smart Match)</option>
                       <option value="same-path">Same Path</option>
                       <option value="same-name">Same Name</option>
+                      <option value="by-content-hash">Same Content (Renames)</option>
                     </select>
                   </div>
                   
@@ -492,12 +549,27 @@ function App() {
             )}
           </button>
 
+          {/* Cancel Button */}
+          {isRunning && (
+            <button
+              onClick={cancelComparison}
+              className="w-full mt-2 px-4 py-2 bg-slate-700 hover:bg-slate-600 rounded text-sm text-slate-200"
+            >
+              Cancel
+            </button>
+          )}
+
           {/* Progress */}
           {isRunning && progress && (
             <div className="mt-4 p-3 bg-slate-800/50 rounded-lg">
               <div className="flex justify-between text-xs text-slate-400 mb-2">
                 <span>{progress.stage}</span>
-                <span>{progress.percentage.toFixed(0)}%</span>
+                <span>
+                  {progress.percentage.toFixed(0)}%
+                  {progress.etaSeconds != null && progress.current < progress.total && (
+                    <> · ~{formatEta(progress.etaSeconds)} remaining</>
+                  )}
+                </span>
               </div>
               <div className="h-2 bg-slate-700 rounded-full overflow-hidden">
                 <div 
@@ -543,6 +615,11 @@ function App() {
                 <div className="card">
                   <div className="card-header">Pairs Compared</div>
                   <div className="text-2xl font-bold text-white">{response.summary.pairsCompared}</div>
+                  {response.summary.onlyInSet1 + response.summary.onlyInSet2 > 0 && (
+                    <div className="text-xs text-sky-400">
+                      {response.summary.onlyInSet1} / {response.summary.onlyInSet2} only in one side
+                    </div>
+                  )}
                 </div>
                 <div className="card">
                   <div className="card-header">Identical</div>
@@ -551,6 +628,11 @@ function App() {
                 <div className="card">
                   <div className="card-header">Different</div>
                   <div className="text-2xl font-bold text-amber-400">{response.summary.differentPairs}</div>
+                  {response.summary.differentPairs > 0 && (
+                    <div className="text-xs text-amber-300">
+                      {response.summary.contentDiffPairs} content / {response.summary.structuralDiffPairs} structural
+                    </div>
+                  )}
                 </div>
                 <div className="card">
                   <div className="card-header">Errors</div>
@@ -563,9 +645,19 @@ function App() {
                   <div className="text-2xl font-bold text-cyan-400">
                     {(response.summary.averageSimilarity * 100).toFixed(1)}%
                   </div>
+                  <div className="text-xs text-slate-400">
+                    {(response.summary.weightedAverageSimilarity * 100).toFixed(1)}% size-weighted
+                  </div>
                 </div>
               </div>
 
+              {/* Cancelled Notice */}
+              {response.cancelled && (
+                <div className="text-xs text-amber-400">
+                  Comparison was cancelled - showing partial results.
+                </div>
+              )}
+
               {/* Results Dir */}
               {response.resultsDir && (
                 <div className="text-xs text-slate-500">
@@ -600,10 +692,10 @@ function App() {
                         >
                           <td className="px-4 py-2">{getStatusBadge(result)}</td>
                           <td className="px-4 py-2 text-slate-300 font-mono text-xs">
-                            {truncatePath(result.file1_path, 30)}
+                            {truncatePath(getPaths(result)[0], 30)}
                           </td>
                           <td className="px-4 py-2 text-slate-300 font-mono text-xs">
-                            {truncatePath(result.file2_path, 30)}
+                            {truncatePath(getPaths(result)[1], 30)}
                           </td>
                           <td className="px-4 py-2">
                             <div className="flex items-center gap-2">
@@ -621,7 +713,8 @@ function App() {
                           <td className="px-4 py-2 text-slate-400 text-xs">
                             {result.type === "Text" ? "text" : 
                              result.type === "Structured" ? "csv" : 
-                             result.type === "HashOnly" ? "binary" : "error"}
+                             result.type === "HashOnly" || result.type === "Binary" ? "binary" :
+                             result.type === "OnlyInOne" ? "unmatched" : "error"}
                           </td>
                         </tr>
                       ))}
@@ -631,7 +724,7 @@ function App() {
               </div>
 
               {/* Detail View */}
-              {selectedResult && selectedResult.type !== "Error" && (
+              {selectedResult && selectedResult.type !== "Error" && selectedResult.type !== "OnlyInOne" && (
                 <div className="card">
                   <div className="flex justify-between items-start mb-4">
                     <div>